@@ -0,0 +1,402 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use image::{DynamicImage, codecs::jpeg::JpegEncoder, imageops::FilterType};
+use thiserror::Error;
+
+use crate::artwork::render::ArtStyle;
+
+pub mod render;
+
+/// File names, without extension, that are treated as a release's front cover when found next
+/// to its tracks.
+const SIDECAR_NAMES: &[&str] = &["cover", "folder", "front", "album"];
+/// File names, without extension, treated as an artist photo when found in the directory above
+/// a release.
+const ARTIST_SIDECAR_NAMES: &[&str] = &["artist"];
+const SIDECAR_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png"];
+const THUMBNAIL_QUALITY: u8 = 90;
+
+#[derive(Debug, Error)]
+pub enum ArtworkError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+}
+
+/// What a cached image belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArtworkKey {
+    Release(i64),
+    Artist(i64),
+}
+
+/// Pre-scaled sizes stored for every cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Thumbnail {
+    /// Rows in the library lists.
+    Small,
+    /// Grid tiles in the library.
+    Medium,
+    /// The full square display, used by the player.
+    Large,
+}
+
+impl Thumbnail {
+    pub const ALL: [Thumbnail; 3] = [Thumbnail::Small, Thumbnail::Medium, Thumbnail::Large];
+
+    pub fn size(&self) -> u32 {
+        match self {
+            Thumbnail::Small => 96,
+            Thumbnail::Medium => 240,
+            Thumbnail::Large => 720,
+        }
+    }
+}
+
+/// On-disk store of release covers.
+///
+/// Images are deduplicated by the hash of their source bytes and kept under `images/`, each
+/// release or artist then only records which hash its image has under `releases/<id>` or
+/// `artists/<id>`. Styled renderings are kept alongside under `rendered/`.
+#[derive(Debug, Clone)]
+pub struct ArtworkCache {
+    root: PathBuf,
+}
+
+impl ArtworkCache {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn contains(&self, key: ArtworkKey) -> bool {
+        self.link_path(key).exists()
+    }
+
+    /// Path of the cached image for a release or artist, if one was stored.
+    pub fn path(&self, key: ArtworkKey, thumbnail: Thumbnail) -> Option<PathBuf> {
+        let hash = fs::read_to_string(self.link_path(key)).ok()?;
+        let path = self.image_path(hash.trim(), thumbnail);
+        path.exists().then_some(path)
+    }
+
+    pub fn read(&self, key: ArtworkKey, thumbnail: Thumbnail) -> Option<Vec<u8>> {
+        fs::read(self.path(key, thumbnail)?).ok()
+    }
+
+    /// Scales `bytes` to every thumbnail size and records it as the image for `key`, the
+    /// scaling is skipped when an identical image is already cached for something else.
+    pub fn store(&self, key: ArtworkKey, bytes: &[u8]) -> Result<(), ArtworkError> {
+        let hash = format!("{:016x}", fnv1a(bytes));
+
+        if !self.has_thumbnails(&hash) {
+            self.write_thumbnails(&hash, &image::load_from_memory(bytes)?)?;
+        }
+
+        self.link(key, &hash)
+    }
+
+    /// Builds a 2x2 collage from the cached images of `sources` and records it for `key`.
+    ///
+    /// A single source is linked directly rather than tiled, returns `false` when none of the
+    /// sources have an image.
+    pub fn store_collage(
+        &self,
+        key: ArtworkKey,
+        sources: &[ArtworkKey],
+    ) -> Result<bool, ArtworkError> {
+        let hashes: Vec<String> = sources
+            .iter()
+            .filter_map(|source| fs::read_to_string(self.link_path(*source)).ok())
+            .map(|hash| hash.trim().to_string())
+            .filter(|hash| self.has_thumbnails(hash))
+            .take(4)
+            .collect();
+
+        match hashes.as_slice() {
+            [] => return Ok(false),
+            [single] => {
+                self.link(key, single)?;
+                return Ok(true);
+            }
+            _ => {}
+        }
+
+        let hash = format!("{:016x}", fnv1a(hashes.concat().as_bytes()));
+
+        if !self.has_thumbnails(&hash) {
+            let size = Thumbnail::Large.size();
+            let cell = size / 2;
+            let mut collage = image::RgbImage::new(size, size);
+
+            for (position, tile_hash) in hashes.iter().cycle().take(4).enumerate() {
+                let tile = image::open(self.image_path(tile_hash, Thumbnail::Large))?
+                    .resize_to_fill(cell, cell, FilterType::Triangle)
+                    .to_rgb8();
+                let x = (position as u32 % 2) * cell;
+                let y = (position as u32 / 2) * cell;
+                image::imageops::replace(&mut collage, &tile, x.into(), y.into());
+            }
+
+            self.write_thumbnails(&hash, &collage.into())?;
+        }
+
+        self.link(key, &hash)?;
+        Ok(true)
+    }
+
+    /// The image for `key` drawn in `style` at `size`, rendered from the large thumbnail on
+    /// first use and read back from disk afterwards.
+    pub fn render(
+        &self,
+        key: ArtworkKey,
+        style: ArtStyle,
+        size: u32,
+    ) -> Result<Option<Vec<u8>>, ArtworkError> {
+        let Ok(hash) = fs::read_to_string(self.link_path(key)) else {
+            return Ok(None);
+        };
+        let hash = hash.trim();
+
+        let path = self.rendered_path(hash, style, size);
+        if path.exists() {
+            return Ok(Some(fs::read(path)?));
+        }
+
+        let source = image::open(self.image_path(hash, Thumbnail::Large))?;
+        let output = render::encode(&style.renderer().render(&source, size))?;
+
+        fs::create_dir_all(self.root.join("rendered"))?;
+        fs::write(path, &output)?;
+
+        Ok(Some(output))
+    }
+
+    fn has_thumbnails(&self, hash: &str) -> bool {
+        Thumbnail::ALL
+            .iter()
+            .all(|thumbnail| self.image_path(hash, *thumbnail).exists())
+    }
+
+    fn write_thumbnails(&self, hash: &str, source: &DynamicImage) -> Result<(), ArtworkError> {
+        fs::create_dir_all(self.root.join("images"))?;
+
+        for thumbnail in Thumbnail::ALL {
+            let size = thumbnail.size();
+            let scaled = source
+                .resize_to_fill(size, size, FilterType::Lanczos3)
+                .to_rgb8();
+
+            let mut output = Vec::new();
+            JpegEncoder::new_with_quality(Cursor::new(&mut output), THUMBNAIL_QUALITY)
+                .encode_image(&scaled)?;
+            fs::write(self.image_path(hash, thumbnail), output)?;
+        }
+
+        Ok(())
+    }
+
+    fn link(&self, key: ArtworkKey, hash: &str) -> Result<(), ArtworkError> {
+        let path = self.link_path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, hash)?;
+
+        Ok(())
+    }
+
+    fn link_path(&self, key: ArtworkKey) -> PathBuf {
+        match key {
+            ArtworkKey::Release(id) => self.root.join("releases").join(id.to_string()),
+            ArtworkKey::Artist(id) => self.root.join("artists").join(id.to_string()),
+        }
+    }
+
+    fn image_path(&self, hash: &str, thumbnail: Thumbnail) -> PathBuf {
+        self.root
+            .join("images")
+            .join(format!("{}_{}.jpg", hash, thumbnail.size()))
+    }
+
+    fn rendered_path(&self, hash: &str, style: ArtStyle, size: u32) -> PathBuf {
+        self.root
+            .join("rendered")
+            .join(format!("{}_{}_{}.png", hash, style.cache_key(), size))
+    }
+}
+
+/// Looks for a cover image such as `cover.jpg` or `folder.png` in `dir`.
+pub fn find_sidecar(dir: &Path) -> Option<PathBuf> {
+    find_image(dir, SIDECAR_NAMES)
+}
+
+/// Looks for an artist photo such as `artist.jpg` in `dir`.
+pub fn find_artist_sidecar(dir: &Path) -> Option<PathBuf> {
+    find_image(dir, ARTIST_SIDECAR_NAMES)
+}
+
+fn find_image(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    let entries = fs::read_dir(dir).ok()?;
+
+    let mut candidates: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            let stem = path.file_stem().and_then(|s| s.to_str());
+            let extension = path.extension().and_then(|e| e.to_str());
+
+            match (stem, extension) {
+                (Some(stem), Some(extension)) => {
+                    names.contains(&stem.to_lowercase().as_str())
+                        && SIDECAR_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+                }
+                _ => false,
+            }
+        })
+        .collect();
+
+    // Prefer names in the order they're listed so "cover" wins over "folder".
+    candidates.sort_by_key(|path| {
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        names.iter().position(|name| *name == stem)
+    });
+
+    candidates.into_iter().next()
+}
+
+/// 64-bit FNV-1a, stable across builds unlike `DefaultHasher` so cache keys survive upgrades.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A solid `color` square encoded as PNG.
+    fn png(color: [u8; 3], size: u32) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(size, size, image::Rgb(color));
+        let mut bytes = Vec::new();
+        DynamicImage::from(image)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .expect("encode png");
+        bytes
+    }
+
+    fn image_count(root: &Path) -> usize {
+        fs::read_dir(root.join("images")).map_or(0, |entries| entries.count())
+    }
+
+    #[test]
+    fn covers_are_scaled_once_and_shared_between_releases() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = ArtworkCache::new(dir.path().to_path_buf());
+        let cover = png([200, 40, 40], 300);
+
+        assert!(!cache.contains(ArtworkKey::Release(1)));
+        cache.store(ArtworkKey::Release(1), &cover).expect("store");
+        cache.store(ArtworkKey::Release(2), &cover).expect("store");
+
+        assert!(cache.contains(ArtworkKey::Release(2)));
+        assert_eq!(image_count(dir.path()), Thumbnail::ALL.len());
+        assert_eq!(
+            cache.path(ArtworkKey::Release(1), Thumbnail::Small),
+            cache.path(ArtworkKey::Release(2), Thumbnail::Small)
+        );
+
+        for thumbnail in Thumbnail::ALL {
+            let bytes = cache
+                .read(ArtworkKey::Release(1), thumbnail)
+                .expect("thumbnail");
+            let image = image::load_from_memory(&bytes).expect("decode");
+            assert_eq!(
+                (image.width(), image.height()),
+                (thumbnail.size(), thumbnail.size())
+            );
+        }
+
+        assert!(
+            cache
+                .read(ArtworkKey::Artist(1), Thumbnail::Small)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn artist_collages_tile_their_release_covers() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = ArtworkCache::new(dir.path().to_path_buf());
+        let artist = ArtworkKey::Artist(1);
+
+        assert!(!cache.store_collage(artist, &[]).expect("collage"));
+        assert!(
+            !cache
+                .store_collage(artist, &[ArtworkKey::Release(9)])
+                .expect("collage")
+        );
+        assert!(!cache.contains(artist));
+
+        cache
+            .store(ArtworkKey::Release(1), &png([255, 0, 0], 64))
+            .expect("store");
+        assert!(
+            cache
+                .store_collage(artist, &[ArtworkKey::Release(1), ArtworkKey::Release(9)])
+                .expect("collage")
+        );
+        assert_eq!(
+            cache.path(artist, Thumbnail::Large),
+            cache.path(ArtworkKey::Release(1), Thumbnail::Large)
+        );
+
+        cache
+            .store(ArtworkKey::Release(2), &png([0, 0, 255], 64))
+            .expect("store");
+        assert!(
+            cache
+                .store_collage(artist, &[ArtworkKey::Release(1), ArtworkKey::Release(2)])
+                .expect("collage")
+        );
+        let bytes = cache.read(artist, Thumbnail::Large).expect("collage image");
+        let collage = image::load_from_memory(&bytes).expect("decode").to_rgb8();
+        let quarter = Thumbnail::Large.size() / 4;
+        let [red, _, blue] = collage.get_pixel(quarter, quarter).0;
+        assert!(red > 200 && blue < 50);
+        let [red, _, blue] = collage.get_pixel(quarter * 3, quarter).0;
+        assert!(blue > 200 && red < 50);
+    }
+
+    #[test]
+    fn sidecars_are_found_by_name_in_preference_order() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        assert_eq!(find_sidecar(dir.path()), None);
+
+        fs::write(dir.path().join("Folder.PNG"), b"").expect("write");
+        fs::write(dir.path().join("notes.jpg"), b"").expect("write");
+        assert_eq!(
+            find_sidecar(dir.path()),
+            Some(dir.path().join("Folder.PNG"))
+        );
+
+        fs::write(dir.path().join("cover.jpg"), b"").expect("write");
+        fs::write(dir.path().join("cover.txt"), b"").expect("write");
+        assert_eq!(find_sidecar(dir.path()), Some(dir.path().join("cover.jpg")));
+        assert_eq!(find_artist_sidecar(dir.path()), None);
+
+        fs::write(dir.path().join("artist.jpeg"), b"").expect("write");
+        assert_eq!(
+            find_artist_sidecar(dir.path()),
+            Some(dir.path().join("artist.jpeg"))
+        );
+    }
+}
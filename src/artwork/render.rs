@@ -0,0 +1,351 @@
+use std::{fmt::Display, io::Cursor};
+
+use color_quant::NeuQuant;
+use image::{
+    DynamicImage, ImageFormat, Luma, Rgba,
+    imageops::{self, ColorMap, FilterType},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    artwork::ArtworkError,
+    settings::{cycle_index, cycle_option},
+};
+
+const BLOCK_OPTIONS: [u32; 5] = [30, 45, 60, 90, 120];
+const LEVEL_OPTIONS: [u8; 4] = [2, 4, 8, 16];
+const COLOR_OPTIONS: [u16; 5] = [4, 8, 16, 32, 64];
+/// NeuQuant sampling factor, 1 is slowest and best, 10 is plenty for a cover.
+const QUANTIZER_SAMPLING: i32 = 10;
+
+/// How covers are drawn on the display, picked in settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "style", rename_all = "snake_case")]
+pub enum ArtStyle {
+    /// Chunky blocks, `blocks` across the cover.
+    Pixelated {
+        blocks: u32,
+    },
+    FullResolution,
+    /// Greyscale with error diffusion down to `levels` shades, 2 gives 1-bit.
+    Dithered {
+        levels: u8,
+    },
+    /// Error diffusion down to an adaptive palette of `colors` entries.
+    Palette {
+        colors: u16,
+    },
+}
+
+impl Default for ArtStyle {
+    fn default() -> Self {
+        ArtStyle::Pixelated { blocks: 90 }
+    }
+}
+
+impl Display for ArtStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArtStyle::Pixelated { .. } => write!(f, "Pixelated"),
+            ArtStyle::FullResolution => write!(f, "Full"),
+            ArtStyle::Dithered { .. } => write!(f, "Dithered"),
+            ArtStyle::Palette { .. } => write!(f, "Palette"),
+        }
+    }
+}
+
+impl ArtStyle {
+    const STYLES: [ArtStyle; 4] = [
+        ArtStyle::Pixelated { blocks: 90 },
+        ArtStyle::FullResolution,
+        ArtStyle::Dithered { levels: 2 },
+        ArtStyle::Palette { colors: 16 },
+    ];
+
+    /// The neighbouring style, with its default parameter.
+    pub fn cycle(&self, forward: bool) -> ArtStyle {
+        let position = Self::STYLES
+            .iter()
+            .position(|style| std::mem::discriminant(style) == std::mem::discriminant(self))
+            .unwrap_or_default();
+
+        Self::STYLES[cycle_index(position, Self::STYLES.len(), forward)]
+    }
+
+    /// The same style with its parameter moved to the neighbouring option.
+    pub fn adjust(&self, forward: bool) -> ArtStyle {
+        match *self {
+            ArtStyle::Pixelated { blocks } => ArtStyle::Pixelated {
+                blocks: cycle_option(&BLOCK_OPTIONS, blocks, forward),
+            },
+            ArtStyle::FullResolution => ArtStyle::FullResolution,
+            ArtStyle::Dithered { levels } => ArtStyle::Dithered {
+                levels: cycle_option(&LEVEL_OPTIONS, levels, forward),
+            },
+            ArtStyle::Palette { colors } => ArtStyle::Palette {
+                colors: cycle_option(&COLOR_OPTIONS, colors, forward),
+            },
+        }
+    }
+
+    /// Human readable parameter of the style, `None` when it has nothing to tune.
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            ArtStyle::Pixelated { blocks } => Some(format!("{} blocks", blocks)),
+            ArtStyle::FullResolution => None,
+            ArtStyle::Dithered { levels: 2 } => Some("1-bit".to_string()),
+            ArtStyle::Dithered { levels } => Some(format!("{} greys", levels)),
+            ArtStyle::Palette { colors } => Some(format!("{} colors", colors)),
+        }
+    }
+
+    /// Stable name used for cached renderings, changing it orphans existing files.
+    pub fn cache_key(&self) -> String {
+        match self {
+            ArtStyle::Pixelated { blocks } => format!("pixelated-{}", blocks),
+            ArtStyle::FullResolution => "full".to_string(),
+            ArtStyle::Dithered { levels } => format!("dithered-{}", levels),
+            ArtStyle::Palette { colors } => format!("palette-{}", colors),
+        }
+    }
+
+    pub fn renderer(&self) -> Box<dyn ArtRenderer> {
+        match *self {
+            ArtStyle::Pixelated { blocks } => Box::new(Pixelated { blocks }),
+            ArtStyle::FullResolution => Box::new(FullResolution),
+            ArtStyle::Dithered { levels } => Box::new(Dithered { levels }),
+            ArtStyle::Palette { colors } => Box::new(Palette { colors }),
+        }
+    }
+}
+
+/// Turns a decoded cover into the square image drawn on screen.
+pub trait ArtRenderer {
+    fn render(&self, source: &DynamicImage, size: u32) -> DynamicImage;
+}
+
+pub struct Pixelated {
+    pub blocks: u32,
+}
+
+impl ArtRenderer for Pixelated {
+    fn render(&self, source: &DynamicImage, size: u32) -> DynamicImage {
+        // Downsample to the block grid, then scale back up with nearest neighbour so every
+        // source pixel becomes a solid block.
+        source
+            .resize_exact(self.blocks, self.blocks, FilterType::Nearest)
+            .resize_exact(size, size, FilterType::Nearest)
+    }
+}
+
+pub struct FullResolution;
+
+impl ArtRenderer for FullResolution {
+    fn render(&self, source: &DynamicImage, size: u32) -> DynamicImage {
+        source.resize_to_fill(size, size, FilterType::Lanczos3)
+    }
+}
+
+pub struct Dithered {
+    pub levels: u8,
+}
+
+impl ArtRenderer for Dithered {
+    fn render(&self, source: &DynamicImage, size: u32) -> DynamicImage {
+        let mut grey = source
+            .resize_to_fill(size, size, FilterType::Lanczos3)
+            .to_luma8();
+        imageops::dither(
+            &mut grey,
+            &GreyLevels {
+                levels: self.levels.max(2),
+            },
+        );
+
+        grey.into()
+    }
+}
+
+pub struct Palette {
+    pub colors: u16,
+}
+
+impl ArtRenderer for Palette {
+    fn render(&self, source: &DynamicImage, size: u32) -> DynamicImage {
+        let mut rgba = source
+            .resize_to_fill(size, size, FilterType::Lanczos3)
+            .to_rgba8();
+        let quantizer = Quantizer(NeuQuant::new(
+            QUANTIZER_SAMPLING,
+            self.colors.into(),
+            rgba.as_raw(),
+        ));
+        imageops::dither(&mut rgba, &quantizer);
+
+        rgba.into()
+    }
+}
+
+/// Renders encoded image bytes in `style`, for covers that aren't in the cache.
+pub fn render_bytes(bytes: &[u8], style: ArtStyle, size: u32) -> Result<Vec<u8>, ArtworkError> {
+    let source = image::load_from_memory(bytes)?;
+    encode(&style.renderer().render(&source, size))
+}
+
+/// Encodes a rendering losslessly so dithering patterns survive.
+pub(crate) fn encode(image: &DynamicImage) -> Result<Vec<u8>, ArtworkError> {
+    let mut output = Vec::new();
+    image.write_to(&mut Cursor::new(&mut output), ImageFormat::Png)?;
+    Ok(output)
+}
+
+/// Evenly spaced shades of grey.
+struct GreyLevels {
+    levels: u8,
+}
+
+impl ColorMap for GreyLevels {
+    type Color = Luma<u8>;
+
+    fn index_of(&self, color: &Luma<u8>) -> usize {
+        let steps = u16::from(self.levels - 1);
+        ((u16::from(color.0[0]) * steps + 127) / 255) as usize
+    }
+
+    fn map_color(&self, color: &mut Luma<u8>) {
+        let steps = usize::from(self.levels - 1);
+        color.0[0] = (self.index_of(color) * 255 / steps) as u8;
+    }
+}
+
+struct Quantizer(NeuQuant);
+
+impl ColorMap for Quantizer {
+    type Color = Rgba<u8>;
+
+    fn index_of(&self, color: &Rgba<u8>) -> usize {
+        self.0.index_of(&color.0)
+    }
+
+    fn map_color(&self, color: &mut Rgba<u8>) {
+        self.0.map_pixel(&mut color.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use image::{GenericImageView, RgbImage};
+
+    use super::*;
+
+    /// A horizontal grey ramp, so every column differs from its neighbours.
+    fn ramp(size: u32) -> DynamicImage {
+        RgbImage::from_fn(size, size, |x, _| {
+            let level = (x * 255 / (size - 1)) as u8;
+            image::Rgb([level, level, level])
+        })
+        .into()
+    }
+
+    fn distinct_colors(image: &DynamicImage) -> usize {
+        image
+            .pixels()
+            .map(|(_, _, pixel)| pixel.0)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    #[test]
+    fn styles_cycle_through_all_and_wrap() {
+        let mut style = ArtStyle::default();
+        let mut seen = vec![];
+        for _ in 0..ArtStyle::STYLES.len() {
+            seen.push(style.cache_key());
+            style = style.cycle(true);
+        }
+        assert_eq!(style, ArtStyle::default());
+        assert_eq!(seen.len(), seen.iter().collect::<HashSet<_>>().len());
+
+        assert_eq!(
+            ArtStyle::FullResolution.cycle(false),
+            ArtStyle::Pixelated { blocks: 90 }
+        );
+        assert_eq!(
+            ArtStyle::Pixelated { blocks: 120 }.adjust(true),
+            ArtStyle::Pixelated { blocks: 30 }
+        );
+        assert_eq!(
+            ArtStyle::Dithered { levels: 3 }.adjust(true),
+            ArtStyle::Dithered { levels: 2 }
+        );
+        assert_eq!(
+            ArtStyle::Dithered { levels: 2 }.detail().as_deref(),
+            Some("1-bit")
+        );
+    }
+
+    #[test]
+    fn every_style_renders_the_requested_square() {
+        let source = ramp(64);
+        for style in ArtStyle::STYLES {
+            let rendered = style.renderer().render(&source, 48);
+            assert_eq!(rendered.dimensions(), (48, 48), "{}", style);
+        }
+    }
+
+    #[test]
+    fn styles_reduce_detail_as_configured() {
+        let source = ramp(64);
+
+        let pixelated = Pixelated { blocks: 4 }.render(&source, 64);
+        assert!(distinct_colors(&pixelated) <= 4);
+        assert_eq!(pixelated.get_pixel(0, 0), pixelated.get_pixel(15, 63));
+
+        let one_bit = Dithered { levels: 2 }.render(&source, 64);
+        assert!(
+            one_bit
+                .to_luma8()
+                .pixels()
+                .all(|pixel| pixel.0[0] == 0 || pixel.0[0] == 255)
+        );
+        assert!(distinct_colors(&Dithered { levels: 4 }.render(&source, 64)) <= 4);
+
+        let palette = Palette { colors: 4 }.render(&source, 64);
+        assert!(distinct_colors(&palette) <= 4);
+    }
+
+    #[test]
+    fn renderings_are_cached_per_style_and_size() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = crate::artwork::ArtworkCache::new(dir.path().to_path_buf());
+        let key = crate::artwork::ArtworkKey::Release(1);
+        let style = ArtStyle::Dithered { levels: 2 };
+
+        assert!(cache.render(key, style, 32).expect("render").is_none());
+
+        let mut cover = Vec::new();
+        ramp(64)
+            .write_to(&mut Cursor::new(&mut cover), ImageFormat::Png)
+            .expect("encode");
+        cache.store(key, &cover).expect("store");
+
+        let first = cache
+            .render(key, style, 32)
+            .expect("render")
+            .expect("image");
+        cache
+            .render(key, ArtStyle::FullResolution, 32)
+            .expect("render");
+        cache.render(key, style, 48).expect("render");
+        let rendered = std::fs::read_dir(dir.path().join("rendered")).expect("rendered");
+        assert_eq!(rendered.count(), 3);
+
+        let again = cache
+            .render(key, style, 32)
+            .expect("render")
+            .expect("image");
+        assert_eq!(first, again);
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use rodio::{ChannelCount, Sample, SampleRate, Source, source::SeekError};
+
+/// Plays the part of a source between `start` and `end`, as if it were the whole thing.
+///
+/// Used for tracks a CUE sheet places within a larger file. Positions are relative to `start`
+/// both ways, so seeking and the sink's position behave as they would for a file of its own.
+pub struct Bounded<S: Source> {
+    input: S,
+    start: Duration,
+    /// Length of the section, `None` runs to the end of the input.
+    length: Option<Duration>,
+    /// Samples left to play, across all channels.
+    remaining: Option<u64>,
+}
+
+impl<S: Source> Bounded<S> {
+    pub fn new(mut input: S, start: Duration, end: Option<Duration>) -> Result<Self, SeekError> {
+        if !start.is_zero() {
+            input.try_seek(start)?;
+        }
+
+        let length = end.map(|end| end.saturating_sub(start));
+        let mut bounded = Self {
+            input,
+            start,
+            length,
+            remaining: None,
+        };
+        bounded.remaining = length.map(|length| bounded.samples(length));
+
+        Ok(bounded)
+    }
+
+    /// Whole frames' worth of samples in `duration`, so the section never ends mid-frame.
+    fn samples(&self, duration: Duration) -> u64 {
+        let frames = (duration.as_secs_f64() * f64::from(self.input.sample_rate())) as u64;
+        frames * u64::from(self.input.channels())
+    }
+}
+
+impl<S: Source> Iterator for Bounded<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.checked_sub(1)?;
+        }
+
+        self.input.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.input.size_hint();
+
+        match self.remaining {
+            Some(remaining) => (
+                lower.min(remaining as usize),
+                Some(upper.map_or(remaining as usize, |u| u.min(remaining as usize))),
+            ),
+            None => (lower, upper),
+        }
+    }
+}
+
+impl<S: Source> Source for Bounded<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        match (self.input.current_span_len(), self.remaining) {
+            (Some(len), Some(remaining)) => Some(len.min(remaining as usize)),
+            (None, Some(remaining)) => Some(remaining as usize),
+            (len, None) => len,
+        }
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.length.or_else(|| {
+            self.input
+                .total_duration()
+                .map(|total| total.saturating_sub(self.start))
+        })
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        let position = match self.length {
+            Some(length) => position.min(length),
+            None => position,
+        };
+
+        self.input.try_seek(self.start + position)?;
+        self.remaining = self
+            .length
+            .map(|length| self.samples(length.saturating_sub(position)));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    const RATE: u32 = 1_000;
+
+    /// Ten seconds of stereo, each sample holding its own index.
+    fn ramp() -> SamplesBuffer {
+        let samples: Vec<f32> = (0..RATE * 2 * 10).map(|i| i as f32).collect();
+        SamplesBuffer::new(2, RATE, samples)
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn plays_only_the_section() {
+        let mut bounded = Bounded::new(ramp(), secs(2), Some(secs(5))).unwrap();
+
+        assert_eq!(bounded.total_duration(), Some(secs(3)));
+        assert_eq!(bounded.next(), Some(4_000.0));
+        assert_eq!(bounded.count(), 3 * 2_000 - 1);
+    }
+
+    #[test]
+    fn runs_to_the_end_without_one() {
+        let bounded = Bounded::new(ramp(), secs(2), None).unwrap();
+
+        assert_eq!(bounded.total_duration(), Some(secs(8)));
+        assert_eq!(bounded.count(), 8 * 2_000);
+    }
+
+    #[test]
+    fn seeks_relative_to_the_start() {
+        let mut bounded = Bounded::new(ramp(), secs(2), Some(secs(5))).unwrap();
+
+        bounded.try_seek(secs(1)).unwrap();
+        assert_eq!(bounded.next(), Some(6_000.0));
+        assert_eq!(bounded.count(), 2 * 2_000 - 1);
+
+        // Past the end of the section is the end of it, not the rest of the file.
+        let mut bounded = Bounded::new(ramp(), secs(2), Some(secs(5))).unwrap();
+        bounded.try_seek(secs(9)).unwrap();
+        assert_eq!(bounded.next(), None);
+    }
+}
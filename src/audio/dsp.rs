@@ -0,0 +1,672 @@
+use std::{
+    f64::consts::PI,
+    fmt::Display,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{ChannelCount, Sample, SampleRate, Source, source::SeekError};
+use serde::{Deserialize, Serialize};
+
+/// Bands the equalizer runs at most, any more are ignored.
+pub const MAX_BANDS: usize = 10;
+/// Centres of the bands an equalizer starts with, an octave apart, the presets are given at
+/// these.
+const OCTAVES: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+/// Q of a band an octave wide.
+const OCTAVE_Q: f32 = 1.41;
+/// Frames played between checks for new settings.
+const CHECK_INTERVAL: usize = 512;
+
+/// Shape of an equalizer band's filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterKind {
+    LowShelf,
+    #[default]
+    Peaking,
+    HighShelf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EqBand {
+    #[serde(default)]
+    pub kind: FilterKind,
+    /// Centre of the band, or corner of a shelf, in Hz.
+    pub frequency: f32,
+    /// Boost or cut in dB.
+    pub gain: f32,
+    /// How narrow the band is, or how steep a shelf.
+    #[serde(default = "octave_q")]
+    pub q: f32,
+}
+
+fn octave_q() -> f32 {
+    OCTAVE_Q
+}
+
+/// Ready made equalizer curves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EqPreset {
+    #[default]
+    Flat,
+    Bass,
+    Treble,
+    Vocal,
+    Loudness,
+    /// Each band's own gain.
+    Custom,
+}
+
+impl Display for EqPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EqPreset::Flat => write!(f, "Flat"),
+            EqPreset::Bass => write!(f, "Bass"),
+            EqPreset::Treble => write!(f, "Treble"),
+            EqPreset::Vocal => write!(f, "Vocal"),
+            EqPreset::Loudness => write!(f, "Loudness"),
+            EqPreset::Custom => write!(f, "Custom"),
+        }
+    }
+}
+
+impl EqPreset {
+    pub const ALL: [EqPreset; 6] = [
+        EqPreset::Flat,
+        EqPreset::Bass,
+        EqPreset::Treble,
+        EqPreset::Vocal,
+        EqPreset::Loudness,
+        EqPreset::Custom,
+    ];
+
+    /// Gains in dB at each of the [`OCTAVES`], `None` for custom.
+    fn gains(&self) -> Option<[f32; 10]> {
+        match self {
+            EqPreset::Flat => Some([0.0; 10]),
+            EqPreset::Bass => Some([6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            EqPreset::Treble => Some([0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 4.0, 5.0, 6.0]),
+            EqPreset::Vocal => Some([-2.0, -2.0, -1.0, 0.0, 2.0, 3.0, 3.0, 2.0, 0.0, -1.0]),
+            EqPreset::Loudness => Some([5.0, 4.0, 2.0, 0.0, -1.0, -1.0, 0.0, 2.0, 4.0, 5.0]),
+            EqPreset::Custom => None,
+        }
+    }
+
+    /// The preset's gain at `frequency`, between the octaves it's given at.
+    fn gain_at(&self, frequency: f32) -> Option<f32> {
+        let gains = self.gains()?;
+        let octave = (frequency / OCTAVES[0])
+            .log2()
+            .clamp(0.0, (OCTAVES.len() - 1) as f32);
+        let below = octave.floor() as usize;
+        let above = (below + 1).min(OCTAVES.len() - 1);
+
+        Some(gains[below] + (gains[above] - gains[below]) * octave.fract())
+    }
+}
+
+/// How much of each channel is fed into the other, the levels of Bauer's stereophonic to
+/// binaural (bs2b) filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossfeedLevel {
+    #[default]
+    Light,
+    Medium,
+    Strong,
+}
+
+impl Display for CrossfeedLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CrossfeedLevel::Light => write!(f, "Light"),
+            CrossfeedLevel::Medium => write!(f, "Medium"),
+            CrossfeedLevel::Strong => write!(f, "Strong"),
+        }
+    }
+}
+
+impl CrossfeedLevel {
+    pub const ALL: [CrossfeedLevel; 3] = [
+        CrossfeedLevel::Light,
+        CrossfeedLevel::Medium,
+        CrossfeedLevel::Strong,
+    ];
+
+    /// Where the fed over channel is cut off, in Hz, and how far below the direct one it's fed
+    /// in, in dB.
+    fn cutoff_and_feed(&self) -> (f64, f64) {
+        match self {
+            CrossfeedLevel::Light => (700.0, 4.5),
+            CrossfeedLevel::Medium => (700.0, 6.0),
+            CrossfeedLevel::Strong => (650.0, 9.5),
+        }
+    }
+}
+
+/// What the audio goes through before it's played: a preamp, an equalizer and crossfeed for
+/// headphones, in that order, each of which can be bypassed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DspSettings {
+    pub preamp_enabled: bool,
+    /// Gain in dB, turned down to leave headroom for boosted bands.
+    pub preamp: f32,
+    pub equalizer_enabled: bool,
+    /// Where the band gains come from, custom takes each band's own.
+    pub preset: EqPreset,
+    /// Up to [`MAX_BANDS`] bands.
+    pub bands: Vec<EqBand>,
+    pub crossfeed_enabled: bool,
+    pub crossfeed: CrossfeedLevel,
+}
+
+impl Default for DspSettings {
+    fn default() -> Self {
+        Self {
+            preamp_enabled: false,
+            preamp: 0.0,
+            equalizer_enabled: false,
+            preset: EqPreset::default(),
+            bands: OCTAVES
+                .map(|frequency| EqBand {
+                    kind: FilterKind::Peaking,
+                    frequency,
+                    gain: 0.0,
+                    q: OCTAVE_Q,
+                })
+                .to_vec(),
+            crossfeed_enabled: false,
+            crossfeed: CrossfeedLevel::default(),
+        }
+    }
+}
+
+impl DspSettings {
+    /// The bands as they're played, with their gains from the preset unless it's custom.
+    pub fn current_bands(&self) -> Vec<EqBand> {
+        self.bands
+            .iter()
+            .take(MAX_BANDS)
+            .map(|band| EqBand {
+                gain: self.preset.gain_at(band.frequency).unwrap_or(band.gain),
+                ..*band
+            })
+            .collect()
+    }
+
+    /// Switches to a custom curve starting from the one playing, so a band can be changed by
+    /// hand.
+    pub fn customize(&mut self) {
+        for band in &mut self.bands {
+            band.gain = self.preset.gain_at(band.frequency).unwrap_or(band.gain);
+        }
+        self.preset = EqPreset::Custom;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    settings: Mutex<DspSettings>,
+    /// Bumped whenever the settings change.
+    version: AtomicU64,
+}
+
+/// Settings for the [`Chain`]s sharing it, picked up as they play.
+#[derive(Debug, Clone, Default)]
+pub struct Dsp {
+    shared: Arc<Shared>,
+}
+
+impl Dsp {
+    pub fn apply(&self, settings: &DspSettings) {
+        if let Ok(mut current) = self.shared.settings.lock() {
+            *current = settings.clone();
+        }
+        self.shared.version.fetch_add(1, Ordering::Release);
+    }
+
+    fn version(&self) -> u64 {
+        self.shared.version.load(Ordering::Acquire)
+    }
+
+    /// The settings, unless they're being changed right now.
+    fn settings(&self) -> Option<DspSettings> {
+        self.shared
+            .settings
+            .try_lock()
+            .ok()
+            .map(|settings| settings.clone())
+    }
+}
+
+/// Normalised coefficients of a second order filter, from the Audio EQ Cookbook.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Biquad {
+    fn new(band: &EqBand, sample_rate: SampleRate) -> Self {
+        let rate = f64::from(sample_rate);
+        let frequency = f64::from(band.frequency).clamp(10.0, rate * 0.45);
+        let (sin, cos) = (2.0 * PI * frequency / rate).sin_cos();
+        let a = 10f64.powf(f64::from(band.gain) / 40.0);
+        let alpha = sin / (2.0 * f64::from(band.q.max(0.1)));
+        let shelf = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match band.kind {
+            FilterKind::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            FilterKind::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos + shelf),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - shelf),
+                (a + 1.0) + (a - 1.0) * cos + shelf,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - shelf,
+            ),
+            FilterKind::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos + shelf),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - shelf),
+                (a + 1.0) - (a - 1.0) * cos + shelf,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - shelf,
+            ),
+        };
+
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+}
+
+/// The last two samples into and out of a [`Biquad`] on one channel.
+#[derive(Debug, Clone, Copy, Default)]
+struct History {
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl History {
+    fn process(&mut self, filter: &Biquad, x: f64) -> f64 {
+        let y = filter.b0 * x + filter.b1 * self.x1 + filter.b2 * self.x2
+            - filter.a1 * self.y1
+            - filter.a2 * self.y2;
+
+        (self.x2, self.x1) = (self.x1, x);
+        (self.y2, self.y1) = (self.y1, y);
+        y
+    }
+}
+
+/// Feeds each side of a stereo pair into the other through a low-pass, the way the far ear
+/// hears a speaker. It's the difference between the sides that's fed over, taking from one what
+/// it adds to the other, so sound common to both passes through untouched.
+#[derive(Debug, Clone)]
+struct Crossfeed {
+    level: CrossfeedLevel,
+    a0: f64,
+    b1: f64,
+    /// The low-passed difference fed into the left side, the right gets it inverted.
+    fed: f64,
+}
+
+impl Crossfeed {
+    fn new(level: CrossfeedLevel, sample_rate: SampleRate) -> Self {
+        let rate = f64::from(sample_rate);
+        let (cutoff, feed) = level.cutoff_and_feed();
+
+        // Below the cutoff a side played alone reaches the other `feed` dB down.
+        let ratio = 10f64.powf(-feed / 20.0);
+        let b1 = (-2.0 * PI * cutoff / rate).exp();
+
+        Self {
+            level,
+            a0: ratio / (1.0 + ratio) * (1.0 - b1),
+            b1,
+            fed: 0.0,
+        }
+    }
+
+    fn process(&mut self, left: f64, right: f64) -> (f64, f64) {
+        self.fed = self.a0 * (right - left) + self.b1 * self.fed;
+
+        (left + self.fed, right - self.fed)
+    }
+
+    fn reset(&mut self) {
+        self.fed = 0.0;
+    }
+}
+
+/// Runs a source through the preamp, equalizer and crossfeed set on a [`Dsp`], picking up
+/// changes to them as it plays.
+///
+/// The input has to keep to one format throughout, as a [`Stretch`](super::stretch::Stretch)
+/// does. It works on any source, so it can be rendered to a buffer away from the audio output.
+pub struct Chain<S: Source> {
+    input: S,
+    dsp: Dsp,
+    /// Version of the settings the stages were set up from.
+    version: u64,
+    channels: usize,
+    sample_rate: SampleRate,
+    /// Preamp gain as a factor, 1.0 while it's bypassed.
+    gain: f64,
+    filters: Vec<Biquad>,
+    /// Each filter's history on each channel, filter by filter.
+    history: Vec<History>,
+    /// Only for stereo.
+    crossfeed: Option<Crossfeed>,
+    /// The frame being played and how much of it has been.
+    frame: Vec<f32>,
+    played: usize,
+    until_check: usize,
+}
+
+impl<S: Source> Chain<S> {
+    pub fn new(input: S, dsp: Dsp) -> Self {
+        let channels = usize::from(input.channels().max(1));
+        let sample_rate = input.sample_rate();
+
+        let mut chain = Self {
+            input,
+            dsp,
+            version: 0,
+            channels,
+            sample_rate,
+            gain: 1.0,
+            filters: Vec::new(),
+            history: Vec::new(),
+            crossfeed: None,
+            frame: Vec::with_capacity(channels),
+            played: 0,
+            until_check: CHECK_INTERVAL,
+        };
+        chain.reload();
+
+        chain
+    }
+
+    /// Sets the stages up from the latest settings. The filters keep their history as long as
+    /// the number of bands stays the same, so adjusting one doesn't click.
+    fn reload(&mut self) {
+        let version = self.dsp.version();
+        let Some(settings) = self.dsp.settings() else {
+            return;
+        };
+        self.version = version;
+
+        self.gain = match settings.preamp_enabled {
+            true => 10f64.powf(f64::from(settings.preamp) / 20.0),
+            false => 1.0,
+        };
+
+        self.filters = match settings.equalizer_enabled {
+            true => settings
+                .current_bands()
+                .iter()
+                .map(|band| Biquad::new(band, self.sample_rate))
+                .collect(),
+            false => Vec::new(),
+        };
+        self.history
+            .resize(self.filters.len() * self.channels, History::default());
+
+        let level = Some(settings.crossfeed).filter(|_| settings.crossfeed_enabled);
+        match (level, self.channels) {
+            (Some(level), 2) if self.crossfeed.as_ref().map(|c| c.level) != Some(level) => {
+                self.crossfeed = Some(Crossfeed::new(level, self.sample_rate));
+            }
+            (Some(_), 2) => {}
+            _ => self.crossfeed = None,
+        }
+    }
+
+    /// Reads the next frame of input and runs it through each stage, `false` once the input has
+    /// run out.
+    fn step(&mut self) -> bool {
+        if self.until_check == 0 {
+            if self.dsp.version() != self.version {
+                self.reload();
+            }
+            self.until_check = CHECK_INTERVAL;
+        }
+        self.until_check -= 1;
+
+        let Some(first) = self.input.next() else {
+            return false;
+        };
+        self.frame.clear();
+        self.frame.push(first);
+        for _ in 1..self.channels {
+            self.frame.push(self.input.next().unwrap_or(0.0));
+        }
+
+        for (channel, sample) in self.frame.iter_mut().enumerate() {
+            let history = self.history.iter_mut().skip(channel).step_by(self.channels);
+
+            *sample = self
+                .filters
+                .iter()
+                .zip(history)
+                .fold(f64::from(*sample) * self.gain, |x, (filter, history)| {
+                    history.process(filter, x)
+                }) as f32;
+        }
+
+        if let Some(crossfeed) = &mut self.crossfeed {
+            let (left, right) =
+                crossfeed.process(f64::from(self.frame[0]), f64::from(self.frame[1]));
+            self.frame[0] = left as f32;
+            self.frame[1] = right as f32;
+        }
+
+        self.played = 0;
+        true
+    }
+}
+
+impl<S: Source> Iterator for Chain<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.played >= self.frame.len() && !self.step() {
+            return None;
+        }
+
+        let sample = self.frame[self.played];
+        self.played += 1;
+
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Chain<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(position)?;
+
+        self.frame.clear();
+        self.played = 0;
+        self.history.fill(History::default());
+        if let Some(crossfeed) = &mut self.crossfeed {
+            crossfeed.reset();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    const RATE: SampleRate = 48_000;
+
+    /// A second of a sine at `frequency`, on the left and right as given.
+    fn sine(frequency: f64, left: f32, right: f32) -> Vec<f32> {
+        (0..RATE)
+            .flat_map(|i| {
+                let x = (2.0 * PI * frequency * f64::from(i) / f64::from(RATE)).sin() as f32;
+                [x * left, x * right]
+            })
+            .collect()
+    }
+
+    fn render(samples: &[f32], settings: DspSettings) -> Vec<f32> {
+        let dsp = Dsp::default();
+        dsp.apply(&settings);
+
+        Chain::new(SamplesBuffer::new(2, RATE, samples.to_vec()), dsp).collect()
+    }
+
+    /// Level of one channel in dB, leaving out the first half while the filters settle.
+    fn loudness(samples: &[f32], channel: usize) -> f64 {
+        let settled: Vec<f64> = samples
+            .iter()
+            .skip(samples.len() / 2 + channel)
+            .step_by(2)
+            .map(|x| f64::from(*x))
+            .collect();
+        let rms = (settled.iter().map(|x| x * x).sum::<f64>() / settled.len() as f64).sqrt();
+
+        20.0 * rms.log10()
+    }
+
+    #[test]
+    fn bypass_is_bit_identical() {
+        let samples = sine(440.0, 0.8, -0.3);
+        let settings = DspSettings {
+            preamp: -6.0,
+            preset: EqPreset::Loudness,
+            ..Default::default()
+        };
+
+        assert_eq!(render(&samples, settings), samples);
+    }
+
+    #[test]
+    fn preamp_gain() {
+        let samples = sine(440.0, 0.8, 0.8);
+        let rendered = render(
+            &samples,
+            DspSettings {
+                preamp_enabled: true,
+                preamp: -6.0,
+                ..Default::default()
+            },
+        );
+
+        let gain = 10f32.powf(-6.0 / 20.0);
+        for (out, sample) in rendered.iter().zip(&samples) {
+            assert!((out - sample * gain).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn peaking_band_at_its_centre() {
+        let samples = sine(1000.0, 0.25, 0.25);
+
+        for gain in [6.0, -9.0] {
+            let rendered = render(
+                &samples,
+                DspSettings {
+                    equalizer_enabled: true,
+                    preset: EqPreset::Custom,
+                    bands: vec![EqBand {
+                        kind: FilterKind::Peaking,
+                        frequency: 1000.0,
+                        gain,
+                        q: OCTAVE_Q,
+                    }],
+                    ..Default::default()
+                },
+            );
+
+            let boost = loudness(&rendered, 0) - loudness(&samples, 0);
+            assert!((boost - f64::from(gain)).abs() < 0.05, "{} dB", boost);
+        }
+    }
+
+    #[test]
+    fn crossfeed_leaves_mono_at_unity() {
+        for level in CrossfeedLevel::ALL {
+            for frequency in [50.0, 700.0, 8000.0] {
+                let samples = sine(frequency, 0.5, 0.5);
+                let rendered = render(
+                    &samples,
+                    DspSettings {
+                        crossfeed_enabled: true,
+                        crossfeed: level,
+                        ..Default::default()
+                    },
+                );
+
+                assert_eq!(rendered, samples);
+            }
+        }
+    }
+
+    #[test]
+    fn crossfeed_feeds_the_other_side() {
+        let samples = sine(50.0, 0.5, 0.0);
+
+        for level in CrossfeedLevel::ALL {
+            let rendered = render(
+                &samples,
+                DspSettings {
+                    crossfeed_enabled: true,
+                    crossfeed: level,
+                    ..Default::default()
+                },
+            );
+
+            let (_, feed) = level.cutoff_and_feed();
+            let difference = loudness(&rendered, 0) - loudness(&rendered, 1);
+            assert!((difference - feed).abs() < 0.2, "{} dB", difference);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    f32::consts::FRAC_PI_2,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{ChannelCount, Sample, SampleRate, Source, source::SeekError};
+
+#[derive(Debug)]
+struct Shared {
+    /// Length of the fade out asked for in microseconds, `u64::MAX` while there's none.
+    fade_out: AtomicU64,
+    /// Set once the fade out has finished, everything sharing it is silent from then on.
+    finished: AtomicBool,
+}
+
+impl Default for Shared {
+    fn default() -> Self {
+        Self {
+            fade_out: AtomicU64::new(u64::MAX),
+            finished: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Tells the [`Fade`]s sharing it when to fade out.
+#[derive(Debug, Clone, Default)]
+pub struct Envelope {
+    shared: Arc<Shared>,
+}
+
+impl Envelope {
+    /// Fades out whatever is playing over `length`, starting now.
+    pub fn fade_out(&self, length: Duration) {
+        self.shared
+            .fade_out
+            .store(length.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn requested(&self) -> Option<Duration> {
+        match self.shared.fade_out.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.shared.finished.load(Ordering::Relaxed)
+    }
+}
+
+/// Fades a source in from its start and out once its [`Envelope`] says to, along equal power
+/// curves so the loudness holds steady while one track fades into another. The source ends
+/// when the fade out does.
+pub struct Fade<S: Source> {
+    input: S,
+    envelope: Envelope,
+    channels: usize,
+    sample_rate: SampleRate,
+    /// Frames played so far, and the position within the one playing.
+    frame: u64,
+    channel: usize,
+    /// Length of the fade in, in frames.
+    fade_in: u64,
+    /// Frame the fade out started at and how many it lasts.
+    fade_out: Option<(u64, u64)>,
+    gain: f32,
+}
+
+impl<S: Source> Fade<S> {
+    pub fn new(input: S, envelope: Envelope, fade_in: Option<Duration>) -> Self {
+        let channels = usize::from(input.channels().max(1));
+        let sample_rate = input.sample_rate();
+
+        let mut fade = Self {
+            input,
+            envelope,
+            channels,
+            sample_rate,
+            frame: 0,
+            channel: 0,
+            fade_in: 0,
+            fade_out: None,
+            gain: 1.0,
+        };
+        fade.fade_in = fade_in.map_or(0, |length| fade.frames(length));
+
+        fade
+    }
+
+    fn frames(&self, length: Duration) -> u64 {
+        (length.as_secs_f64() * f64::from(self.sample_rate)) as u64
+    }
+
+    /// The gain for the frame about to play, `None` once it's faded out.
+    fn next_gain(&mut self) -> Option<f32> {
+        if self.envelope.is_finished() {
+            return None;
+        }
+
+        if self.fade_out.is_none()
+            && let Some(length) = self.envelope.requested()
+        {
+            self.fade_out = Some((self.frame, self.frames(length).max(1)));
+        }
+
+        let fade_in = match self.frame < self.fade_in {
+            true => (FRAC_PI_2 * self.frame as f32 / self.fade_in as f32).sin(),
+            false => 1.0,
+        };
+        let fade_out = match self.fade_out {
+            Some((start, length)) if self.frame - start >= length => {
+                self.envelope.shared.finished.store(true, Ordering::Relaxed);
+                return None;
+            }
+            Some((start, length)) => {
+                (FRAC_PI_2 * (self.frame - start) as f32 / length as f32).cos()
+            }
+            None => 1.0,
+        };
+
+        Some(fade_in * fade_out)
+    }
+}
+
+impl<S: Source> Iterator for Fade<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.channel == 0 {
+            self.gain = self.next_gain()?;
+        }
+
+        let sample = self.input.next()?;
+
+        self.channel += 1;
+        if self.channel == self.channels {
+            self.channel = 0;
+            self.frame += 1;
+        }
+
+        Some(sample * self.gain)
+    }
+}
+
+impl<S: Source> Source for Fade<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    /// Moves the input, the fades carry on from where they were.
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    const RATE: u32 = 100;
+
+    /// `seconds` of full scale stereo at a rate where a frame is 10ms.
+    fn ones(seconds: u32) -> SamplesBuffer {
+        SamplesBuffer::new(2, RATE, vec![1.0; (RATE * seconds * 2) as usize])
+    }
+
+    #[test]
+    fn fades_in_along_a_quarter_sine() {
+        let samples: Vec<f32> =
+            Fade::new(ones(2), Envelope::default(), Some(Duration::from_secs(1))).collect();
+
+        assert_eq!(samples.len(), (RATE * 2 * 2) as usize);
+        for (frame, pair) in samples.chunks(2).enumerate() {
+            let expected = match frame < RATE as usize {
+                true => (FRAC_PI_2 * frame as f32 / RATE as f32).sin(),
+                false => 1.0,
+            };
+            assert!((pair[0] - expected).abs() < 1e-6, "frame {}", frame);
+            assert_eq!(pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn a_crossfade_keeps_the_power_steady() {
+        let envelope = Envelope::default();
+        let mut outgoing = Fade::new(ones(5), envelope.clone(), None);
+        // Some of the outgoing track plays before the crossfade starts.
+        assert!(outgoing.by_ref().take(20).all(|sample| sample == 1.0));
+
+        envelope.fade_out(Duration::from_secs(1));
+        let incoming = Fade::new(ones(5), Envelope::default(), Some(Duration::from_secs(1)));
+        let mixed: Vec<(f32, f32)> = outgoing.zip(incoming).step_by(2).collect();
+
+        assert_eq!(mixed.len(), RATE as usize);
+        for (out, into) in mixed {
+            assert!((out * out + into * into - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn a_faded_out_envelope_ends_every_source_sharing_it() {
+        let envelope = Envelope::default();
+        let mut first = Fade::new(ones(5), envelope.clone(), None);
+        let mut second = Fade::new(ones(5), envelope.clone(), None);
+
+        envelope.fade_out(Duration::ZERO);
+        assert_eq!(first.next(), Some(1.0));
+        assert_eq!(first.next(), Some(1.0));
+        assert_eq!(first.next(), None);
+        assert!(envelope.is_finished());
+        assert_eq!(second.next(), None);
+    }
+}
@@ -1,49 +1,201 @@
-use rodio::{Decoder, OutputStream, OutputStreamBuilder, Sink};
+use rodio::{
+    Decoder, OutputStream, OutputStreamBuilder, Sink, decoder::DecoderError, mixer::Mixer,
+    source::SeekError,
+};
 use std::fs::File;
 use std::io::BufReader;
-use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
 
-pub struct AudioHandle {
+use crate::{
+    audio::{
+        bounds::Bounded,
+        dsp::{Chain, Dsp},
+        fade::{Envelope, Fade},
+        stretch::{Stretch, Tempo},
+        tap::{SampleTap, Tap},
+    },
+    model::track::Track,
+};
+
+pub mod bounds;
+pub mod dsp;
+pub mod fade;
+pub mod stretch;
+pub mod tap;
+
+#[derive(Debug, Error)]
+pub enum AudioError {
+    #[error("unable to open track: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("unable to decode track: {0}")]
+    Decoder(#[from] DecoderError),
+    #[error("unable to seek to the start of the track: {0}")]
+    Seek(#[from] SeekError),
+}
+
+/// A sink and what the sources queued on it share: their speed and position, the samples for
+/// the visualizer and when they fade out.
+struct Deck {
     sink: Sink,
-    _stream: OutputStream, // must keep alive
+    tempo: Tempo,
+    tap: SampleTap,
+    envelope: Envelope,
+}
+
+impl Deck {
+    fn new(mixer: &Mixer) -> Self {
+        Self {
+            sink: Sink::connect_new(mixer),
+            tempo: Tempo::default(),
+            tap: SampleTap::default(),
+            envelope: Envelope::default(),
+        }
+    }
+
+    /// Queues `track`, fading it in over `fade_in` when given.
+    fn append(
+        &self,
+        track: &Track,
+        dsp: &Dsp,
+        fade_in: Option<Duration>,
+    ) -> Result<(), AudioError> {
+        let file = File::open(&track.path)?;
+        let source = Decoder::new(BufReader::new(file))?;
+        let source = Bounded::new(source, track.start.unwrap_or_default(), track.end)?;
+
+        let source = Stretch::new(source, self.tempo.clone());
+        let source = Chain::new(source, dsp.clone());
+        let source = Fade::new(source, self.envelope.clone(), fade_in);
+
+        self.sink.append(Tap::new(source, self.tap.clone()));
+        Ok(())
+    }
+}
+
+pub struct AudioHandle {
+    /// What's playing, everything but the fade out of a crossfade.
+    deck: Deck,
+    /// Tracks fading out under the one that took over from them, more than one when a crossfade
+    /// starts before the last has finished. Dropping a sink stops it, so they're kept until
+    /// they've played out.
+    fading: Vec<Deck>,
+    dsp: Dsp,
+    stream: OutputStream, // must keep alive
+}
+
+impl Default for AudioHandle {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AudioHandle {
     pub fn new() -> Self {
         let stream = OutputStreamBuilder::open_default_stream().unwrap();
-        let sink = Sink::connect_new(&stream.mixer());
 
         Self {
-            sink,
-            _stream: stream,
+            deck: Deck::new(stream.mixer()),
+            fading: Vec::new(),
+            dsp: Dsp::default(),
+            stream,
         }
     }
 
-    pub fn load(&self, path: &PathBuf) {
-        self.sink.stop();
-        let file = File::open(path).unwrap();
-        let source = Decoder::new(BufReader::new(file)).unwrap();
-        self.sink.append(source);
-        self.sink.pause();
+    pub fn load(&self, track: &Track) -> Result<(), AudioError> {
+        self.stop();
+        self.deck.tempo.set_position(Duration::ZERO);
+        self.enqueue(track)?;
+        self.deck.sink.pause();
+
+        Ok(())
+    }
+
+    /// Queues `track` to start the moment the one playing ends, with no gap between them.
+    pub fn enqueue(&self, track: &Track) -> Result<(), AudioError> {
+        self.deck.append(track, &self.dsp, None)
+    }
+
+    /// Starts `track` over the end of the one playing, one fading out as the other fades in
+    /// over `length`, both mixed into the same output. It takes over as what's playing at
+    /// once.
+    pub fn crossfade(&mut self, track: &Track, length: Duration) -> Result<(), AudioError> {
+        let deck = Deck::new(self.stream.mixer());
+        deck.sink.set_volume(self.deck.sink.volume());
+        deck.tempo.set_speed(self.deck.tempo.speed());
+        deck.tap.set_enabled(self.deck.tap.is_enabled());
+        deck.append(track, &self.dsp, Some(length))?;
+
+        self.deck.envelope.fade_out(length);
+        self.deck.tap.set_enabled(false);
+        self.fading.retain(|fading| !fading.sink.empty());
+        self.fading.push(std::mem::replace(&mut self.deck, deck));
+
+        Ok(())
+    }
+
+    /// Number of tracks loaded which haven't finished, including the one playing.
+    pub fn queued(&self) -> usize {
+        self.deck.sink.len()
+    }
+
+    /// Samples as they're decoded, for the visualizer.
+    pub fn tap(&self) -> &SampleTap {
+        &self.deck.tap
+    }
+
+    /// The preamp, equalizer and crossfeed everything loaded plays through.
+    pub fn dsp(&self) -> &Dsp {
+        &self.dsp
+    }
+
+    /// The sinks playing, those fading out included.
+    fn sinks(&self) -> impl Iterator<Item = &Sink> {
+        std::iter::once(&self.deck.sink).chain(self.fading.iter().map(|deck| &deck.sink))
     }
 
     pub fn play(&self) {
-        self.sink.play();
+        self.sinks().for_each(Sink::play);
     }
 
     pub fn pause(&self) {
-        self.sink.pause();
+        self.sinks().for_each(Sink::pause);
     }
 
     pub fn stop(&self) {
-        self.sink.stop();
+        self.sinks().for_each(Sink::stop);
+    }
+
+    /// Scales the loudness, 1.0 plays as decoded.
+    pub fn set_volume(&self, volume: f32) {
+        self.sinks().for_each(|sink| sink.set_volume(volume));
     }
 
     pub fn is_paused(&self) -> bool {
-        self.sink.is_paused()
+        self.deck.sink.is_paused()
+    }
+
+    /// Whether everything loaded has been played.
+    pub fn is_finished(&self) -> bool {
+        self.deck.sink.empty()
+    }
+
+    /// Where playback is within the track, which runs at the track's own pace whatever the
+    /// speed.
+    pub fn position(&self) -> Duration {
+        self.deck.tempo.position()
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.deck.tempo.speed()
+    }
+
+    /// Plays faster or slower without changing pitch, queued tracks play at the same speed.
+    pub fn set_speed(&self, speed: f32) {
+        self.deck.tempo.set_speed(speed);
     }
 
-    pub fn position(&self) -> std::time::Duration {
-        self.sink.get_pos()
+    pub fn seek(&self, position: Duration) -> Result<(), SeekError> {
+        self.deck.sink.try_seek(position)
     }
 }
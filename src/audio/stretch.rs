@@ -0,0 +1,438 @@
+use std::{
+    f32::consts::PI,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{ChannelCount, Sample, SampleRate, Source, source::SeekError};
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 2.0;
+/// Length of the overlapping frames the input is cut into, long enough to hold a few periods of
+/// a low voice.
+const FRAME: Duration = Duration::from_millis(40);
+/// How far a frame may be moved from where the speed puts it so it lines up with the last one.
+const TOLERANCE: Duration = Duration::from_millis(10);
+/// Lining frames up first tries offsets this far apart, comparing every this many samples, then
+/// refines the best of them one offset at a time. That's a small part of what trying every
+/// offset costs, which keeps it cheap enough for the audio thread on a slow CPU.
+const COARSE_STEP: usize = 8;
+/// Samples compared while refining are spaced out by this.
+const FINE_STEP: usize = 2;
+
+#[derive(Debug)]
+struct Shared {
+    /// The speed as the bits of an `f32`.
+    speed: AtomicU32,
+    /// Where playback is within the track, in microseconds.
+    position: AtomicU64,
+}
+
+impl Default for Shared {
+    fn default() -> Self {
+        Self {
+            speed: AtomicU32::new(1.0f32.to_bits()),
+            position: AtomicU64::new(0),
+        }
+    }
+}
+
+/// How fast the sources sharing it play, and where the one playing is within its track.
+///
+/// The position is in the track's own time, so it's the same whatever the speed.
+#[derive(Debug, Clone, Default)]
+pub struct Tempo {
+    shared: Arc<Shared>,
+}
+
+impl Tempo {
+    pub fn speed(&self) -> f32 {
+        f32::from_bits(self.shared.speed.load(Ordering::Relaxed))
+    }
+
+    /// Changes the speed, kept between [`MIN_SPEED`] and [`MAX_SPEED`], from the next frame on.
+    pub fn set_speed(&self, speed: f32) {
+        self.shared.speed.store(
+            speed.clamp(MIN_SPEED, MAX_SPEED).to_bits(),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub fn position(&self) -> Duration {
+        Duration::from_micros(self.shared.position.load(Ordering::Relaxed))
+    }
+
+    pub fn set_position(&self, position: Duration) {
+        self.shared
+            .position
+            .store(position.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Plays a source faster or slower without changing its pitch, by waveform similarity overlap-add
+/// (WSOLA).
+///
+/// The input is cut into overlapping frames which are spaced out or squeezed together by the
+/// speed, each nudged to where it best continues the one before so the waveform stays smooth,
+/// then cross-faded back together. At normal speed each frame simply follows on from the last,
+/// so the input comes out unchanged.
+pub struct Stretch<S: Source> {
+    input: S,
+    tempo: Tempo,
+    channels: usize,
+    sample_rate: SampleRate,
+    /// Hann window a frame long, two of them half a frame apart add up to one.
+    window: Vec<f32>,
+    /// Half a frame, in frames of samples.
+    hop: usize,
+    tolerance: usize,
+    /// Decoded input, interleaved, starting at frame `base`.
+    buffer: Vec<f32>,
+    base: u64,
+    /// Length of the input in frames, once it's run out.
+    end: Option<u64>,
+    /// Where the speed puts the next frame.
+    analysis: f64,
+    /// Where the last frame was taken from, `None` when starting afresh.
+    previous: Option<u64>,
+    /// Frames being added together, interleaved, one frame long.
+    overlap: Vec<f32>,
+    /// The channels mixed together while lining frames up, kept to not allocate every frame.
+    scratch: Vec<f32>,
+    /// Finished samples and how many of them have been played.
+    output: Vec<f32>,
+    played: usize,
+    /// Where in the input `output` starts and how far the input moves on per frame of it.
+    output_start: f64,
+    output_speed: f64,
+}
+
+impl<S: Source> Stretch<S> {
+    pub fn new(input: S, tempo: Tempo) -> Self {
+        let channels = usize::from(input.channels().max(1));
+        let sample_rate = input.sample_rate();
+        let hop = (FRAME.as_secs_f64() * f64::from(sample_rate) / 2.0) as usize;
+        let window = (0..hop * 2)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / (hop * 2) as f32).cos())
+            .collect();
+
+        Self {
+            input,
+            tempo,
+            channels,
+            sample_rate,
+            window,
+            hop,
+            tolerance: (TOLERANCE.as_secs_f64() * f64::from(sample_rate)) as usize,
+            buffer: Vec::new(),
+            base: 0,
+            end: None,
+            analysis: 0.0,
+            previous: None,
+            overlap: vec![0.0; hop * 2 * channels],
+            scratch: Vec::new(),
+            output: Vec::new(),
+            played: 0,
+            output_start: 0.0,
+            output_speed: 1.0,
+        }
+    }
+
+    /// Starts afresh from frame `start` of the input.
+    fn reset(&mut self, start: u64) {
+        self.buffer.clear();
+        self.base = start;
+        self.end = None;
+        self.analysis = start as f64;
+        self.previous = None;
+        self.overlap.fill(0.0);
+        self.output.clear();
+        self.played = 0;
+    }
+
+    /// A sample of the input, silence outside of what's been decoded.
+    fn sample(&self, frame: u64, channel: usize) -> f32 {
+        frame
+            .checked_sub(self.base)
+            .and_then(|offset| self.buffer.get(offset as usize * self.channels + channel))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// The channels of a frame of input mixed together, for lining frames up.
+    fn mono(&self, frame: u64) -> f32 {
+        (0..self.channels).map(|c| self.sample(frame, c)).sum()
+    }
+
+    /// The frame after the last one decoded.
+    fn decoded_end(&self) -> u64 {
+        self.base + (self.buffer.len() / self.channels) as u64
+    }
+
+    /// Decodes input up to frame `until`, or as far as it goes.
+    fn fill(&mut self, until: u64) {
+        while self.end.is_none() && self.decoded_end() < until {
+            let Some(first) = self.input.next() else {
+                self.end = Some(self.decoded_end());
+                break;
+            };
+
+            self.buffer.push(first);
+            for _ in 1..self.channels {
+                self.buffer.push(self.input.next().unwrap_or(0.0));
+            }
+        }
+    }
+
+    /// Forgets the input before frame `frame`.
+    fn discard(&mut self, frame: u64) {
+        let frames = frame.min(self.decoded_end()).saturating_sub(self.base);
+
+        self.buffer.drain(..frames as usize * self.channels);
+        self.base += frames;
+    }
+
+    /// Where in `lo..=hi` a frame best continues the waveform from `target` on, going by the
+    /// half frame they overlap in.
+    fn best_match(&mut self, lo: u64, hi: u64, target: u64) -> u64 {
+        let hop = self.hop;
+        let span = (hi - lo) as usize;
+
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        scratch.extend((target..target + hop as u64).map(|frame| self.mono(frame)));
+        scratch.extend((lo..=hi + hop as u64).map(|frame| self.mono(frame)));
+        let (target, candidates) = scratch.split_at(hop);
+
+        let score = |offset: usize, step: usize| {
+            let (mut correlation, mut energy) = (0.0, 0.0);
+            for i in (0..hop).step_by(step) {
+                let sample = candidates[offset + i];
+                correlation += sample * target[i];
+                energy += sample * sample;
+            }
+            correlation / (energy + f32::EPSILON).sqrt()
+        };
+
+        let coarse = best_of((0..=span).step_by(COARSE_STEP), |offset| {
+            score(offset, COARSE_STEP)
+        });
+        let fine = best_of(
+            coarse.saturating_sub(COARSE_STEP)..=(coarse + COARSE_STEP).min(span),
+            |offset| score(offset, FINE_STEP),
+        );
+
+        self.scratch = scratch;
+        lo + fine as u64
+    }
+
+    /// Adds the next frame in and finishes the half frame before it, `false` once the input
+    /// has run out.
+    fn step(&mut self) -> bool {
+        let speed = f64::from(self.tempo.speed());
+        let (len, hop, channels) = (self.window.len(), self.hop, self.channels);
+        let nominal = self.analysis.round() as u64;
+
+        if self.end.is_some_and(|end| nominal >= end) {
+            return false;
+        }
+
+        let start = match self.previous {
+            // Nothing came before, so fade in from a frame's worth of the input behind it.
+            None => {
+                self.fill(nominal + len as u64);
+                for i in 0..hop {
+                    for c in 0..channels {
+                        self.overlap[i * channels + c] =
+                            self.sample(nominal + i as u64, c) * self.window[hop + i];
+                    }
+                }
+                nominal
+            }
+            // Following straight on from the last frame is a perfect match.
+            Some(previous) if speed == 1.0 => {
+                self.fill(previous + (hop + len) as u64);
+                previous + hop as u64
+            }
+            Some(previous) => {
+                let target = previous + hop as u64;
+                let lo = nominal.saturating_sub(self.tolerance as u64);
+                let hi = nominal + self.tolerance as u64;
+
+                self.discard(lo.min(target));
+                self.fill(hi.max(target) + len as u64);
+                self.best_match(lo.max(self.base), hi, target)
+            }
+        };
+
+        for i in 0..len {
+            for c in 0..channels {
+                self.overlap[i * channels + c] += self.sample(start + i as u64, c) * self.window[i];
+            }
+        }
+
+        self.output.clear();
+        self.output.extend(self.overlap.drain(..hop * channels));
+        self.overlap.resize(len * channels, 0.0);
+        self.played = 0;
+        self.output_start = self.analysis;
+        self.output_speed = speed;
+
+        self.previous = Some(start);
+        self.analysis += hop as f64 * speed;
+        if speed == 1.0 {
+            self.discard(start + hop as u64);
+        }
+
+        true
+    }
+}
+
+impl<S: Source> Iterator for Stretch<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.played >= self.output.len() && !self.step() {
+            return None;
+        }
+
+        if self.played.is_multiple_of(self.channels) {
+            let frame =
+                self.output_start + (self.played / self.channels) as f64 * self.output_speed;
+            let frame = match self.end {
+                Some(end) => frame.min(end as f64),
+                None => frame,
+            };
+            self.tempo
+                .set_position(Duration::from_secs_f64(frame / f64::from(self.sample_rate)));
+        }
+
+        let sample = self.output[self.played];
+        self.played += 1;
+
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Stretch<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Unknown, it depends on the speed, which may change as it plays.
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(position)?;
+        self.reset((position.as_secs_f64() * f64::from(self.sample_rate)).round() as u64);
+        self.tempo.set_position(position);
+
+        Ok(())
+    }
+}
+
+/// The offset that scores highest, the first of them on a tie.
+fn best_of(offsets: impl Iterator<Item = usize>, score: impl Fn(usize) -> f32) -> usize {
+    offsets
+        .map(|offset| (score(offset), offset))
+        .fold((f32::MIN, 0), |best, next| match next.0 > best.0 {
+            true => next,
+            false => best,
+        })
+        .1
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    const RATE: u32 = 8_000;
+    const TONE: f32 = 440.0;
+
+    /// Two seconds of a mono sine.
+    fn sine() -> SamplesBuffer {
+        let samples: Vec<f32> = (0..RATE * 2)
+            .map(|i| (2.0 * PI * TONE * i as f32 / RATE as f32).sin() * 0.5)
+            .collect();
+        SamplesBuffer::new(1, RATE, samples)
+    }
+
+    fn render(speed: f32) -> Vec<f32> {
+        let tempo = Tempo::default();
+        tempo.set_speed(speed);
+        Stretch::new(sine(), tempo).collect()
+    }
+
+    /// The frequency of a tone, from how often it crosses zero on the way up.
+    fn frequency(samples: &[f32]) -> f32 {
+        let crossings = samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count();
+        crossings as f32 * RATE as f32 / samples.len() as f32
+    }
+
+    #[test]
+    fn normal_speed_plays_the_input_unchanged() {
+        let input: Vec<f32> = sine().collect();
+        let output = render(1.0);
+
+        assert!(output.len().abs_diff(input.len()) <= RATE as usize / 50);
+        for (i, (a, b)) in input.iter().zip(&output).enumerate() {
+            assert!((a - b).abs() < 1e-4, "sample {i}: {a} != {b}");
+        }
+    }
+
+    #[test]
+    fn length_scales_with_speed_and_pitch_does_not() {
+        let input = (RATE * 2) as f32;
+        let frame = RATE as f32 * FRAME.as_secs_f32();
+
+        for speed in [0.5, 2.0] {
+            let output = render(speed);
+
+            let expected = input / speed;
+            assert!(
+                (output.len() as f32 - expected).abs() <= frame,
+                "{speed}x: {} samples, expected {expected}",
+                output.len()
+            );
+
+            let tone = frequency(&output[frame as usize..output.len() - frame as usize]);
+            assert!((tone - TONE).abs() < 10.0, "{speed}x: {tone} Hz");
+        }
+    }
+
+    #[test]
+    fn position_is_in_track_time_after_a_seek() {
+        let tempo = Tempo::default();
+        tempo.set_speed(2.0);
+        let mut stretch = Stretch::new(sine(), tempo.clone());
+
+        stretch.try_seek(Duration::from_millis(500)).unwrap();
+        assert_eq!(tempo.position(), Duration::from_millis(500));
+
+        // A tenth of a second played at twice the speed is a fifth of one of the track.
+        stretch.by_ref().take(RATE as usize / 10).for_each(drop);
+        let position = tempo.position().as_secs_f32();
+        assert!((position - 0.7).abs() < 0.005, "at {position}s");
+
+        stretch.by_ref().for_each(drop);
+        assert_eq!(tempo.position(), Duration::from_secs(2));
+    }
+}
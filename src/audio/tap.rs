@@ -0,0 +1,196 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{ChannelCount, Sample, SampleRate, Source, source::SeekError};
+
+/// Mono samples kept for readers, enough for one analysis window.
+const CAPACITY: usize = 2048;
+/// Frames gathered before taking the lock, so the audio thread rarely touches it.
+const CHUNK: usize = 256;
+
+#[derive(Debug, Default)]
+struct Shared {
+    enabled: AtomicBool,
+    sample_rate: AtomicU32,
+    samples: Mutex<VecDeque<f32>>,
+}
+
+/// The most recently decoded samples, mixed down to mono, for anything drawing the audio.
+///
+/// Nothing is copied until the tap is enabled.
+#[derive(Debug, Clone, Default)]
+pub struct SampleTap {
+    shared: Arc<Shared>,
+}
+
+impl SampleTap {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.shared.enabled.store(enabled, Ordering::Relaxed);
+
+        if !enabled && let Ok(mut samples) = self.shared.samples.lock() {
+            samples.clear();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.shared.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.shared.sample_rate.load(Ordering::Relaxed)
+    }
+
+    /// The latest `len` samples, oldest first, once that many have been decoded.
+    pub fn latest(&self, len: usize) -> Option<Vec<f32>> {
+        let samples = self.shared.samples.lock().ok()?;
+        let start = samples.len().checked_sub(len)?;
+
+        Some(samples.range(start..).copied().collect())
+    }
+}
+
+/// Passes a source through unchanged while copying its samples into a [`SampleTap`].
+pub struct Tap<S> {
+    input: S,
+    tap: SampleTap,
+    /// Position within the current frame.
+    channel: ChannelCount,
+    frame: f32,
+    pending: Vec<f32>,
+}
+
+impl<S: Source> Tap<S> {
+    pub fn new(input: S, tap: SampleTap) -> Self {
+        Self {
+            input,
+            tap,
+            channel: 0,
+            frame: 0.0,
+            pending: Vec::with_capacity(CHUNK),
+        }
+    }
+
+    fn flush(&mut self) {
+        self.tap
+            .shared
+            .sample_rate
+            .store(self.input.sample_rate(), Ordering::Relaxed);
+
+        // Skip a chunk rather than hold up playback while a reader has the lock.
+        if let Ok(mut samples) = self.tap.shared.samples.try_lock() {
+            samples.extend(self.pending.drain(..));
+
+            let excess = samples.len().saturating_sub(CAPACITY);
+            samples.drain(..excess);
+        }
+
+        self.pending.clear();
+    }
+}
+
+impl<S: Source> Iterator for Tap<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        let sample = self.input.next()?;
+
+        if !self.tap.is_enabled() {
+            return Some(sample);
+        }
+
+        let channels = self.input.channels().max(1);
+        self.frame += sample;
+        self.channel += 1;
+
+        if self.channel >= channels {
+            self.pending.push(self.frame / f32::from(channels));
+            self.frame = 0.0;
+            self.channel = 0;
+
+            if self.pending.len() >= CHUNK {
+                self.flush();
+            }
+        }
+
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S: Source> Source for Tap<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.channel = 0;
+        self.frame = 0.0;
+        self.pending.clear();
+
+        self.input.try_seek(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    /// Stereo frames whose left and right channels average to the frame's index.
+    fn frames(count: usize) -> SamplesBuffer {
+        let samples = (0..count)
+            .flat_map(|i| [i as f32 - 1.0, i as f32 + 1.0])
+            .collect::<Vec<_>>();
+        SamplesBuffer::new(2, 8_000, samples)
+    }
+
+    #[test]
+    fn a_disabled_tap_passes_samples_through_without_copying() {
+        let tap = SampleTap::default();
+        let output: Vec<f32> = Tap::new(frames(CHUNK * 2), tap.clone()).collect();
+
+        assert_eq!(output, frames(CHUNK * 2).collect::<Vec<_>>());
+        assert_eq!(tap.latest(1), None);
+    }
+
+    #[test]
+    fn an_enabled_tap_keeps_the_latest_samples_in_mono() {
+        let tap = SampleTap::default();
+        tap.set_enabled(true);
+        let count = CAPACITY + CHUNK * 2;
+        let output: Vec<f32> = Tap::new(frames(count), tap.clone()).collect();
+
+        assert_eq!(output.len(), count * 2);
+        assert_eq!(tap.sample_rate(), 8_000);
+        assert_eq!(tap.latest(CAPACITY + 1), None);
+        let latest = tap.latest(CAPACITY).expect("a full window");
+        let first = (count - CAPACITY) as f32;
+        assert_eq!(latest[0], first);
+        assert_eq!(latest[CAPACITY - 1], (count - 1) as f32);
+
+        tap.set_enabled(false);
+        assert_eq!(tap.latest(1), None);
+    }
+}
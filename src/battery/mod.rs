@@ -1,22 +1,223 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc, time::Duration};
 
-use battery::{Battery, Manager, units::Ratio};
+use battery::{
+    Battery, Manager, State,
+    units::{ratio::ratio, time::second},
+};
 use thiserror::Error;
 
+const POWER_SUPPLY_ROOT: &str = "/sys/class/power_supply";
+
 #[derive(Debug, Error)]
 pub enum BatteryHandleError {
     #[error("no battery found for device")]
     BatteryNotFound,
+    #[error("invalid value in {0}")]
+    InvalidValue(String),
     #[error(transparent)]
     Internal(#[from] anyhow::Error),
     #[error("an unknown error occured")]
     Unknown,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BatteryState {
+    Charging,
+    Discharging,
+    Full,
+    #[default]
+    Unknown,
+}
+
+/// A reading of the battery, taken on refresh.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BatteryStatus {
+    /// Charge between 0 and 1.
+    pub charge: f32,
+    pub state: BatteryState,
+    pub time_to_empty: Option<Duration>,
+    pub time_to_full: Option<Duration>,
+    /// Full capacity relative to the design capacity, between 0 and 1.
+    pub health: Option<f32>,
+}
+
+impl BatteryStatus {
+    pub fn percent(&self) -> f32 {
+        self.charge * 100.0
+    }
+
+    pub fn is_charging(&self) -> bool {
+        self.state == BatteryState::Charging
+    }
+
+    pub fn is_discharging(&self) -> bool {
+        self.state == BatteryState::Discharging
+    }
+}
+
+/// A fuel gauge exposed under `/sys/class/power_supply/<name>`.
+///
+/// Reads the same attributes the kernel documents for power supplies, preferring the energy
+/// (µWh) readings and falling back to charge (µAh) ones, so it works against any directory laid
+/// out that way.
+#[derive(Debug, Clone)]
+pub struct SysfsBattery {
+    path: PathBuf,
+}
+
+impl SysfsBattery {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Finds the first supply of type `Battery` under `root`, normally `/sys/class/power_supply`.
+    pub fn detect(root: PathBuf) -> Option<Self> {
+        let mut supplies: Vec<PathBuf> = fs::read_dir(root)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .collect();
+        supplies.sort();
+
+        supplies
+            .into_iter()
+            .map(SysfsBattery::new)
+            .find(|supply| supply.read("type").as_deref() == Some("Battery"))
+    }
+
+    pub fn status(&self) -> Result<BatteryStatus, BatteryHandleError> {
+        if self.read("present").as_deref() == Some("0") {
+            return Err(BatteryHandleError::BatteryNotFound);
+        }
+
+        let (now, full, design, rate) = match self.number("energy_now") {
+            Some(now) => (
+                Some(now),
+                self.number("energy_full"),
+                self.number("energy_full_design"),
+                self.number("power_now"),
+            ),
+            None => (
+                self.number("charge_now"),
+                self.number("charge_full"),
+                self.number("charge_full_design"),
+                self.number("current_now"),
+            ),
+        };
+
+        let charge = match self.number("capacity") {
+            Some(capacity) => capacity / 100.0,
+            None => match (now, full) {
+                (Some(now), Some(full)) if full > 0.0 => now / full,
+                _ => return Err(BatteryHandleError::InvalidValue("capacity".to_string())),
+            },
+        };
+
+        let state = match self.read("status").as_deref() {
+            Some("Charging") => BatteryState::Charging,
+            Some("Discharging") => BatteryState::Discharging,
+            Some("Full") => BatteryState::Full,
+            _ => BatteryState::Unknown,
+        };
+
+        // Drivers report the rate unsigned or negative while discharging depending on the chip.
+        let rate = rate.map(f32::abs).filter(|rate| *rate > 0.0);
+        let hours = |amount: f32, rate: f32| seconds(amount / rate * 3600.0);
+
+        let time_to_empty = match (self.number("time_to_empty_now"), state, now, rate) {
+            (Some(time), _, _, _) => seconds(time),
+            (None, BatteryState::Discharging, Some(now), Some(rate)) => hours(now, rate),
+            _ => None,
+        };
+        let time_to_full = match (self.number("time_to_full_now"), state, now.zip(full), rate) {
+            (Some(time), _, _, _) => seconds(time),
+            (None, BatteryState::Charging, Some((now, full)), Some(rate)) => {
+                hours((full - now).max(0.0), rate)
+            }
+            _ => None,
+        };
+
+        let health = match (full, design) {
+            (Some(full), Some(design)) if design > 0.0 => Some((full / design).min(1.0)),
+            _ => None,
+        };
+
+        Ok(BatteryStatus {
+            charge: charge.clamp(0.0, 1.0),
+            state,
+            time_to_empty,
+            time_to_full,
+            health,
+        })
+    }
+
+    fn read(&self, attribute: &str) -> Option<String> {
+        fs::read_to_string(self.path.join(attribute))
+            .ok()
+            .map(|value| value.trim().to_string())
+    }
+
+    /// A numeric attribute, `None` for the NaN and infinite values some drivers report.
+    fn number(&self, attribute: &str) -> Option<f32> {
+        self.read(attribute)?
+            .parse()
+            .ok()
+            .filter(|value: &f32| value.is_finite())
+    }
+}
+
+/// A time reading in seconds, `None` when it's negative, not a number or too large to be one.
+fn seconds(value: f32) -> Option<Duration> {
+    Duration::try_from_secs_f32(value).ok()
+}
+
+#[derive(Debug)]
+enum Backend {
+    System { manager: Manager, battery: Battery },
+    Sysfs(SysfsBattery),
+}
+
+impl Backend {
+    fn status(&mut self) -> Result<BatteryStatus, BatteryHandleError> {
+        match self {
+            Backend::System { manager, battery } => {
+                manager
+                    .refresh(battery)
+                    .map_err(|e| BatteryHandleError::Internal(e.into()))?;
+
+                let state = match battery.state() {
+                    State::Charging => BatteryState::Charging,
+                    State::Discharging => BatteryState::Discharging,
+                    State::Full => BatteryState::Full,
+                    _ => BatteryState::Unknown,
+                };
+
+                Ok(BatteryStatus {
+                    charge: battery.state_of_charge().get::<ratio>(),
+                    state,
+                    time_to_empty: battery
+                        .time_to_empty()
+                        .and_then(|time| seconds(time.get::<second>())),
+                    time_to_full: battery
+                        .time_to_full()
+                        .and_then(|time| seconds(time.get::<second>())),
+                    health: Some(battery.state_of_health().get::<ratio>()),
+                })
+            }
+            Backend::Sysfs(sysfs) => sysfs.status(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    backend: Backend,
+    status: BatteryStatus,
+}
+
+/// Shared handle on the device battery, clones see the same latest reading.
 #[derive(Debug, Clone)]
 pub struct BatteryHandle {
-    battery: Rc<RefCell<Battery>>,
-    manager: Rc<RefCell<Manager>>,
+    inner: Rc<RefCell<Inner>>,
 }
 
 impl BatteryHandle {
@@ -38,22 +239,138 @@ impl BatteryHandle {
             }
         };
 
+        Self::with_backend(Backend::System { manager, battery })
+    }
+
+    pub fn from_sysfs(battery: SysfsBattery) -> Result<Self, BatteryHandleError> {
+        Self::with_backend(Backend::Sysfs(battery))
+    }
+
+    /// The battery at `path` when one is configured, otherwise whatever the system reports,
+    /// falling back to the first battery-type power supply in sysfs.
+    ///
+    /// Returns `None` on devices without a battery.
+    pub fn detect(path: Option<PathBuf>) -> Option<Self> {
+        match path {
+            Some(path) => Self::from_sysfs(SysfsBattery::new(path)).ok(),
+            None => Self::new().ok().or_else(|| {
+                SysfsBattery::detect(PathBuf::from(POWER_SUPPLY_ROOT))
+                    .and_then(|battery| Self::from_sysfs(battery).ok())
+            }),
+        }
+    }
+
+    fn with_backend(mut backend: Backend) -> Result<Self, BatteryHandleError> {
+        let status = backend.status()?;
+
         Ok(Self {
-            battery: Rc::new(RefCell::new(battery)),
-            manager: Rc::new(RefCell::new(manager)),
+            inner: Rc::new(RefCell::new(Inner { backend, status })),
         })
     }
 
     pub fn refresh(&mut self) -> Result<(), BatteryHandleError> {
-        let mut battery = self.battery.borrow_mut();
+        let mut inner = self.inner.borrow_mut();
+        inner.status = inner.backend.status()?;
+
+        Ok(())
+    }
 
-        self.manager
-            .borrow()
-            .refresh(&mut *battery)
-            .map_err(|e| BatteryHandleError::Internal(e.into()))
+    pub fn status(&self) -> BatteryStatus {
+        self.inner.borrow().status
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// A power supply directory holding `attributes`, laid out as under sysfs.
+    fn fixture(attributes: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, value) in attributes {
+            fs::write(dir.path().join(name), format!("{}\n", value)).unwrap();
+        }
+        dir
+    }
+
+    fn status(dir: &Path) -> BatteryStatus {
+        SysfsBattery::new(dir.to_path_buf()).status().unwrap()
+    }
+
+    #[test]
+    fn discharging_energy_gauge() {
+        let dir = fixture(&[
+            ("type", "Battery"),
+            ("status", "Discharging"),
+            ("capacity", "50"),
+            ("energy_now", "10000000"),
+            ("energy_full", "20000000"),
+            ("energy_full_design", "25000000"),
+            ("power_now", "5000000"),
+        ]);
+
+        let status = status(dir.path());
+        assert_eq!(status.charge, 0.5);
+        assert_eq!(status.state, BatteryState::Discharging);
+        assert_eq!(status.time_to_empty, Some(Duration::from_secs(7200)));
+        assert_eq!(status.time_to_full, None);
+        assert_eq!(status.health, Some(0.8));
+    }
+
+    #[test]
+    fn charging_charge_gauge_with_negative_current() {
+        let dir = fixture(&[
+            ("status", "Charging"),
+            ("charge_now", "1000000"),
+            ("charge_full", "2000000"),
+            ("current_now", "-1000000"),
+        ]);
+
+        let status = status(dir.path());
+        assert_eq!(status.charge, 0.5);
+        assert!(status.is_charging());
+        assert_eq!(status.time_to_full, Some(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn bad_times_are_ignored() {
+        for value in ["-60", "NaN", "inf", "1e30"] {
+            let dir = fixture(&[
+                ("status", "Discharging"),
+                ("capacity", "40"),
+                ("time_to_empty_now", value),
+                ("time_to_full_now", value),
+            ]);
+
+            let status = status(dir.path());
+            assert_eq!(status.time_to_empty, None, "{}", value);
+            assert_eq!(status.time_to_full, None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn absent_battery() {
+        let dir = fixture(&[("present", "0"), ("capacity", "80")]);
+
+        assert!(matches!(
+            SysfsBattery::new(dir.path().to_path_buf()).status(),
+            Err(BatteryHandleError::BatteryNotFound)
+        ));
+    }
+
+    #[test]
+    fn detects_the_battery_among_supplies() {
+        let root = tempfile::tempdir().unwrap();
+        for (name, kind) in [("AC", "Mains"), ("BAT0", "Battery")] {
+            fs::create_dir(root.path().join(name)).unwrap();
+            fs::write(root.path().join(name).join("type"), kind).unwrap();
+        }
 
-    pub fn charge(&self) -> Ratio {
-        self.battery.borrow().state_of_charge()
+        let battery = SysfsBattery::detect(root.path().to_path_buf()).unwrap();
+        assert_eq!(battery.path, root.path().join("BAT0"));
     }
 }
@@ -0,0 +1,86 @@
+use std::{fs, path::PathBuf};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BacklightError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid brightness value: {0}")]
+    InvalidValue(String),
+}
+
+/// Brightness control through a sysfs backlight directory, which holds a writable `brightness`
+/// next to a read-only `max_brightness`.
+#[derive(Debug, Clone)]
+pub struct Backlight {
+    path: PathBuf,
+}
+
+impl Backlight {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn max_brightness(&self) -> Result<u32, BacklightError> {
+        let value = fs::read_to_string(self.path.join("max_brightness"))?;
+
+        value
+            .trim()
+            .parse()
+            .map_err(|_| BacklightError::InvalidValue(value.trim().to_string()))
+    }
+
+    /// Sets the brightness as a fraction of the maximum.
+    pub fn set_level(&self, level: f32) -> Result<(), BacklightError> {
+        let max = self.max_brightness()?;
+        let value = (max as f32 * level.clamp(0.0, 1.0)).round() as u32;
+
+        fs::write(self.path.join("brightness"), value.to_string())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backlight(max: &str) -> (tempfile::TempDir, Backlight) {
+        let dir = tempfile::tempdir().expect("tempdir");
+        fs::write(dir.path().join("max_brightness"), max).expect("write");
+        let backlight = Backlight::new(dir.path().to_path_buf());
+        (dir, backlight)
+    }
+
+    fn brightness(dir: &tempfile::TempDir) -> String {
+        fs::read_to_string(dir.path().join("brightness")).expect("brightness")
+    }
+
+    #[test]
+    fn levels_are_scaled_to_the_maximum_and_clamped() {
+        let (dir, backlight) = backlight("255\n");
+
+        backlight.set_level(0.2).expect("set");
+        assert_eq!(brightness(&dir), "51");
+        backlight.set_level(1.5).expect("set");
+        assert_eq!(brightness(&dir), "255");
+        backlight.set_level(-1.0).expect("set");
+        assert_eq!(brightness(&dir), "0");
+    }
+
+    #[test]
+    fn an_unreadable_maximum_is_an_error() {
+        let (dir, backlight) = backlight("bright");
+        assert!(matches!(
+            backlight.set_level(0.5),
+            Err(BacklightError::InvalidValue(value)) if value == "bright"
+        ));
+        assert!(!dir.path().join("brightness").exists());
+
+        let missing = Backlight::new(dir.path().join("missing"));
+        assert!(matches!(
+            missing.max_brightness(),
+            Err(BacklightError::Io(_))
+        ));
+    }
+}
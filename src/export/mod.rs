@@ -0,0 +1,483 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+use thiserror::Error;
+use tracing::info;
+
+use crate::{
+    model::{
+        CatalogItem, artist::Artist, listen::Listen, playlist::Playlist, release::Release,
+        track::Track,
+    },
+    repository::playlist::PlaylistFilter,
+    scrobble::listenbrainz::listen_json,
+    service::CatalogService,
+};
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("unable to write export: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("unable to serialize export: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+/// Writes the play history, favorites, playlists and library stats into `dir`.
+///
+/// History, favorites and playlists come as both JSON and CSV, the history also as ListenBrainz
+/// JSONL so it can be imported there. Favorites are the tracks, releases and artists marked so.
+/// Each playlist is also written as M3U8 under `playlists/`.
+pub async fn export(catalog: &CatalogService, dir: &Path) -> Result<Vec<PathBuf>, ExportError> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    let history_service = catalog.history.lock().await;
+    let history = history_service
+        .history()
+        .await
+        .map_err(|e| ExportError::Internal(e.into()))?;
+    let favorites = Favorites {
+        tracks: history_service
+            .favorite_tracks()
+            .await
+            .map_err(|e| ExportError::Internal(e.into()))?,
+        releases: history_service
+            .favorite_releases()
+            .await
+            .map_err(|e| ExportError::Internal(e.into()))?,
+        artists: history_service
+            .favorite_artists()
+            .await
+            .map_err(|e| ExportError::Internal(e.into()))?,
+    };
+    let stats = history_service
+        .stats()
+        .await
+        .map_err(|e| ExportError::Internal(e.into()))?;
+    drop(history_service);
+
+    let playlists = playlists(catalog).await?;
+
+    let files = [
+        (
+            "history.json",
+            serde_json::to_string_pretty(&history.iter().map(history_json).collect::<Vec<_>>())?,
+        ),
+        ("history.csv", history_csv(&history)),
+        ("listens.jsonl", listens_jsonl(&history)),
+        (
+            "favorites.json",
+            serde_json::to_string_pretty(&favorites_json(&favorites))?,
+        ),
+        ("favorites.csv", favorites_csv(&favorites)),
+        (
+            "playlists.json",
+            serde_json::to_string_pretty(
+                &playlists
+                    .iter()
+                    .map(|(playlist, tracks)| playlist_json(playlist, tracks))
+                    .collect::<Vec<_>>(),
+            )?,
+        ),
+        ("playlists.csv", playlists_csv(&playlists)),
+        ("stats.json", serde_json::to_string_pretty(&stats)?),
+    ];
+
+    let mut written = Vec::with_capacity(files.len());
+    for (name, contents) in files {
+        let path = dir.join(name);
+        tokio::fs::write(&path, contents).await?;
+        written.push(path);
+    }
+
+    written.extend(write_m3u8(&playlists, &dir.join("playlists")).await?);
+
+    info!(
+        "exported {} listens, {} favorites and {} playlists to {}",
+        history.len(),
+        favorites.tracks.len() + favorites.releases.len() + favorites.artists.len(),
+        playlists.len(),
+        dir.display()
+    );
+    Ok(written)
+}
+
+/// Everything marked as a favorite.
+struct Favorites {
+    tracks: Vec<CatalogItem<Track>>,
+    releases: Vec<CatalogItem<Release>>,
+    artists: Vec<CatalogItem<Artist>>,
+}
+
+/// Every playlist as it stands in the catalog, with its tracks in order.
+async fn playlists(
+    catalog: &CatalogService,
+) -> Result<Vec<(CatalogItem<Playlist>, Vec<Track>)>, ExportError> {
+    let mut playlist_service = catalog.playlist.lock().await;
+    let playlists = playlist_service
+        .list_playlists(PlaylistFilter::default())
+        .await
+        .map_err(|e| ExportError::Internal(e.into()))?;
+
+    let mut with_tracks = Vec::with_capacity(playlists.len());
+    for playlist in playlists {
+        let tracks = playlist_service
+            .list_tracks_page(playlist.id, 0, None)
+            .await
+            .map_err(|e| ExportError::Internal(e.into()))?
+            .into_iter()
+            .map(|item| item.metadata)
+            .collect();
+        with_tracks.push((playlist, tracks));
+    }
+
+    Ok(with_tracks)
+}
+
+/// Writes each playlist to `<name>.m3u8` in `dir`.
+async fn write_m3u8(
+    playlists: &[(CatalogItem<Playlist>, Vec<Track>)],
+    dir: &Path,
+) -> Result<Vec<PathBuf>, ExportError> {
+    if playlists.is_empty() {
+        return Ok(Vec::new());
+    }
+    tokio::fs::create_dir_all(dir).await?;
+
+    let mut written = Vec::with_capacity(playlists.len());
+    for (playlist, tracks) in playlists {
+        let contents = playlist.metadata.to_m3u8(tracks);
+
+        let name = file_name(&playlist.metadata.name);
+        let mut path = dir.join(format!("{}.m3u8", name));
+        // Playlists imported from different folders can share a name.
+        if written.contains(&path) {
+            path = dir.join(format!("{} ({}).m3u8", name, playlist.id));
+        }
+        tokio::fs::write(&path, contents).await?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+/// `name` with the characters file systems reject replaced.
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    match name.trim().is_empty() {
+        true => "playlist".to_string(),
+        false => name.trim().to_string(),
+    }
+}
+
+fn history_json(listen: &Listen) -> Value {
+    json!({
+        "title": listen.title,
+        "artist": listen.artist,
+        "release": listen.release,
+        "duration_seconds": listen.duration.as_secs(),
+        "listened_at": listen.listened_at.to_rfc3339(),
+    })
+}
+
+/// One listen per line in the format ListenBrainz imports.
+fn listens_jsonl(history: &[Listen]) -> String {
+    history
+        .iter()
+        .map(|listen| listen_json(listen).to_string() + "\n")
+        .collect()
+}
+
+fn track_json(id: Option<i64>, track: &Track) -> Value {
+    json!({
+        "id": id,
+        "title": track.title,
+        "artist": track.artist,
+        "release": track.release,
+        "duration_seconds": track.duration.as_secs(),
+        "path": track.path,
+    })
+}
+
+fn favorites_json(favorites: &Favorites) -> Value {
+    json!({
+        "tracks": favorites
+            .tracks
+            .iter()
+            .map(|item| track_json(Some(item.id), &item.metadata))
+            .collect::<Vec<_>>(),
+        "releases": favorites
+            .releases
+            .iter()
+            .map(|item| json!({
+                "id": item.id,
+                "title": item.metadata.title,
+                "artist": item.metadata.artist,
+                "year": item.metadata.year,
+            }))
+            .collect::<Vec<_>>(),
+        "artists": favorites
+            .artists
+            .iter()
+            .map(|item| json!({ "id": item.id, "name": item.metadata.name }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn playlist_json(playlist: &CatalogItem<Playlist>, tracks: &[Track]) -> Value {
+    json!({
+        "id": playlist.id,
+        "name": playlist.metadata.name,
+        "path": playlist.metadata.path,
+        "tracks": tracks.iter().map(|track| track_json(None, track)).collect::<Vec<_>>(),
+        "unresolved": playlist.metadata.unresolved,
+    })
+}
+
+fn history_csv(history: &[Listen]) -> String {
+    let mut csv = String::from("listened_at,title,artist,release,duration_seconds\n");
+    for listen in history {
+        csv_row(
+            &mut csv,
+            &[
+                &listen.listened_at.to_rfc3339(),
+                &listen.title,
+                &listen.artist,
+                listen.release.as_deref().unwrap_or_default(),
+                &listen.duration.as_secs().to_string(),
+            ],
+        );
+    }
+    csv
+}
+
+/// Favorite tracks, releases and artists told apart by the first column, with the columns that
+/// don't apply to a kind left empty.
+fn favorites_csv(favorites: &Favorites) -> String {
+    let mut csv = String::from("kind,id,title,artist,release,duration_seconds,path\n");
+    for item in &favorites.tracks {
+        let track = &item.metadata;
+        csv_row(
+            &mut csv,
+            &[
+                "track",
+                &item.id.to_string(),
+                &track.title,
+                track.artist.as_deref().unwrap_or_default(),
+                track.release.as_deref().unwrap_or_default(),
+                &track.duration.as_secs().to_string(),
+                &track.path.to_string_lossy(),
+            ],
+        );
+    }
+    for item in &favorites.releases {
+        let release = &item.metadata;
+        csv_row(
+            &mut csv,
+            &[
+                "release",
+                &item.id.to_string(),
+                "",
+                release.artist.as_deref().unwrap_or_default(),
+                &release.title,
+                "",
+                "",
+            ],
+        );
+    }
+    for item in &favorites.artists {
+        csv_row(
+            &mut csv,
+            &[
+                "artist",
+                &item.id.to_string(),
+                "",
+                &item.metadata.name,
+                "",
+                "",
+                "",
+            ],
+        );
+    }
+    csv
+}
+
+/// A line per playlist entry, in order within each playlist.
+fn playlists_csv(playlists: &[(CatalogItem<Playlist>, Vec<Track>)]) -> String {
+    let mut csv = String::from("playlist,position,title,artist,release,duration_seconds,path\n");
+    for (playlist, tracks) in playlists {
+        for (position, track) in tracks.iter().enumerate() {
+            csv_row(
+                &mut csv,
+                &[
+                    &playlist.metadata.name,
+                    &(position + 1).to_string(),
+                    &track.title,
+                    track.artist.as_deref().unwrap_or_default(),
+                    track.release.as_deref().unwrap_or_default(),
+                    &track.duration.as_secs().to_string(),
+                    &track.path.to_string_lossy(),
+                ],
+            );
+        }
+    }
+    csv
+}
+
+/// Appends a line, quoting the fields that need it.
+fn csv_row(csv: &mut String, fields: &[&str]) {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|field| match field.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", field.replace('"', "\"\"")),
+            false => field.to_string(),
+        })
+        .collect();
+
+    csv.push_str(&fields.join(","));
+    csv.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    fn row(fields: &[&str]) -> String {
+        let mut csv = String::new();
+        csv_row(&mut csv, fields);
+        csv
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_they_need_it() {
+        assert_eq!(row(&["plain", "two words"]), "plain,two words\n");
+        assert_eq!(row(&["a, b", "c"]), "\"a, b\",c\n");
+        assert_eq!(row(&["say \"hi\""]), "\"say \"\"hi\"\"\"\n");
+        assert_eq!(row(&["line\nbreak", "cr\r"]), "\"line\nbreak\",\"cr\r\"\n");
+        assert_eq!(row(&["", ""]), ",\n");
+    }
+
+    #[test]
+    fn listens_are_one_object_per_line() {
+        let listen = |title: &str, at: i64| Listen {
+            title: title.to_string(),
+            artist: "Artist".to_string(),
+            release: Some("Album, Deluxe".to_string()),
+            duration: Duration::from_secs(200),
+            listened_at: Utc.timestamp_opt(at, 0).unwrap(),
+            ..Default::default()
+        };
+        let jsonl = listens_jsonl(&[
+            listen("One\nTwo", 1_700_000_000),
+            listen("Three", 1_700_000_300),
+        ]);
+
+        let lines: Vec<&str> = jsonl.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(jsonl.ends_with('\n'));
+
+        let first: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["listened_at"], 1_700_000_000);
+        assert_eq!(first["track_metadata"]["track_name"], "One\nTwo");
+        assert_eq!(first["track_metadata"]["artist_name"], "Artist");
+        assert_eq!(first["track_metadata"]["release_name"], "Album, Deluxe");
+        assert_eq!(
+            first["track_metadata"]["additional_info"]["duration_ms"],
+            200_000
+        );
+    }
+
+    #[tokio::test]
+    async fn favorites_and_playlists_come_as_json_and_csv() {
+        use crate::{
+            artwork::ArtworkCache,
+            model::playlist::PlaylistEntry,
+            repository::{Repository, RepositoryContext},
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let context = RepositoryContext::in_memory().await;
+        let catalog = CatalogService::new(context.clone(), ArtworkCache::new(dir.path().into()))
+            .await
+            .unwrap();
+
+        let track = Track {
+            title: "Song".to_string(),
+            artist: Some("Artist".to_string()),
+            path: dir.path().join("song.flac"),
+            ..Default::default()
+        };
+        let mut stored = context.track.lock().await.create(track).await.unwrap();
+        stored.favorite = true;
+        context.track.lock().await.update(stored).await.unwrap();
+
+        let artist = Artist {
+            name: "Artist".to_string(),
+            releases: Vec::new(),
+        };
+        let mut stored = context.artist.lock().await.create(artist).await.unwrap();
+        stored.favorite = true;
+        context.artist.lock().await.update(stored).await.unwrap();
+
+        let playlist = Playlist {
+            name: "Mix".to_string(),
+            path: Some(dir.path().join("mix.m3u8")),
+            unresolved: Vec::new(),
+            modified: None,
+            edited: false,
+        };
+        let entry = PlaylistEntry {
+            location: "song.flac".to_string(),
+            ..Default::default()
+        };
+        catalog
+            .playlist
+            .lock()
+            .await
+            .import(playlist, vec![entry])
+            .await
+            .unwrap();
+
+        let out = dir.path().join("export");
+        export(&catalog, &out).await.unwrap();
+
+        let favorites: Value =
+            serde_json::from_str(&std::fs::read_to_string(out.join("favorites.json")).unwrap())
+                .unwrap();
+        assert_eq!(favorites["tracks"][0]["title"], "Song");
+        assert_eq!(favorites["artists"][0]["name"], "Artist");
+        assert_eq!(favorites["releases"], json!([]));
+        let csv = std::fs::read_to_string(out.join("favorites.csv")).unwrap();
+        assert!(csv.lines().any(|line| line.starts_with("artist,")));
+
+        let playlists: Value =
+            serde_json::from_str(&std::fs::read_to_string(out.join("playlists.json")).unwrap())
+                .unwrap();
+        assert_eq!(playlists[0]["name"], "Mix");
+        assert_eq!(playlists[0]["tracks"][0]["title"], "Song");
+        let csv = std::fs::read_to_string(out.join("playlists.csv")).unwrap();
+        assert_eq!(
+            csv.lines()
+                .nth(1)
+                .unwrap()
+                .split(',')
+                .take(3)
+                .collect::<Vec<_>>(),
+            ["Mix", "1", "Song"]
+        );
+        assert!(out.join("playlists/Mix.m3u8").is_file());
+    }
+}
@@ -1,26 +1,41 @@
-use std::fmt::{Display, Formatter};
+use std::{
+    fmt::{Display, Formatter},
+    time::Duration,
+};
 
 use iced::{
-    Element, Padding, Subscription, Task,
+    Color, Element, Length, Padding, Subscription, Task,
     keyboard::{self, Key},
-    widget::column,
-    widget::container,
+    widget::{Stack, column, container},
 };
-use tracing::debug;
+use tracing::{debug, error, info};
 
 use crate::{
+    battery::BatteryHandle,
     frontend::{
+        equalizer::{EqualizerMessage, EqualizerPage},
+        idle::{Idle, IdleMessage, IdleState},
         library::{Library, LibraryMessage, LibraryView},
+        lyrics::{LyricsMessage, LyricsPage},
         menu::{Menu, MenuMessage},
-        player::{Player, PlayerMessage, PlayerState},
+        player::{Player, PlayerMessage},
+        power::{Power, PowerMessage, ShutdownReason},
+        scrobble::{ScrobbleMessage, Scrobbler},
+        settings::{SettingsMessage, SettingsPage},
     },
+    model::track::Track,
     service::CatalogService,
+    session::{Session, SessionStore},
+    settings::SettingsStore,
 };
 
 #[derive(Debug, Clone)]
 pub enum ApplicationView {
     Library(LibraryView),
     Player,
+    Lyrics,
+    Settings,
+    Equalizer,
 }
 
 impl Default for ApplicationView {
@@ -34,6 +49,9 @@ impl Display for ApplicationView {
         match self {
             ApplicationView::Library(library_view) => write!(f, "Library - {:?}", library_view),
             ApplicationView::Player => write!(f, "Player"),
+            ApplicationView::Lyrics => write!(f, "Lyrics"),
+            ApplicationView::Settings => write!(f, "Settings"),
+            ApplicationView::Equalizer => write!(f, "Equalizer"),
         }
     }
 }
@@ -44,37 +62,108 @@ pub enum ApplicationMessage {
     Library(LibraryMessage),
     Menu(MenuMessage),
     Player(PlayerMessage),
+    Lyrics(LyricsMessage),
+    Settings(SettingsMessage),
+    Equalizer(EqualizerMessage),
+    Idle(IdleMessage),
+    Power(PowerMessage),
+    Scrobble(ScrobbleMessage),
     Input(Key),
+    InputHeld(Key),
+    InputReleased(Key),
 }
 
 pub struct Application {
     current_view: ApplicationView,
+    /// Where backing out of the settings screen returns to.
+    previous_view: ApplicationView,
     pub player: Player,
+    pub lyrics: LyricsPage,
     pub library: Library,
     pub menu: Menu,
+    pub settings: SettingsPage,
+    pub equalizer: EqualizerPage,
+    pub idle: Idle,
+    pub power: Power,
+    pub scrobbler: Scrobbler,
+    catalog: CatalogService,
+    session_store: SessionStore,
 }
 
 impl Application {
-    pub fn new(catalog: CatalogService) -> (Self, Task<ApplicationMessage>) {
+    pub fn new(
+        catalog: CatalogService,
+        settings_store: SettingsStore,
+        session_store: SessionStore,
+    ) -> (Self, Task<ApplicationMessage>) {
         let (library, library_task) = Library::new(catalog.clone());
+        let settings = SettingsPage::new(settings_store);
+        let (idle, idle_task) = Idle::new(settings.settings().display.clone());
+        let battery = BatteryHandle::detect(settings.settings().power.battery.clone());
+        let power = Power::new(settings.settings().power.clone(), battery.clone());
+        let scrobbler = Scrobbler::new(catalog.clone(), settings.settings().scrobble.clone());
+
+        let resume_task = match session_store.load() {
+            Some(Session {
+                track: Some(path),
+                start,
+                position,
+            }) => Task::perform(load_track(path, start), |track| track).and_then(move |track| {
+                Task::done(ApplicationMessage::Player(PlayerMessage::Resume(
+                    track, position,
+                )))
+            }),
+            _ => Task::none(),
+        };
 
         let application = Self {
             current_view: ApplicationView::default(),
-            player: Player::default(),
-            library: library,
-            menu: Menu::default(),
+            previous_view: ApplicationView::default(),
+            player: Player::new(
+                catalog.artwork.clone(),
+                settings.settings().art_style,
+                settings.settings().visualizer.clone(),
+                settings.settings().playback.clone(),
+                settings.settings().dsp.clone(),
+            ),
+            lyrics: LyricsPage::default(),
+            library,
+            menu: Menu::new(ApplicationView::default(), battery),
+            equalizer: EqualizerPage::new(settings.settings().dsp.clone()),
+            settings,
+            idle,
+            power,
+            scrobbler,
+            catalog,
+            session_store,
         };
 
-        (application, library_task.map(ApplicationMessage::Library))
+        (
+            application,
+            Task::batch([
+                library_task.map(ApplicationMessage::Library),
+                idle_task.map(ApplicationMessage::Idle),
+                Task::done(ApplicationMessage::Scrobble(ScrobbleMessage::Submit)),
+                resume_task,
+            ]),
+        )
     }
 
     pub fn view(&self) -> Element<'_, ApplicationMessage> {
+        if self.idle.state() == IdleState::Asleep {
+            return self.idle.view().map(ApplicationMessage::Idle);
+        }
+
         let element = match self.current_view {
             ApplicationView::Library(_) => self.library.view().map(ApplicationMessage::Library),
             ApplicationView::Player => self.player.view().map(ApplicationMessage::Player),
+            ApplicationView::Lyrics => self.lyrics.view().map(ApplicationMessage::Lyrics),
+            ApplicationView::Settings => self.settings.view().map(ApplicationMessage::Settings),
+            ApplicationView::Equalizer => self.equalizer.view().map(ApplicationMessage::Equalizer),
         };
 
-        column![
+        let offset = self.idle.offset();
+        let content = container(column![
             self.menu.view().map(ApplicationMessage::Menu),
             container(element)
                 .padding(Padding {
@@ -87,22 +176,96 @@ impl Application {
                 .height(720)
                 .width(720)
                 .clip(true)
-        ]
-        .into()
+        ])
+        .padding(Padding::new(0.0).left(offset.x).top(offset.y));
+
+        let mut layers = Stack::new().push(content);
+
+        if let Some(warning) = self.power.view() {
+            layers = layers.push(warning.map(ApplicationMessage::Power));
+        }
+
+        if self.idle.dims_in_software() {
+            layers = layers.push(
+                container("")
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .style(|_| container::Style {
+                        background: Some(Color::from_rgba(0.0, 0.0, 0.0, 0.7).into()),
+                        ..Default::default()
+                    }),
+            );
+        }
+
+        layers.into()
     }
 
     pub fn update(&mut self, message: ApplicationMessage) -> Task<ApplicationMessage> {
+        // The power button works the same whatever is on screen, even while it's asleep.
+        match &message {
+            ApplicationMessage::Input(Key::Named(keyboard::key::Named::Power))
+            | ApplicationMessage::InputHeld(Key::Named(keyboard::key::Named::Power)) => {
+                return self
+                    .power
+                    .update(PowerMessage::PowerKey(true))
+                    .map(ApplicationMessage::Power);
+            }
+            ApplicationMessage::InputReleased(Key::Named(keyboard::key::Named::Power)) => {
+                return self
+                    .power
+                    .update(PowerMessage::PowerKey(false))
+                    .map(ApplicationMessage::Power);
+            }
+            _ => {}
+        }
+
+        if let ApplicationMessage::Input(_)
+        | ApplicationMessage::InputHeld(_)
+        | ApplicationMessage::InputReleased(_) = message
+        {
+            self.power.dismiss_warning();
+            self.power.activity();
+
+            let waking = !self.idle.is_awake();
+            let task = self
+                .idle
+                .update(IdleMessage::Activity)
+                .map(ApplicationMessage::Idle);
+
+            self.update_visibility();
+
+            // The key press that wakes the display isn't passed on.
+            if waking {
+                return task;
+            }
+        }
+
         match message {
             ApplicationMessage::ChangeView(view) => {
-                self.current_view = view.clone();
-                self.menu
-                    .update(MenuMessage::ViewChange(view))
-                    .map(ApplicationMessage::Menu)
+                // The equalizer opens from settings, so moving between the two keeps where
+                // backing out of settings returns to.
+                match (&self.current_view, &view) {
+                    (ApplicationView::Settings, ApplicationView::Equalizer)
+                    | (ApplicationView::Equalizer, ApplicationView::Settings) => {
+                        self.current_view = view.clone()
+                    }
+                    _ => {
+                        self.previous_view = std::mem::replace(&mut self.current_view, view.clone())
+                    }
+                }
+                self.update_visibility();
+
+                Task::batch([
+                    self.menu
+                        .update(MenuMessage::ViewChange(view))
+                        .map(ApplicationMessage::Menu),
+                    self.follow_lyrics(),
+                ])
             }
             ApplicationMessage::Library(message) => match message {
-                LibraryMessage::TrackSelect(track) => {
-                    Task::done(ApplicationMessage::Player(PlayerMessage::Load(track)))
-                }
+                LibraryMessage::TrackSelect(tracks, position) => Task::done(
+                    ApplicationMessage::Player(PlayerMessage::Queue(tracks, position)),
+                ),
                 LibraryMessage::ChangeView(view) => Task::done(ApplicationMessage::ChangeView(
                     ApplicationView::Library(view),
                 )),
@@ -111,20 +274,139 @@ impl Application {
                     .update(message)
                     .map(ApplicationMessage::Library),
             },
-            ApplicationMessage::Menu(message) => match message {
-                message => self.menu.update(message).map(ApplicationMessage::Menu),
-            },
+            ApplicationMessage::Menu(message) => {
+                self.menu.update(message).map(ApplicationMessage::Menu)
+            }
+            ApplicationMessage::Player(PlayerMessage::Listened(listen)) => Task::done(
+                ApplicationMessage::Scrobble(ScrobbleMessage::Record(listen)),
+            ),
+            ApplicationMessage::Player(PlayerMessage::Played(listen, played)) => Task::done(
+                ApplicationMessage::Scrobble(ScrobbleMessage::Played(listen, played)),
+            ),
+            ApplicationMessage::Player(PlayerMessage::Bookmark(track, position)) => {
+                let catalog = self.catalog.clone();
+
+                Task::perform(
+                    async move {
+                        save_resume(&catalog, &track, position).await;
+                        (track, position)
+                    },
+                    |(track, position)| {
+                        ApplicationMessage::Library(LibraryMessage::Bookmarked(track, position))
+                    },
+                )
+            }
+            ApplicationMessage::Player(PlayerMessage::SpeedChosen(track, speed)) => {
+                let catalog = self.catalog.clone();
+
+                Task::perform(
+                    async move {
+                        if let Err(e) = catalog
+                            .track
+                            .lock()
+                            .await
+                            .save_speed(&track, Some(speed))
+                            .await
+                        {
+                            error!("unable to save the speed of {:?}: {}", track.path, e);
+                        }
+                        (track, speed)
+                    },
+                    |(track, speed)| {
+                        ApplicationMessage::Library(LibraryMessage::SpeedChosen(track, speed))
+                    },
+                )
+            }
+            ApplicationMessage::Player(PlayerMessage::Slept) => {
+                let _ = self.menu.update(MenuMessage::SleepTimer(None));
+
+                self.power
+                    .update(PowerMessage::SleepTimerEnded(
+                        self.settings.settings().playback.sleep_power_off,
+                    ))
+                    .map(ApplicationMessage::Power)
+            }
             ApplicationMessage::Player(message) => {
                 let task = self
                     .player
                     .update(message.clone())
                     .map(ApplicationMessage::Player);
+                let _ = self
+                    .menu
+                    .update(MenuMessage::SleepTimer(self.player.sleep_remaining()));
+                self.power.set_playing(self.player.is_playing());
 
                 match message {
-                    PlayerMessage::Load(_) => task.chain(Task::done(
+                    PlayerMessage::Load(_)
+                    | PlayerMessage::Queue(..)
+                    | PlayerMessage::Resume(..) => task.chain(Task::done(
                         ApplicationMessage::ChangeView(ApplicationView::Player),
                     )),
-                    _ => task,
+                    _ => Task::batch([task, self.follow_lyrics()]),
+                }
+            }
+            ApplicationMessage::Lyrics(message) => {
+                self.lyrics.update(message).map(ApplicationMessage::Lyrics)
+            }
+            ApplicationMessage::Settings(message) => match message {
+                SettingsMessage::Changed(settings) => {
+                    self.power.apply(settings.power.clone());
+                    self.scrobbler.apply(settings.scrobble.clone());
+                    self.equalizer.apply(settings.dsp.clone());
+
+                    Task::batch([
+                        Task::done(ApplicationMessage::Player(PlayerMessage::ArtStyleChange(
+                            settings.art_style,
+                        ))),
+                        Task::done(ApplicationMessage::Player(PlayerMessage::VisualizerChange(
+                            settings.visualizer,
+                        ))),
+                        Task::done(ApplicationMessage::Player(PlayerMessage::PlaybackChange(
+                            settings.playback,
+                        ))),
+                        Task::done(ApplicationMessage::Player(PlayerMessage::DspChange(
+                            settings.dsp,
+                        ))),
+                        self.idle
+                            .apply(settings.display)
+                            .map(ApplicationMessage::Idle),
+                    ])
+                }
+                SettingsMessage::OpenEqualizer => {
+                    Task::done(ApplicationMessage::ChangeView(ApplicationView::Equalizer))
+                }
+                message => self
+                    .settings
+                    .update(message)
+                    .map(ApplicationMessage::Settings),
+            },
+            ApplicationMessage::Equalizer(EqualizerMessage::Changed(dsp)) => self
+                .settings
+                .edit(|settings| settings.dsp = dsp)
+                .map(ApplicationMessage::Settings),
+            ApplicationMessage::Equalizer(message) => self
+                .equalizer
+                .update(message)
+                .map(ApplicationMessage::Equalizer),
+            ApplicationMessage::Power(PowerMessage::Shutdown(reason)) => self.shutdown(reason),
+            ApplicationMessage::Power(message) => {
+                self.power.update(message).map(ApplicationMessage::Power)
+            }
+            ApplicationMessage::Scrobble(message) => self
+                .scrobbler
+                .update(message)
+                .map(ApplicationMessage::Scrobble),
+            ApplicationMessage::Idle(message) => {
+                let task = self.idle.update(message).map(ApplicationMessage::Idle);
+                self.update_visibility();
+                task
+            }
+            ApplicationMessage::Input(Key::Named(keyboard::key::Named::Escape)) => {
+                match self.current_view {
+                    ApplicationView::Settings => {
+                        Task::done(ApplicationMessage::ChangeView(self.previous_view.clone()))
+                    }
+                    _ => Task::done(ApplicationMessage::ChangeView(ApplicationView::Settings)),
                 }
             }
             ApplicationMessage::Input(key) => match self.current_view {
@@ -138,11 +420,61 @@ impl Application {
                             LibraryView::Track,
                         )))
                     }
+                    Key::Named(keyboard::key::Named::ArrowUp) => {
+                        Task::done(ApplicationMessage::ChangeView(ApplicationView::Lyrics))
+                    }
+                    key => Task::done(ApplicationMessage::Player(PlayerMessage::Input(key))),
+                },
+                // Scrolling stays with the lyrics, playback keys still reach the player.
+                ApplicationView::Lyrics => match key {
+                    Key::Named(keyboard::key::Named::Backspace) => {
+                        Task::done(ApplicationMessage::ChangeView(ApplicationView::Player))
+                    }
+                    Key::Named(keyboard::key::Named::ArrowUp | keyboard::key::Named::ArrowDown) => {
+                        self.lyrics
+                            .update(LyricsMessage::InputEvent(key))
+                            .map(ApplicationMessage::Lyrics)
+                    }
+                    key => Task::done(ApplicationMessage::Player(PlayerMessage::Input(key))),
+                },
+                ApplicationView::Settings => match key {
+                    Key::Named(keyboard::key::Named::Backspace) => {
+                        Task::done(ApplicationMessage::ChangeView(self.previous_view.clone()))
+                    }
                     key => self
-                        .player
-                        .update(PlayerMessage::Input(key))
-                        .map(ApplicationMessage::Player),
+                        .settings
+                        .update(SettingsMessage::InputEvent(key))
+                        .map(ApplicationMessage::Settings),
                 },
+                ApplicationView::Equalizer => match key {
+                    Key::Named(keyboard::key::Named::Backspace) => {
+                        Task::done(ApplicationMessage::ChangeView(ApplicationView::Settings))
+                    }
+                    key => self
+                        .equalizer
+                        .update(EqualizerMessage::InputEvent(key))
+                        .map(ApplicationMessage::Equalizer),
+                },
+            },
+            ApplicationMessage::InputHeld(key) => match self.current_view {
+                ApplicationView::Library(_) => self
+                    .library
+                    .update(LibraryMessage::InputHeld(key))
+                    .map(ApplicationMessage::Library),
+                ApplicationView::Player
+                | ApplicationView::Lyrics
+                | ApplicationView::Settings
+                | ApplicationView::Equalizer => Task::none(),
+            },
+            ApplicationMessage::InputReleased(key) => match self.current_view {
+                ApplicationView::Library(_) => self
+                    .library
+                    .update(LibraryMessage::InputReleased(key))
+                    .map(ApplicationMessage::Library),
+                ApplicationView::Player
+                | ApplicationView::Lyrics
+                | ApplicationView::Settings
+                | ApplicationView::Equalizer => Task::none(),
             },
         }
     }
@@ -152,12 +484,104 @@ impl Application {
             keyboard::listen().filter_map(|event| {
                 debug!("keyboard event detected: {:?}", event);
                 match event {
+                    keyboard::Event::KeyPressed {
+                        key, repeat: true, ..
+                    } => Some(ApplicationMessage::InputHeld(key)),
                     keyboard::Event::KeyPressed { key, .. } => Some(ApplicationMessage::Input(key)),
+                    keyboard::Event::KeyReleased { key, .. } => {
+                        Some(ApplicationMessage::InputReleased(key))
+                    }
                     _ => None,
                 }
             }),
             self.player.subscription().map(ApplicationMessage::Player),
             self.menu.subscription().map(ApplicationMessage::Menu),
+            self.idle.subscription().map(ApplicationMessage::Idle),
+            self.power.subscription().map(ApplicationMessage::Power),
+            self.scrobbler
+                .subscription()
+                .map(ApplicationMessage::Scrobble),
         ])
     }
+
+    /// Tells the player whether it's on screen, so nothing is drawn for a dimmed display.
+    fn update_visibility(&mut self) {
+        self.player.set_visible(
+            self.idle.is_awake() && matches!(self.current_view, ApplicationView::Player),
+        );
+    }
+
+    /// Keeps the lyrics screen on the playing track and position while it's showing.
+    fn follow_lyrics(&mut self) -> Task<ApplicationMessage> {
+        let track = match (&self.current_view, self.player.track()) {
+            (ApplicationView::Lyrics, Some(track)) => track.clone(),
+            _ => return Task::none(),
+        };
+
+        self.lyrics
+            .update(LyricsMessage::Progress(
+                Box::new(track),
+                self.player.position(),
+            ))
+            .map(ApplicationMessage::Lyrics)
+    }
+
+    /// Saves the session and stops playback, then powers off when the reason calls for it and
+    /// exits.
+    fn shutdown(&mut self, reason: ShutdownReason) -> Task<ApplicationMessage> {
+        info!("shutting down: {:?}", reason);
+
+        let session = self.player.session();
+        let place = self.player.place();
+        let _ = self.player.update(PlayerMessage::Stop);
+
+        let store = self.session_store.clone();
+        let catalog = self.catalog.clone();
+        let control = self.settings.settings().power.shutdown.control();
+
+        Task::perform(
+            async move {
+                if let Err(e) = store.save(session).await {
+                    error!("unable to save session: {}", e);
+                }
+                if let Some((track, position)) = place {
+                    save_resume(&catalog, &track, position).await;
+                }
+
+                match tokio::task::spawn_blocking(move || reason.power_off(control.as_ref())).await
+                {
+                    Ok(Err(e)) => error!("unable to power off: {}", e),
+                    Err(e) => error!("unable to power off: {}", e),
+                    Ok(Ok(_)) => {}
+                }
+            },
+            |_| (),
+        )
+        .then(|_| iced::exit())
+    }
+}
+
+/// Stores where a track that resumes was left off.
+async fn save_resume(catalog: &CatalogService, track: &Track, position: Option<Duration>) {
+    if let Err(e) = catalog
+        .track
+        .lock()
+        .await
+        .save_resume(track, position)
+        .await
+    {
+        error!("unable to save the place in {:?}: {}", track.path, e);
+    }
+}
+
+/// Reads a track's tags off the UI thread.
+async fn load_track(path: std::path::PathBuf, start: Option<std::time::Duration>) -> Option<Track> {
+    tokio::task::spawn_blocking(move || {
+        Track::from_path_at(path.clone(), start)
+            .inspect_err(|e| error!("unable to restore {:?}: {}", path, e))
+            .ok()
+    })
+    .await
+    .ok()
+    .flatten()
 }
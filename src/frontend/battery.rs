@@ -1,6 +1,7 @@
-use battery::units::ratio::percent;
 use iced::{
-    Background, Border, Color, Element, Task, widget::progress_bar, widget::progress_bar::Style,
+    Background, Border, Color, Element, Task,
+    alignment::Vertical,
+    widget::{Space, progress_bar, progress_bar::Style, row, text},
 };
 
 use crate::battery::BatteryHandle;
@@ -12,32 +13,52 @@ pub enum BatteryMessage {
 
 #[derive(Debug, Clone)]
 pub struct Battery {
-    handle: BatteryHandle,
+    handle: Option<BatteryHandle>,
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        Self::new(BatteryHandle::detect(None))
+    }
 }
 
 impl Battery {
-    pub fn new() -> Self {
-        Self {
-            handle: BatteryHandle::new().expect("something went horribly wrong"),
-        }
+    pub fn new(handle: Option<BatteryHandle>) -> Self {
+        Self { handle }
     }
 
+    /// The charge gauge, with a marker while charging, or nothing on devices without a battery.
     pub fn view(&self) -> Element<'_, BatteryMessage> {
-        progress_bar(0.0..=100.0, self.handle.charge().get::<percent>())
+        let Some(handle) = &self.handle else {
+            return Space::new().into();
+        };
+
+        let status = handle.status();
+
+        let gauge = progress_bar(0.0..=100.0, status.percent())
             .length(50)
             .girth(30)
             .style(|_| Style {
                 background: Background::Color(Color::BLACK),
                 bar: Background::Color(Color::WHITE),
                 border: Border::default().width(5).color(Color::WHITE),
-            })
-            .into()
+            });
+
+        match status.is_charging() {
+            true => row![text("+").size(30), gauge]
+                .spacing(5)
+                .align_y(Vertical::Center)
+                .into(),
+            false => gauge.into(),
+        }
     }
 
     pub fn update(&mut self, message: BatteryMessage) -> Task<BatteryMessage> {
         match message {
             BatteryMessage::Tick => {
-                let _ = self.handle.refresh();
+                if let Some(handle) = &mut self.handle {
+                    let _ = handle.refresh();
+                }
                 Task::none()
             }
         }
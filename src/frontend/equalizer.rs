@@ -0,0 +1,286 @@
+use iced::{
+    Alignment::Center,
+    Background, Border, Color, Element, Length, Padding, Task,
+    keyboard::{Key, key::Named},
+    widget::{Column, Row, progress_bar, row, text},
+};
+
+use crate::{
+    audio::dsp::{CrossfeedLevel, DspSettings, EqPreset},
+    settings::cycle_option,
+};
+
+/// How far a band or the preamp moves per press, in dB.
+const GAIN_STEP: f32 = 1.0;
+/// Bands boost or cut by at most this much, in dB.
+const MAX_GAIN: f32 = 12.0;
+const MIN_PREAMP: f32 = -12.0;
+const MAX_PREAMP: f32 = 6.0;
+const CROSSFEED_OPTIONS: [Option<CrossfeedLevel>; 4] = [
+    None,
+    Some(CrossfeedLevel::Light),
+    Some(CrossfeedLevel::Medium),
+    Some(CrossfeedLevel::Strong),
+];
+const BAR_HEIGHT: f32 = 300.0;
+const BAR_WIDTH: f32 = 30.0;
+const LABEL_TEXT_SIZE: f32 = 24.0;
+const INACTIVE_COLOR: Color = Color::from_rgb(0.5, 0.5, 0.5);
+
+#[derive(Debug, Clone)]
+pub enum EqualizerMessage {
+    InputEvent(Key),
+    /// The settings as edited, to be saved and applied.
+    Changed(DspSettings),
+}
+
+/// A row on the equalizer screen, the bands each get one after the others.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EqualizerRow {
+    Equalizer,
+    Preset,
+    Preamp,
+    Crossfeed,
+    Band(usize),
+}
+
+impl EqualizerRow {
+    const FIXED: [EqualizerRow; 4] = [
+        EqualizerRow::Equalizer,
+        EqualizerRow::Preset,
+        EqualizerRow::Preamp,
+        EqualizerRow::Crossfeed,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            EqualizerRow::Equalizer => "Equalizer",
+            EqualizerRow::Preset => "Preset",
+            EqualizerRow::Preamp => "Preamp",
+            EqualizerRow::Crossfeed => "Crossfeed",
+            EqualizerRow::Band(_) => "Band",
+        }
+    }
+
+    fn value(&self, settings: &DspSettings) -> String {
+        match self {
+            EqualizerRow::Equalizer => match settings.equalizer_enabled {
+                true => "On".to_string(),
+                false => "Off".to_string(),
+            },
+            EqualizerRow::Preset => settings.preset.to_string(),
+            EqualizerRow::Preamp => match settings.preamp_enabled {
+                true => format_gain(settings.preamp),
+                false => "Off".to_string(),
+            },
+            EqualizerRow::Crossfeed => match settings.crossfeed_enabled {
+                true => settings.crossfeed.to_string(),
+                false => "Off".to_string(),
+            },
+            EqualizerRow::Band(index) => settings
+                .current_bands()
+                .get(*index)
+                .map(|band| {
+                    format!(
+                        "{}Hz {}",
+                        format_frequency(band.frequency),
+                        format_gain(band.gain)
+                    )
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Moves the row's setting on, which turns its stage on if it was bypassed.
+    fn adjust(&self, settings: &mut DspSettings, forward: bool) {
+        let step = match forward {
+            true => GAIN_STEP,
+            false => -GAIN_STEP,
+        };
+
+        match self {
+            EqualizerRow::Equalizer => settings.equalizer_enabled = !settings.equalizer_enabled,
+            EqualizerRow::Preset => {
+                settings.preset = cycle_option(&EqPreset::ALL, settings.preset, forward);
+                settings.equalizer_enabled = true;
+            }
+            EqualizerRow::Preamp => {
+                settings.preamp = (settings.preamp + step).clamp(MIN_PREAMP, MAX_PREAMP);
+                settings.preamp_enabled = true;
+            }
+            EqualizerRow::Crossfeed => {
+                let current = Some(settings.crossfeed).filter(|_| settings.crossfeed_enabled);
+                match cycle_option(&CROSSFEED_OPTIONS, current, forward) {
+                    Some(level) => {
+                        settings.crossfeed = level;
+                        settings.crossfeed_enabled = true;
+                    }
+                    None => settings.crossfeed_enabled = false,
+                }
+            }
+            EqualizerRow::Band(index) => {
+                settings.customize();
+                if let Some(band) = settings.bands.get_mut(*index) {
+                    band.gain = (band.gain + step).clamp(-MAX_GAIN, MAX_GAIN);
+                }
+                settings.equalizer_enabled = true;
+            }
+        }
+    }
+
+    /// Bypasses the row's stage, or brings it back.
+    fn toggle(&self, settings: &mut DspSettings) {
+        match self {
+            EqualizerRow::Equalizer | EqualizerRow::Preset | EqualizerRow::Band(_) => {
+                settings.equalizer_enabled = !settings.equalizer_enabled
+            }
+            EqualizerRow::Preamp => settings.preamp_enabled = !settings.preamp_enabled,
+            EqualizerRow::Crossfeed => settings.crossfeed_enabled = !settings.crossfeed_enabled,
+        }
+    }
+}
+
+/// Editor for the preamp, equalizer and crossfeed, with the bands drawn as sliders.
+#[derive(Debug, Clone, Default)]
+pub struct EqualizerPage {
+    settings: DspSettings,
+    selected: usize,
+}
+
+impl EqualizerPage {
+    pub fn new(settings: DspSettings) -> Self {
+        Self {
+            settings,
+            selected: 0,
+        }
+    }
+
+    /// Takes the settings as they were saved, keeping the selection where it is.
+    pub fn apply(&mut self, settings: DspSettings) {
+        self.settings = settings;
+        self.selected = self.selected.min(self.rows().len() - 1);
+    }
+
+    fn rows(&self) -> Vec<EqualizerRow> {
+        let bands = self.settings.current_bands().len();
+
+        EqualizerRow::FIXED
+            .into_iter()
+            .chain((0..bands).map(EqualizerRow::Band))
+            .collect()
+    }
+
+    pub fn view(&self) -> Element<'_, EqualizerMessage> {
+        let rows = self.rows();
+        let selected = rows[self.selected];
+
+        let mut col = Column::new()
+            .spacing(5)
+            .padding(Padding::new(0.0).horizontal(10.0));
+
+        for row in EqualizerRow::FIXED {
+            let label = match row == selected {
+                true => format!("> {}", row.label()),
+                false => row.label().to_string(),
+            };
+
+            col = col.push(
+                row![
+                    text(label).width(Length::Fill),
+                    text(row.value(&self.settings)),
+                ]
+                .height(60),
+            );
+        }
+
+        let enabled = self.settings.equalizer_enabled;
+        let bands = self.settings.current_bands().into_iter().enumerate().fold(
+            Row::new(),
+            |bars, (index, band)| {
+                let color = match enabled && selected == EqualizerRow::Band(index) {
+                    true => Color::WHITE,
+                    false => INACTIVE_COLOR,
+                };
+
+                let bar = progress_bar(-MAX_GAIN..=MAX_GAIN, band.gain)
+                    .vertical()
+                    .length(BAR_HEIGHT)
+                    .girth(BAR_WIDTH)
+                    .style(move |_| progress_bar::Style {
+                        background: Background::Color(Color::BLACK),
+                        bar: Background::Color(color),
+                        border: Border::default().width(3).color(color),
+                    });
+
+                bars.push(
+                    Column::new()
+                        .align_x(Center)
+                        .spacing(5)
+                        .width(Length::Fill)
+                        .push(bar)
+                        .push(text(format_frequency(band.frequency)).size(LABEL_TEXT_SIZE)),
+                )
+            },
+        );
+
+        let detail = match selected {
+            EqualizerRow::Band(_) => selected.value(&self.settings),
+            _ => String::new(),
+        };
+
+        col.push(bands.padding(Padding::new(0.0).vertical(10.0)))
+            .push(text(detail).width(Length::Fill).align_x(Center))
+            .into()
+    }
+
+    pub fn update(&mut self, message: EqualizerMessage) -> Task<EqualizerMessage> {
+        match message {
+            EqualizerMessage::InputEvent(key) => match key.as_ref() {
+                Key::Named(Named::ArrowUp) => {
+                    self.selected = self.selected.saturating_sub(1);
+                    Task::none()
+                }
+                Key::Named(Named::ArrowDown) => {
+                    self.selected = (self.selected + 1).min(self.rows().len() - 1);
+                    Task::none()
+                }
+                Key::Named(Named::ArrowLeft) => {
+                    self.change(|row, settings| row.adjust(settings, false))
+                }
+                Key::Named(Named::ArrowRight) => {
+                    self.change(|row, settings| row.adjust(settings, true))
+                }
+                Key::Named(Named::Enter) => self.change(|row, settings| row.toggle(settings)),
+                _ => Task::none(),
+            },
+            EqualizerMessage::Changed(_) => Task::none(),
+        }
+    }
+
+    /// Applies `change` to the selected row's setting and announces the result.
+    fn change(
+        &mut self,
+        change: impl FnOnce(&EqualizerRow, &mut DspSettings),
+    ) -> Task<EqualizerMessage> {
+        let row = self.rows()[self.selected];
+        change(&row, &mut self.settings);
+
+        Task::done(EqualizerMessage::Changed(self.settings.clone()))
+    }
+}
+
+/// "+3 dB", "0 dB", "-4.5 dB"
+fn format_gain(gain: f32) -> String {
+    match gain > 0.0 {
+        true => format!("+{} dB", gain),
+        false => format!("{} dB", gain),
+    }
+}
+
+/// "62", "1k", "16k"
+fn format_frequency(frequency: f32) -> String {
+    match frequency >= 1000.0 {
+        true => format!("{}k", (frequency / 100.0).round() / 10.0),
+        false => format!("{}", frequency.round()),
+    }
+}
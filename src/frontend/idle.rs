@@ -0,0 +1,303 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use iced::{
+    Element, Length, Padding, Subscription, Task, Vector,
+    time::every,
+    widget::{Space, container, text},
+};
+use tracing::error;
+
+use crate::{
+    display::Backlight,
+    settings::{DisplaySettings, IdleScreen},
+};
+
+/// How often the interface moves to the next pixel shift offset.
+const SHIFT_INTERVAL: Duration = Duration::from_secs(120);
+/// Offsets walked through by the pixel shift, in units of `SHIFT_DISTANCE`.
+const SHIFT_PATTERN: [(f32, f32); 8] = [
+    (0.0, 0.0),
+    (1.0, 0.0),
+    (2.0, 0.0),
+    (2.0, 1.0),
+    (2.0, 2.0),
+    (1.0, 2.0),
+    (0.0, 2.0),
+    (0.0, 1.0),
+];
+const SHIFT_DISTANCE: f32 = 2.0;
+/// Room left around the idle clock so it can move about the screen.
+const CLOCK_WIDTH: u32 = 160;
+const CLOCK_HEIGHT: u32 = 60;
+const SCREEN_SIZE: u32 = 720;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IdleState {
+    #[default]
+    Awake,
+    Dimmed,
+    Asleep,
+}
+
+#[derive(Debug, Clone)]
+pub enum IdleMessage {
+    Tick(Instant),
+    Activity,
+    BacklightChanged,
+    Error(String),
+}
+
+/// Tracks input activity and dims, then sleeps, the display when there's none.
+pub struct Idle {
+    settings: DisplaySettings,
+    state: IdleState,
+    started: Instant,
+    last_input: Instant,
+    shift_step: usize,
+    datetime: DateTime<Local>,
+}
+
+impl Idle {
+    pub fn new(settings: DisplaySettings) -> (Self, Task<IdleMessage>) {
+        let now = Instant::now();
+        let idle = Self {
+            settings,
+            state: IdleState::Awake,
+            started: now,
+            last_input: now,
+            shift_step: 0,
+            datetime: Local::now(),
+        };
+        let task = idle.apply_backlight();
+
+        (idle, task)
+    }
+
+    pub fn state(&self) -> IdleState {
+        self.state
+    }
+
+    pub fn is_awake(&self) -> bool {
+        self.state == IdleState::Awake
+    }
+
+    /// Whether dimming has to be drawn over the interface since there's no backlight to turn down.
+    pub fn dims_in_software(&self) -> bool {
+        self.state == IdleState::Dimmed && self.settings.backlight.is_none()
+    }
+
+    /// Current pixel shift offset for static elements.
+    pub fn offset(&self) -> Vector {
+        match self.settings.pixel_shift {
+            true => {
+                let (x, y) = SHIFT_PATTERN[self.shift_step];
+                Vector::new(x * SHIFT_DISTANCE, y * SHIFT_DISTANCE)
+            }
+            false => Vector::ZERO,
+        }
+    }
+
+    /// Swaps in new display settings, re-applying the backlight level for the current state.
+    pub fn apply(&mut self, settings: DisplaySettings) -> Task<IdleMessage> {
+        self.settings = settings;
+        self.apply_backlight()
+    }
+
+    /// The screen shown while asleep, either nothing or a clock that moves every minute.
+    pub fn view(&self) -> Element<'_, IdleMessage> {
+        let content: Element<'_, IdleMessage> = match self.settings.idle_screen {
+            IdleScreen::Blank => Space::new().into(),
+            IdleScreen::Clock => {
+                let (x, y) = clock_position(&self.datetime);
+
+                container(text(self.datetime.format("%H:%M").to_string()))
+                    .padding(Padding::new(0.0).left(x as f32).top(y as f32))
+                    .into()
+            }
+        };
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    pub fn update(&mut self, message: IdleMessage) -> Task<IdleMessage> {
+        match message {
+            IdleMessage::Tick(now) => {
+                self.datetime = Local::now();
+                self.shift_step = ((now.duration_since(self.started).as_secs()
+                    / SHIFT_INTERVAL.as_secs()) as usize)
+                    % SHIFT_PATTERN.len();
+
+                let idle_for = now.duration_since(self.last_input);
+                let state = match (self.settings.dim_after(), self.settings.sleep_after()) {
+                    (_, Some(sleep_after)) if idle_for >= sleep_after => IdleState::Asleep,
+                    (Some(dim_after), _) if idle_for >= dim_after => IdleState::Dimmed,
+                    _ => IdleState::Awake,
+                };
+
+                self.transition(state)
+            }
+            IdleMessage::Activity => {
+                self.last_input = Instant::now();
+                self.transition(IdleState::Awake)
+            }
+            IdleMessage::BacklightChanged => Task::none(),
+            IdleMessage::Error(e) => {
+                error!("idle error: {}", e);
+                Task::none()
+            }
+        }
+    }
+
+    pub fn subscription(&self) -> Subscription<IdleMessage> {
+        every(Duration::from_secs(1)).map(IdleMessage::Tick)
+    }
+
+    fn transition(&mut self, state: IdleState) -> Task<IdleMessage> {
+        if self.state == state {
+            return Task::none();
+        }
+
+        self.state = state;
+        self.apply_backlight()
+    }
+
+    fn apply_backlight(&self) -> Task<IdleMessage> {
+        let Some(path) = self.settings.backlight.clone() else {
+            return Task::none();
+        };
+
+        let level = match (self.state, self.settings.idle_screen) {
+            (IdleState::Awake, _) => self.settings.brightness,
+            (IdleState::Dimmed, _) | (IdleState::Asleep, IdleScreen::Clock) => {
+                self.settings.dim_brightness
+            }
+            (IdleState::Asleep, IdleScreen::Blank) => 0.0,
+        };
+
+        Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || Backlight::new(path).set_level(level))
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .map_err(|e| e.to_string())
+            },
+            |result| match result {
+                Ok(()) => IdleMessage::BacklightChanged,
+                Err(e) => IdleMessage::Error(e),
+            },
+        )
+    }
+}
+
+/// Picks a spot for the idle clock from the current minute, so it wanders without needing any
+/// state of its own.
+fn clock_position(datetime: &DateTime<Local>) -> (u32, u32) {
+    let minute = (datetime.timestamp() / 60) as u32;
+
+    (
+        minute.wrapping_mul(7919) % (SCREEN_SIZE - CLOCK_WIDTH),
+        minute.wrapping_mul(104729) % (SCREEN_SIZE - CLOCK_HEIGHT),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn idle(settings: DisplaySettings) -> Idle {
+        Idle::new(settings).0
+    }
+
+    fn tick(idle: &mut Idle, after: u64) {
+        let now = idle.last_input + Duration::from_secs(after);
+        let _ = idle.update(IdleMessage::Tick(now));
+    }
+
+    #[test]
+    fn dims_then_sleeps_without_input_and_wakes_on_activity() {
+        let mut idle = idle(DisplaySettings::default());
+
+        tick(&mut idle, 29);
+        assert_eq!(idle.state(), IdleState::Awake);
+        tick(&mut idle, 30);
+        assert_eq!(idle.state(), IdleState::Dimmed);
+        assert!(idle.dims_in_software());
+        tick(&mut idle, 120);
+        assert_eq!(idle.state(), IdleState::Asleep);
+
+        let _ = idle.update(IdleMessage::Activity);
+        assert!(idle.is_awake());
+    }
+
+    #[test]
+    fn zero_timeouts_never_dim_or_sleep() {
+        let mut idle = idle(DisplaySettings {
+            dim_after: 0,
+            sleep_after: 0,
+            ..Default::default()
+        });
+        tick(&mut idle, 86_400);
+        assert!(idle.is_awake());
+
+        let mut idle = self::idle(DisplaySettings {
+            dim_after: 0,
+            ..Default::default()
+        });
+        tick(&mut idle, 60);
+        assert!(idle.is_awake());
+        tick(&mut idle, 120);
+        assert_eq!(idle.state(), IdleState::Asleep);
+    }
+
+    #[test]
+    fn pixel_shift_walks_its_pattern_only_when_enabled() {
+        let mut idle = idle(DisplaySettings::default());
+        assert_eq!(idle.offset(), Vector::ZERO);
+
+        let shift = SHIFT_INTERVAL.as_secs();
+        let mut offsets = vec![];
+        for step in 0..=SHIFT_PATTERN.len() as u64 {
+            let now = idle.started + Duration::from_secs(step * shift);
+            let _ = idle.update(IdleMessage::Tick(now));
+            offsets.push(idle.offset());
+        }
+        assert_eq!(offsets[1], Vector::new(SHIFT_DISTANCE, 0.0));
+        assert_eq!(offsets.first(), offsets.last());
+        assert!(
+            offsets
+                .iter()
+                .all(|offset| offset.x <= 2.0 * SHIFT_DISTANCE && offset.y <= 2.0 * SHIFT_DISTANCE)
+        );
+
+        let _ = idle.apply(DisplaySettings {
+            pixel_shift: false,
+            ..Default::default()
+        });
+        assert_eq!(idle.offset(), Vector::ZERO);
+    }
+
+    #[test]
+    fn the_clock_moves_each_minute_and_stays_on_screen() {
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let positions: Vec<(u32, u32)> = (0..24 * 60)
+            .map(|minute| clock_position(&(start + chrono::Duration::minutes(minute))))
+            .collect();
+
+        assert!(
+            positions.iter().all(|(x, y)| {
+                x + CLOCK_WIDTH <= SCREEN_SIZE && y + CLOCK_HEIGHT <= SCREEN_SIZE
+            })
+        );
+        assert!(positions.windows(2).all(|pair| pair[0] != pair[1]));
+        assert_eq!(
+            clock_position(&(start + chrono::Duration::seconds(59))),
+            positions[0]
+        );
+    }
+}
@@ -1,30 +1,40 @@
 use std::{
+    collections::HashMap,
     fmt::{Debug, Display, Formatter},
+    ops::Range,
     slice,
+    time::Duration,
 };
 
 use crate::{
-    model::{CatalogItem, CatalogMetadata, artist::Artist, release::Release, track::Track},
-    repository::{artist::ArtistFilter, release::ReleaseFilter, track::TrackFilter},
+    artwork::{ArtworkCache, ArtworkKey, Thumbnail},
+    frontend::library::source::LibrarySource,
+    model::{
+        CatalogItem, CatalogMetadata, collation,
+        stats::{LibraryStats, format_span},
+        track::Track,
+    },
     service::CatalogService,
 };
-use iced::widget::{image, operation::scroll_to, row};
+use iced::widget::{Space, image, operation::scroll_to, row, stack};
 use iced::{
     Background, Border, Color, Element, Length, Padding, Shadow, Task, Theme,
     alignment::{Horizontal, Vertical},
-    keyboard::Key,
+    keyboard::{Key, key::Named},
     widget::{
-        Id, button,
+        self, Id, button,
         button::{Status, Style},
         container,
         image::Handle,
         keyed::Column,
-        scrollable, text,
+        progress_bar, scrollable, text,
     },
 };
 use thiserror::Error;
 use tracing::info;
 
+pub mod source;
+
 const FAVORITE_ICON: &[u8] = include_bytes!("../../resources/favorite.png");
 
 const ITEM_HEIGHT: f32 = 60.0;
@@ -32,6 +42,23 @@ const ITEM_SPACING: f32 = 5.0;
 const ROW_STRIDE: f32 = ITEM_HEIGHT + ITEM_SPACING;
 const MENU_HEIGHT: f32 = 50.0;
 const VIEWPORT_HEIGHT: f32 = 720.0 - MENU_HEIGHT; // 670.0
+const PAGE_SIZE: usize = (VIEWPORT_HEIGHT / ROW_STRIDE) as usize; // 10
+// Items held in memory at once, rows outside the window are fetched as the selection nears them.
+const WINDOW_SIZE: usize = PAGE_SIZE * 6;
+/// Tracks queued around the one picked from a list, the rest of a long list isn't loaded for it.
+const QUEUE_BEFORE: usize = 50;
+const QUEUE_AFTER: usize = 500;
+const SPACER_BEFORE_KEY: i64 = -1;
+const SPACER_AFTER_KEY: i64 = -2;
+const THUMBNAIL_SIZE: f32 = 52.0;
+const DISC_LABEL_WIDTH: f32 = 90.0;
+/// Size of the bar marking how far into a partly played track playback was left off.
+const RESUME_BAR_WIDTH: f32 = 60.0;
+const RESUME_BAR_HEIGHT: f32 = 10.0;
+
+// Holding this button shows the A-Z index, arrows then move between letters.
+const INDEX_KEY: Named = Named::Tab;
+const INDEX_LETTER_SIZE: f32 = 22.0;
 
 #[derive(Debug, Clone, Default)]
 pub enum LibraryView {
@@ -39,6 +66,14 @@ pub enum LibraryView {
     Artist,
     Release,
     Track,
+    Genre,
+    Decade,
+    Book,
+    RecentlyAdded,
+    RecentlyPlayed,
+    Favorites,
+    Playlist,
+    Stats,
 }
 
 impl Display for LibraryView {
@@ -47,6 +82,14 @@ impl Display for LibraryView {
             LibraryView::Artist => write!(f, "Artist"),
             LibraryView::Release => write!(f, "Release"),
             LibraryView::Track => write!(f, "Track"),
+            LibraryView::Genre => write!(f, "Genre"),
+            LibraryView::Decade => write!(f, "Decade"),
+            LibraryView::Book => write!(f, "Book"),
+            LibraryView::RecentlyAdded => write!(f, "Recently Added"),
+            LibraryView::RecentlyPlayed => write!(f, "Recently Played"),
+            LibraryView::Favorites => write!(f, "Favorites"),
+            LibraryView::Playlist => write!(f, "Playlist"),
+            LibraryView::Stats => write!(f, "Stats"),
         }
     }
 }
@@ -99,6 +142,10 @@ impl LibraryItem {
         match self.catalog_item.metadata {
             CatalogMetadata::Artist(_) => true,
             CatalogMetadata::Release(_) => true,
+            CatalogMetadata::Playlist(_) => true,
+            CatalogMetadata::Genre(_) => true,
+            CatalogMetadata::Decade(_) => true,
+            CatalogMetadata::Book(_) => true,
             CatalogMetadata::Track(_) => false,
         }
     }
@@ -106,12 +153,22 @@ impl LibraryItem {
 
 #[derive(Debug, Clone)]
 pub enum LibraryMessage {
-    ItemsLoad(Vec<LibraryItem>),
-    TrackSelect(Track),
-    ItemRefresh(Vec<CatalogItem<CatalogMetadata>>),
+    ListLoad(LibraryListing),
+    WindowLoad(LibraryWindow),
+    /// Every track in the list and the position of the chosen one, to be queued.
+    TrackSelect(Vec<Track>, usize),
     ItemLoad(Option<LibraryItem>),
+    /// Queries the current list again after it was changed.
+    Reload,
+    /// Where a track that resumes was left off was saved, shown without reloading the list.
+    Bookmarked(Track, Option<Duration>),
+    /// A track's speed was saved, kept with it so it plays at that speed when picked again.
+    SpeedChosen(Track, f32),
     ChangeView(LibraryView),
     InputEvent(Key),
+    InputHeld(Key),
+    InputReleased(Key),
+    ThumbnailLoad(ArtworkKey, Option<Handle>),
     Scrolled(f32),
     Error(String),
 }
@@ -120,77 +177,171 @@ pub enum LibraryMessage {
 pub enum LibraryItemAction {
     SelectNext,
     SelectPrevious,
+    SelectForward(usize),
+    SelectBackward(usize),
     SelectByCatalogId(i64),
+    SelectLetter(char),
+}
+
+/// A freshly queried list, its size and letter index plus the first window of items.
+#[derive(Debug, Clone)]
+pub struct LibraryListing {
+    generation: u64,
+    source: LibrarySource,
+    total: usize,
+    index: Vec<(char, usize)>,
+    items: Vec<CatalogItem<CatalogMetadata>>,
+    stats: Option<LibraryStats>,
 }
 
+/// A window of items starting at `offset` within the current list.
 #[derive(Debug, Clone)]
+pub struct LibraryWindow {
+    generation: u64,
+    offset: usize,
+    items: Vec<CatalogItem<CatalogMetadata>>,
+}
+
+/// The loaded window of a list, only `inner` is held in memory while `total` items exist.
+#[derive(Debug, Clone, Default)]
 pub struct LibraryItems {
     inner: Vec<LibraryItem>,
+    offset: usize,
+    total: usize,
+    selected: Option<usize>,
+    index: Vec<(char, usize)>,
 }
 
 impl LibraryItems {
-    pub fn new(items: Vec<LibraryItem>) -> Self {
-        Self { inner: items }
+    pub fn new(total: usize, index: Vec<(char, usize)>, items: Vec<LibraryItem>) -> Self {
+        let mut library_items = Self {
+            inner: items,
+            offset: 0,
+            total,
+            selected: (total > 0).then_some(0),
+            index,
+        };
+        library_items.mark_selected();
+        library_items
     }
 
     pub fn find(&self, catalog_id: i64) -> Option<&LibraryItem> {
         self.inner
             .iter()
-            .find(|item| item.catalog_item.clone().id == catalog_id)
+            .find(|item| item.catalog_item.id == catalog_id)
     }
 
     pub fn select(&mut self, action: LibraryItemAction) {
-        let selected_idx = self.inner.iter().position(|item| item.selected == true);
-
-        match selected_idx {
-            Some(index) => {
-                // Toggled the element as no longer selected
-                self.inner[index].toggled_selected();
-
-                match action {
-                    LibraryItemAction::SelectNext => {
-                        if index < self.inner.len() - 1 {
-                            self.inner[index + 1].toggled_selected();
-                        } else {
-                            self.inner[index].toggled_selected();
-                        }
-                    }
-                    LibraryItemAction::SelectPrevious => {
-                        if index > 0 {
-                            self.inner[index - 1].toggled_selected();
-                        } else {
-                            self.inner[index].toggled_selected();
-                        }
-                    }
-                    LibraryItemAction::SelectByCatalogId(id) => {
-                        if let Some(item_idx) = self
-                            .inner
-                            .iter()
-                            .position(|item| item.catalog_item.id == id)
-                        {
-                            self.inner[item_idx].toggled_selected();
-                        }
-                    }
-                }
-            }
-            None => self.inner[0].toggled_selected(),
+        if self.total == 0 {
+            return;
         }
+
+        let Some(index) = self.selected else {
+            self.selected = Some(0);
+            self.mark_selected();
+            return;
+        };
+
+        let last = self.total - 1;
+        let target = match action {
+            LibraryItemAction::SelectNext => (index + 1).min(last),
+            LibraryItemAction::SelectPrevious => index.saturating_sub(1),
+            LibraryItemAction::SelectForward(count) => index.saturating_add(count).min(last),
+            LibraryItemAction::SelectBackward(count) => index.saturating_sub(count),
+            LibraryItemAction::SelectByCatalogId(id) => self
+                .inner
+                .iter()
+                .position(|item| item.catalog_item.id == id)
+                .map(|position| self.offset + position)
+                .unwrap_or(index),
+            LibraryItemAction::SelectLetter(letter) => {
+                self.letter_position(letter).unwrap_or(index)
+            }
+        };
+
+        self.selected = Some(target);
+        self.mark_selected();
     }
 
+    /// The selected item, `None` while its window is still being fetched.
     pub fn selected(&self) -> Option<&LibraryItem> {
-        self.inner.iter().find(|item| item.selected == true)
+        self.selected
+            .and_then(|index| index.checked_sub(self.offset))
+            .and_then(|position| self.inner.get(position))
+    }
+
+    pub fn selected_index(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Index letters which have at least one item filed under them, in index order.
+    pub fn letters(&self) -> Vec<char> {
+        collation::INDEX_LETTERS
+            .into_iter()
+            .filter(|letter| self.index.iter().any(|(l, _)| l == letter))
+            .collect()
+    }
+
+    /// Position of the first item filed under `letter`, or the first one after it when the
+    /// letter has no items.
+    fn letter_position(&self, letter: char) -> Option<usize> {
+        let rank = |l: char| collation::INDEX_LETTERS.iter().position(|c| *c == l);
+        let target = rank(letter)?;
+
+        self.index
+            .iter()
+            .filter(|(l, _)| match l {
+                '#' => target == 0,
+                l => rank(*l).is_some_and(|r| r >= target),
+            })
+            .map(|(_, position)| *position)
+            .min()
     }
 
-    pub fn refresh(&mut self, items: Vec<LibraryItem>) -> &Self {
+    /// Replaces the loaded window.
+    pub fn load_window(&mut self, offset: usize, items: Vec<LibraryItem>) {
+        self.offset = offset;
         self.inner = items;
-        self.inner[0].toggled_selected();
+        self.mark_selected();
+    }
+
+    /// Whether the loaded window holds every item in `range`.
+    pub fn covers(&self, range: Range<usize>) -> bool {
+        let end = range.end.min(self.total);
+        range.start >= self.offset && end <= self.offset + self.inner.len()
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
 
-        self
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    fn mark_selected(&mut self) {
+        let offset = self.offset;
+        let selected = self.selected;
+
+        for (position, item) in self.inner.iter_mut().enumerate() {
+            item.selected = selected == Some(offset + position);
+        }
     }
 
     pub fn iter(&self) -> slice::Iter<'_, LibraryItem> {
         self.inner.iter()
     }
+
+    /// The loaded copies of `track`, to update when something about it is saved.
+    pub fn tracks_mut(&mut self, track: &Track) -> impl Iterator<Item = &mut Track> {
+        self.inner
+            .iter_mut()
+            .filter_map(|item| match &mut item.catalog_item.metadata {
+                CatalogMetadata::Track(loaded) => Some(loaded),
+                _ => None,
+            })
+            .filter(|loaded| loaded.path == track.path && loaded.start == track.start)
+    }
 }
 
 impl IntoIterator for LibraryItems {
@@ -222,67 +373,134 @@ pub enum LibraryError {
 #[derive(Debug, Clone)]
 pub struct Library {
     catalog: Option<CatalogService>,
+    source: LibrarySource,
+    generation: u64,
+    pending_window: Option<usize>,
     items: LibraryItems,
+    stats: Option<LibraryStats>,
     scroll_id: Id,
     current_scroll_y: f32,
     favorite_image: Handle,
+    index_letter: Option<char>,
+    held_repeats: u32,
+    thumbnails: HashMap<ArtworkKey, Option<Handle>>,
 }
 
 impl Default for Library {
     fn default() -> Self {
         Self {
             catalog: None,
-            items: LibraryItems::new(vec![]),
+            source: LibrarySource::Artists,
+            generation: 0,
+            pending_window: None,
+            items: LibraryItems::default(),
+            stats: None,
             scroll_id: Id::unique(),
             current_scroll_y: 0.0,
             favorite_image: Handle::from_bytes(FAVORITE_ICON),
+            index_letter: None,
+            held_repeats: 0,
+            thumbnails: HashMap::new(),
         }
     }
 }
 
 impl Library {
     pub fn new(catalog: CatalogService) -> (Library, Task<LibraryMessage>) {
-        let library = Self {
-            catalog: Some(catalog.clone()),
-            items: LibraryItems::new(vec![]),
-            scroll_id: Id::unique(),
-            current_scroll_y: 0.0,
-            favorite_image: Handle::from_bytes(FAVORITE_ICON),
+        let mut library = Self {
+            catalog: Some(catalog),
+            ..Default::default()
         };
 
-        let task = Task::perform(
-            async move {
-                catalog
-                    .artist
-                    .lock()
-                    .await
-                    .list_artists(ArtistFilter::default())
-                    .await
-            },
-            |result| match result {
-                Ok(items) => {
-                    info!("ItemsLoad received {} items", items.len());
-                    LibraryMessage::ItemsLoad(
-                        items
-                            .into_iter()
-                            .map(|i| LibraryItem::new(i.into()))
-                            .collect(),
-                    )
-                }
-                Err(e) => LibraryMessage::Error(e.to_string()),
-            },
-        );
+        let task = library.load(LibrarySource::Artists);
 
         (library, task)
     }
 
     pub fn view(&self) -> Element<'_, LibraryMessage> {
+        if let (LibrarySource::Stats, Some(stats)) = (&self.source, &self.stats) {
+            return self.stats_view(stats);
+        }
+
         let mut col = Column::new()
             .spacing(ITEM_SPACING)
             .padding(Padding::new(0.0).bottom(ITEM_HEIGHT));
 
-        for item in self.items.iter() {
-            let mut row = row![item.view()].height(ITEM_HEIGHT);
+        // Rows outside the loaded window are stood in for by spacers so offsets stay stable.
+        let before = self.items.offset();
+        if before > 0 {
+            col = col.push(
+                SPACER_BEFORE_KEY,
+                Space::new().height(before as f32 * ROW_STRIDE - ITEM_SPACING),
+            );
+        }
+
+        let discs = match self.source {
+            LibrarySource::Tracks { .. } => disc_starts(&self.items),
+            _ => None,
+        };
+
+        for (position, item) in self.items.iter().enumerate() {
+            let mut row = row![].height(ITEM_HEIGHT).align_y(Vertical::Center);
+
+            // Multi-disc releases get a gutter marking where each disc starts.
+            if let Some(discs) = &discs {
+                let label = match discs[position] {
+                    Some(disc) => text(format!("Disc {}", disc)),
+                    None => text(""),
+                };
+
+                row = row.push(
+                    container(label)
+                        .padding(Padding::default().left(10))
+                        .width(DISC_LABEL_WIDTH)
+                        .center_y(ITEM_HEIGHT),
+                );
+            }
+
+            if let Some(key) = artwork_key(&item.catalog_item) {
+                let thumbnail: Element<'_, LibraryMessage> =
+                    match self.thumbnails.get(&key).and_then(Option::as_ref) {
+                        Some(handle) => image(handle)
+                            .width(THUMBNAIL_SIZE)
+                            .height(THUMBNAIL_SIZE)
+                            .into(),
+                        None => Space::new()
+                            .width(THUMBNAIL_SIZE)
+                            .height(THUMBNAIL_SIZE)
+                            .into(),
+                    };
+
+                row = row.push(
+                    container(thumbnail)
+                        .padding(Padding::default().left(10))
+                        .center_y(ITEM_HEIGHT),
+                );
+            }
+
+            row = row.push(item.view());
+
+            if let CatalogMetadata::Track(track) = &item.catalog_item.metadata
+                && let Some(resume) = track.resume
+            {
+                row = row.push(
+                    container(
+                        progress_bar(
+                            0.0..=track.duration.as_secs_f32().max(1.0),
+                            resume.as_secs_f32(),
+                        )
+                        .length(RESUME_BAR_WIDTH)
+                        .girth(RESUME_BAR_HEIGHT)
+                        .style(|_| progress_bar::Style {
+                            background: Background::Color(Color::BLACK),
+                            bar: Background::Color(Color::WHITE),
+                            border: Border::default().width(1).color(Color::WHITE),
+                        }),
+                    )
+                    .padding(Padding::default().horizontal(10))
+                    .center_y(ITEM_HEIGHT),
+                );
+            }
 
             if item.catalog_item.favorite {
                 row = row.push(
@@ -298,329 +516,355 @@ impl Library {
             col = col.push(item.catalog_item.id, row);
         }
 
-        scrollable(col)
+        let after = self
+            .items
+            .total()
+            .saturating_sub(before + self.items.iter().len());
+        if after > 0 {
+            col = col.push(
+                SPACER_AFTER_KEY,
+                Space::new().height(after as f32 * ROW_STRIDE - ITEM_SPACING),
+            );
+        }
+
+        let list = scrollable(col)
             .id(self.scroll_id.clone())
             .on_scroll(|viewport| {
                 let y = viewport.absolute_offset().y;
                 let snapped = (y / ROW_STRIDE).round() * ROW_STRIDE;
                 LibraryMessage::Scrolled(snapped)
             })
-            .style(|theme, status| scrollable::Style {
-                container: container::Style::default(),
-                vertical_rail: scrollable::Rail {
-                    background: None,
-                    border: Border::default(),
-                    scroller: scrollable::Scroller {
-                        border: Border::default(),
-                        background: Background::Color(Color::TRANSPARENT),
-                    },
-                },
-                horizontal_rail: scrollable::Rail {
-                    background: None,
-                    border: Border::default(),
-                    scroller: scrollable::Scroller {
-                        border: Border::default(),
-                        background: Background::Color(Color::TRANSPARENT),
-                    },
-                },
-                gap: None,
-                auto_scroll: scrollable::AutoScroll {
-                    background: Background::Color(Color::TRANSPARENT),
-                    border: Border::default(),
-                    shadow: Shadow::default(),
-                    icon: Color::WHITE,
-                },
-            })
+            .style(hidden_scrollbar)
+            .height(Length::Fill);
+
+        match self.index_letter {
+            Some(letter) => stack![list, self.index_view(letter)].into(),
+            None => list.into(),
+        }
+    }
+
+    /// The stats page, one row per figure laid out like list items so it scrolls the same way.
+    fn stats_view(&self, stats: &LibraryStats) -> Element<'_, LibraryMessage> {
+        let mut col = widget::Column::new()
+            .spacing(ITEM_SPACING)
+            .padding(Padding::new(0.0).bottom(ITEM_HEIGHT));
+
+        for (label, value) in stats_rows(stats) {
+            let color = match value.is_empty() {
+                true => Color::WHITE.scale_alpha(0.5),
+                false => Color::WHITE,
+            };
+
+            col = col.push(
+                row![
+                    text(label)
+                        .color(color)
+                        .wrapping(text::Wrapping::None)
+                        .width(Length::Fill),
+                    text(value).wrapping(text::Wrapping::None),
+                ]
+                .height(ITEM_HEIGHT)
+                .align_y(Vertical::Center)
+                .padding(Padding::new(0.0).left(10).right(10)),
+            );
+        }
+
+        scrollable(col)
+            .id(self.scroll_id.clone())
+            .style(hidden_scrollbar)
             .height(Length::Fill)
             .into()
     }
 
+    fn index_view(&self, current: char) -> Element<'_, LibraryMessage> {
+        let available = self.items.letters();
+
+        let mut rail = widget::Column::new();
+        for letter in collation::INDEX_LETTERS {
+            let selected = letter == current;
+            let color = match (selected, available.contains(&letter)) {
+                (true, _) => Color::BLACK,
+                (false, true) => Color::WHITE,
+                (false, false) => Color::WHITE.scale_alpha(0.3),
+            };
+
+            let label = text(letter.to_string())
+                .size(INDEX_LETTER_SIZE)
+                .line_height(text::LineHeight::Relative(1.0))
+                .color(color);
+
+            rail = rail.push(
+                container(label)
+                    .center_x(INDEX_LETTER_SIZE * 1.5)
+                    .style(move |_| match selected {
+                        true => container::Style::default().background(Color::WHITE),
+                        false => container::Style::default().background(Color::BLACK),
+                    }),
+            );
+        }
+
+        let badge = container(text(current.to_string()).size(160))
+            .center(200)
+            .style(|_| {
+                container::Style::default()
+                    .background(Color::BLACK)
+                    .border(Border::default().width(5).color(Color::WHITE))
+            });
+
+        stack![
+            container(badge).center(Length::Fill),
+            container(rail)
+                .align_right(Length::Fill)
+                .center_y(Length::Fill),
+        ]
+        .into()
+    }
+
     pub fn update(&mut self, message: LibraryMessage) -> Task<LibraryMessage> {
-        let Some(catalog) = self.catalog.clone() else {
+        if self.catalog.is_none() {
             return Task::done(LibraryMessage::Error("No catalog".into()));
-        };
+        }
 
         match message {
-            LibraryMessage::ItemsLoad(items) => {
-                self.items = LibraryItems::new(items);
+            LibraryMessage::ListLoad(listing) => {
+                if listing.generation != self.generation {
+                    return Task::none();
+                }
+
+                info!(
+                    "ListLoad received {} of {} items",
+                    listing.items.len(),
+                    listing.total
+                );
+
+                self.source = listing.source;
+                self.stats = listing.stats;
+                self.pending_window = None;
+                self.items = LibraryItems::new(
+                    listing.total,
+                    listing.index,
+                    listing.items.into_iter().map(LibraryItem::new).collect(),
+                );
+                self.current_scroll_y = 0.0;
+
+                Task::batch([
+                    scroll_to(
+                        self.scroll_id.clone(),
+                        scrollable::AbsoluteOffset { x: 0.0, y: 0.0 },
+                    ),
+                    self.load_thumbnails(),
+                ])
+            }
+            LibraryMessage::WindowLoad(window) => {
+                if window.generation != self.generation {
+                    return Task::none();
+                }
+
+                self.pending_window = None;
+                self.items.load_window(
+                    window.offset,
+                    window.items.into_iter().map(LibraryItem::new).collect(),
+                );
+                self.load_thumbnails()
+            }
+            LibraryMessage::ThumbnailLoad(key, handle) => {
+                // Drop results for rows that scrolled out of the window in the meantime.
+                if let Some(thumbnail) = self.thumbnails.get_mut(&key) {
+                    *thumbnail = handle;
+                }
                 Task::none()
             }
             LibraryMessage::Scrolled(y) => {
                 self.current_scroll_y = y;
-                scroll_to(
-                    self.scroll_id.clone(),
-                    scrollable::AbsoluteOffset { x: 0.0, y },
-                )
+                let first_visible = (y / ROW_STRIDE) as usize;
+
+                Task::batch([
+                    scroll_to(
+                        self.scroll_id.clone(),
+                        scrollable::AbsoluteOffset { x: 0.0, y },
+                    ),
+                    self.fetch_window(first_visible..first_visible + PAGE_SIZE + 1),
+                ])
             }
             LibraryMessage::ItemLoad(item) => match item {
                 Some(library_item) => match library_item.catalog_item.metadata {
-                    CatalogMetadata::Artist(a) => {
-                        let artist = a.clone();
-
-                        Task::perform(
-                            async move {
-                                let releases = catalog
-                                    .release
-                                    .lock()
-                                    .await
-                                    .list_releases(ReleaseFilter {
-                                        title: None,
-                                        artist: Some(artist.name),
-                                    })
-                                    .await
-                                    .map_err(|_| LibraryError::Unknown)?;
-
-                                Ok::<Vec<CatalogItem<CatalogMetadata>>, LibraryError>(
-                                    releases.into_iter().map(Into::into).collect(),
-                                )
-                            },
-                            |result| match result {
-                                Ok(items) => LibraryMessage::ItemRefresh(items),
-                                Err(e) => LibraryMessage::Error(e.to_string()),
-                            },
-                        )
+                    CatalogMetadata::Artist(artist) => self.load(LibrarySource::Releases {
+                        artist: Some(artist.name),
+                    }),
+                    CatalogMetadata::Genre(genre) => {
+                        self.load(LibrarySource::GenreReleases { genre: genre.name })
                     }
-                    CatalogMetadata::Release(r) => {
-                        let release = r.clone();
-
-                        Task::perform(
-                            async move {
-                                let tracks = catalog
-                                    .track
-                                    .lock()
-                                    .await
-                                    .list_tracks(TrackFilter {
-                                        name: None,
-                                        artist: release.artist,
-                                        release: Some(release.title),
-                                    })
-                                    .await
-                                    .map_err(|e| LibraryError::Internal(e.into()))?;
-
-                                Ok::<Vec<CatalogItem<CatalogMetadata>>, LibraryError>(
-                                    tracks.into_iter().map(Into::into).collect(),
-                                )
+                    CatalogMetadata::Decade(decade) => self.load(LibrarySource::DecadeReleases {
+                        start: decade.start,
+                    }),
+                    // Compilations are reached from each of their artists and releases from
+                    // their genre, decade or the recent and favorite lists too, so back out to
+                    // whichever list this came from.
+                    CatalogMetadata::Release(release) => {
+                        let from = match &self.source {
+                            LibrarySource::Releases { artist: Some(_) }
+                            | LibrarySource::GenreReleases { .. }
+                            | LibrarySource::DecadeReleases { .. }
+                            | LibrarySource::RecentlyAdded
+                            | LibrarySource::Favorites => self.source.clone(),
+                            _ => LibrarySource::Releases {
+                                artist: release.artist,
                             },
-                            |result| match result {
-                                Ok(items) => LibraryMessage::ItemRefresh(items),
-                                Err(e) => LibraryMessage::Error(e.to_string()),
-                            },
-                        )
+                        };
+
+                        self.load(LibrarySource::Tracks {
+                            from: Box::new(from),
+                            release_id: library_item.catalog_item.id,
+                        })
                     }
+                    CatalogMetadata::Playlist(_) => self.load(LibrarySource::PlaylistTracks {
+                        id: library_item.catalog_item.id,
+                    }),
+                    CatalogMetadata::Book(_) => self.load(LibrarySource::BookTracks {
+                        id: library_item.catalog_item.id,
+                    }),
                     _ => Task::none(),
                 },
-                None => Task::perform(
-                    async move {
-                        let releases = catalog
-                            .artist
-                            .lock()
-                            .await
-                            .list_artists(ArtistFilter::default())
-                            .await
-                            .map_err(|e| LibraryError::Internal(e.into()))?;
-
-                        Ok::<Vec<CatalogItem<CatalogMetadata>>, LibraryError>(
-                            releases.into_iter().map(Into::into).collect(),
-                        )
-                    },
-                    |result| match result {
-                        Ok(items) => LibraryMessage::ItemRefresh(items),
-                        Err(e) => LibraryMessage::Error(e.to_string()),
-                    },
-                ),
+                None => self.load(LibrarySource::Artists),
             },
-            LibraryMessage::ItemRefresh(items) => {
-                let items = items
-                    .iter()
-                    .map(|item| LibraryItem::new(item.clone()))
-                    .collect();
-
-                self.items.refresh(items);
-
+            LibraryMessage::Reload => self.load(self.source.clone()),
+            LibraryMessage::Bookmarked(track, position) => {
+                for loaded in self.items.tracks_mut(&track) {
+                    loaded.resume = position;
+                }
+                Task::none()
+            }
+            LibraryMessage::SpeedChosen(track, speed) => {
+                for loaded in self.items.tracks_mut(&track) {
+                    loaded.speed = Some(speed);
+                }
                 Task::none()
             }
             LibraryMessage::InputEvent(key) => match key.as_ref() {
-                Key::Named(iced::keyboard::key::Named::ArrowUp) => {
-                    self.items.select(LibraryItemAction::SelectPrevious);
-
-                    let selected_idx = self
-                        .items
-                        .inner
-                        .iter()
-                        .position(|item| item.selected)
-                        .unwrap_or(0);
-
-                    let item_top = selected_idx as f32 * ROW_STRIDE;
-
-                    if item_top < self.current_scroll_y {
-                        self.current_scroll_y = item_top;
-                        scroll_to(
-                            self.scroll_id.clone(),
-                            scrollable::AbsoluteOffset {
-                                x: 0.0,
-                                y: item_top,
-                            },
-                        )
-                    } else {
-                        Task::none()
-                    }
+                Key::Named(Named::ArrowUp) | Key::Named(Named::ArrowDown)
+                    if self.index_letter.is_some() =>
+                {
+                    self.step_index_letter(key.as_ref() == Key::Named(Named::ArrowDown))
                 }
-                Key::Named(iced::keyboard::key::Named::ArrowDown) => {
-                    self.items.select(LibraryItemAction::SelectNext);
-
-                    let selected_idx = self
-                        .items
-                        .inner
-                        .iter()
-                        .position(|item| item.selected)
-                        .unwrap_or(0);
-
-                    let item_top = selected_idx as f32 * ROW_STRIDE;
-                    let item_bottom = item_top + ITEM_HEIGHT;
-
-                    if item_bottom > self.current_scroll_y + VIEWPORT_HEIGHT {
-                        let new_scroll_y = item_top - VIEWPORT_HEIGHT + ITEM_HEIGHT;
-                        self.current_scroll_y = new_scroll_y;
-                        scroll_to(
-                            self.scroll_id.clone(),
-                            scrollable::AbsoluteOffset {
-                                x: 0.0,
-                                y: new_scroll_y,
-                            },
-                        )
-                    } else {
-                        Task::none()
-                    }
+                Key::Named(Named::ArrowUp) | Key::Named(Named::ArrowDown)
+                    if self.source == LibrarySource::Stats =>
+                {
+                    self.scroll_stats(key.as_ref() == Key::Named(Named::ArrowDown))
                 }
-                Key::Named(iced::keyboard::key::Named::Enter) => match self.items.selected() {
-                    Some(item) => match &item.catalog_item.metadata {
-                        CatalogMetadata::Track(t) => {
-                            Task::done(LibraryMessage::TrackSelect(t.clone()))
-                        }
-                        CatalogMetadata::Artist(a) => {
-                            let name = a.name.clone();
+                Key::Named(Named::ArrowLeft) | Key::Named(Named::ArrowRight) => {
+                    match self
+                        .source
+                        .sibling(key.as_ref() == Key::Named(Named::ArrowRight))
+                    {
+                        Some(sibling) => {
+                            let view = sibling.view();
                             Task::batch(vec![
-                                Task::perform(
-                                    async move {
-                                        let item = catalog
-                                            .artist
-                                            .lock()
-                                            .await
-                                            .get_artist(&name)
-                                            .await
-                                            .map_err(|e| LibraryError::Internal(e.into()))?;
-                                        Ok::<CatalogItem<CatalogMetadata>, LibraryError>(
-                                            item.into(),
-                                        )
-                                    },
-                                    |result| match result {
-                                        Ok(item) => {
-                                            LibraryMessage::ItemLoad(Some(LibraryItem::new(item)))
-                                        }
-                                        Err(e) => LibraryMessage::Error(e.to_string()),
-                                    },
-                                ),
-                                Task::done(LibraryMessage::ChangeView(LibraryView::Release)),
+                                self.load(sibling),
+                                Task::done(LibraryMessage::ChangeView(view)),
                             ])
                         }
-                        CatalogMetadata::Release(r) => {
-                            let title = r.title.clone();
-                            Task::batch(vec![
-                                Task::perform(
-                                    async move {
-                                        let item = catalog
-                                            .release
-                                            .lock()
-                                            .await
-                                            .get_release(&title)
-                                            .await
-                                            .map_err(|e| LibraryError::Internal(e.into()))?;
-                                        Ok::<CatalogItem<CatalogMetadata>, LibraryError>(
-                                            item.into(),
-                                        )
-                                    },
-                                    |result| match result {
-                                        Ok(item) => {
-                                            LibraryMessage::ItemLoad(Some(LibraryItem::new(item)))
-                                        }
-                                        Err(e) => LibraryMessage::Error(e.to_string()),
-                                    },
-                                ),
-                                Task::done(LibraryMessage::ChangeView(LibraryView::Track)),
-                            ])
-                        }
-                    },
-                    None => todo!(),
+                        None => Task::none(),
+                    }
+                }
+                Key::Named(Named::ArrowUp) => {
+                    self.held_repeats = 0;
+                    self.navigate(LibraryItemAction::SelectPrevious)
+                }
+                Key::Named(Named::ArrowDown) => {
+                    self.held_repeats = 0;
+                    self.navigate(LibraryItemAction::SelectNext)
+                }
+                Key::Named(Named::PageUp) => {
+                    self.navigate(LibraryItemAction::SelectBackward(PAGE_SIZE))
+                }
+                Key::Named(Named::PageDown) => {
+                    self.navigate(LibraryItemAction::SelectForward(PAGE_SIZE))
+                }
+                Key::Named(INDEX_KEY) => {
+                    self.index_letter = self.items.selected().map(|item| {
+                        collation::index_letter(item.catalog_item.metadata.display_text())
+                    });
+                    Task::none()
+                }
+                Key::Character(c) => match c.chars().next() {
+                    Some(letter) if letter.is_ascii_alphabetic() => {
+                        self.navigate(LibraryItemAction::SelectLetter(letter.to_ascii_uppercase()))
+                    }
+                    _ => Task::none(),
                 },
-                Key::Named(iced::keyboard::key::Named::Backspace) => match self.items.selected() {
+                Key::Named(Named::Enter) => match self.items.selected() {
                     Some(item) => match &item.catalog_item.metadata {
-                        CatalogMetadata::Release(_) => Task::batch(vec![
-                            Task::perform(
-                                async move {
-                                    let items = catalog
-                                        .artist
-                                        .lock()
-                                        .await
-                                        .list_artists(ArtistFilter {
-                                            name: None,
-                                            track: None,
-                                        })
-                                        .await
-                                        .map_err(|e| LibraryError::Internal(e.into()))?;
-
-                                    Ok::<Vec<CatalogItem<Artist>>, LibraryError>(items)
-                                },
-                                |result| match result {
-                                    Ok(item) => LibraryMessage::ItemsLoad(
-                                        item.iter()
-                                            .map(|i| LibraryItem::new(i.to_owned().into()))
-                                            .collect(),
-                                    ),
-                                    Err(e) => LibraryMessage::Error(e.to_string()),
-                                },
-                            ),
-                            Task::done(LibraryMessage::ChangeView(LibraryView::Artist)),
+                        CatalogMetadata::Track(_) => self.select_track(),
+                        CatalogMetadata::Artist(_)
+                        | CatalogMetadata::Genre(_)
+                        | CatalogMetadata::Decade(_) => Task::batch(vec![
+                            Task::done(LibraryMessage::ItemLoad(Some(item.clone()))),
+                            Task::done(LibraryMessage::ChangeView(LibraryView::Release)),
+                        ]),
+                        CatalogMetadata::Release(_)
+                        | CatalogMetadata::Playlist(_)
+                        | CatalogMetadata::Book(_) => Task::batch(vec![
+                            Task::done(LibraryMessage::ItemLoad(Some(item.clone()))),
+                            Task::done(LibraryMessage::ChangeView(LibraryView::Track)),
                         ]),
-                        CatalogMetadata::Track(t) => {
-                            let track = t.clone();
-                            Task::batch(vec![
-                                Task::perform(
-                                    async move {
-                                        let items = catalog
-                                            .release
-                                            .lock()
-                                            .await
-                                            .list_releases(ReleaseFilter {
-                                                title: None,
-                                                artist: track.artist,
-                                            })
-                                            .await
-                                            .map_err(|_| LibraryError::Unknown)?;
-
-                                        Ok::<Vec<CatalogItem<Release>>, LibraryError>(items)
-                                    },
-                                    |result| match result {
-                                        Ok(item) => LibraryMessage::ItemsLoad(
-                                            item.iter()
-                                                .map(|i| LibraryItem::new(i.to_owned().into()))
-                                                .collect(),
-                                        ),
-                                        Err(e) => LibraryMessage::Error(e.to_string()),
-                                    },
-                                ),
-                                Task::done(LibraryMessage::ChangeView(LibraryView::Release)),
-                            ])
-                        }
-                        _ => Task::none(),
                     },
-                    None => {
-                        self.items.select(LibraryItemAction::SelectNext);
-                        Task::none()
+                    None => Task::none(),
+                },
+                Key::Named(Named::Delete) => self.remove_from_playlist(),
+                Key::Named(Named::Backspace) => match self.source.parent() {
+                    Some(parent) => {
+                        let view = parent.view();
+                        Task::batch(vec![
+                            self.load(parent),
+                            Task::done(LibraryMessage::ChangeView(view)),
+                        ])
                     }
+                    None => Task::none(),
                 },
                 _ => Task::none(),
             },
-            LibraryMessage::TrackSelect(track) => Task::none(),
+            LibraryMessage::InputHeld(key) => match key.as_ref() {
+                Key::Named(Named::ArrowUp) | Key::Named(Named::ArrowDown)
+                    if self.index_letter.is_some() =>
+                {
+                    self.step_index_letter(key.as_ref() == Key::Named(Named::ArrowDown))
+                }
+                Key::Named(Named::ArrowUp) | Key::Named(Named::ArrowDown)
+                    if self.source == LibrarySource::Stats =>
+                {
+                    self.scroll_stats(key.as_ref() == Key::Named(Named::ArrowDown))
+                }
+                Key::Named(Named::ArrowUp) => {
+                    self.held_repeats += 1;
+                    self.navigate(LibraryItemAction::SelectBackward(held_step(
+                        self.held_repeats,
+                    )))
+                }
+                Key::Named(Named::ArrowDown) => {
+                    self.held_repeats += 1;
+                    self.navigate(LibraryItemAction::SelectForward(held_step(
+                        self.held_repeats,
+                    )))
+                }
+                Key::Named(Named::PageUp) => {
+                    self.navigate(LibraryItemAction::SelectBackward(PAGE_SIZE))
+                }
+                Key::Named(Named::PageDown) => {
+                    self.navigate(LibraryItemAction::SelectForward(PAGE_SIZE))
+                }
+                _ => Task::none(),
+            },
+            LibraryMessage::InputReleased(key) => {
+                match key.as_ref() {
+                    Key::Named(INDEX_KEY) => self.index_letter = None,
+                    Key::Named(Named::ArrowUp) | Key::Named(Named::ArrowDown) => {
+                        self.held_repeats = 0
+                    }
+                    _ => {}
+                }
+                Task::none()
+            }
+            LibraryMessage::TrackSelect(..) => Task::none(),
             LibraryMessage::Error(test) => {
                 info!(test);
                 Task::none()
@@ -628,4 +872,495 @@ impl Library {
             LibraryMessage::ChangeView(_) => Task::none(),
         }
     }
+
+    /// Applies a selection change and scrolls just enough to keep the selected item visible.
+    fn navigate(&mut self, action: LibraryItemAction) -> Task<LibraryMessage> {
+        self.items.select(action);
+
+        let Some(selected_idx) = self.items.selected_index() else {
+            return Task::none();
+        };
+
+        let window =
+            self.fetch_window(selected_idx.saturating_sub(PAGE_SIZE)..selected_idx + PAGE_SIZE);
+
+        let item_top = selected_idx as f32 * ROW_STRIDE;
+        let item_bottom = item_top + ITEM_HEIGHT;
+
+        let y = if item_top < self.current_scroll_y {
+            item_top
+        } else if item_bottom > self.current_scroll_y + VIEWPORT_HEIGHT {
+            item_top - VIEWPORT_HEIGHT + ITEM_HEIGHT
+        } else {
+            return window;
+        };
+
+        self.current_scroll_y = y;
+        Task::batch([
+            scroll_to(
+                self.scroll_id.clone(),
+                scrollable::AbsoluteOffset { x: 0.0, y },
+            ),
+            window,
+        ])
+    }
+
+    /// Queries `source` from scratch, replacing whatever list is currently shown.
+    fn load(&mut self, source: LibrarySource) -> Task<LibraryMessage> {
+        let Some(catalog) = self.catalog.clone() else {
+            return Task::done(LibraryMessage::Error("No catalog".into()));
+        };
+
+        self.generation += 1;
+        let generation = self.generation;
+
+        Task::perform(
+            async move {
+                let total = source.count(&catalog).await?;
+                let index = source.index(&catalog).await?;
+                let items = source.fetch(&catalog, 0, WINDOW_SIZE).await?;
+                let stats = source.stats(&catalog).await?;
+
+                Ok::<LibraryListing, LibraryError>(LibraryListing {
+                    generation,
+                    source,
+                    total,
+                    index,
+                    items,
+                    stats,
+                })
+            },
+            |result| match result {
+                Ok(listing) => LibraryMessage::ListLoad(listing),
+                Err(e) => LibraryMessage::Error(e.to_string()),
+            },
+        )
+    }
+
+    /// Takes the selected track out of the playlist being shown and reloads it.
+    fn remove_from_playlist(&mut self) -> Task<LibraryMessage> {
+        let (LibrarySource::PlaylistTracks { id }, Some(catalog), Some(position)) = (
+            self.source.clone(),
+            self.catalog.clone(),
+            self.items.selected_index(),
+        ) else {
+            return Task::none();
+        };
+
+        Task::perform(
+            async move {
+                catalog
+                    .playlist
+                    .lock()
+                    .await
+                    .remove_track(id, position)
+                    .await
+            },
+            move |result| match result {
+                Ok(()) => LibraryMessage::Reload,
+                Err(e) => LibraryMessage::Error(e.to_string()),
+            },
+        )
+    }
+
+    /// Fetches the tracks around the selected one so those after it play on from it.
+    fn select_track(&self) -> Task<LibraryMessage> {
+        let (Some(catalog), Some(selected)) = (self.catalog.clone(), self.items.selected_index())
+        else {
+            return Task::none();
+        };
+
+        let source = self.source.clone();
+        let window = queue_window(selected, self.items.total());
+        let position = selected - window.start;
+
+        Task::perform(
+            async move { source.fetch(&catalog, window.start, window.len()).await },
+            move |result| match result {
+                Ok(items) => {
+                    // Favorites list artists and releases ahead of the tracks.
+                    let skipped = items[..position.min(items.len())]
+                        .iter()
+                        .filter(|item| !matches!(item.metadata, CatalogMetadata::Track(_)))
+                        .count();
+
+                    LibraryMessage::TrackSelect(
+                        items
+                            .into_iter()
+                            .filter_map(|item| match item.metadata {
+                                CatalogMetadata::Track(track) => Some(track),
+                                _ => None,
+                            })
+                            .collect(),
+                        position - skipped,
+                    )
+                }
+                Err(e) => LibraryMessage::Error(e.to_string()),
+            },
+        )
+    }
+
+    /// Decodes thumbnails for the rows in the loaded window and forgets those outside it.
+    fn load_thumbnails(&mut self) -> Task<LibraryMessage> {
+        let Some(artwork) = self.catalog.as_ref().map(|c| c.artwork.clone()) else {
+            return Task::none();
+        };
+
+        let keys: Vec<ArtworkKey> = self
+            .items
+            .iter()
+            .filter_map(|item| artwork_key(&item.catalog_item))
+            .collect();
+
+        self.thumbnails.retain(|key, _| keys.contains(key));
+
+        let mut tasks = Vec::new();
+        for key in keys {
+            if self.thumbnails.contains_key(&key) {
+                continue;
+            }
+
+            // Reserve the slot so the next window load doesn't request it again.
+            self.thumbnails.insert(key, None);
+            tasks.push(Task::perform(
+                load_thumbnail(artwork.clone(), key),
+                move |handle| LibraryMessage::ThumbnailLoad(key, handle),
+            ));
+        }
+
+        Task::batch(tasks)
+    }
+
+    /// Fetches a window centred on `range` unless the loaded one already covers it.
+    fn fetch_window(&mut self, range: Range<usize>) -> Task<LibraryMessage> {
+        if self.items.covers(range.clone()) {
+            return Task::none();
+        }
+
+        let Some(catalog) = self.catalog.clone() else {
+            return Task::none();
+        };
+
+        let centre = (range.start + range.end) / 2;
+        let offset = centre.saturating_sub(WINDOW_SIZE / 2);
+        if self.pending_window == Some(offset) {
+            return Task::none();
+        }
+        self.pending_window = Some(offset);
+
+        let source = self.source.clone();
+        let generation = self.generation;
+
+        Task::perform(
+            async move {
+                let items = source.fetch(&catalog, offset, WINDOW_SIZE).await?;
+
+                Ok::<LibraryWindow, LibraryError>(LibraryWindow {
+                    generation,
+                    offset,
+                    items,
+                })
+            },
+            |result| match result {
+                Ok(window) => LibraryMessage::WindowLoad(window),
+                Err(e) => LibraryMessage::Error(e.to_string()),
+            },
+        )
+    }
+
+    /// Scrolls the stats page a row at a time, stopping once the last row is in view.
+    fn scroll_stats(&mut self, forward: bool) -> Task<LibraryMessage> {
+        let rows = self
+            .stats
+            .as_ref()
+            .map(|s| stats_rows(s).len())
+            .unwrap_or(0);
+        let bottom = (rows as f32 * ROW_STRIDE - VIEWPORT_HEIGHT).max(0.0);
+
+        let y = match forward {
+            true => (self.current_scroll_y + ROW_STRIDE).min(bottom),
+            false => (self.current_scroll_y - ROW_STRIDE).max(0.0),
+        };
+        self.current_scroll_y = y;
+
+        scroll_to(
+            self.scroll_id.clone(),
+            scrollable::AbsoluteOffset { x: 0.0, y },
+        )
+    }
+
+    /// Moves the index overlay to the next or previous letter that has items and jumps to it.
+    fn step_index_letter(&mut self, forward: bool) -> Task<LibraryMessage> {
+        let letters = self.items.letters();
+        let Some(current) = self.index_letter else {
+            return Task::none();
+        };
+
+        let position = letters.iter().position(|l| *l == current);
+        let next = match (position, forward) {
+            (Some(p), true) => letters.get(p + 1).or(letters.get(p)),
+            (Some(p), false) => letters.get(p.saturating_sub(1)),
+            (None, _) => letters.first(),
+        };
+
+        match next.copied() {
+            Some(letter) => {
+                self.index_letter = Some(letter);
+                self.navigate(LibraryItemAction::SelectLetter(letter))
+            }
+            None => Task::none(),
+        }
+    }
+}
+
+/// The cached image shown next to an item, tracks share their release's cover so show none.
+fn artwork_key(item: &CatalogItem<CatalogMetadata>) -> Option<ArtworkKey> {
+    match item.metadata {
+        CatalogMetadata::Artist(_) => Some(ArtworkKey::Artist(item.id)),
+        CatalogMetadata::Release(_) => Some(ArtworkKey::Release(item.id)),
+        CatalogMetadata::Track(_)
+        | CatalogMetadata::Playlist(_)
+        | CatalogMetadata::Genre(_)
+        | CatalogMetadata::Decade(_)
+        | CatalogMetadata::Book(_) => None,
+    }
+}
+
+/// Reads and decodes a small thumbnail off the UI thread so the renderer never has to.
+async fn load_thumbnail(artwork: ArtworkCache, key: ArtworkKey) -> Option<Handle> {
+    tokio::task::spawn_blocking(move || {
+        let bytes = artwork.read(key, Thumbnail::Small)?;
+        let decoded = ::image::load_from_memory(&bytes).ok()?.to_rgba8();
+
+        Some(Handle::from_rgba(
+            decoded.width(),
+            decoded.height(),
+            decoded.into_raw(),
+        ))
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// For each track in the loaded window, the disc it starts when it's the first on one.
+///
+/// `None` unless the tracks span more than one disc.
+fn disc_starts(items: &LibraryItems) -> Option<Vec<Option<u32>>> {
+    let discs: Vec<Option<u32>> = items
+        .iter()
+        .map(|item| match &item.catalog_item.metadata {
+            CatalogMetadata::Track(track) => track.disc_number,
+            _ => None,
+        })
+        .collect();
+
+    let multi_disc = items.iter().any(|item| match &item.catalog_item.metadata {
+        CatalogMetadata::Track(track) => {
+            track.disc_total.is_some_and(|total| total > 1)
+                || track.disc_number.is_some_and(|disc| disc > 1)
+        }
+        _ => false,
+    });
+    if !multi_disc {
+        return None;
+    }
+
+    Some(
+        discs
+            .iter()
+            .enumerate()
+            .map(|(position, disc)| match position.checked_sub(1) {
+                Some(previous) if discs[previous] == *disc => None,
+                // The window may start partway through a disc.
+                None if items.offset() > 0 => None,
+                _ => *disc,
+            })
+            .collect(),
+    )
+}
+
+/// Label and value for each row of the stats page, section headings have no value.
+fn stats_rows(stats: &LibraryStats) -> Vec<(String, String)> {
+    let mut rows = vec![
+        ("Tracks".to_string(), stats.tracks.to_string()),
+        ("Releases".to_string(), stats.releases.to_string()),
+        ("Artists".to_string(), stats.artists.to_string()),
+        ("Duration".to_string(), format_span(stats.total_duration)),
+        ("Plays".to_string(), stats.plays.to_string()),
+        ("Listened".to_string(), format_span(stats.listening_time)),
+    ];
+
+    if !stats.top_artists.is_empty() {
+        rows.push(("Top artists".to_string(), String::new()));
+        rows.extend(
+            stats
+                .top_artists
+                .iter()
+                .map(|(artist, plays)| (artist.clone(), plays.to_string())),
+        );
+    }
+
+    if !stats.weekly.is_empty() {
+        rows.push(("Weekly".to_string(), String::new()));
+        rows.extend(
+            stats
+                .weekly
+                .iter()
+                .map(|week| (week.week.clone(), format_span(week.time))),
+        );
+    }
+
+    rows
+}
+
+fn hidden_scrollbar(_: &Theme, _: scrollable::Status) -> scrollable::Style {
+    scrollable::Style {
+        container: container::Style::default(),
+        vertical_rail: scrollable::Rail {
+            background: None,
+            border: Border::default(),
+            scroller: scrollable::Scroller {
+                border: Border::default(),
+                background: Background::Color(Color::TRANSPARENT),
+            },
+        },
+        horizontal_rail: scrollable::Rail {
+            background: None,
+            border: Border::default(),
+            scroller: scrollable::Scroller {
+                border: Border::default(),
+                background: Background::Color(Color::TRANSPARENT),
+            },
+        },
+        gap: None,
+        auto_scroll: scrollable::AutoScroll {
+            background: Background::Color(Color::TRANSPARENT),
+            border: Border::default(),
+            shadow: Shadow::default(),
+            icon: Color::WHITE,
+        },
+    }
+}
+
+/// Number of items to move per key repeat, growing the longer a direction is held.
+fn held_step(repeats: u32) -> usize {
+    match repeats {
+        0..10 => 1,
+        10..25 => 2,
+        25..50 => PAGE_SIZE / 2,
+        _ => PAGE_SIZE,
+    }
+}
+
+/// The part of a list of `total` items queued when the one at `selected` is picked.
+fn queue_window(selected: usize, total: usize) -> Range<usize> {
+    let start = selected.saturating_sub(QUEUE_BEFORE);
+    let end = (selected + QUEUE_AFTER).min(total).max(selected + 1);
+
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(ids: Range<i64>) -> Vec<LibraryItem> {
+        ids.map(|id| {
+            LibraryItem::new(CatalogItem {
+                id,
+                favorite: false,
+                metadata: CatalogMetadata::Track(Track::default()),
+            })
+        })
+        .collect()
+    }
+
+    #[test]
+    fn moves_stay_within_the_list() {
+        let mut list = LibraryItems::new(5, vec![], items(0..5));
+        assert_eq!(list.selected_index(), Some(0));
+
+        list.select(LibraryItemAction::SelectPrevious);
+        assert_eq!(list.selected_index(), Some(0));
+        list.select(LibraryItemAction::SelectForward(3));
+        assert_eq!(list.selected_index(), Some(3));
+        list.select(LibraryItemAction::SelectForward(PAGE_SIZE));
+        assert_eq!(list.selected_index(), Some(4));
+        list.select(LibraryItemAction::SelectNext);
+        assert_eq!(list.selected_index(), Some(4));
+        list.select(LibraryItemAction::SelectBackward(PAGE_SIZE));
+        assert_eq!(list.selected_index(), Some(0));
+
+        let mut empty = LibraryItems::new(0, vec![], vec![]);
+        empty.select(LibraryItemAction::SelectNext);
+        assert_eq!(empty.selected_index(), None);
+    }
+
+    #[test]
+    fn a_letter_jumps_to_its_first_item_or_the_next_filed_one() {
+        let index = vec![('#', 0), ('B', 2), ('D', 5), ('D', 4)];
+        let mut list = LibraryItems::new(8, index, items(0..8));
+
+        assert_eq!(list.letters(), vec!['#', 'B', 'D']);
+        assert_eq!(list.letter_position('#'), Some(0));
+        assert_eq!(list.letter_position('B'), Some(2));
+        assert_eq!(list.letter_position('C'), Some(4));
+        assert_eq!(list.letter_position('E'), None);
+
+        list.select(LibraryItemAction::SelectLetter('D'));
+        assert_eq!(list.selected_index(), Some(4));
+        list.select(LibraryItemAction::SelectLetter('Z'));
+        assert_eq!(list.selected_index(), Some(4));
+    }
+
+    #[test]
+    fn holding_a_key_speeds_up_to_a_page() {
+        assert_eq!(held_step(0), 1);
+        assert_eq!(held_step(9), 1);
+        assert_eq!(held_step(10), 2);
+        assert_eq!(held_step(25), PAGE_SIZE / 2);
+        assert_eq!(held_step(u32::MAX), PAGE_SIZE);
+        assert!((0..100).map(held_step).is_sorted());
+    }
+
+    #[test]
+    fn only_the_loaded_window_is_held() {
+        let mut list = LibraryItems::new(1_000, vec![], items(0..WINDOW_SIZE as i64));
+        assert!(list.covers(0..PAGE_SIZE));
+        assert!(!list.covers(WINDOW_SIZE - 1..WINDOW_SIZE + 1));
+
+        list.select(LibraryItemAction::SelectForward(700));
+        assert_eq!(list.selected_index(), Some(700));
+        assert!(list.selected().is_none());
+
+        list.load_window(690, items(690..690 + WINDOW_SIZE as i64));
+        assert_eq!(list.offset(), 690);
+        assert!(list.covers(700..710));
+        let selected = list.selected().expect("window holds the selection");
+        assert_eq!(selected.catalog_item.id, 700);
+        assert!(selected.selected);
+        assert_eq!(list.inner.iter().filter(|item| item.selected).count(), 1);
+
+        list.select(LibraryItemAction::SelectByCatalogId(695));
+        assert_eq!(list.selected_index(), Some(695));
+        list.select(LibraryItemAction::SelectByCatalogId(3));
+        assert_eq!(list.selected_index(), Some(695));
+
+        let mut tail = LibraryItems::new(5, vec![], items(0..5));
+        assert!(tail.covers(0..PAGE_SIZE));
+        tail.load_window(0, vec![]);
+        assert!(!tail.covers(0..1));
+    }
+
+    #[test]
+    fn only_the_tracks_around_a_pick_are_queued() {
+        assert_eq!(queue_window(0, 3), 0..3);
+        assert_eq!(queue_window(10, 50_000), 0..10 + QUEUE_AFTER);
+        assert_eq!(
+            queue_window(20_000, 50_000),
+            20_000 - QUEUE_BEFORE..20_000 + QUEUE_AFTER
+        );
+        assert_eq!(queue_window(49_999, 50_000), 49_999 - QUEUE_BEFORE..50_000);
+    }
 }
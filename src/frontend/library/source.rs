@@ -0,0 +1,758 @@
+use crate::{
+    frontend::library::{LibraryError, LibraryView},
+    model::{
+        CatalogItem, CatalogMetadata, collation, decade::Decade, stats::LibraryStats,
+        track::MediaKind,
+    },
+    repository::{
+        Page, SortDirection,
+        artist::ArtistFilter,
+        book::BookFilter,
+        genre::GenreFilter,
+        playlist::PlaylistFilter,
+        release::{ReleaseFilter, ReleaseSort},
+        track::{TrackFilter, TrackSort},
+    },
+    service::CatalogService,
+};
+
+/// The query behind the list currently shown in the library, windows of it are fetched on demand.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LibrarySource {
+    Artists,
+    Releases {
+        artist: Option<String>,
+    },
+    Genres,
+    GenreReleases {
+        genre: String,
+    },
+    Decades,
+    /// Releases from the ten years starting with `start`, oldest first.
+    DecadeReleases {
+        start: i32,
+    },
+    /// Audiobooks and podcasts, kept apart from the music.
+    Books,
+    /// The tracks of a book, in order.
+    BookTracks {
+        id: i64,
+    },
+    /// The tracks of a release, reached from the list of releases `from`.
+    Tracks {
+        from: Box<LibrarySource>,
+        release_id: i64,
+    },
+    /// Tracks by others which credit `artist`, such as ones featuring them.
+    Appearances {
+        artist: String,
+    },
+    /// Releases by when sync first found them, newest first.
+    RecentlyAdded,
+    /// Tracks by when they were last listened to, latest first.
+    RecentlyPlayed,
+    /// Favorite artists, then releases, then tracks.
+    Favorites,
+    Playlists,
+    /// The tracks of a playlist, in playlist order.
+    PlaylistTracks {
+        id: i64,
+    },
+    /// Totals over the catalog and listening history rather than a list of items.
+    Stats,
+}
+
+impl LibrarySource {
+    pub fn view(&self) -> LibraryView {
+        match self {
+            LibrarySource::Artists => LibraryView::Artist,
+            LibrarySource::Releases { .. }
+            | LibrarySource::GenreReleases { .. }
+            | LibrarySource::DecadeReleases { .. } => LibraryView::Release,
+            LibrarySource::Genres => LibraryView::Genre,
+            LibrarySource::Decades => LibraryView::Decade,
+            LibrarySource::Books => LibraryView::Book,
+            LibrarySource::Tracks { .. }
+            | LibrarySource::BookTracks { .. }
+            | LibrarySource::Appearances { .. }
+            | LibrarySource::PlaylistTracks { .. } => LibraryView::Track,
+            LibrarySource::RecentlyAdded => LibraryView::RecentlyAdded,
+            LibrarySource::RecentlyPlayed => LibraryView::RecentlyPlayed,
+            LibrarySource::Favorites => LibraryView::Favorites,
+            LibrarySource::Playlists => LibraryView::Playlist,
+            LibrarySource::Stats => LibraryView::Stats,
+        }
+    }
+
+    /// The list reached by moving sideways from this one, the next top level list from one of
+    /// those or between an artist's releases and appearances.
+    pub fn sibling(&self, forward: bool) -> Option<LibrarySource> {
+        match self {
+            LibrarySource::Releases {
+                artist: Some(artist),
+            } => {
+                return Some(LibrarySource::Appearances {
+                    artist: artist.clone(),
+                });
+            }
+            LibrarySource::Appearances { artist } => {
+                return Some(LibrarySource::Releases {
+                    artist: Some(artist.clone()),
+                });
+            }
+            _ => {}
+        }
+
+        let roots = [
+            LibrarySource::Artists,
+            LibrarySource::Genres,
+            LibrarySource::Decades,
+            LibrarySource::Books,
+            LibrarySource::RecentlyAdded,
+            LibrarySource::RecentlyPlayed,
+            LibrarySource::Favorites,
+            LibrarySource::Playlists,
+            LibrarySource::Stats,
+        ];
+        let position = roots.iter().position(|root| root == self)?;
+
+        let next = match forward {
+            true => (position + 1) % roots.len(),
+            false => (position + roots.len() - 1) % roots.len(),
+        };
+        Some(roots[next].clone())
+    }
+
+    /// The list one level up, shown when backing out of this one.
+    pub fn parent(&self) -> Option<LibrarySource> {
+        match self {
+            LibrarySource::Artists
+            | LibrarySource::Genres
+            | LibrarySource::Decades
+            | LibrarySource::Books
+            | LibrarySource::RecentlyAdded
+            | LibrarySource::RecentlyPlayed
+            | LibrarySource::Favorites
+            | LibrarySource::Playlists
+            | LibrarySource::Stats => None,
+            LibrarySource::Releases { .. } | LibrarySource::Appearances { .. } => {
+                Some(LibrarySource::Artists)
+            }
+            LibrarySource::GenreReleases { .. } => Some(LibrarySource::Genres),
+            LibrarySource::DecadeReleases { .. } => Some(LibrarySource::Decades),
+            LibrarySource::BookTracks { .. } => Some(LibrarySource::Books),
+            LibrarySource::Tracks { from, .. } => Some(*from.clone()),
+            LibrarySource::PlaylistTracks { .. } => Some(LibrarySource::Playlists),
+        }
+    }
+
+    pub async fn count(&self, catalog: &CatalogService) -> Result<usize, LibraryError> {
+        let count = match self {
+            LibrarySource::Artists => catalog
+                .artist
+                .lock()
+                .await
+                .count_artists(ArtistFilter::default())
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Releases { artist } => catalog
+                .release
+                .lock()
+                .await
+                .count_releases(ReleaseFilter {
+                    title: None,
+                    artist: artist.clone(),
+                    genre: None,
+                    years: None,
+                    favorite: None,
+                })
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Genres => catalog
+                .genre
+                .lock()
+                .await
+                .count_genres(GenreFilter::default())
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::GenreReleases { genre } => catalog
+                .release
+                .lock()
+                .await
+                .count_releases(ReleaseFilter {
+                    genre: Some(genre.clone()),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Decades => catalog
+                .release
+                .lock()
+                .await
+                .list_decades()
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .len() as i64,
+            LibrarySource::DecadeReleases { start } => catalog
+                .release
+                .lock()
+                .await
+                .count_releases(ReleaseFilter {
+                    years: Some(Decade::new(*start).years()),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Books => catalog
+                .book
+                .lock()
+                .await
+                .count_books(BookFilter::default())
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::BookTracks { id } => catalog
+                .track
+                .lock()
+                .await
+                .count_tracks(TrackFilter {
+                    book_id: Some(*id),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Tracks { release_id, .. } => catalog
+                .track
+                .lock()
+                .await
+                .count_tracks(TrackFilter {
+                    name: None,
+                    artist: None,
+                    release: None,
+                    release_id: Some(*release_id),
+                    book_id: None,
+                    kind: None,
+                    featuring: None,
+                    genre: None,
+                    years: None,
+                    played: None,
+                    lyrics: None,
+                    favorite: None,
+                })
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Appearances { artist } => catalog
+                .track
+                .lock()
+                .await
+                .count_tracks(TrackFilter {
+                    featuring: Some(artist.clone()),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Playlists => catalog
+                .playlist
+                .lock()
+                .await
+                .count_playlists(PlaylistFilter::default())
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::PlaylistTracks { id } => catalog
+                .playlist
+                .lock()
+                .await
+                .count_tracks(*id)
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::RecentlyAdded => catalog
+                .release
+                .lock()
+                .await
+                .count_releases(ReleaseFilter::default())
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::RecentlyPlayed => catalog
+                .track
+                .lock()
+                .await
+                .count_tracks(TrackFilter {
+                    played: Some(true),
+                    kind: Some(MediaKind::Music),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Favorites => {
+                let (artists, releases, tracks) = favorite_counts(catalog).await?;
+                (artists + releases + tracks) as i64
+            }
+            LibrarySource::Stats => 0,
+        };
+
+        Ok(count as usize)
+    }
+
+    /// The library stats, only queried for the stats page.
+    pub async fn stats(
+        &self,
+        catalog: &CatalogService,
+    ) -> Result<Option<LibraryStats>, LibraryError> {
+        match self {
+            LibrarySource::Stats => catalog
+                .history
+                .lock()
+                .await
+                .stats()
+                .await
+                .map(Some)
+                .map_err(|e| LibraryError::Internal(e.into())),
+            _ => Ok(None),
+        }
+    }
+
+    /// Builds the jump table for the A-Z index, the position of the first item under each letter.
+    ///
+    /// Tracks are listed in release, book or playlist order, decades by year and the recent lists by
+    /// time rather than alphabetically, so they have no index.
+    pub async fn index(
+        &self,
+        catalog: &CatalogService,
+    ) -> Result<Vec<(char, usize)>, LibraryError> {
+        let initials = match self {
+            LibrarySource::Artists => catalog
+                .artist
+                .lock()
+                .await
+                .artist_initials(ArtistFilter::default())
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Releases { artist } => catalog
+                .release
+                .lock()
+                .await
+                .release_initials(ReleaseFilter {
+                    title: None,
+                    artist: artist.clone(),
+                    genre: None,
+                    years: None,
+                    favorite: None,
+                })
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Genres => catalog
+                .genre
+                .lock()
+                .await
+                .genre_initials(GenreFilter::default())
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::GenreReleases { genre } => catalog
+                .release
+                .lock()
+                .await
+                .release_initials(ReleaseFilter {
+                    genre: Some(genre.clone()),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Books => catalog
+                .book
+                .lock()
+                .await
+                .book_initials(BookFilter::default())
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Playlists => catalog
+                .playlist
+                .lock()
+                .await
+                .playlist_initials(PlaylistFilter::default())
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?,
+            LibrarySource::Decades
+            | LibrarySource::DecadeReleases { .. }
+            | LibrarySource::RecentlyAdded
+            | LibrarySource::RecentlyPlayed
+            | LibrarySource::Favorites
+            | LibrarySource::Tracks { .. }
+            | LibrarySource::BookTracks { .. }
+            | LibrarySource::Appearances { .. }
+            | LibrarySource::PlaylistTracks { .. }
+            | LibrarySource::Stats => return Ok(Vec::new()),
+        };
+
+        let mut index: Vec<(char, usize)> = Vec::new();
+        let mut position = 0;
+        for (initial, count) in initials {
+            let letter = collation::index_letter(&initial);
+            if !index.iter().any(|(l, _)| *l == letter) {
+                index.push((letter, position));
+            }
+            position += count as usize;
+        }
+
+        Ok(index)
+    }
+
+    pub async fn fetch(
+        &self,
+        catalog: &CatalogService,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<CatalogItem<CatalogMetadata>>, LibraryError> {
+        let items = match self {
+            LibrarySource::Artists => catalog
+                .artist
+                .lock()
+                .await
+                .list_artists_page(ArtistFilter::default(), Page::window(offset, limit))
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::Releases { artist } => catalog
+                .release
+                .lock()
+                .await
+                .list_releases_page(
+                    ReleaseFilter {
+                        title: None,
+                        artist: artist.clone(),
+                        genre: None,
+                        years: None,
+                        favorite: None,
+                    },
+                    Page::window(offset, limit),
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::Genres => catalog
+                .genre
+                .lock()
+                .await
+                .list_genres_page(GenreFilter::default(), Page::window(offset, limit))
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::GenreReleases { genre } => catalog
+                .release
+                .lock()
+                .await
+                .list_releases_page(
+                    ReleaseFilter {
+                        genre: Some(genre.clone()),
+                        ..Default::default()
+                    },
+                    Page::window(offset, limit),
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            // Decades aren't stored, they're an item per decade with releases, keyed by its start.
+            LibrarySource::Decades => catalog
+                .release
+                .lock()
+                .await
+                .list_decades()
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .map(|decade| {
+                    CatalogItem {
+                        id: decade.start as i64,
+                        favorite: false,
+                        metadata: decade,
+                    }
+                    .into()
+                })
+                .collect(),
+            LibrarySource::DecadeReleases { start } => catalog
+                .release
+                .lock()
+                .await
+                .list_releases_page(
+                    ReleaseFilter {
+                        years: Some(Decade::new(*start).years()),
+                        ..Default::default()
+                    },
+                    Page {
+                        sort: ReleaseSort::Year,
+                        ..Page::window(offset, limit)
+                    },
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::Books => catalog
+                .book
+                .lock()
+                .await
+                .list_books_page(BookFilter::default(), Page::window(offset, limit))
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::BookTracks { id } => catalog
+                .track
+                .lock()
+                .await
+                .list_tracks_page(
+                    TrackFilter {
+                        book_id: Some(*id),
+                        ..Default::default()
+                    },
+                    Page {
+                        sort: TrackSort::Number,
+                        ..Page::window(offset, limit)
+                    },
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::Tracks { release_id, .. } => catalog
+                .track
+                .lock()
+                .await
+                .list_tracks_page(
+                    TrackFilter {
+                        name: None,
+                        artist: None,
+                        release: None,
+                        release_id: Some(*release_id),
+                        book_id: None,
+                        kind: None,
+                        featuring: None,
+                        genre: None,
+                        years: None,
+                        played: None,
+                        lyrics: None,
+                        favorite: None,
+                    },
+                    Page {
+                        sort: TrackSort::Number,
+                        ..Page::window(offset, limit)
+                    },
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::Appearances { artist } => catalog
+                .track
+                .lock()
+                .await
+                .list_tracks_page(
+                    TrackFilter {
+                        featuring: Some(artist.clone()),
+                        ..Default::default()
+                    },
+                    Page {
+                        sort: TrackSort::Release,
+                        ..Page::window(offset, limit)
+                    },
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::Playlists => catalog
+                .playlist
+                .lock()
+                .await
+                .list_playlists_page(PlaylistFilter::default(), Page::window(offset, limit))
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::PlaylistTracks { id } => catalog
+                .playlist
+                .lock()
+                .await
+                .list_tracks_page(*id, offset, Some(limit))
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::RecentlyAdded => catalog
+                .release
+                .lock()
+                .await
+                .list_releases_page(
+                    ReleaseFilter::default(),
+                    Page {
+                        sort: ReleaseSort::Added,
+                        direction: SortDirection::Descending,
+                        ..Page::window(offset, limit)
+                    },
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::RecentlyPlayed => catalog
+                .track
+                .lock()
+                .await
+                .list_tracks_page(
+                    TrackFilter {
+                        played: Some(true),
+                        kind: Some(MediaKind::Music),
+                        ..Default::default()
+                    },
+                    Page {
+                        sort: TrackSort::Played,
+                        direction: SortDirection::Descending,
+                        ..Page::window(offset, limit)
+                    },
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            LibrarySource::Favorites => fetch_favorites(catalog, offset, limit).await?,
+            LibrarySource::Stats => Vec::new(),
+        };
+
+        Ok(items)
+    }
+}
+
+/// How many artists, releases and tracks are favorites.
+async fn favorite_counts(catalog: &CatalogService) -> Result<(usize, usize, usize), LibraryError> {
+    let artists = catalog
+        .artist
+        .lock()
+        .await
+        .count_artists(ArtistFilter {
+            favorite: Some(true),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| LibraryError::Internal(e.into()))?;
+    let releases = catalog
+        .release
+        .lock()
+        .await
+        .count_releases(ReleaseFilter {
+            favorite: Some(true),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| LibraryError::Internal(e.into()))?;
+    let tracks = catalog
+        .track
+        .lock()
+        .await
+        .count_tracks(TrackFilter {
+            favorite: Some(true),
+            kind: Some(MediaKind::Music),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| LibraryError::Internal(e.into()))?;
+
+    Ok((artists as usize, releases as usize, tracks as usize))
+}
+
+/// A window of the favorites list, which runs through the favorite artists, releases and tracks
+/// in turn so may take items from more than one of them.
+async fn fetch_favorites(
+    catalog: &CatalogService,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<CatalogItem<CatalogMetadata>>, LibraryError> {
+    let (artists, releases, _) = favorite_counts(catalog).await?;
+    let mut items: Vec<CatalogItem<CatalogMetadata>> = Vec::new();
+
+    if let Some((offset, limit)) = segment(offset, limit, 0, artists) {
+        items.extend(
+            catalog
+                .artist
+                .lock()
+                .await
+                .list_artists_page(
+                    ArtistFilter {
+                        favorite: Some(true),
+                        ..Default::default()
+                    },
+                    Page::window(offset, limit),
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into),
+        );
+    }
+    if let Some((offset, limit)) = segment(offset, limit, artists, releases) {
+        items.extend(
+            catalog
+                .release
+                .lock()
+                .await
+                .list_releases_page(
+                    ReleaseFilter {
+                        favorite: Some(true),
+                        ..Default::default()
+                    },
+                    Page::window(offset, limit),
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into),
+        );
+    }
+    if let Some((offset, limit)) = segment(offset, limit, artists + releases, usize::MAX) {
+        items.extend(
+            catalog
+                .track
+                .lock()
+                .await
+                .list_tracks_page(
+                    TrackFilter {
+                        favorite: Some(true),
+                        kind: Some(MediaKind::Music),
+                        ..Default::default()
+                    },
+                    Page::window(offset, limit),
+                )
+                .await
+                .map_err(|e| LibraryError::Internal(e.into()))?
+                .into_iter()
+                .map(Into::into),
+        );
+    }
+
+    Ok(items)
+}
+
+/// The part of the window `offset..offset + limit` over a list that falls in the `size` items
+/// starting at `start`, as an offset and limit within those items.
+fn segment(offset: usize, limit: usize, start: usize, size: usize) -> Option<(usize, usize)> {
+    let from = offset.max(start);
+    let to = (offset + limit).min(start.saturating_add(size));
+
+    (from < to).then(|| (from - start, to - from))
+}
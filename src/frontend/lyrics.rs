@@ -0,0 +1,179 @@
+use std::{path::PathBuf, time::Duration};
+
+use iced::{
+    Color, Element, Length, Padding, Task,
+    alignment::Horizontal,
+    keyboard::{Key, key::Named},
+    widget::{
+        Column, Id, container, operation::scroll_to, scrollable, scrollable::Scrollbar, text,
+    },
+};
+
+use crate::model::{lyrics::Lyrics, track::Track};
+
+const LINE_HEIGHT: f32 = 50.0;
+const LINE_TEXT_SIZE: f32 = 32.0;
+/// Height of the area the lyrics scroll in, below the menu.
+const VIEWPORT_HEIGHT: f32 = 670.0;
+const INACTIVE_COLOR: Color = Color::from_rgb(0.5, 0.5, 0.5);
+
+#[derive(Debug, Clone)]
+pub enum LyricsMessage {
+    /// The track playing and how far into it playback is.
+    Progress(Box<Track>, Duration),
+    Loaded(PathBuf, Option<Lyrics>),
+    InputEvent(Key),
+}
+
+/// The playing track's lyrics, following along with playback when they're synced.
+pub struct LyricsPage {
+    track: Option<PathBuf>,
+    lyrics: Option<Lyrics>,
+    loading: bool,
+    /// The line being sung, or for unsynced lyrics the one scrolled to.
+    current: Option<usize>,
+    position: Duration,
+    scroll_id: Id,
+}
+
+impl Default for LyricsPage {
+    fn default() -> Self {
+        Self {
+            track: None,
+            lyrics: None,
+            loading: false,
+            current: None,
+            position: Duration::ZERO,
+            scroll_id: Id::unique(),
+        }
+    }
+}
+
+impl LyricsPage {
+    pub fn view(&self) -> Element<'_, LyricsMessage> {
+        let Some(lyrics) = &self.lyrics else {
+            let message = match (&self.track, self.loading) {
+                (None, _) => "No track loaded",
+                (Some(_), true) => "...",
+                (Some(_), false) => "No lyrics",
+            };
+
+            return container(text(message)).center(Length::Fill).into();
+        };
+
+        // Pad either end so the first and last lines can sit in the middle of the screen.
+        let padding = (VIEWPORT_HEIGHT - LINE_HEIGHT) / 2.0;
+        let synced = matches!(lyrics, Lyrics::Synced(_));
+
+        let lines = lyrics
+            .lines()
+            .into_iter()
+            .enumerate()
+            .fold(Column::new(), |column, (index, line)| {
+                let color = match !synced || self.current == Some(index) {
+                    true => Color::WHITE,
+                    false => INACTIVE_COLOR,
+                };
+
+                column.push(
+                    text(line)
+                        .size(LINE_TEXT_SIZE)
+                        .color(color)
+                        .height(LINE_HEIGHT)
+                        .width(Length::Fill)
+                        .align_x(Horizontal::Center)
+                        .wrapping(text::Wrapping::None),
+                )
+            })
+            .padding(Padding::new(0.0).vertical(padding));
+
+        scrollable(lines)
+            .id(self.scroll_id.clone())
+            .direction(scrollable::Direction::Vertical(Scrollbar::hidden()))
+            .height(Length::Fill)
+            .into()
+    }
+
+    pub fn update(&mut self, message: LyricsMessage) -> Task<LyricsMessage> {
+        match message {
+            LyricsMessage::Progress(track, position) => {
+                self.position = position;
+
+                if self.track.as_ref() != Some(&track.path) {
+                    self.track = Some(track.path.clone());
+                    self.lyrics = None;
+                    self.current = None;
+                    self.loading = true;
+
+                    return Task::batch([self.scroll(), load_lyrics(*track)]);
+                }
+
+                self.follow()
+            }
+            LyricsMessage::Loaded(path, lyrics) => {
+                if self.track.as_ref() != Some(&path) {
+                    return Task::none();
+                }
+
+                self.lyrics = lyrics;
+                self.loading = false;
+                self.current = None;
+
+                Task::batch([self.scroll(), self.follow()])
+            }
+            // Unsynced lyrics are scrolled by hand.
+            LyricsMessage::InputEvent(key) => {
+                let Some(Lyrics::Unsynced(text)) = &self.lyrics else {
+                    return Task::none();
+                };
+
+                let last = text.lines().count().saturating_sub(1);
+                let line = self.current.unwrap_or_default();
+                self.current = match key.as_ref() {
+                    Key::Named(Named::ArrowUp) => Some(line.saturating_sub(1)),
+                    Key::Named(Named::ArrowDown) => Some((line + 1).min(last)),
+                    _ => return Task::none(),
+                };
+
+                self.scroll()
+            }
+        }
+    }
+
+    /// Moves the highlight to the line being sung, scrolling it into the middle.
+    fn follow(&mut self) -> Task<LyricsMessage> {
+        let current = match &self.lyrics {
+            Some(lyrics @ Lyrics::Synced(_)) => lyrics.line_at(self.position),
+            _ => return Task::none(),
+        };
+
+        if current == self.current {
+            return Task::none();
+        }
+
+        self.current = current;
+        self.scroll()
+    }
+
+    fn scroll(&self) -> Task<LyricsMessage> {
+        let y = self.current.unwrap_or_default() as f32 * LINE_HEIGHT;
+        scroll_to(
+            self.scroll_id.clone(),
+            scrollable::AbsoluteOffset { x: 0.0, y },
+        )
+    }
+}
+
+fn load_lyrics(track: Track) -> Task<LyricsMessage> {
+    let path = track.path.clone();
+
+    Task::perform(
+        async move {
+            tokio::task::spawn_blocking(move || track.lyrics())
+                .await
+                .ok()
+                .flatten()
+        },
+        move |lyrics| LyricsMessage::Loaded(path.clone(), lyrics),
+    )
+}
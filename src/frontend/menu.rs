@@ -1,10 +1,9 @@
 use std::time::Duration;
 
-use battery::units::{Ratio, ratio::percent};
-use chrono::{DateTime, Local, Utc};
+use chrono::{DateTime, Local};
 use iced::{
     Element, Length, Padding, Subscription, Task,
-    alignment::Horizontal,
+    alignment::{Horizontal, Vertical},
     time::every,
     widget::{Column, row, text},
 };
@@ -15,7 +14,6 @@ use crate::{
     frontend::{
         application::ApplicationView,
         battery::{Battery, BatteryMessage},
-        player::PlayerState,
     },
 };
 
@@ -23,6 +21,8 @@ use crate::{
 pub enum MenuMessage {
     ViewChange(ApplicationView),
     Battery(BatteryMessage),
+    /// How long the sleep timer has left, `None` while it's off.
+    SleepTimer(Option<Duration>),
     Tick,
 }
 
@@ -30,8 +30,8 @@ pub enum MenuMessage {
 pub struct Menu {
     current_view: ApplicationView,
     datetime: DateTime<Local>,
-    player_status: Option<PlayerState>,
     battery: Battery,
+    sleep_timer: Option<Duration>,
 }
 
 impl Default for Menu {
@@ -39,22 +39,28 @@ impl Default for Menu {
         Self {
             current_view: ApplicationView::default(),
             datetime: Local::now(),
-            player_status: None,
-            battery: Battery::new(),
+            battery: Battery::default(),
+            sleep_timer: None,
         }
     }
 }
 
 impl Menu {
-    pub fn new(current_view: ApplicationView) -> Self {
+    pub fn new(current_view: ApplicationView, battery: Option<BatteryHandle>) -> Self {
         Self {
             current_view,
             datetime: Local::now(),
-            player_status: None,
-            battery: Battery::new(),
+            battery: Battery::new(battery),
+            sleep_timer: None,
         }
     }
     pub fn view(&self) -> Element<'_, MenuMessage> {
+        let mut status = row![].spacing(10).align_y(Vertical::Center);
+        if let Some(remaining) = self.sleep_timer {
+            status = status.push(text(format_countdown(remaining)).size(30));
+        }
+        status = status.push(self.battery.view().map(MenuMessage::Battery));
+
         row![
             Column::new()
                 .push(text(self.current_view.to_string()))
@@ -64,7 +70,7 @@ impl Menu {
                 .width(Length::Shrink)
                 .align_x(Horizontal::Center),
             Column::new()
-                .push(self.battery.view().map(MenuMessage::Battery))
+                .push(status)
                 .width(Length::FillPortion(1))
                 .padding(Padding::new(0.0).top(15))
                 .align_x(Horizontal::Right),
@@ -86,6 +92,10 @@ impl Menu {
                     .map(MenuMessage::Battery)
             }
             MenuMessage::Battery(message) => self.battery.update(message).map(MenuMessage::Battery),
+            MenuMessage::SleepTimer(remaining) => {
+                self.sleep_timer = remaining;
+                Task::none()
+            }
         }
     }
     pub fn subscription(&self) -> Subscription<MenuMessage> {
@@ -93,3 +103,13 @@ impl Menu {
         every(Duration::from_millis(60000)).map(|_| MenuMessage::Tick)
     }
 }
+
+/// "Zz 14:59", with the hours in front once it's an hour or more.
+fn format_countdown(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+
+    match secs / 3600 {
+        0 => format!("Zz {}:{:02}", secs / 60, secs % 60),
+        hours => format!("Zz {}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
@@ -1,5 +1,12 @@
 pub mod application;
 pub mod battery;
+pub mod equalizer;
+pub mod idle;
 pub mod library;
+pub mod lyrics;
 pub mod menu;
 pub mod player;
+pub mod power;
+pub mod scrobble;
+pub mod settings;
+pub mod visualizer;
@@ -1,15 +1,76 @@
-use crate::{audio::AudioHandle, model::track::Track};
+use crate::{
+    artwork::{ArtworkCache, ArtworkKey, render, render::ArtStyle},
+    audio::{AudioHandle, dsp::DspSettings},
+    frontend::visualizer::{FRAME_INTERVAL, Visualizer},
+    model::{
+        chapter::{self, Chapter},
+        listen::Listen,
+        track::{MediaKind, StreamInfo, Track},
+    },
+    session::Session,
+    settings::{PlaybackSettings, SPEED_OPTIONS, VisualizerSettings, cycle_option},
+};
+use chrono::{DateTime, Utc};
 use iced::{
     Alignment::Center,
-    Border, Color, Element, Length, Subscription, Task,
+    Background, Border, Color, Element, Length, Padding, Subscription, Task,
     advanced::image::Handle as ImageHandle,
+    alignment::Horizontal,
     keyboard::{Key, key::Named},
     time::every,
-    widget::{Column, Container, Image, Space, container::Style, row, text},
+    widget::{Column, Container, Image, Space, container::Style, progress_bar, row, text},
+};
+use std::{
+    fmt::Debug,
+    path::PathBuf,
+    time::{Duration, Instant},
 };
-use image::{ImageFormat, imageops::FilterType};
-use std::{fmt::Debug, time::Duration};
-use tracing::debug;
+use tracing::{debug, error, warn};
+
+/// Side of the square cover drawn in the player.
+const COVER_SIZE: u32 = 360;
+const PROGRESS_HEIGHT: f32 = 16.0;
+const SECONDARY_TEXT_SIZE: f32 = 36.0;
+const FOOTER_TEXT_SIZE: f32 = 30.0;
+/// Going back within this long of a track or chapter starting skips to the previous one instead
+/// of restarting it.
+const RESTART_THRESHOLD: Duration = Duration::from_secs(3);
+/// How long before the end of a track the next one is loaded, so it follows without a gap.
+const PRELOAD_AHEAD: Duration = Duration::from_secs(10);
+/// How often the place in a track that resumes is saved while it plays.
+const BOOKMARK_INTERVAL: Duration = Duration::from_secs(30);
+/// Leaving a track that resumes this close to its end counts as having played it through.
+const FINISHED_WITHIN: Duration = Duration::from_secs(30);
+/// Minutes the sleep timer can be set for, before it moves on to the end of the track and then
+/// of the queue.
+const SLEEP_MINUTES: [u64; 5] = [15, 30, 45, 60, 90];
+/// How often the sleep timer's countdown is updated, and checked while paused.
+const SLEEP_INTERVAL: Duration = Duration::from_secs(1);
+/// The furthest the position moves between two updates during playback, anything beyond it is a
+/// seek rather than time heard.
+const MAX_PLAYED_STEP: Duration = Duration::from_secs(1);
+
+/// Whether the next track in the queue has been loaded behind the current one.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+enum Preload {
+    #[default]
+    Pending,
+    Queued,
+    /// Loading it failed, it's left to be skipped once the current track ends.
+    Failed,
+}
+
+/// When the sleep timer stops playback.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum SleepTimer {
+    /// A number of minutes after it was set.
+    After {
+        minutes: u64,
+        deadline: Instant,
+    },
+    EndOfTrack,
+    EndOfQueue,
+}
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub enum PlayerState {
@@ -33,6 +94,34 @@ pub enum PlayerMessage {
     Pause,
     Playing,
     Load(Track),
+    /// Replaces the queue and starts playing the track at the given position in it.
+    Queue(Vec<Track>, usize),
+    /// Loads a track paused at a position, when restoring the last session.
+    Resume(Track, Duration),
+    Next,
+    Previous,
+    /// Skips to the next chapter of the current track, or the next track after its last one.
+    NextChapter,
+    PreviousChapter,
+    Stop,
+    CoverLoad(PathBuf, Option<ImageHandle>),
+    StreamInfoLoad(PathBuf, Option<StreamInfo>),
+    ArtStyleChange(ArtStyle),
+    VisualizerChange(VisualizerSettings),
+    PlaybackChange(PlaybackSettings),
+    DspChange(DspSettings),
+    VisualizerFrame,
+    /// The current track has played far enough to count as listened to.
+    Listened(Listen),
+    /// Playback has left a listened to track, after hearing this much of it.
+    Played(Listen, Duration),
+    /// Where a track that resumes was left off, `None` once it's been played through.
+    Bookmark(Track, Option<Duration>),
+    /// The speed picked for a track, to be remembered for it.
+    SpeedChosen(Track, f32),
+    SleepTick,
+    /// The sleep timer has stopped playback.
+    Slept,
     Input(Key),
     Error(String),
 }
@@ -43,6 +132,27 @@ pub struct Player {
     audio: Option<AudioHandle>,
     cover: Option<ImageHandle>,
     position: Option<Duration>,
+    artwork: Option<ArtworkCache>,
+    art_style: ArtStyle,
+    queue: Vec<Track>,
+    queue_position: usize,
+    show_info: bool,
+    stream_info: Option<StreamInfo>,
+    visualizer: Visualizer,
+    /// Whether the player is on an awake screen, the visualizer only runs while it is.
+    visible: bool,
+    /// When the current track first started playing.
+    started_at: Option<DateTime<Utc>>,
+    /// The current track's listen, once it's played far enough to count.
+    listen: Option<Listen>,
+    /// How much of the current track has actually been heard, seeks left out.
+    played: Duration,
+    preload: Preload,
+    playback: PlaybackSettings,
+    dsp: DspSettings,
+    /// Where the current track was when its place was last saved, when it resumes.
+    bookmarked: Option<Duration>,
+    sleep: Option<SleepTimer>,
 }
 
 impl Default for Player {
@@ -53,128 +163,898 @@ impl Default for Player {
             audio: None,
             cover: None,
             position: None,
+            artwork: None,
+            art_style: ArtStyle::default(),
+            queue: Vec::new(),
+            queue_position: 0,
+            show_info: false,
+            stream_info: None,
+            visualizer: Visualizer::default(),
+            visible: false,
+            started_at: None,
+            listen: None,
+            played: Duration::ZERO,
+            preload: Preload::default(),
+            playback: PlaybackSettings::default(),
+            dsp: DspSettings::default(),
+            bookmarked: None,
+            sleep: None,
         }
     }
 }
 
 impl Player {
-    pub fn new() -> Self {
+    pub fn new(
+        artwork: ArtworkCache,
+        art_style: ArtStyle,
+        visualizer: VisualizerSettings,
+        playback: PlaybackSettings,
+        dsp: DspSettings,
+    ) -> Self {
         Self {
-            state: PlayerState::default(),
-            track: None,
-            audio: None,
-            cover: None,
-            position: None,
+            artwork: Some(artwork),
+            art_style,
+            visualizer: Visualizer::new(visualizer),
+            playback,
+            dsp,
+            ..Default::default()
         }
     }
 
     pub fn view(&self) -> Element<'_, PlayerMessage> {
         let content = match &self.track {
-            Some(track) => match &self.cover {
-                Some(cover) => Column::new()
+            Some(track) => {
+                // Keep the frame in place while the cover is still loading.
+                let cover: Element<'_, PlayerMessage> = match &self.cover {
+                    Some(cover) => Image::new(cover)
+                        .width(COVER_SIZE as f32)
+                        .height(COVER_SIZE as f32)
+                        .into(),
+                    None => Space::new()
+                        .width(COVER_SIZE as f32)
+                        .height(COVER_SIZE as f32)
+                        .into(),
+                };
+
+                let elapsed = self.position.unwrap_or_default();
+                let remaining = track.duration.saturating_sub(elapsed);
+
+                let progress = progress_bar(
+                    0.0..=track.duration.as_secs_f32().max(1.0),
+                    elapsed.as_secs_f32(),
+                )
+                .length(Length::Fill)
+                .girth(PROGRESS_HEIGHT)
+                .style(|_| progress_bar::Style {
+                    background: Background::Color(Color::BLACK),
+                    bar: Background::Color(Color::WHITE),
+                    border: Border::default().width(3).color(Color::WHITE),
+                });
+
+                // The visualizer takes the space above the cover.
+                let top = match self.visualizer.is_enabled() {
+                    true => 0,
+                    false => 20,
+                };
+
+                let mut column = Column::new()
                     .align_x(Center)
-                    .push(Space::new().height(30))
+                    .push(Space::new().height(top))
                     .push(
-                        Container::new(
-                            Image::new(cover)
-                                .width(Length::Fixed(400.0))
-                                .height(Length::Fixed(400.0)),
-                        )
-                        .width(Length::Shrink)
-                        .padding(5)
-                        .style(|theme| Style {
-                            border: Border {
-                                color: Color::WHITE,
-                                width: 5.0,
-                                radius: 0.0.into(), // Sharp corners
-                            },
-                            ..Default::default()
-                        }),
+                        Container::new(cover)
+                            .width(Length::Shrink)
+                            .padding(5)
+                            .style(|_| Style {
+                                border: Border {
+                                    color: Color::WHITE,
+                                    width: 5.0,
+                                    radius: 0.0.into(), // Sharp corners
+                                },
+                                ..Default::default()
+                            }),
                     )
-                    .push(Space::new().height(20))
-                    .push(text(track.title.clone()))
-                    .push(text(track.artist.clone().unwrap_or_default()))
-                    .push(row![
-                        text(
-                            self.position
-                                .map(format_duration)
-                                .unwrap_or_else(|| "--:--".into())
-                        ),
-                        text(" / "),
-                        text(
-                            self.track
-                                .as_ref()
-                                .map(|t| format_duration(t.duration))
-                                .unwrap_or_else(|| "--:--".into())
-                        ),
-                    ])
-                    .width(Length::Fill),
-                None => Column::new(),
-            },
+                    .push(Space::new().height(10))
+                    .push(text(track.title.clone()).wrapping(text::Wrapping::None))
+                    .push(
+                        text(track.artist.clone().unwrap_or_default())
+                            .size(SECONDARY_TEXT_SIZE)
+                            .wrapping(text::Wrapping::None),
+                    )
+                    .push(Space::new().height(10))
+                    .push(Container::new(progress).padding(Padding::new(0.0).horizontal(40.0)))
+                    .push(
+                        row![
+                            text(format_duration(elapsed))
+                                .size(SECONDARY_TEXT_SIZE)
+                                .width(Length::Fill),
+                            text(
+                                [self.track_position(), self.speed().map(format_speed)]
+                                    .into_iter()
+                                    .flatten()
+                                    .collect::<Vec<_>>()
+                                    .join(" ")
+                            )
+                            .size(SECONDARY_TEXT_SIZE),
+                            text(format!("-{}", format_duration(remaining)))
+                                .size(SECONDARY_TEXT_SIZE)
+                                .width(Length::Fill)
+                                .align_x(Horizontal::Right),
+                        ]
+                        .padding(Padding::new(0.0).horizontal(40.0)),
+                    );
+
+                let footer = match self.show_info {
+                    true => self
+                        .stream_info
+                        .as_ref()
+                        .map(format_stream_info)
+                        .unwrap_or_else(|| "...".to_string()),
+                    false => match self.chapter() {
+                        Some((_, chapter)) => chapter.title.clone(),
+                        None => self
+                            .next_track()
+                            .map(|next| format!("Next: {}", next.title))
+                            .unwrap_or_default(),
+                    },
+                };
+                column = column.push(
+                    text(footer)
+                        .size(FOOTER_TEXT_SIZE)
+                        .wrapping(text::Wrapping::None),
+                );
+
+                if self.visualizer.is_enabled() {
+                    column = column.push(
+                        Container::new(self.visualizer.view())
+                            .padding(Padding::new(0.0).horizontal(40.0)),
+                    );
+                }
+
+                column.width(Length::Fill)
+            }
             None => Column::new().align_x(Center).push(text("No track loaded")),
         };
 
         Container::new(content)
             .width(Length::Fill)
             .height(Length::Fill)
+            .clip(true)
             .into()
     }
 
     pub fn update(&mut self, message: PlayerMessage) -> Task<PlayerMessage> {
         match message {
-            PlayerMessage::Load(track) => {
-                if let Some(inner) = &self.track {
-                    if &track == inner {
-                        return Task::none();
+            PlayerMessage::Load(track) => Task::done(PlayerMessage::Queue(vec![track], 0)),
+            PlayerMessage::Queue(queue, position) => {
+                let Some(track) = queue.get(position).cloned() else {
+                    return Task::none();
+                };
+
+                self.queue = queue;
+                self.queue_position = position;
+
+                if self.track.as_ref() == Some(&track) {
+                    return Task::none();
+                }
+
+                Task::batch([self.start(track), Task::done(PlayerMessage::Play)])
+            }
+            PlayerMessage::Resume(track, position) => {
+                self.queue = vec![track.clone()];
+                self.queue_position = 0;
+
+                let task = self.start(track.clone());
+                if let Some(audio) = &self.audio
+                    && let Err(e) = audio.seek(position)
+                {
+                    warn!("unable to resume {:?} at {:?}: {}", track.path, position, e);
+                }
+                self.position = Some(position);
+
+                task
+            }
+            PlayerMessage::Next => match self.queue.get(self.queue_position + 1).cloned() {
+                Some(track) => {
+                    self.queue_position += 1;
+                    Task::batch([self.start(track), Task::done(PlayerMessage::Play)])
+                }
+                None => Task::batch([self.bookmark(), Task::done(PlayerMessage::Stop)]),
+            },
+            PlayerMessage::Previous => {
+                let elapsed = self.position.unwrap_or_default();
+
+                match self.queue_position.checked_sub(1) {
+                    Some(previous) if elapsed < RESTART_THRESHOLD => {
+                        let track = self.queue[previous].clone();
+                        self.queue_position = previous;
+                        Task::batch([self.start(track), Task::done(PlayerMessage::Play)])
+                    }
+                    _ => {
+                        self.seek(Duration::ZERO);
+                        Task::none()
                     }
                 }
+            }
+            PlayerMessage::NextChapter => {
+                let next = self.chapter().map_or(0, |(index, _)| index + 1);
+                let start = self
+                    .track
+                    .as_ref()
+                    .and_then(|track| track.chapters.get(next))
+                    .map(|chapter| chapter.start);
+
+                match start {
+                    Some(start) => {
+                        self.seek(start);
+                        Task::none()
+                    }
+                    None => Task::done(PlayerMessage::Next),
+                }
+            }
+            PlayerMessage::PreviousChapter => {
+                let Some((index, chapter)) = self.chapter() else {
+                    return Task::done(PlayerMessage::Previous);
+                };
+                let into_chapter = self.position().saturating_sub(chapter.start);
+                let restart = chapter.start;
+
+                let previous = index
+                    .checked_sub(1)
+                    .and_then(|previous| self.track.as_ref()?.chapters.get(previous))
+                    .map(|chapter| chapter.start);
+
+                match (previous, into_chapter < RESTART_THRESHOLD) {
+                    (Some(start), true) => self.seek(start),
+                    (None, true) => return Task::done(PlayerMessage::Previous),
+                    (_, false) => self.seek(restart),
+                }
+                Task::none()
+            }
+            PlayerMessage::Stop => {
+                if let Some(audio) = &self.audio {
+                    audio.stop();
+                }
+
+                self.state = PlayerState::Paused;
+                Task::none()
+            }
+            PlayerMessage::ArtStyleChange(art_style) => {
+                self.art_style = art_style;
+
+                match self.track.clone() {
+                    Some(track) => self.load_cover(track),
+                    None => Task::none(),
+                }
+            }
+            PlayerMessage::VisualizerChange(settings) => {
+                self.visualizer.apply(settings);
+                self.update_tap();
+                Task::none()
+            }
+            PlayerMessage::PlaybackChange(settings) => {
+                self.playback = settings;
 
-                let handle = AudioHandle::new();
-                handle.load(&track.path);
+                if let (Some(audio), Some(track)) = (&self.audio, &self.track) {
+                    audio.set_speed(self.playback.speed(track));
+                }
+                self.fade();
+                Task::none()
+            }
+            PlayerMessage::DspChange(settings) => {
+                if let Some(audio) = &self.audio {
+                    audio.dsp().apply(&settings);
+                }
+                self.dsp = settings;
+                Task::none()
+            }
+            PlayerMessage::VisualizerFrame => {
+                if let Some(audio) = &self.audio {
+                    self.visualizer.feed(audio.tap());
+                }
 
-                self.track = Some(track.clone());
-                self.audio = Some(handle);
+                Task::none()
+            }
+            PlayerMessage::CoverLoad(path, cover) => {
+                if self.track.as_ref().map(|t| &t.path) == Some(&path) {
+                    self.cover = cover;
+                }
 
-                self.cover = pixelate_image(track.cover().byte_data, 90 as u32).ok();
+                Task::none()
+            }
+            PlayerMessage::StreamInfoLoad(path, stream_info) => {
+                if self.track.as_ref().map(|t| &t.path) == Some(&path) {
+                    self.stream_info = stream_info;
+                }
 
-                Task::done(PlayerMessage::Play)
+                Task::none()
             }
             PlayerMessage::Play if self.audio.is_some() => {
                 self.state = PlayerState::Playing;
+                self.started_at.get_or_insert_with(Utc::now);
                 self.audio.as_ref().unwrap().play();
                 Task::none()
             }
             PlayerMessage::Pause if self.audio.is_some() => {
                 self.state = PlayerState::Paused;
                 self.audio.as_ref().unwrap().pause();
-                Task::none()
+                self.bookmark()
             }
             PlayerMessage::Playing => {
-                if let Some(audio) = &self.audio {
-                    self.position = Some(audio.position());
+                let Some(audio) = &self.audio else {
+                    return Task::none();
+                };
+
+                let finished = audio.is_finished();
+                if self.sleep_due(finished) {
+                    return self.sleep();
+                }
+                if finished {
+                    return Task::done(PlayerMessage::Next);
                 }
 
-                Task::none()
+                // The preloaded track has taken over from the one that just ended, which is where
+                // a timer set for the end of the track after it was loaded stops.
+                if self.preload == Preload::Queued && audio.queued() <= 1 {
+                    return match self.sleep {
+                        Some(SleepTimer::EndOfTrack) => Task::batch([self.advance(), self.sleep()]),
+                        _ => self.advance(),
+                    };
+                }
+
+                let position = audio.position();
+                if let Some(step) = self
+                    .position
+                    .map(|previous| position.saturating_sub(previous))
+                    && step <= MAX_PLAYED_STEP
+                {
+                    self.played += step.div_f32(audio.speed());
+                }
+                self.position = Some(position);
+                self.fade();
+
+                let bookmark_due =
+                    position.abs_diff(self.bookmarked.unwrap_or_default()) >= BOOKMARK_INTERVAL;
+
+                if let (Preload::Pending, Some(track), Some(next)) =
+                    (self.preload, &self.track, self.next_track())
+                    && self.sleep != Some(SleepTimer::EndOfTrack)
+                {
+                    let left = track.duration.saturating_sub(position);
+
+                    match self.crossfade(track, next) {
+                        Some(length) if left.div_f32(audio.speed()) < length => {
+                            return self.crossfade_into(next.clone(), length);
+                        }
+                        Some(_) => {}
+                        None if left < PRELOAD_AHEAD => {
+                            self.preload = match audio.enqueue(next) {
+                                Ok(()) => Preload::Queued,
+                                Err(e) => {
+                                    warn!("unable to preload {:?}: {}", next.path, e);
+                                    Preload::Failed
+                                }
+                            };
+                        }
+                        None => {}
+                    }
+                }
+
+                let listened = match (&self.track, self.started_at) {
+                    (Some(track), Some(started_at))
+                        if self.listen.is_none()
+                            && Listen::threshold(track.duration)
+                                .is_some_and(|threshold| position >= threshold) =>
+                    {
+                        let listen = Listen::new(track, started_at);
+                        self.listen = Some(listen.clone());
+                        Task::done(PlayerMessage::Listened(listen))
+                    }
+                    _ => Task::none(),
+                };
+
+                match bookmark_due {
+                    true => Task::batch([self.bookmark(), listened]),
+                    false => listened,
+                }
             }
             PlayerMessage::Input(key) => match key.as_ref() {
                 Key::Named(Named::Space) => match self.state {
                     PlayerState::Playing => Task::done(PlayerMessage::Pause),
                     PlayerState::Paused => Task::done(PlayerMessage::Play),
                 },
+                Key::Named(Named::ArrowRight) => match self.has_chapters() {
+                    true => Task::done(PlayerMessage::NextChapter),
+                    false => Task::done(PlayerMessage::Next),
+                },
+                Key::Named(Named::ArrowLeft) => match self.has_chapters() {
+                    true => Task::done(PlayerMessage::PreviousChapter),
+                    false => Task::done(PlayerMessage::Previous),
+                },
+                Key::Named(Named::ArrowDown) => self.cycle_speed(),
+                Key::Named(Named::Tab) => {
+                    self.cycle_sleep();
+                    Task::none()
+                }
+                Key::Named(Named::Enter) => {
+                    self.show_info = !self.show_info;
+
+                    match (self.show_info, &self.stream_info, self.track.clone()) {
+                        (true, None, Some(track)) => self.load_stream_info(track),
+                        _ => Task::none(),
+                    }
+                }
                 _ => Task::none(),
             },
-            _ => todo!(),
+            PlayerMessage::SleepTick => match self.sleep_due(false) {
+                true => self.sleep(),
+                false => Task::none(),
+            },
+            PlayerMessage::Listened(_)
+            | PlayerMessage::Played(..)
+            | PlayerMessage::Bookmark(..)
+            | PlayerMessage::SpeedChosen(..)
+            | PlayerMessage::Slept => Task::none(),
+            // Nothing is loaded yet, such as on Space before any track was picked.
+            PlayerMessage::Play | PlayerMessage::Pause => Task::none(),
+            PlayerMessage::Error(e) => {
+                error!("playback error: {}", e);
+                Task::none()
+            }
+        }
+    }
+
+    pub fn track(&self) -> Option<&Track> {
+        self.track.as_ref()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == PlayerState::Playing
+    }
+
+    pub fn position(&self) -> Duration {
+        self.position.unwrap_or_default()
+    }
+
+    /// Shows or hides the player, the visualizer stops reading samples while it's hidden.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.update_tap();
+    }
+
+    fn update_tap(&self) {
+        if let Some(audio) = &self.audio {
+            audio
+                .tap()
+                .set_enabled(self.visible && self.visualizer.is_enabled());
+        }
+    }
+
+    /// The track after the current one in the queue.
+    pub fn next_track(&self) -> Option<&Track> {
+        self.queue.get(self.queue_position + 1)
+    }
+
+    fn has_chapters(&self) -> bool {
+        self.track
+            .as_ref()
+            .is_some_and(|track| !track.chapters.is_empty())
+    }
+
+    /// The chapter of the current track that's playing, with its index.
+    fn chapter(&self) -> Option<(usize, &Chapter)> {
+        let chapters = &self.track.as_ref()?.chapters;
+        let index = chapter::at(chapters, self.position())?;
+
+        Some((index, &chapters[index]))
+    }
+
+    /// The speed the audio plays at, when it's not normal speed.
+    fn speed(&self) -> Option<f32> {
+        self.audio
+            .as_ref()
+            .map(|audio| audio.speed())
+            .filter(|speed| *speed != 1.0)
+    }
+
+    /// Moves the current track on to the next speed, which it keeps from then on.
+    fn cycle_speed(&mut self) -> Task<PlayerMessage> {
+        let (Some(audio), Some(track)) = (&self.audio, &mut self.track) else {
+            return Task::none();
+        };
+
+        let speed = cycle_option(&SPEED_OPTIONS, audio.speed(), true);
+        audio.set_speed(speed);
+
+        track.speed = Some(speed);
+        for queued in self
+            .queue
+            .iter_mut()
+            .filter(|queued| queued.path == track.path && queued.start == track.start)
+        {
+            queued.speed = Some(speed);
+        }
+
+        Task::done(PlayerMessage::SpeedChosen(track.clone(), speed))
+    }
+
+    /// Moves the sleep timer on to its next setting, from off through the minutes to the end of
+    /// the track and of the queue, then off again.
+    fn cycle_sleep(&mut self) {
+        let after = |minutes: u64| SleepTimer::After {
+            minutes,
+            deadline: Instant::now() + Duration::from_secs(minutes * 60),
+        };
+
+        self.sleep = match self.sleep {
+            None => Some(after(SLEEP_MINUTES[0])),
+            Some(SleepTimer::After { minutes, .. }) => {
+                match SLEEP_MINUTES.iter().find(|next| **next > minutes) {
+                    Some(next) => Some(after(*next)),
+                    None => Some(SleepTimer::EndOfTrack),
+                }
+            }
+            Some(SleepTimer::EndOfTrack) => Some(SleepTimer::EndOfQueue),
+            Some(SleepTimer::EndOfQueue) => None,
+        };
+        self.fade();
+    }
+
+    /// How long until the sleep timer stops playback, the ends of tracks going by the current
+    /// speed.
+    pub fn sleep_remaining(&self) -> Option<Duration> {
+        let left_in_track = self.track.as_ref().map_or(Duration::ZERO, |track| {
+            track.duration.saturating_sub(self.position())
+        });
+
+        let remaining = match self.sleep? {
+            SleepTimer::After { deadline, .. } => {
+                return Some(deadline.saturating_duration_since(Instant::now()));
+            }
+            SleepTimer::EndOfTrack => left_in_track,
+            SleepTimer::EndOfQueue => {
+                left_in_track
+                    + self
+                        .queue
+                        .iter()
+                        .skip(self.queue_position + 1)
+                        .map(|track| track.duration)
+                        .sum::<Duration>()
+            }
+        };
+        let speed = self.audio.as_ref().map_or(1.0, |audio| audio.speed());
+
+        Some(remaining.div_f32(speed))
+    }
+
+    /// Whether the sleep timer has run out, `finished` saying whether everything loaded has
+    /// played.
+    fn sleep_due(&self, finished: bool) -> bool {
+        match self.sleep {
+            None => false,
+            Some(SleepTimer::After { deadline, .. }) => Instant::now() >= deadline,
+            Some(SleepTimer::EndOfTrack) => finished,
+            Some(SleepTimer::EndOfQueue) => finished && self.next_track().is_none(),
+        }
+    }
+
+    /// Stops playback for the sleep timer, with the volume back up for whatever plays next.
+    fn sleep(&mut self) -> Task<PlayerMessage> {
+        self.sleep = None;
+
+        let bookmark = match &self.audio {
+            Some(audio) => {
+                audio.pause();
+                audio.set_volume(1.0);
+                self.state = PlayerState::Paused;
+                self.bookmark()
+            }
+            None => Task::none(),
+        };
+
+        Task::batch([bookmark, Task::done(PlayerMessage::Slept)])
+    }
+
+    /// Turns the volume down over the last of the sleep timer, squared so it sounds like an even
+    /// fade.
+    fn fade(&self) {
+        let Some(audio) = &self.audio else {
+            return;
+        };
+
+        let volume = match (self.sleep_remaining(), self.playback.sleep_fade()) {
+            (Some(remaining), Some(fade)) if remaining < fade => {
+                (remaining.as_secs_f32() / fade.as_secs_f32()).powi(2)
+            }
+            _ => 1.0,
+        };
+        audio.set_volume(volume);
+    }
+
+    /// Moves playback within the current track.
+    fn seek(&mut self, position: Duration) {
+        if let Some(audio) = &self.audio
+            && let Err(e) = audio.seek(position)
+        {
+            warn!("unable to seek to {:?}: {}", position, e);
+        }
+        self.position = Some(position);
+    }
+
+    /// "3 / 12", the chapter of a track that has them, otherwise from the track's tags or else
+    /// its place in the queue.
+    fn track_position(&self) -> Option<String> {
+        let track = self.track.as_ref()?;
+
+        if let Some((index, _)) = self.chapter() {
+            return Some(format!("Ch {} / {}", index + 1, track.chapters.len()));
+        }
+
+        match (track.track_number, track.track_total) {
+            (Some(number), Some(total)) => Some(format!("{} / {}", number, total)),
+            (Some(number), None) => Some(number.to_string()),
+            _ if self.queue.len() > 1 => Some(format!(
+                "{} / {}",
+                self.queue_position + 1,
+                self.queue.len()
+            )),
+            _ => None,
+        }
+    }
+
+    /// Swaps the audio over to `track`, paused, and starts loading what's displayed for it.
+    ///
+    /// A track that resumes starts from where it was left off.
+    fn start(&mut self, track: Track) -> Task<PlayerMessage> {
+        let bookmark = self.bookmark();
+
+        let handle = AudioHandle::new();
+        handle.dsp().apply(&self.dsp);
+        if let Err(e) = handle.load(&track) {
+            warn!("unable to load {:?}: {}", track.path, e);
+        }
+
+        handle.set_speed(self.playback.speed(&track));
+
+        let resume = track.resume.filter(|_| self.playback.resumes(&track));
+        if let Some(position) = resume
+            && let Err(e) = handle.seek(position)
+        {
+            warn!("unable to resume {:?} at {:?}: {}", track.path, position, e);
+        }
+
+        self.state = PlayerState::Paused;
+        self.audio = Some(handle);
+        self.update_tap();
+        self.fade();
+
+        let task = self.show(track);
+        self.position = resume;
+        self.bookmarked = resume;
+
+        Task::batch([bookmark, task])
+    }
+
+    /// Moves on to the next track in the queue once the audio has, it was already playing.
+    ///
+    /// The outgoing track played to its end. The audio's position is already the next track's,
+    /// so it's bookmarked as finished rather than going by `place`.
+    fn advance(&mut self) -> Task<PlayerMessage> {
+        let finished = self.finish();
+        Task::batch([finished, self.follow_audio()])
+    }
+
+    /// Shows the next track in the queue as the current one, the audio having moved on to it.
+    fn follow_audio(&mut self) -> Task<PlayerMessage> {
+        let Some(track) = self.next_track().cloned() else {
+            return Task::none();
+        };
+
+        self.queue_position += 1;
+        if let Some(audio) = &self.audio {
+            audio.set_speed(self.playback.speed(&track));
+        }
+        let task = self.show(track);
+        self.started_at = Some(Utc::now());
+
+        task
+    }
+
+    /// How long `track` fades into `next` over, `None` when they play gaplessly: with
+    /// crossfading off, for spoken word and within a release meant to play straight through.
+    /// It's never more than half of either track, so a short one isn't faded over entirely.
+    fn crossfade(&self, track: &Track, next: &Track) -> Option<Duration> {
+        let music = track.kind == MediaKind::Music && next.kind == MediaKind::Music;
+        let gapless = track.gapless && next.gapless && track.same_release(next);
+
+        self.playback
+            .crossfade()
+            .filter(|_| music && !gapless)
+            .map(|length| length.min(track.duration / 2).min(next.duration / 2))
+    }
+
+    /// Fades `next` in over the end of the current track, it takes over straight away.
+    fn crossfade_into(&mut self, next: Track, length: Duration) -> Task<PlayerMessage> {
+        // Saved before the position moves on to the next track.
+        let bookmark = self.bookmark();
+
+        let Some(audio) = &mut self.audio else {
+            return bookmark;
+        };
+        match audio.crossfade(&next, length) {
+            Ok(()) => Task::batch([bookmark, self.follow_audio()]),
+            Err(e) => {
+                warn!("unable to crossfade into {:?}: {}", next.path, e);
+                self.preload = Preload::Failed;
+                bookmark
+            }
+        }
+    }
+
+    /// Saves where the current track is at when it resumes, or that it's been played through
+    /// when it's at the end.
+    fn bookmark(&mut self) -> Task<PlayerMessage> {
+        match self.place() {
+            Some((track, position)) => Task::done(PlayerMessage::Bookmark(track, position)),
+            None => Task::none(),
+        }
+    }
+
+    /// Saves that the current track has been played through, when it resumes.
+    fn finish(&mut self) -> Task<PlayerMessage> {
+        let Some(track) = self
+            .track
+            .clone()
+            .filter(|track| self.playback.resumes(track))
+        else {
+            return Task::none();
+        };
+
+        self.resume_queued(&track, None);
+        Task::done(PlayerMessage::Bookmark(track, None))
+    }
+
+    /// The current track and where it's at, when it resumes, `None` for the position once it's
+    /// been played through. Copies of it in the queue get the position too, so going back to one
+    /// picks up there.
+    pub fn place(&mut self) -> Option<(Track, Option<Duration>)> {
+        let track = self
+            .track
+            .clone()
+            .filter(|track| self.playback.resumes(track))?;
+
+        let elapsed = self.audio.as_ref().map(|audio| audio.position());
+        let finished = self.audio.as_ref().is_some_and(|audio| audio.is_finished())
+            || elapsed
+                .is_some_and(|elapsed| track.duration.saturating_sub(elapsed) < FINISHED_WITHIN);
+        let position = match finished {
+            true => None,
+            false => elapsed,
+        };
+
+        self.bookmarked = elapsed;
+        self.resume_queued(&track, position);
+
+        Some((track, position))
+    }
+
+    /// Sets where the copies of `track` in the queue resume from.
+    fn resume_queued(&mut self, track: &Track, position: Option<Duration>) {
+        for queued in self
+            .queue
+            .iter_mut()
+            .filter(|queued| queued.path == track.path && queued.start == track.start)
+        {
+            queued.resume = position;
+        }
+    }
+
+    /// Resets what's displayed and tracked for the current track to `track`.
+    fn show(&mut self, track: Track) -> Task<PlayerMessage> {
+        let played = match self.listen.take() {
+            Some(listen) => Task::done(PlayerMessage::Played(listen, self.played)),
+            None => Task::none(),
+        };
+
+        self.track = Some(track.clone());
+        self.preload = Preload::default();
+        self.position = None;
+        self.started_at = None;
+        self.played = Duration::ZERO;
+        self.bookmarked = None;
+        self.cover = None;
+        self.stream_info = None;
+
+        match self.show_info {
+            true => Task::batch([
+                played,
+                self.load_cover(track.clone()),
+                self.load_stream_info(track),
+            ]),
+            false => Task::batch([played, self.load_cover(track)]),
+        }
+    }
+
+    fn load_stream_info(&self, track: Track) -> Task<PlayerMessage> {
+        let path = track.path.clone();
+
+        Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || track.stream_info().ok())
+                    .await
+                    .ok()
+                    .flatten()
+            },
+            move |stream_info| PlayerMessage::StreamInfoLoad(path.clone(), stream_info),
+        )
+    }
+
+    /// What's needed to pick playback back up after a restart.
+    pub fn session(&self) -> Session {
+        Session {
+            track: self.track.as_ref().map(|track| track.path.clone()),
+            start: self.track.as_ref().and_then(|track| track.start),
+            position: self
+                .audio
+                .as_ref()
+                .map(|audio| audio.position())
+                .unwrap_or_default(),
         }
     }
 
+    fn load_cover(&self, track: Track) -> Task<PlayerMessage> {
+        let path = track.path.clone();
+
+        Task::perform(
+            load_cover(self.artwork.clone(), self.art_style, track),
+            move |cover| PlayerMessage::CoverLoad(path.clone(), cover),
+        )
+    }
+
     pub fn subscription(&self) -> Subscription<PlayerMessage> {
+        let mut subscriptions = Vec::new();
+
         if self.state == PlayerState::Playing {
             debug!("playback event emitting");
-            every(Duration::from_millis(100)).map(|_| PlayerMessage::Playing)
-        } else {
-            Subscription::none()
+
+            subscriptions.push(every(Duration::from_millis(100)).map(|_| PlayerMessage::Playing));
+
+            if self.visible && self.visualizer.is_enabled() {
+                subscriptions.push(every(FRAME_INTERVAL).map(|_| PlayerMessage::VisualizerFrame));
+            }
         }
+
+        // Keeps the countdown going, and runs the timer out while paused.
+        if self.sleep.is_some() {
+            subscriptions.push(every(SLEEP_INTERVAL).map(|_| PlayerMessage::SleepTick));
+        }
+
+        Subscription::batch(subscriptions)
+    }
+}
+
+/// "FLAC 44.1kHz 16bit 2ch 912kbps", leaving out whatever the container doesn't say.
+fn format_stream_info(info: &StreamInfo) -> String {
+    let mut parts = vec![info.codec.clone()];
+
+    if let Some(sample_rate) = info.sample_rate {
+        parts.push(format!("{}kHz", sample_rate as f32 / 1000.0));
+    }
+    if let Some(bits) = info.bits_per_sample {
+        parts.push(format!("{}bit", bits));
+    }
+    if let Some(channels) = info.channels {
+        parts.push(format!("{}ch", channels));
     }
+    if let Some(bitrate) = info.bitrate {
+        parts.push(format!("{}kbps", bitrate));
+    }
+
+    parts.join(" ")
+}
+
+/// "1.5x"
+fn format_speed(speed: f32) -> String {
+    format!("{}x", speed)
 }
 
 fn format_duration(d: Duration) -> String {
@@ -184,22 +1064,240 @@ fn format_duration(d: Duration) -> String {
     format!("{:02}:{:02}", mins, secs)
 }
 
-fn pixelate_image(img_bytes: Vec<u8>, block_size: u32) -> Result<ImageHandle, image::ImageError> {
-    // Load the original image
-    let img = image::load_from_memory(img_bytes.as_ref())?;
+/// Renders the track's cover in `style`, from the artwork cache when the release has an image
+/// there and otherwise from the art embedded in the file, off the UI thread.
+async fn load_cover(
+    artwork: Option<ArtworkCache>,
+    style: ArtStyle,
+    track: Track,
+) -> Option<ImageHandle> {
+    tokio::task::spawn_blocking(move || {
+        let cached = artwork
+            .zip(track.release_id)
+            .and_then(|(artwork, release_id)| {
+                artwork
+                    .render(ArtworkKey::Release(release_id), style, COVER_SIZE)
+                    .inspect_err(|e| warn!("unable to render cached cover: {}", e))
+                    .ok()
+                    .flatten()
+            });
+
+        let bytes = match cached {
+            Some(bytes) => bytes,
+            None => render::render_bytes(&track.cover().byte_data, style, COVER_SIZE).ok()?,
+        };
+
+        Some(ImageHandle::from_bytes(bytes))
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(title: &str, seconds: u64) -> Track {
+        Track {
+            title: title.to_string(),
+            path: PathBuf::from(format!("/music/{}.flac", title)),
+            duration: Duration::from_secs(seconds),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn durations_and_stream_details_are_formatted_compactly() {
+        assert_eq!(format_duration(Duration::from_millis(65_900)), "01:05");
+        assert_eq!(format_duration(Duration::from_secs(100 * 60)), "100:00");
+        assert_eq!(format_speed(1.5), "1.5x");
 
-    // Downsample to create chunky pixels
-    // For 720x720 display:
-    // block_size 90 = 8x8 pixel blocks (720/90 = 8)
-    // block_size 60 = 12x12 pixel blocks (720/60 = 12)
-    // block_size 45 = 16x16 pixel blocks (720/45 = 16)
-    let small = img.resize_exact(block_size, block_size, FilterType::Nearest);
+        let info = StreamInfo {
+            codec: "FLAC".to_string(),
+            sample_rate: Some(44_100),
+            bits_per_sample: Some(16),
+            channels: Some(2),
+            bitrate: Some(912),
+        };
+        assert_eq!(format_stream_info(&info), "FLAC 44.1kHz 16bit 2ch 912kbps");
+        let info = StreamInfo {
+            codec: "MP3".to_string(),
+            sample_rate: None,
+            bits_per_sample: None,
+            channels: Some(1),
+            bitrate: None,
+        };
+        assert_eq!(format_stream_info(&info), "MP3 1ch");
+    }
+
+    #[test]
+    fn the_track_position_comes_from_tags_or_else_the_queue() {
+        let mut player = Player::default();
+        assert_eq!(player.track_position(), None);
+
+        player.track = Some(Track {
+            track_number: Some(3),
+            track_total: Some(12),
+            ..track("a", 180)
+        });
+        assert_eq!(player.track_position().as_deref(), Some("3 / 12"));
+
+        player.track = Some(Track {
+            track_number: Some(3),
+            ..track("a", 180)
+        });
+        assert_eq!(player.track_position().as_deref(), Some("3"));
+
+        player.track = Some(track("b", 180));
+        assert_eq!(player.track_position(), None);
+        player.queue = vec![track("a", 180), track("b", 180), track("c", 180)];
+        player.queue_position = 1;
+        assert_eq!(player.track_position().as_deref(), Some("2 / 3"));
+        assert_eq!(player.next_track().map(|t| t.title.as_str()), Some("c"));
+    }
+
+    #[test]
+    fn a_finished_track_resumes_from_the_start_wherever_it_is_queued() {
+        let saved = Some(Duration::from_secs(600));
+        let mix = Track {
+            resume: saved,
+            ..track("mix", 3600)
+        };
+        let song = Track {
+            resume: saved,
+            ..track("song", 180)
+        };
+
+        let mut player = Player {
+            track: Some(mix.clone()),
+            queue: vec![mix.clone(), song.clone(), mix],
+            ..Default::default()
+        };
+        let _ = player.finish();
+
+        let resumes: Vec<_> = player.queue.iter().map(|track| track.resume).collect();
+        assert_eq!(resumes, [None, saved, None]);
+
+        // A short track isn't bookmarked at all.
+        player.track = Some(song);
+        let _ = player.finish();
+        assert_eq!(player.queue[1].resume, saved);
+    }
+
+    #[test]
+    fn the_sleep_timer_cycles_from_minutes_to_track_and_queue_ends() {
+        let mut player = Player::default();
+        let mut settings = vec![];
+        for _ in 0..SLEEP_MINUTES.len() + 3 {
+            player.cycle_sleep();
+            settings.push(match player.sleep {
+                Some(SleepTimer::After { minutes, deadline }) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    assert!(left <= Duration::from_secs(minutes * 60));
+                    assert!(left > Duration::from_secs(minutes * 60 - 5));
+                    format!("{}m", minutes)
+                }
+                Some(SleepTimer::EndOfTrack) => "track".to_string(),
+                Some(SleepTimer::EndOfQueue) => "queue".to_string(),
+                None => "off".to_string(),
+            });
+        }
+
+        assert_eq!(
+            settings,
+            ["15m", "30m", "45m", "60m", "90m", "track", "queue", "off"]
+        );
+    }
+
+    #[test]
+    fn the_sleep_timer_runs_out_at_its_deadline_or_the_end_of_what_plays() {
+        let mut player = Player::default();
+        assert!(!player.sleep_due(true));
+
+        player.sleep = Some(SleepTimer::After {
+            minutes: 15,
+            deadline: Instant::now() + Duration::from_secs(60),
+        });
+        assert!(!player.sleep_due(true));
+        player.sleep = Some(SleepTimer::After {
+            minutes: 15,
+            deadline: Instant::now() - Duration::from_secs(1),
+        });
+        assert!(player.sleep_due(false));
+
+        player.sleep = Some(SleepTimer::EndOfTrack);
+        assert!(!player.sleep_due(false));
+        assert!(player.sleep_due(true));
+
+        player.queue = vec![track("a", 200), track("b", 100), track("c", 50)];
+        player.track = Some(track("a", 200));
+        player.position = Some(Duration::from_secs(150));
+        player.sleep = Some(SleepTimer::EndOfQueue);
+        assert!(!player.sleep_due(true));
+        assert_eq!(player.sleep_remaining(), Some(Duration::from_secs(200)));
+
+        player.queue_position = 2;
+        assert!(player.sleep_due(true));
+
+        let _ = player.sleep();
+        assert_eq!(player.sleep, None);
+        assert_eq!(player.sleep_remaining(), None);
+    }
+
+    #[test]
+    fn crossfades_are_clamped_and_skipped_for_gapless_albums_and_spoken_word() {
+        let mut player = Player::default();
+        let release = |title: &str, seconds: u64, id: i64, gapless: bool| Track {
+            release_id: Some(id),
+            gapless,
+            ..track(title, seconds)
+        };
+        let long = release("a", 300, 1, false);
+        let next = release("b", 300, 2, false);
+        assert_eq!(player.crossfade(&long, &next), None);
 
-    // Scale back up to 720x720 with nearest neighbor (creates the pixel blocks)
-    let pixelated = small.resize_exact(720, 720, FilterType::Nearest);
+        player.playback.crossfade = 8;
+        assert_eq!(player.crossfade(&long, &next), Some(Duration::from_secs(8)));
+        assert_eq!(
+            player.crossfade(&long, &release("short", 10, 2, false)),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            player.crossfade(&release("short", 6, 1, false), &next),
+            Some(Duration::from_secs(3))
+        );
 
-    // Encode to PNG
-    let mut output = Vec::new();
-    pixelated.write_to(&mut std::io::Cursor::new(&mut output), ImageFormat::Png)?;
-    Ok(ImageHandle::from_bytes(output))
+        let gapless = release("a", 300, 1, true);
+        assert_eq!(
+            player.crossfade(&gapless, &release("b", 300, 1, true)),
+            None
+        );
+        assert_eq!(
+            player.crossfade(&gapless, &release("b", 300, 2, true)),
+            Some(Duration::from_secs(8))
+        );
+        assert_eq!(
+            player.crossfade(&gapless, &release("b", 300, 1, false)),
+            Some(Duration::from_secs(8))
+        );
+
+        let chapter = Track {
+            kind: MediaKind::SpokenWord,
+            ..next
+        };
+        assert_eq!(player.crossfade(&long, &chapter), None);
+    }
+
+    #[test]
+    fn play_and_pause_without_a_track_do_nothing() {
+        let mut player = Player::default();
+
+        let _ = player.update(PlayerMessage::Play);
+        assert!(!player.is_playing());
+        let _ = player.update(PlayerMessage::Pause);
+        let _ = player.update(PlayerMessage::Error("no output device".into()));
+        assert!(!player.is_playing());
+        assert!(player.track().is_none());
+    }
 }
@@ -0,0 +1,354 @@
+use std::time::{Duration, Instant};
+
+use iced::{
+    Color, Element, Length, Subscription, Task,
+    futures::{SinkExt, Stream},
+    time::every,
+    widget::{container, text},
+};
+use tokio::signal::unix::{SignalKind, signal};
+use tracing::error;
+
+use crate::{
+    battery::BatteryHandle,
+    power::{PowerControl, PowerError},
+    settings::PowerSettings,
+};
+
+/// How long the power button has to be held before the device powers off.
+const LONG_PRESS: Duration = Duration::from_secs(2);
+const BATTERY_INTERVAL: Duration = Duration::from_secs(30);
+const IDLE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShutdownReason {
+    CriticalBattery,
+    PowerButton,
+    /// Nothing played and no button was pressed for as long as the settings allow.
+    Idle,
+    /// The sleep timer ran out with powering off turned on.
+    SleepTimer,
+    /// systemd asked the application to stop, the device itself stays on.
+    Terminated,
+}
+
+impl ShutdownReason {
+    pub fn powers_off(&self) -> bool {
+        match self {
+            ShutdownReason::CriticalBattery
+            | ShutdownReason::PowerButton
+            | ShutdownReason::Idle
+            | ShutdownReason::SleepTimer => true,
+            ShutdownReason::Terminated => false,
+        }
+    }
+
+    /// Powers the device off through `control` when the reason calls for it, `false` when it
+    /// doesn't.
+    pub fn power_off(&self, control: &dyn PowerControl) -> Result<bool, PowerError> {
+        match self.powers_off() {
+            true => control.power_off().map(|_| true),
+            false => Ok(false),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PowerMessage {
+    BatteryCheck,
+    PowerKey(bool),
+    HoldCheck(Instant),
+    IdleCheck(Instant),
+    /// The sleep timer stopped playback, powering off after it when set.
+    SleepTimerEnded(bool),
+    Shutdown(ShutdownReason),
+    Error(String),
+}
+
+/// Watches the battery and power button, raising warnings and deciding when to shut down.
+pub struct Power {
+    settings: PowerSettings,
+    battery: Option<BatteryHandle>,
+    last_charge: Option<f32>,
+    warning: Option<u8>,
+    pressed_at: Option<Instant>,
+    /// The last button press, or when playback stopped if that was later.
+    last_activity: Instant,
+    playing: bool,
+    shutting_down: bool,
+}
+
+impl Power {
+    pub fn new(settings: PowerSettings, battery: Option<BatteryHandle>) -> Self {
+        Self {
+            settings,
+            battery,
+            last_charge: None,
+            warning: None,
+            pressed_at: None,
+            last_activity: Instant::now(),
+            playing: false,
+            shutting_down: false,
+        }
+    }
+
+    pub fn apply(&mut self, settings: PowerSettings) {
+        self.settings = settings;
+    }
+
+    pub fn dismiss_warning(&mut self) {
+        self.warning = None;
+    }
+
+    /// Restarts the idle countdown, on any button press.
+    pub fn activity(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// Keeps the device on while something plays, counting idle time from when it stops.
+    pub fn set_playing(&mut self, playing: bool) {
+        if self.playing && !playing {
+            self.last_activity = Instant::now();
+        }
+        self.playing = playing;
+    }
+
+    /// The low battery banner, while a warning is showing.
+    pub fn view(&self) -> Option<Element<'_, PowerMessage>> {
+        let level = self.warning?;
+
+        Some(
+            container(text(format!("Battery below {}%", level)).color(Color::BLACK))
+                .center_x(Length::Fill)
+                .padding(5)
+                .style(|_| container::Style {
+                    background: Some(Color::WHITE.into()),
+                    ..Default::default()
+                })
+                .into(),
+        )
+    }
+
+    pub fn update(&mut self, message: PowerMessage) -> Task<PowerMessage> {
+        match message {
+            PowerMessage::BatteryCheck => {
+                let Some(battery) = &mut self.battery else {
+                    return Task::none();
+                };
+
+                if let Err(e) = battery.refresh() {
+                    return Task::done(PowerMessage::Error(e.to_string()));
+                }
+
+                let status = battery.status();
+                let charge = status.percent();
+                let previous = self.last_charge.replace(charge).unwrap_or(100.0);
+
+                // Warn once per threshold as the charge drops past it.
+                if let Some(level) = self
+                    .settings
+                    .warning_levels
+                    .iter()
+                    .filter(|level| previous > f32::from(**level) && charge <= f32::from(**level))
+                    .min()
+                {
+                    self.warning = Some(*level);
+                }
+
+                let critical = self.settings.critical_level > 0
+                    && charge <= f32::from(self.settings.critical_level)
+                    && status.is_discharging();
+
+                match critical {
+                    true => self.shutdown(ShutdownReason::CriticalBattery),
+                    false => Task::none(),
+                }
+            }
+            PowerMessage::PowerKey(true) => {
+                self.pressed_at.get_or_insert_with(Instant::now);
+                Task::none()
+            }
+            PowerMessage::PowerKey(false) => {
+                let held = self
+                    .pressed_at
+                    .take()
+                    .map(|pressed_at| pressed_at.elapsed());
+
+                match held {
+                    Some(held) if held >= LONG_PRESS => self.shutdown(ShutdownReason::PowerButton),
+                    _ => Task::none(),
+                }
+            }
+            PowerMessage::HoldCheck(now) => match self.pressed_at {
+                Some(pressed_at) if now.duration_since(pressed_at) >= LONG_PRESS => {
+                    self.pressed_at = None;
+                    self.shutdown(ShutdownReason::PowerButton)
+                }
+                _ => Task::none(),
+            },
+            PowerMessage::IdleCheck(now) => announce(self.idle_check(now)),
+            PowerMessage::SleepTimerEnded(power_off) => announce(self.sleep_timer_ended(power_off)),
+            PowerMessage::Shutdown(_) => Task::none(),
+            PowerMessage::Error(e) => {
+                error!("power error: {}", e);
+                Task::none()
+            }
+        }
+    }
+
+    pub fn subscription(&self) -> Subscription<PowerMessage> {
+        let mut subscriptions = vec![
+            every(BATTERY_INTERVAL).map(|_| PowerMessage::BatteryCheck),
+            Subscription::run(terminate_signal),
+        ];
+
+        if self.settings.idle_power_off().is_some() {
+            subscriptions.push(every(IDLE_INTERVAL).map(PowerMessage::IdleCheck));
+        }
+
+        // Buttons don't always auto-repeat, so poll while one is held down.
+        if self.pressed_at.is_some() {
+            subscriptions.push(every(Duration::from_millis(250)).map(PowerMessage::HoldCheck));
+        }
+
+        Subscription::batch(subscriptions)
+    }
+
+    /// Shuts down once nothing has played and no button was pressed for the idle power off
+    /// time.
+    fn idle_check(&mut self, now: Instant) -> Option<ShutdownReason> {
+        let idle_power_off = self.settings.idle_power_off()?;
+
+        match !self.playing && now.saturating_duration_since(self.last_activity) >= idle_power_off {
+            true => self.begin_shutdown(ShutdownReason::Idle),
+            false => None,
+        }
+    }
+
+    fn sleep_timer_ended(&mut self, power_off: bool) -> Option<ShutdownReason> {
+        match power_off {
+            true => self.begin_shutdown(ShutdownReason::SleepTimer),
+            false => None,
+        }
+    }
+
+    fn shutdown(&mut self, reason: ShutdownReason) -> Task<PowerMessage> {
+        announce(self.begin_shutdown(reason))
+    }
+
+    /// The reason to shut down for, only the first time one comes up.
+    fn begin_shutdown(&mut self, reason: ShutdownReason) -> Option<ShutdownReason> {
+        if self.shutting_down {
+            return None;
+        }
+
+        self.shutting_down = true;
+        Some(reason)
+    }
+}
+
+fn announce(reason: Option<ShutdownReason>) -> Task<PowerMessage> {
+    match reason {
+        Some(reason) => Task::done(PowerMessage::Shutdown(reason)),
+        None => Task::none(),
+    }
+}
+
+/// Emits a shutdown whenever the process receives SIGTERM.
+fn terminate_signal() -> impl Stream<Item = PowerMessage> {
+    iced::stream::channel(1, async |mut output| {
+        let mut terminate = match signal(SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(e) => {
+                error!("unable to listen for SIGTERM: {}", e);
+                return;
+            }
+        };
+
+        while terminate.recv().await.is_some() {
+            let _ = output
+                .send(PowerMessage::Shutdown(ShutdownReason::Terminated))
+                .await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::power::RecordingControl;
+
+    fn power(idle_power_off: u64) -> Power {
+        Power::new(
+            PowerSettings {
+                idle_power_off,
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn idle_powers_off() {
+        let mut power = power(600);
+        let control = RecordingControl::default();
+        let start = Instant::now();
+
+        assert_eq!(power.idle_check(start + Duration::from_secs(300)), None);
+
+        let reason = power.idle_check(start + Duration::from_secs(601));
+        assert_eq!(reason, Some(ShutdownReason::Idle));
+        assert!(reason.unwrap().power_off(&control).unwrap());
+        assert_eq!(control.count(), 1);
+
+        // Shutting down already, the next check doesn't start another.
+        assert_eq!(power.idle_check(start + Duration::from_secs(900)), None);
+    }
+
+    #[test]
+    fn playing_keeps_the_device_on() {
+        let mut power = power(600);
+        power.set_playing(true);
+
+        let later = Instant::now() + Duration::from_secs(3600);
+        assert_eq!(power.idle_check(later), None);
+
+        // Counted from when playback stops rather than the last button press.
+        power.set_playing(false);
+        let stopped = Instant::now();
+        assert_eq!(power.idle_check(stopped + Duration::from_secs(300)), None);
+        assert_eq!(
+            power.idle_check(stopped + Duration::from_secs(601)),
+            Some(ShutdownReason::Idle)
+        );
+    }
+
+    #[test]
+    fn idle_power_off_disabled() {
+        let mut power = power(0);
+        assert_eq!(
+            power.idle_check(Instant::now() + Duration::from_secs(86400)),
+            None
+        );
+    }
+
+    #[test]
+    fn sleep_timer_powers_off() {
+        let control = RecordingControl::default();
+
+        assert_eq!(power(0).sleep_timer_ended(false), None);
+
+        let reason = power(0).sleep_timer_ended(true);
+        assert_eq!(reason, Some(ShutdownReason::SleepTimer));
+        assert!(reason.unwrap().power_off(&control).unwrap());
+        assert_eq!(control.count(), 1);
+    }
+
+    #[test]
+    fn terminated_leaves_the_device_on() {
+        let control = RecordingControl::default();
+
+        assert!(!ShutdownReason::Terminated.power_off(&control).unwrap());
+        assert_eq!(control.count(), 0);
+    }
+}
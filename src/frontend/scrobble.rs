@@ -0,0 +1,154 @@
+use std::{sync::Arc, time::Duration};
+
+use iced::{Subscription, Task, time::every};
+use tracing::{debug, error, info};
+
+use crate::{
+    model::listen::Listen,
+    scrobble::{ScrobbleClient, ScrobbleTarget},
+    service::{CatalogService, scrobble::ScrobbleServiceError},
+};
+
+/// How often queued listens are retried while there's somewhere to send them.
+const SUBMIT_INTERVAL: Duration = Duration::from_secs(300);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone)]
+pub enum ScrobbleMessage {
+    Record(Listen),
+    /// How long a recorded listen was actually heard for, once playback left it.
+    Played(Listen, Duration),
+    Submit,
+    Submitted(usize),
+    /// The service couldn't be reached, the listens stay queued.
+    Offline,
+    Error(String),
+}
+
+/// Records listens to the history and queues them, submitting whenever the service can be
+/// reached.
+pub struct Scrobbler {
+    catalog: CatalogService,
+    http: reqwest::Client,
+    client: Option<Arc<dyn ScrobbleClient>>,
+    submitting: bool,
+}
+
+impl Scrobbler {
+    pub fn new(catalog: CatalogService, target: Option<ScrobbleTarget>) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            catalog,
+            client: target.map(|target| target.client(http.clone())),
+            http,
+            submitting: false,
+        }
+    }
+
+    pub fn apply(&mut self, target: Option<ScrobbleTarget>) {
+        self.client = target.map(|target| target.client(self.http.clone()));
+    }
+
+    pub fn update(&mut self, message: ScrobbleMessage) -> Task<ScrobbleMessage> {
+        match message {
+            ScrobbleMessage::Record(listen) => {
+                let history = self.catalog.history.clone();
+                let scrobble = self.catalog.scrobble.clone();
+
+                Task::perform(
+                    async move {
+                        history
+                            .lock()
+                            .await
+                            .record(&listen)
+                            .await
+                            .map_err(|e| e.to_string())?;
+                        scrobble
+                            .lock()
+                            .await
+                            .record(listen)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    |result| match result {
+                        Ok(()) => ScrobbleMessage::Submit,
+                        Err(e) => ScrobbleMessage::Error(e),
+                    },
+                )
+            }
+            ScrobbleMessage::Played(listen, played) => {
+                let history = self.catalog.history.clone();
+
+                Task::perform(
+                    async move {
+                        history
+                            .lock()
+                            .await
+                            .listened(&listen, played)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    |result| result.err().map(ScrobbleMessage::Error),
+                )
+                .and_then(Task::done)
+            }
+            ScrobbleMessage::Submit => {
+                let Some(client) = self.client.clone() else {
+                    return Task::none();
+                };
+
+                if self.submitting {
+                    return Task::none();
+                }
+
+                self.submitting = true;
+                let scrobble = self.catalog.scrobble.clone();
+
+                Task::perform(
+                    async move {
+                        // Submitting takes a round trip per batch, recording new listens
+                        // shouldn't wait on it.
+                        let service = scrobble.lock().await.clone();
+                        service.submit(client.as_ref()).await
+                    },
+                    |result| match result {
+                        Ok(submitted) => ScrobbleMessage::Submitted(submitted),
+                        Err(ScrobbleServiceError::Scrobble(e)) if e.is_offline() => {
+                            ScrobbleMessage::Offline
+                        }
+                        Err(e) => ScrobbleMessage::Error(e.to_string()),
+                    },
+                )
+            }
+            ScrobbleMessage::Submitted(submitted) => {
+                self.submitting = false;
+
+                if submitted > 0 {
+                    info!("submitted {} listens", submitted);
+                }
+                Task::none()
+            }
+            ScrobbleMessage::Offline => {
+                self.submitting = false;
+                debug!("scrobble service unreachable, keeping listens queued");
+                Task::none()
+            }
+            ScrobbleMessage::Error(e) => {
+                self.submitting = false;
+                error!("scrobble error: {}", e);
+                Task::none()
+            }
+        }
+    }
+
+    pub fn subscription(&self) -> Subscription<ScrobbleMessage> {
+        match self.client.is_some() {
+            true => every(SUBMIT_INTERVAL).map(|_| ScrobbleMessage::Submit),
+            false => Subscription::none(),
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use iced::{
+    Element, Length, Padding, Task,
+    keyboard::{Key, key::Named},
+    widget::{Column, row, text},
+};
+use tracing::error;
+
+use crate::settings::{
+    IdleScreen, SPEED_OPTIONS, Settings, SettingsStore, VisualizerMode, cycle_option,
+};
+
+#[derive(Debug, Clone)]
+pub enum SettingsMessage {
+    InputEvent(Key),
+    Changed(Box<Settings>),
+    /// The equalizer row was picked, its settings are edited on their own screen.
+    OpenEqualizer,
+    Saved,
+    Error(String),
+}
+
+/// Choices offered for the idle timeouts, in seconds with 0 meaning never.
+const TIMEOUT_OPTIONS: [u64; 7] = [0, 15, 30, 60, 120, 300, 600];
+const BRIGHTNESS_OPTIONS: [f32; 4] = [0.25, 0.5, 0.75, 1.0];
+/// Choices for the critical battery level, in percent with 0 meaning off.
+const CRITICAL_OPTIONS: [u8; 5] = [0, 3, 5, 8, 10];
+/// Choices for powering off when idle, in seconds with 0 meaning never.
+const IDLE_POWER_OFF_OPTIONS: [u64; 5] = [0, 600, 1200, 1800, 3600];
+const VISUALIZER_OPTIONS: [VisualizerMode; 3] = [
+    VisualizerMode::Off,
+    VisualizerMode::Spectrum,
+    VisualizerMode::Waveform,
+];
+const BAR_OPTIONS: [u16; 5] = [8, 16, 24, 32, 48];
+/// Choices for how long a track has to be to resume, in seconds with 0 meaning by genre only.
+const RESUME_OPTIONS: [u64; 5] = [0, 600, 1200, 1800, 3600];
+/// Choices for the sleep timer's fade out, in seconds with 0 meaning none.
+const FADE_OPTIONS: [u64; 5] = [0, 10, 30, 60, 120];
+/// Choices for crossfading between tracks, in seconds with 0 meaning gapless.
+const CROSSFADE_OPTIONS: [u64; 7] = [0, 2, 4, 6, 8, 10, 12];
+/// Rows that fit on screen, the list scrolls to keep the selected one in view.
+const VISIBLE_ROWS: usize = 10;
+
+/// A row on the settings screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SettingsField {
+    ArtStyle,
+    ArtDetail,
+    Brightness,
+    DimAfter,
+    SleepAfter,
+    IdleScreen,
+    PixelShift,
+    CriticalBattery,
+    IdlePowerOff,
+    Visualizer,
+    VisualizerBars,
+    ResumeLongerThan,
+    MusicSpeed,
+    SpokenWordSpeed,
+    SleepFade,
+    SleepPowerOff,
+    Crossfade,
+    Equalizer,
+}
+
+impl SettingsField {
+    const ALL: [SettingsField; 18] = [
+        SettingsField::ArtStyle,
+        SettingsField::ArtDetail,
+        SettingsField::Brightness,
+        SettingsField::DimAfter,
+        SettingsField::SleepAfter,
+        SettingsField::IdleScreen,
+        SettingsField::PixelShift,
+        SettingsField::CriticalBattery,
+        SettingsField::IdlePowerOff,
+        SettingsField::Visualizer,
+        SettingsField::VisualizerBars,
+        SettingsField::ResumeLongerThan,
+        SettingsField::MusicSpeed,
+        SettingsField::SpokenWordSpeed,
+        SettingsField::SleepFade,
+        SettingsField::SleepPowerOff,
+        SettingsField::Crossfade,
+        SettingsField::Equalizer,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SettingsField::ArtStyle => "Art style",
+            SettingsField::ArtDetail => "Art detail",
+            SettingsField::Brightness => "Brightness",
+            SettingsField::DimAfter => "Dim after",
+            SettingsField::SleepAfter => "Sleep after",
+            SettingsField::IdleScreen => "Idle screen",
+            SettingsField::PixelShift => "Pixel shift",
+            SettingsField::CriticalBattery => "Power off at",
+            SettingsField::IdlePowerOff => "Idle power off",
+            SettingsField::Visualizer => "Visualizer",
+            SettingsField::VisualizerBars => "Bars",
+            SettingsField::ResumeLongerThan => "Resume over",
+            SettingsField::MusicSpeed => "Music speed",
+            SettingsField::SpokenWordSpeed => "Spoken speed",
+            SettingsField::SleepFade => "Sleep fade",
+            SettingsField::SleepPowerOff => "Sleep power off",
+            SettingsField::Crossfade => "Crossfade",
+            SettingsField::Equalizer => "Equalizer",
+        }
+    }
+
+    fn value(&self, settings: &Settings) -> String {
+        match self {
+            SettingsField::ArtStyle => settings.art_style.to_string(),
+            SettingsField::ArtDetail => settings
+                .art_style
+                .detail()
+                .unwrap_or_else(|| "-".to_string()),
+            SettingsField::Brightness => {
+                format!("{}%", (settings.display.brightness * 100.0).round())
+            }
+            SettingsField::DimAfter => format_timeout(settings.display.dim_after),
+            SettingsField::SleepAfter => format_timeout(settings.display.sleep_after),
+            SettingsField::IdleScreen => match settings.display.idle_screen {
+                IdleScreen::Blank => "Blank".to_string(),
+                IdleScreen::Clock => "Clock".to_string(),
+            },
+            SettingsField::PixelShift => match settings.display.pixel_shift {
+                true => "On".to_string(),
+                false => "Off".to_string(),
+            },
+            SettingsField::CriticalBattery => match settings.power.critical_level {
+                0 => "Never".to_string(),
+                level => format!("{}%", level),
+            },
+            SettingsField::IdlePowerOff => format_timeout(settings.power.idle_power_off),
+            SettingsField::Visualizer => match settings.visualizer.mode {
+                VisualizerMode::Off => "Off".to_string(),
+                VisualizerMode::Spectrum => "Spectrum".to_string(),
+                VisualizerMode::Waveform => "Waveform".to_string(),
+            },
+            SettingsField::VisualizerBars => settings.visualizer.bars.to_string(),
+            SettingsField::ResumeLongerThan => format_timeout(settings.playback.resume_longer_than),
+            SettingsField::MusicSpeed => format!("{}x", settings.playback.music_speed),
+            SettingsField::SpokenWordSpeed => format!("{}x", settings.playback.spoken_word_speed),
+            SettingsField::SleepFade => match settings.playback.sleep_fade {
+                0 => "Off".to_string(),
+                fade => format_timeout(fade),
+            },
+            SettingsField::SleepPowerOff => match settings.playback.sleep_power_off {
+                true => "On".to_string(),
+                false => "Off".to_string(),
+            },
+            SettingsField::Crossfade => match settings.playback.crossfade {
+                0 => "Off".to_string(),
+                seconds => format!("{}s", seconds),
+            },
+            SettingsField::Equalizer => match settings.dsp.equalizer_enabled {
+                true => settings.dsp.preset.to_string(),
+                false => "Off".to_string(),
+            },
+        }
+    }
+
+    fn adjust(&self, settings: &mut Settings, forward: bool) {
+        let display = &mut settings.display;
+
+        match self {
+            SettingsField::ArtStyle => settings.art_style = settings.art_style.cycle(forward),
+            SettingsField::ArtDetail => settings.art_style = settings.art_style.adjust(forward),
+            SettingsField::Brightness => {
+                display.brightness = cycle_option(&BRIGHTNESS_OPTIONS, display.brightness, forward)
+            }
+            SettingsField::DimAfter => {
+                display.dim_after = cycle_option(&TIMEOUT_OPTIONS, display.dim_after, forward)
+            }
+            SettingsField::SleepAfter => {
+                display.sleep_after = cycle_option(&TIMEOUT_OPTIONS, display.sleep_after, forward)
+            }
+            SettingsField::IdleScreen => {
+                display.idle_screen = match display.idle_screen {
+                    IdleScreen::Blank => IdleScreen::Clock,
+                    IdleScreen::Clock => IdleScreen::Blank,
+                }
+            }
+            SettingsField::PixelShift => display.pixel_shift = !display.pixel_shift,
+            SettingsField::CriticalBattery => {
+                settings.power.critical_level =
+                    cycle_option(&CRITICAL_OPTIONS, settings.power.critical_level, forward)
+            }
+            SettingsField::IdlePowerOff => {
+                settings.power.idle_power_off = cycle_option(
+                    &IDLE_POWER_OFF_OPTIONS,
+                    settings.power.idle_power_off,
+                    forward,
+                )
+            }
+            SettingsField::Visualizer => {
+                settings.visualizer.mode =
+                    cycle_option(&VISUALIZER_OPTIONS, settings.visualizer.mode, forward)
+            }
+            SettingsField::VisualizerBars => {
+                settings.visualizer.bars =
+                    cycle_option(&BAR_OPTIONS, settings.visualizer.bars, forward)
+            }
+            SettingsField::ResumeLongerThan => {
+                settings.playback.resume_longer_than = cycle_option(
+                    &RESUME_OPTIONS,
+                    settings.playback.resume_longer_than,
+                    forward,
+                )
+            }
+            SettingsField::MusicSpeed => {
+                settings.playback.music_speed =
+                    cycle_option(&SPEED_OPTIONS, settings.playback.music_speed, forward)
+            }
+            SettingsField::SpokenWordSpeed => {
+                settings.playback.spoken_word_speed =
+                    cycle_option(&SPEED_OPTIONS, settings.playback.spoken_word_speed, forward)
+            }
+            SettingsField::SleepFade => {
+                settings.playback.sleep_fade =
+                    cycle_option(&FADE_OPTIONS, settings.playback.sleep_fade, forward)
+            }
+            SettingsField::SleepPowerOff => {
+                settings.playback.sleep_power_off = !settings.playback.sleep_power_off
+            }
+            SettingsField::Crossfade => {
+                settings.playback.crossfade =
+                    cycle_option(&CROSSFADE_OPTIONS, settings.playback.crossfade, forward)
+            }
+            SettingsField::Equalizer => {}
+        }
+    }
+}
+
+fn format_timeout(seconds: u64) -> String {
+    match seconds {
+        0 => "Never".to_string(),
+        s if s < 60 => format!("{}s", s),
+        s => format!("{}m", s / 60),
+    }
+}
+
+pub struct SettingsPage {
+    store: SettingsStore,
+    settings: Settings,
+    selected: usize,
+}
+
+impl SettingsPage {
+    pub fn new(store: SettingsStore) -> Self {
+        let settings = store.load();
+
+        Self {
+            store,
+            settings,
+            selected: 0,
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn view(&self) -> Element<'_, SettingsMessage> {
+        let mut col = Column::new()
+            .spacing(5)
+            .padding(Padding::new(0.0).horizontal(10.0));
+
+        let first = self.selected.saturating_sub(VISIBLE_ROWS - 1);
+
+        for (position, field) in SettingsField::ALL
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(VISIBLE_ROWS)
+        {
+            let label = match position == self.selected {
+                true => format!("> {}", field.label()),
+                false => field.label().to_string(),
+            };
+
+            col = col.push(
+                row![
+                    text(label).width(Length::Fill),
+                    text(field.value(&self.settings)),
+                ]
+                .height(60),
+            );
+        }
+
+        col.into()
+    }
+
+    pub fn update(&mut self, message: SettingsMessage) -> Task<SettingsMessage> {
+        match message {
+            SettingsMessage::InputEvent(key) => match key.as_ref() {
+                Key::Named(Named::ArrowUp) => {
+                    self.selected = self.selected.saturating_sub(1);
+                    Task::none()
+                }
+                Key::Named(Named::ArrowDown) => {
+                    self.selected = (self.selected + 1).min(SettingsField::ALL.len() - 1);
+                    Task::none()
+                }
+                Key::Named(Named::ArrowLeft) => self.adjust(false),
+                Key::Named(Named::ArrowRight) | Key::Named(Named::Enter) => self.adjust(true),
+                _ => Task::none(),
+            },
+            SettingsMessage::Saved
+            | SettingsMessage::Changed(_)
+            | SettingsMessage::OpenEqualizer => Task::none(),
+            SettingsMessage::Error(e) => {
+                error!("settings error: {}", e);
+                Task::none()
+            }
+        }
+    }
+
+    /// Changes the selected setting, the equalizer opens its own screen instead.
+    fn adjust(&mut self, forward: bool) -> Task<SettingsMessage> {
+        match SettingsField::ALL[self.selected] {
+            SettingsField::Equalizer => Task::done(SettingsMessage::OpenEqualizer),
+            field => self.edit(|settings| field.adjust(settings, forward)),
+        }
+    }
+
+    /// Makes `change` to the settings, announces them and writes them to disk.
+    pub fn edit(&mut self, change: impl FnOnce(&mut Settings)) -> Task<SettingsMessage> {
+        change(&mut self.settings);
+
+        let store = self.store.clone();
+        let settings = self.settings.clone();
+
+        Task::batch([
+            Task::done(SettingsMessage::Changed(Box::new(settings.clone()))),
+            Task::perform(
+                async move { store.save(settings).await },
+                |result| match result {
+                    Ok(()) => SettingsMessage::Saved,
+                    Err(e) => SettingsMessage::Error(e.to_string()),
+                },
+            ),
+        ])
+    }
+}
@@ -0,0 +1,262 @@
+use std::{sync::Arc, time::Duration};
+
+use iced::{
+    Color, Element, Length, Point, Rectangle, Renderer, Size, Theme, mouse,
+    widget::{
+        Canvas,
+        canvas::{self, Cache, Geometry, Path, Stroke},
+    },
+};
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+
+use crate::{
+    audio::tap::SampleTap,
+    settings::{VisualizerMode, VisualizerSettings},
+};
+
+/// How often the visualizer redraws while it's running.
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(40);
+pub const VISUALIZER_HEIGHT: f32 = 48.0;
+
+/// Samples per analysis window, about 23ms at 44.1kHz.
+const FFT_SIZE: usize = 1024;
+const WAVEFORM_SAMPLES: usize = 512;
+/// Range the bars are spread over, logarithmically.
+const MIN_FREQUENCY: f32 = 40.0;
+const MAX_FREQUENCY: f32 = 16_000.0;
+/// Level drawn as an empty bar, in dB below full scale.
+const FLOOR_DB: f32 = -60.0;
+/// Fraction of its height a bar keeps each frame when the level drops, so peaks fall smoothly.
+const DECAY: f32 = 0.8;
+const BAR_GAP: f32 = 4.0;
+
+/// A spectrum or waveform drawn from the samples the audio engine is playing.
+pub struct Visualizer {
+    settings: VisualizerSettings,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    levels: Vec<f32>,
+    waveform: Vec<f32>,
+    cache: Cache,
+}
+
+impl Default for Visualizer {
+    fn default() -> Self {
+        Self::new(VisualizerSettings::default())
+    }
+}
+
+impl Visualizer {
+    pub fn new(settings: VisualizerSettings) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(FFT_SIZE);
+        let scratch = vec![Complex::default(); fft.get_inplace_scratch_len()];
+
+        // Hann window, to keep energy from leaking between neighbouring bars.
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                let phase = std::f32::consts::TAU * i as f32 / (FFT_SIZE - 1) as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+
+        Self {
+            levels: vec![0.0; usize::from(settings.bars)],
+            settings,
+            fft,
+            window,
+            buffer: vec![Complex::default(); FFT_SIZE],
+            scratch,
+            waveform: Vec::new(),
+            cache: Cache::new(),
+        }
+    }
+
+    pub fn apply(&mut self, settings: VisualizerSettings) {
+        self.levels = vec![0.0; usize::from(settings.bars)];
+        self.waveform.clear();
+        self.settings = settings;
+        self.cache.clear();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.mode != VisualizerMode::Off
+    }
+
+    /// Recomputes what's drawn from the latest samples in `tap`.
+    pub fn feed(&mut self, tap: &SampleTap) {
+        match self.settings.mode {
+            VisualizerMode::Off => return,
+            VisualizerMode::Spectrum => match tap.latest(FFT_SIZE) {
+                Some(samples) => self.analyze(&samples, tap.sample_rate()),
+                None => self.levels.iter_mut().for_each(|level| *level *= DECAY),
+            },
+            VisualizerMode::Waveform => {
+                self.waveform = tap.latest(WAVEFORM_SAMPLES).unwrap_or_default();
+            }
+        }
+
+        self.cache.clear();
+    }
+
+    pub fn view<'a, Message: 'a>(&'a self) -> Element<'a, Message> {
+        Canvas::new(self)
+            .width(Length::Fill)
+            .height(VISUALIZER_HEIGHT)
+            .into()
+    }
+
+    fn analyze(&mut self, samples: &[f32], sample_rate: u32) {
+        for ((bin, sample), weight) in self.buffer.iter_mut().zip(samples).zip(&self.window) {
+            *bin = Complex::new(sample * weight, 0.0);
+        }
+        self.fft
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+
+        let bin_width = sample_rate.max(1) as f32 / FFT_SIZE as f32;
+        let max_frequency = MAX_FREQUENCY.min(sample_rate as f32 / 2.0);
+        let bars = self.levels.len();
+
+        for (bar, level) in self.levels.iter_mut().enumerate() {
+            let edge = |bar: usize| {
+                MIN_FREQUENCY * (max_frequency / MIN_FREQUENCY).powf(bar as f32 / bars as f32)
+            };
+
+            let low = ((edge(bar) / bin_width) as usize).max(1);
+            let high = ((edge(bar + 1) / bin_width) as usize)
+                .max(low + 1)
+                .min(FFT_SIZE / 2);
+
+            let peak = self.buffer[low.min(high - 1)..high]
+                .iter()
+                .map(|bin| bin.norm())
+                .fold(0.0, f32::max);
+
+            // Scaled so a full scale sine reads 0dB, the window halves the amplitude.
+            let db = 20.0 * (peak * 4.0 / FFT_SIZE as f32).max(f32::EPSILON).log10();
+            let target = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+
+            *level = target.max(*level * DECAY);
+        }
+    }
+}
+
+impl<Message> canvas::Program<Message> for Visualizer {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let geometry = self.cache.draw(renderer, bounds.size(), |frame| {
+            let Size { width, height } = frame.size();
+
+            match self.settings.mode {
+                VisualizerMode::Off => {}
+                VisualizerMode::Spectrum => {
+                    let bars = self.levels.len().max(1) as f32;
+                    let bar_width = (width - BAR_GAP * (bars - 1.0)) / bars;
+
+                    for (bar, level) in self.levels.iter().enumerate() {
+                        let bar_height = (level * height).max(1.0);
+
+                        frame.fill_rectangle(
+                            Point::new(bar as f32 * (bar_width + BAR_GAP), height - bar_height),
+                            Size::new(bar_width, bar_height),
+                            Color::WHITE,
+                        );
+                    }
+                }
+                VisualizerMode::Waveform if self.waveform.len() > 1 => {
+                    let middle = height / 2.0;
+                    let step = width / (self.waveform.len() - 1) as f32;
+
+                    let path = Path::new(|builder| {
+                        for (index, sample) in self.waveform.iter().enumerate() {
+                            let point = Point::new(
+                                index as f32 * step,
+                                middle - sample.clamp(-1.0, 1.0) * middle,
+                            );
+
+                            match index {
+                                0 => builder.move_to(point),
+                                _ => builder.line_to(point),
+                            }
+                        }
+                    });
+
+                    frame.stroke(
+                        &path,
+                        Stroke::default().with_width(2.0).with_color(Color::WHITE),
+                    );
+                }
+                VisualizerMode::Waveform => {}
+            }
+        });
+
+        vec![geometry]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, sample_rate: u32) -> Vec<f32> {
+        (0..FFT_SIZE)
+            .map(|i| (std::f32::consts::TAU * frequency * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    /// The bar whose band holds `frequency`.
+    fn bar_of(frequency: f32, bars: usize, sample_rate: u32) -> usize {
+        let max_frequency = MAX_FREQUENCY.min(sample_rate as f32 / 2.0);
+        let position = (frequency / MIN_FREQUENCY).ln() / (max_frequency / MIN_FREQUENCY).ln();
+        (position * bars as f32) as usize
+    }
+
+    #[test]
+    fn a_sine_lights_up_the_bar_for_its_frequency() {
+        let mut visualizer = Visualizer::default();
+        let bars = visualizer.levels.len();
+        visualizer.analyze(&sine(1_000.0, 44_100), 44_100);
+
+        // Bands are cut on whole bins, so the peak can land in the neighbouring bar.
+        let expected = bar_of(1_000.0, bars, 44_100);
+        let loudest = visualizer
+            .levels
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(bar, _)| bar)
+            .expect("bars");
+        assert!(loudest.abs_diff(expected) <= 1);
+        assert!(visualizer.levels[loudest] > 0.9);
+        assert!(visualizer.levels[0] < 0.5);
+        assert!(visualizer.levels[bars - 1] < 0.5);
+    }
+
+    #[test]
+    fn bars_fall_back_gradually_in_silence() {
+        let mut visualizer = Visualizer::new(VisualizerSettings {
+            mode: VisualizerMode::Spectrum,
+            bars: 8,
+        });
+        assert_eq!(visualizer.levels.len(), 8);
+
+        visualizer.analyze(&sine(1_000.0, 44_100), 44_100);
+        let peak = visualizer.levels.clone();
+        visualizer.analyze(&[0.0; FFT_SIZE], 44_100);
+        for (level, peak) in visualizer.levels.iter().zip(&peak) {
+            assert!((level - peak * DECAY).abs() < 1e-6);
+        }
+
+        visualizer.feed(&SampleTap::default());
+        assert!((visualizer.levels[0] - peak[0] * DECAY * DECAY).abs() < 1e-6);
+    }
+}
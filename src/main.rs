@@ -1,18 +1,29 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
 
 use cpal::{Device, traits::HostTrait};
-use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use tracing::error;
 
 use iced::{Color, Font, Pixels, Settings, Size, Theme};
 
-use crate::{repository::RepositoryContext, service::CatalogService};
+use crate::{
+    artwork::ArtworkCache, model::collation, repository::RepositoryContext,
+    service::CatalogService, session::SessionStore, settings::SettingsStore,
+};
 
+pub mod artwork;
 pub mod audio;
 pub mod battery;
+pub mod display;
+pub mod export;
 pub mod frontend;
 pub mod model;
+pub mod power;
 pub mod repository;
+pub mod scrobble;
 pub mod service;
+pub mod session;
+pub mod settings;
 
 const APPLICATION_FONT: &[u8] = include_bytes!("resources/jersey_regular.ttf");
 
@@ -24,7 +35,12 @@ fn setup_audio_output() -> Option<Device> {
 fn main() -> iced::Result {
     tracing_subscriber::fmt::init();
 
-    let _ = setup_audio_output().expect("unable to setup audio device");
+    // `player export [dir]` writes the history, favorites and stats out and exits.
+    let mut args = std::env::args().skip(1);
+    let export_dir = match args.next().as_deref() {
+        Some("export") => Some(PathBuf::from(args.next().unwrap_or("export".to_string()))),
+        _ => None,
+    };
 
     // Create a runtime just for setup
     let rt = tokio::runtime::Runtime::new().unwrap();
@@ -32,9 +48,13 @@ fn main() -> iced::Result {
     let catalog_service = rt.block_on(async {
         sqlx::any::install_default_drivers();
 
+        let options = SqliteConnectOptions::from_str("sqlite:database.db?mode=rwc")
+            .expect("error parsing database url")
+            .collation(collation::COLLATION, collation::compare);
+
         let database_pool = SqlitePoolOptions::new()
             .max_connections(5)
-            .connect("sqlite:database.db?mode=rwc")
+            .connect_with(options)
             .await
             .expect("error initializing database");
 
@@ -42,22 +62,49 @@ fn main() -> iced::Result {
             .await
             .expect("error initializing repositories");
 
-        let service = CatalogService::new(repository_context)
-            .await
-            .expect("error initializing services");
+        CatalogService::new(
+            repository_context,
+            ArtworkCache::new(PathBuf::from("artwork")),
+        )
+        .await
+        .expect("error initializing services")
+    });
 
-        let _ = service
-            .sync(PathBuf::from("/Users/ian/Desktop/music/"))
-            .await;
+    if let Some(dir) = export_dir {
+        if let Err(e) = rt.block_on(export::export(&catalog_service, &dir)) {
+            error!("export failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-        return service;
+    let _ = setup_audio_output().expect("unable to setup audio device");
+
+    let settings_store = SettingsStore::new(PathBuf::from("settings.json"));
+    let library_settings = settings_store.load().library;
+
+    rt.block_on(async {
+        let _ = catalog_service
+            .sync(
+                PathBuf::from("/Users/ian/Desktop/music/"),
+                &library_settings,
+            )
+            .await;
     });
 
     // Drop the runtime before Iced creates its own
     drop(rt);
 
+    let session_store = SessionStore::new(PathBuf::from("session.json"));
+
     iced::application(
-        move || frontend::application::Application::new(catalog_service.clone()),
+        move || {
+            frontend::application::Application::new(
+                catalog_service.clone(),
+                settings_store.clone(),
+                session_store.clone(),
+            )
+        },
         frontend::application::Application::update,
         frontend::application::Application::view,
     )
@@ -13,4 +13,8 @@ impl Artist {
             self.releases.push(title.to_string())
         }
     }
+
+    pub fn remove_release(&mut self, title: &str) {
+        self.releases.retain(|r| r != title)
+    }
 }
@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// An audiobook or podcast, the spoken word counterpart of a release.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Book {
+    pub title: String,
+    /// The author or narrator, or whoever publishes a podcast.
+    pub author: Option<String>,
+    pub tracks: Vec<String>,
+    /// Where the book's files are, which tells apart books sharing a title.
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+}
+
+impl Book {
+    pub fn add_track(&mut self, title: &str) {
+        if !self.tracks.iter().any(|t| t == title) {
+            self.tracks.push(title.to_string())
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::container::{
+    self, Reader, atoms, child_atom, id3_frames, id3_text, nested_atom, top_level_atom,
+};
+
+/// Most chapters read from a chapter track, guarding against a corrupt sample table.
+const MAX_CHAPTERS: usize = 10_000;
+/// Most of a chapter track's text sample read, its title is at the start.
+const MAX_TEXT_SAMPLE: u32 = 1024;
+
+/// A named point within a track, such as a chapter of an audiobook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    pub start: Duration,
+}
+
+/// Reads the chapters marked in a file, Nero chapters or else a QuickTime chapter track from MP4
+/// files, and `CHAP` frames from an MP3's ID3 tag. Other files have none.
+pub fn read(path: &Path) -> io::Result<Vec<Chapter>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+
+    let mut chapters = match extension.as_deref() {
+        Some("m4b" | "m4a" | "mp4") => read_mp4(path)?,
+        Some("mp3") => read_id3(path)?,
+        _ => return Ok(Vec::new()),
+    };
+    chapters.sort_by_key(|chapter| chapter.start);
+
+    Ok(chapters)
+}
+
+/// The chapter playing at `position`, by its index.
+pub fn at(chapters: &[Chapter], position: Duration) -> Option<usize> {
+    chapters
+        .iter()
+        .rposition(|chapter| chapter.start <= position)
+}
+
+fn read_mp4(path: &Path) -> io::Result<Vec<Chapter>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let Some(moov) = top_level_atom(&mut file, b"moov")? else {
+        return Ok(Vec::new());
+    };
+
+    let nero = nested_atom(&moov, &[b"udta", b"chpl"])
+        .map(parse_chpl)
+        .unwrap_or_default();
+    if !nero.is_empty() {
+        return Ok(nero);
+    }
+
+    read_chapter_track(&mut file, &moov)
+}
+
+/// QuickTime chapters: a text track another track points at from its `tref/chap`, each sample
+/// the title of a chapter lasting as long as the sample does.
+fn read_chapter_track(file: &mut (impl Read + Seek), moov: &[u8]) -> io::Result<Vec<Chapter>> {
+    let tracks: Vec<&[u8]> = atoms(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .map(|(_, trak)| trak)
+        .collect();
+
+    let chapter_ids: Vec<u32> = tracks
+        .iter()
+        .filter_map(|trak| nested_atom(trak, &[b"tref", b"chap"]))
+        .flat_map(|chap| chap.chunks_exact(4))
+        .filter_map(|id| Reader(id).u32())
+        .collect();
+
+    let Some(samples) = tracks
+        .into_iter()
+        .find(|trak| track_id(trak).is_some_and(|id| chapter_ids.contains(&id)))
+        .and_then(text_samples)
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut chapters = Vec::new();
+    for (position, (start, offset, size)) in samples.into_iter().enumerate() {
+        let mut sample = Vec::new();
+        file.seek(SeekFrom::Start(offset))?;
+        file.take(u64::from(size.min(MAX_TEXT_SAMPLE)))
+            .read_to_end(&mut sample)?;
+
+        let title = Some(text_sample(&sample))
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| format!("Chapter {}", position + 1));
+        chapters.push(Chapter { title, start });
+    }
+
+    Ok(chapters)
+}
+
+/// The id a `trak` gives itself in its header, after the version, flags and times.
+fn track_id(trak: &[u8]) -> Option<u32> {
+    let tkhd = child_atom(trak, b"tkhd")?;
+    let times = match tkhd.first()? {
+        1 => 16,
+        _ => 8,
+    };
+
+    Reader(tkhd.get(4 + times..)?).u32()
+}
+
+/// When each sample of a track starts, where in the file it is and its size, from the track's
+/// sample table.
+fn text_samples(trak: &[u8]) -> Option<Vec<(Duration, u64, u32)>> {
+    let mdhd = nested_atom(trak, &[b"mdia", b"mdhd"])?;
+    let times = match mdhd.first()? {
+        1 => 16,
+        _ => 8,
+    };
+    let timescale = Reader(mdhd.get(4 + times..)?)
+        .u32()
+        .filter(|scale| *scale > 0)?;
+
+    let stbl = nested_atom(trak, &[b"mdia", b"minf", b"stbl"])?;
+
+    // Sample durations, as runs of samples sharing one.
+    let mut starts = Vec::new();
+    let mut reader = Reader(child_atom(stbl, b"stts")?.get(4..)?);
+    let mut time = 0u64;
+    for _ in 0..reader.u32()? {
+        let (count, delta) = (reader.u32()?, reader.u32()?);
+        for _ in 0..count {
+            if starts.len() >= MAX_CHAPTERS {
+                break;
+            }
+            starts.push(Duration::from_nanos(
+                (u128::from(time) * 1_000_000_000 / u128::from(timescale)) as u64,
+            ));
+            time += u64::from(delta);
+        }
+    }
+
+    let mut reader = Reader(child_atom(stbl, b"stsz")?.get(4..)?);
+    let (fixed, count) = (reader.u32()?, reader.u32()? as usize);
+    let sizes: Vec<u32> = match fixed {
+        0 => (0..count.min(MAX_CHAPTERS))
+            .map_while(|_| reader.u32())
+            .collect(),
+        size => vec![size; count.min(MAX_CHAPTERS)],
+    };
+
+    let chunk_offsets: Vec<u64> = match child_atom(stbl, b"stco") {
+        Some(stco) => {
+            let mut reader = Reader(stco.get(4..)?);
+            (0..reader.u32()?)
+                .map_while(|_| reader.u32().map(u64::from))
+                .collect()
+        }
+        None => {
+            let mut reader = Reader(child_atom(stbl, b"co64")?.get(4..)?);
+            (0..reader.u32()?).map_while(|_| reader.u64()).collect()
+        }
+    };
+
+    // Runs of chunks holding the same number of samples, by their first chunk counting from 1.
+    let mut reader = Reader(child_atom(stbl, b"stsc")?.get(4..)?);
+    let chunk_runs: Vec<(u32, u32)> = (0..reader.u32()?)
+        .map_while(|_| {
+            let run = (reader.u32()?, reader.u32()?);
+            reader.u32()?;
+            Some(run)
+        })
+        .collect();
+
+    let mut samples = Vec::new();
+    for (chunk, chunk_offset) in (1..).zip(chunk_offsets) {
+        let per_chunk = chunk_runs
+            .iter()
+            .rev()
+            .find(|(first, _)| *first <= chunk)
+            .map_or(0, |(_, samples)| *samples);
+
+        let mut offset = chunk_offset;
+        for _ in 0..per_chunk {
+            let index = samples.len();
+            let (Some(start), Some(size)) = (starts.get(index), sizes.get(index)) else {
+                break;
+            };
+            samples.push((*start, offset, *size));
+            offset += u64::from(*size);
+        }
+    }
+
+    Some(samples)
+}
+
+/// A text sample's title, prefixed with its length and in UTF-16 when it starts with a byte
+/// order mark, UTF-8 otherwise.
+fn text_sample(sample: &[u8]) -> String {
+    let mut reader = Reader(sample);
+    let Some(text) = reader
+        .u16()
+        .and_then(|len| reader.take(len as usize).or(Some(reader.0)))
+    else {
+        return String::new();
+    };
+
+    let text = match text.get(..2) {
+        Some([0xFE, 0xFF]) => {
+            let units: Vec<u16> = text[2..]
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text).into_owned(),
+    };
+
+    text.trim_end_matches('\0').trim().to_string()
+}
+
+/// Nero chapters: a version and flags, four more bytes from version 1 on, a count, then each
+/// chapter's start in 100ns units and its title prefixed with its length.
+fn parse_chpl(data: &[u8]) -> Vec<Chapter> {
+    let mut reader = Reader(data);
+    let mut chapters = Vec::new();
+
+    let Some(version) = reader.u8() else {
+        return chapters;
+    };
+    let skip = match version {
+        0 => 3,
+        _ => 7,
+    };
+    let Some(count) = reader.take(skip).and_then(|_| reader.u8()) else {
+        return chapters;
+    };
+
+    for _ in 0..count {
+        let Some(start) = reader.u64() else {
+            break;
+        };
+        let Some(title) = reader.u8().and_then(|len| reader.take(len as usize)) else {
+            break;
+        };
+
+        chapters.push(Chapter {
+            title: String::from_utf8_lossy(title).trim().to_string(),
+            start: Duration::from_nanos(start.saturating_mul(100)),
+        });
+    }
+
+    chapters
+}
+
+fn read_id3(path: &Path) -> io::Result<Vec<Chapter>> {
+    let Some((version, frames)) = container::read_id3(path)? else {
+        return Ok(Vec::new());
+    };
+
+    let chapters = id3_frames(&frames, version)
+        .into_iter()
+        .filter(|(id, _)| id == b"CHAP")
+        .enumerate()
+        .filter_map(|(position, (_, body))| parse_chap(body, version, position))
+        .collect();
+
+    Ok(chapters)
+}
+
+/// A `CHAP` frame: an element id ended by a null, start and end times in milliseconds, byte
+/// offsets, then frames describing it such as its `TIT2` title.
+fn parse_chap(data: &[u8], version: u8, position: usize) -> Option<Chapter> {
+    let id_end = data.iter().position(|b| *b == 0)?;
+    let mut reader = Reader(&data[id_end + 1..]);
+    let start = reader.u32()?;
+    reader.take(12)?;
+
+    let title = id3_frames(reader.0, version)
+        .into_iter()
+        .find(|(id, _)| id == b"TIT2")
+        .map(|(_, body)| id3_text(body))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| format!("Chapter {}", position + 1));
+
+    Some(Chapter {
+        title,
+        start: Duration::from_millis(start as u64),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut atom = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend_from_slice(kind);
+        atom.extend_from_slice(body);
+        atom
+    }
+
+    /// A version 0 full atom.
+    fn full_atom(kind: &[u8; 4], fields: &[u32]) -> Vec<u8> {
+        let mut body = vec![0; 4];
+        for field in fields {
+            body.extend_from_slice(&field.to_be_bytes());
+        }
+        atom(kind, &body)
+    }
+
+    fn text(title: &str) -> Vec<u8> {
+        let mut sample = (title.len() as u16).to_be_bytes().to_vec();
+        sample.extend_from_slice(title.as_bytes());
+        sample.extend_from_slice(&atom(b"encd", &[0, 0, 1, 0]));
+        sample
+    }
+
+    #[test]
+    fn chapters_from_a_quicktime_chapter_track() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.m4b");
+
+        let samples = [text("Opening"), text(""), text("Epilogue")];
+        let mdat = atom(b"mdat", &samples.concat());
+
+        // The audio track points at the text track, whose samples sit one after another in the
+        // media data, one chunk of three.
+        let audio = [
+            full_atom(b"tkhd", &[0, 0, 1, 0]),
+            atom(b"tref", &atom(b"chap", &2u32.to_be_bytes())),
+        ]
+        .concat();
+        let sizes = samples.iter().map(|sample| sample.len() as u32);
+        let stbl = [
+            full_atom(b"stts", &[2, 2, 90_000, 1, 30_000]),
+            full_atom(b"stsc", &[1, 1, 3, 1]),
+            full_atom(
+                b"stsz",
+                &[[0, 3].as_slice(), &sizes.collect::<Vec<_>>()].concat(),
+            ),
+            full_atom(b"stco", &[1, 8]),
+        ]
+        .concat();
+        let text = [
+            full_atom(b"tkhd", &[0, 0, 2, 0]),
+            atom(
+                b"mdia",
+                &[
+                    full_atom(b"mdhd", &[0, 0, 600, 0]),
+                    atom(b"minf", &atom(b"stbl", &stbl)),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        let moov = atom(
+            b"moov",
+            &[atom(b"trak", &audio), atom(b"trak", &text)].concat(),
+        );
+
+        fs::write(&path, [mdat, moov].concat()).unwrap();
+
+        let chapters = read(&path).unwrap();
+        let expected = [
+            ("Opening", 0),
+            ("Chapter 2", 150_000),
+            ("Epilogue", 300_000),
+        ];
+        assert_eq!(chapters.len(), expected.len());
+        for (chapter, (title, millis)) in chapters.iter().zip(expected) {
+            assert_eq!(chapter.title, title);
+            assert_eq!(chapter.start, Duration::from_millis(millis));
+        }
+    }
+
+    #[test]
+    fn nero_chapters_come_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.m4b");
+
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 1];
+        chpl.extend_from_slice(&50_000_000u64.to_be_bytes());
+        chpl.push(5);
+        chpl.extend_from_slice(b"Intro");
+        let moov = atom(b"moov", &atom(b"udta", &atom(b"chpl", &chpl)));
+        fs::write(&path, moov).unwrap();
+
+        let chapters = read(&path).unwrap();
+        assert_eq!(
+            chapters,
+            [Chapter {
+                title: "Intro".to_string(),
+                start: Duration::from_secs(5),
+            }]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use std::{cmp::Ordering, sync::LazyLock};
+
+use icu_collator::{Collator, CollatorOptions};
+use icu_locid::Locale;
+
+/// Leading articles that are ignored when sorting, e.g. "The Beatles" sorts under "B".
+const IGNORED_ARTICLES: &[&str] = &["the "];
+
+/// Letters shown in the jump-to-letter index, `#` collects everything that isn't A-Z.
+pub const INDEX_LETTERS: [char; 27] = [
+    '#', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R',
+    'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+];
+
+/// Name the collation is registered under with SQLite, sort keys are ordered with it.
+pub const COLLATION: &str = "library";
+
+/// Environment variables naming the locale lists are sorted for, most specific first.
+const LOCALE_VARIABLES: [&str; 3] = ["LC_ALL", "LC_COLLATE", "LANG"];
+
+static COLLATOR: LazyLock<Collator> = LazyLock::new(|| {
+    Collator::try_new(&locale().into(), CollatorOptions::new())
+        .or_else(|_| Collator::try_new(&Default::default(), CollatorOptions::new()))
+        .expect("root collation data is compiled in")
+});
+
+/// The locale from the environment, "sv_SE.UTF-8" sorts as Swedish, falling back to the root
+/// collation shared by most languages.
+fn locale() -> Locale {
+    LOCALE_VARIABLES
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| {
+            let tag = value.split(['.', '@']).next().unwrap_or_default();
+            tag.replace('_', "-").parse().ok()
+        })
+        .unwrap_or(Locale::UND)
+}
+
+/// Orders two sort keys by the locale's collation rules, registered with SQLite as
+/// [`COLLATION`].
+pub fn compare(a: &str, b: &str) -> Ordering {
+    COLLATOR.compare(a, b)
+}
+
+/// Builds the key used to order catalog items.
+///
+/// Leading punctuation and a leading "The " are dropped and the rest lowercased, accents and
+/// letters outside A-Z are left for [`compare`] to place where the locale puts them.
+pub fn sort_key(text: &str) -> String {
+    let trimmed = text.trim_start_matches(|c: char| !c.is_alphanumeric());
+    let lowered = match trimmed.is_empty() {
+        true => text.to_lowercase(),
+        false => trimmed.to_lowercase(),
+    };
+
+    IGNORED_ARTICLES
+        .iter()
+        .find_map(|article| {
+            lowered
+                .strip_prefix(article)
+                .filter(|rest| !rest.trim().is_empty())
+        })
+        .unwrap_or(&lowered)
+        .to_string()
+}
+
+/// Returns the index letter an item belongs under, the last one it sorts at or after, so "É"
+/// goes under E and, in Swedish, "Ö" under Z. Anything that isn't a latin letter goes under `#`.
+pub fn index_letter(text: &str) -> char {
+    let key = sort_key(text);
+    let Some(first) = key.chars().find(|c| c.is_alphanumeric()) else {
+        return '#';
+    };
+    if !is_latin_letter(first) {
+        return '#';
+    }
+
+    let first = first.to_string();
+    INDEX_LETTERS[1..]
+        .iter()
+        .rev()
+        .find(|letter| compare(&letter.to_ascii_lowercase().to_string(), &first).is_le())
+        .copied()
+        .unwrap_or('#')
+}
+
+fn is_latin_letter(c: char) -> bool {
+    c.is_alphabetic()
+        && matches!(c, 'a'..='z' | 'A'..='Z' | '\u{c0}'..='\u{24f}' | '\u{1e00}'..='\u{1eff}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accents_sort_among_plain_letters() {
+        let mut keys = ["zebra", "emily", "Émilie", "The Emil"].map(sort_key);
+        keys.sort_by(|a, b| compare(a, b));
+
+        assert_eq!(keys, ["emil", "émilie", "emily", "zebra"]);
+    }
+
+    #[test]
+    fn index_letters() {
+        assert_eq!(index_letter("The Beatles"), 'B');
+        assert_eq!(index_letter("Émilie Simon"), 'E');
+        assert_eq!(index_letter("...And You Will Know Us"), 'A');
+        assert_eq!(index_letter("2Pac"), '#');
+        assert_eq!(index_letter("Кино"), '#');
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, ErrorKind, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// The body of the first top level atom of `kind` in an MP4 file, skipping over the others such
+/// as the media data without reading them.
+pub fn top_level_atom(
+    file: &mut (impl Read + Seek),
+    kind: &[u8; 4],
+) -> io::Result<Option<Vec<u8>>> {
+    loop {
+        let mut header = [0; 8];
+        match file.read_exact(&mut header) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+
+        let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            // The size is in the 64 bits after the type, and counts those too.
+            1 => {
+                let mut large = [0; 8];
+                file.read_exact(&mut large)?;
+                match u64::from_be_bytes(large).checked_sub(16) {
+                    Some(size) => Some(size),
+                    None => return Ok(None),
+                }
+            }
+            // The atom runs to the end of the file.
+            0 => None,
+            size => match u64::from(size).checked_sub(8) {
+                Some(size) => Some(size),
+                None => return Ok(None),
+            },
+        };
+
+        match (&header[4..] == kind, size) {
+            (true, Some(size)) => {
+                let mut body = Vec::new();
+                file.take(size).read_to_end(&mut body)?;
+                return Ok(Some(body));
+            }
+            (true, None) => {
+                let mut body = Vec::new();
+                file.read_to_end(&mut body)?;
+                return Ok(Some(body));
+            }
+            (false, Some(size)) => {
+                file.seek(SeekFrom::Current(size as i64))?;
+            }
+            (false, None) => return Ok(None),
+        }
+    }
+}
+
+/// The atoms directly within `data` with their types, up to the first one whose size is too
+/// small for its own header or runs past the end.
+pub fn atoms(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut atoms = Vec::new();
+    let mut rest = data;
+
+    while rest.len() >= 8 {
+        let mut reader = Reader(rest);
+        let Some(size) = reader.u32() else {
+            break;
+        };
+        let (size, header) = match size {
+            1 => match reader.take(4).and_then(|_| reader.u64()) {
+                Some(size) => (usize::try_from(size).unwrap_or(usize::MAX), 16),
+                None => break,
+            },
+            0 => (rest.len(), 8),
+            size => (size as usize, 8),
+        };
+
+        // A size of zero in the 64 bit field lands here too, rather than looping in place.
+        if size < header {
+            break;
+        }
+        let Some(body) = rest.get(header..size) else {
+            break;
+        };
+
+        atoms.push(([rest[4], rest[5], rest[6], rest[7]], body));
+        rest = &rest[size..];
+    }
+
+    atoms
+}
+
+/// The body of the first atom of `kind` directly within `data`, `None` when an atom before it
+/// is malformed.
+pub fn child_atom<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    atoms(data)
+        .into_iter()
+        .find(|(found, _)| found == kind)
+        .map(|(_, body)| body)
+}
+
+/// The body of the atom reached by following `path` down from `data`.
+pub fn nested_atom<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter()
+        .try_fold(data, |data, kind| child_atom(data, kind))
+}
+
+/// The frames of the ID3v2.3 or v2.4 tag a file starts with, and which of the two it is. Only
+/// the header and the tag are read, not the audio after them.
+pub fn read_id3(path: &Path) -> io::Result<Option<(u8, Vec<u8>)>> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    let mut header = [0; 10];
+    match file.read_exact(&mut header) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+
+    // ID3v2.2 has three letter frame ids and isn't read.
+    let version = header[3];
+    if &header[..3] != b"ID3" || !(3..=4).contains(&version) {
+        return Ok(None);
+    }
+
+    let mut tag = Vec::new();
+    file.take(syncsafe(&header[6..10]) as u64)
+        .read_to_end(&mut tag)?;
+
+    // An extended header comes first when flagged, v2.3 leaves its own size out of the size.
+    let frames = match header[5] & 0x40 {
+        0 => Some(tag),
+        _ => {
+            let size = match version {
+                3 => Reader(&tag).u32().map(|size| size as usize + 4),
+                _ => tag.get(..4).map(syncsafe),
+            };
+            size.and_then(|size| tag.get(size..)).map(<[u8]>::to_vec)
+        }
+    };
+
+    Ok(Some((version, frames.unwrap_or_default())))
+}
+
+/// The ids and bodies of the frames in an ID3v2.3 or v2.4 tag, up to the padding.
+pub fn id3_frames(data: &[u8], version: u8) -> Vec<([u8; 4], &[u8])> {
+    let mut frames = Vec::new();
+    let mut rest = data;
+
+    while rest.len() >= 10 && rest[0] != 0 {
+        let size = match version {
+            3 => Reader(&rest[4..8]).u32().unwrap_or_default() as usize,
+            _ => syncsafe(&rest[4..8]),
+        };
+        let Some(body) = rest.get(10..10 + size) else {
+            break;
+        };
+
+        frames.push(([rest[0], rest[1], rest[2], rest[3]], body));
+        rest = &rest[10 + size..];
+    }
+
+    frames
+}
+
+/// The text of a text frame, which starts with a byte saying how it's encoded.
+pub fn id3_text(body: &[u8]) -> String {
+    let Some((encoding, text)) = body.split_first() else {
+        return String::new();
+    };
+
+    let text = match encoding {
+        0 => text.iter().map(|b| *b as char).collect(),
+        1 | 2 => {
+            let big_endian = match text.get(..2) {
+                Some([0xFF, 0xFE]) => false,
+                Some([0xFE, 0xFF]) => true,
+                _ => *encoding == 2,
+            };
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| match big_endian {
+                    true => u16::from_be_bytes([pair[0], pair[1]]),
+                    false => u16::from_le_bytes([pair[0], pair[1]]),
+                })
+                .filter(|unit| *unit != 0xFEFF)
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text).into_owned(),
+    };
+
+    text.trim_end_matches('\0').trim().to_string()
+}
+
+/// The genre a file is tagged with, going by its ID3 tag or MP4 metadata alone so it's cheap
+/// enough to ask of every file before deciding whether to read the rest.
+pub fn read_genre(path: &Path) -> io::Result<Option<String>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+
+    let genre = match extension.as_deref() {
+        Some("mp3") => read_id3(path)?.and_then(|(version, frames)| {
+            id3_frames(&frames, version)
+                .into_iter()
+                .find(|(id, _)| id == b"TCON")
+                .map(|(_, body)| id3_genre(&id3_text(body)))
+        }),
+        Some("m4a" | "m4b" | "mp4") => {
+            let mut file = BufReader::new(File::open(path)?);
+            top_level_atom(&mut file, b"moov")?.and_then(|moov| mp4_genre(&moov))
+        }
+        _ => None,
+    };
+
+    Ok(genre.filter(|genre| !genre.is_empty()))
+}
+
+/// A `TCON` genre without the ID3v1 genre numbers in brackets it may start with, and only the
+/// first of several in v2.4.
+fn id3_genre(text: &str) -> String {
+    let mut genre = text.split('\0').next().unwrap_or_default();
+    while let Some(rest) = genre
+        .strip_prefix('(')
+        .filter(|rest| !rest.starts_with('('))
+    {
+        match rest.split_once(')') {
+            Some((_, after)) => genre = after,
+            None => break,
+        }
+    }
+
+    genre.trim().to_string()
+}
+
+/// The `©gen` text of iTunes style metadata. `meta` is a full atom, its children come after a
+/// version and flags, and the text after a `data` atom's type and locale.
+fn mp4_genre(moov: &[u8]) -> Option<String> {
+    let meta = nested_atom(moov, &[b"udta", b"meta"])?;
+    let data = nested_atom(meta.get(4..)?, &[b"ilst", b"\xA9gen", b"data"])?;
+
+    Some(String::from_utf8_lossy(data.get(8..)?).trim().to_string())
+}
+
+/// A 28 bit size spread over four bytes with the top bit of each clear.
+pub fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .take(4)
+        .fold(0, |size, byte| (size << 7) | (*byte as usize & 0x7F))
+}
+
+/// Reads big endian values off the front of a byte slice.
+pub struct Reader<'a>(pub &'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let taken = self.0.get(..len)?;
+        self.0 = &self.0[len..];
+        Some(taken)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.take(2)?.try_into().ok().map(u16::from_be_bytes)
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.take(4)?.try_into().ok().map(u32::from_be_bytes)
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.take(8)?.try_into().ok().map(u64::from_be_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut atom = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend_from_slice(kind);
+        atom.extend_from_slice(body);
+        atom
+    }
+
+    #[test]
+    fn malformed_atoms_end_the_search() {
+        let udta = atom(b"udta", b"chapters");
+
+        // A 64 bit size of zero, and a size too small for the header.
+        let mut zero = [0, 0, 0, 1].to_vec();
+        zero.extend_from_slice(b"free");
+        zero.extend_from_slice(&[0; 8]);
+        zero.extend_from_slice(&udta);
+        let mut short = [0, 0, 0, 4].to_vec();
+        short.extend_from_slice(b"free");
+        short.extend_from_slice(&udta);
+
+        assert_eq!(child_atom(&zero, b"udta"), None);
+        assert_eq!(child_atom(&short, b"udta"), None);
+
+        let mut fine = atom(b"free", &[0; 4]);
+        fine.extend_from_slice(&udta);
+        assert_eq!(child_atom(&fine, b"udta"), Some(&b"chapters"[..]));
+    }
+
+    #[test]
+    fn genre_from_an_id3_tag() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episode.mp3");
+
+        let mut body = vec![0];
+        body.extend_from_slice(b"(101)Podcast");
+        let mut frame = b"TCON".to_vec();
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(&body);
+
+        let mut file = b"ID3\x03\x00\x00".to_vec();
+        file.extend_from_slice(&[0, 0, 0, frame.len() as u8]);
+        file.extend_from_slice(&frame);
+        file.extend_from_slice(&[0xFF; 4096]);
+        fs::write(&path, file).unwrap();
+
+        assert_eq!(read_genre(&path).unwrap().as_deref(), Some("Podcast"));
+    }
+
+    #[test]
+    fn genre_from_mp4_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.m4a");
+
+        let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+        data.extend_from_slice(b"Audiobook");
+        let ilst = atom(b"ilst", &atom(b"\xA9gen", &atom(b"data", &data)));
+        let mut meta = vec![0; 4];
+        meta.extend_from_slice(&ilst);
+        let moov = atom(b"moov", &atom(b"udta", &atom(b"meta", &meta)));
+
+        let mut file = atom(b"mdat", &[0; 4096]);
+        file.extend_from_slice(&moov);
+        fs::write(&path, file).unwrap();
+
+        assert_eq!(read_genre(&path).unwrap().as_deref(), Some("Audiobook"));
+    }
+
+    #[test]
+    fn untagged_files_have_no_genre() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        fs::write(&path, [0xFF; 4096]).unwrap();
+
+        assert_eq!(read_genre(&path).unwrap(), None);
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::model::playlist::decode;
+
+/// CD frames per second, the unit of the last field of a CUE timestamp.
+const FRAMES_PER_SECOND: u64 = 75;
+
+/// A parsed CUE sheet, only the parts needed to split a file into tracks.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub files: Vec<CueFile>,
+}
+
+/// An audio file referenced by a sheet and the tracks within it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueFile {
+    /// As written in the sheet, usually relative to it.
+    pub name: String,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// Where `INDEX 01` puts the start of the track within its file.
+    pub start: Duration,
+}
+
+impl CueSheet {
+    /// Reads a `.cue` file, `None` when it describes no tracks.
+    pub fn read(path: &Path) -> std::io::Result<Option<CueSheet>> {
+        Ok(CueSheet::parse(&decode(&std::fs::read(path)?)))
+    }
+
+    /// Parses a sheet, ignoring commands that don't affect where tracks are or what they're
+    /// called.
+    ///
+    /// Tracks start at `INDEX 01`, so any pregap stays with the track before it. A line that
+    /// can't be read is skipped, and a track whose number can't be read along with everything
+    /// up to the next one.
+    pub fn parse(contents: &str) -> Option<CueSheet> {
+        let mut sheet = CueSheet::default();
+        // Whether the current track has an INDEX 01, without one it starts at its INDEX 00.
+        let mut indexed = false;
+        // Whether the lines are about a track that was skipped.
+        let mut skipping = false;
+
+        for line in contents.lines() {
+            let line = line.trim();
+            let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+
+            let in_track = sheet
+                .files
+                .last()
+                .is_some_and(|file| !file.tracks.is_empty());
+
+            match command.to_ascii_uppercase().as_str() {
+                "FILE" => {
+                    skipping = false;
+                    sheet.files.push(CueFile {
+                        name: file_name(rest),
+                        tracks: Vec::new(),
+                    });
+                }
+                "TRACK" => {
+                    let number = rest.split_whitespace().next().and_then(|n| n.parse().ok());
+                    skipping = number.is_none();
+                    let Some(number) = number else {
+                        continue;
+                    };
+
+                    // Sheets occasionally leave out FILE when there's only one.
+                    if sheet.files.is_empty() {
+                        sheet.files.push(CueFile::default());
+                    }
+                    indexed = false;
+                    if let Some(file) = sheet.files.last_mut() {
+                        file.tracks.push(CueTrack {
+                            number,
+                            ..Default::default()
+                        });
+                    }
+                }
+                _ if skipping => {}
+                "TITLE" | "PERFORMER" => {
+                    let value = Some(unquote(rest)).filter(|v| !v.is_empty());
+                    let track = match in_track {
+                        true => sheet.files.last_mut().and_then(|f| f.tracks.last_mut()),
+                        false => None,
+                    };
+
+                    match (command.eq_ignore_ascii_case("TITLE"), track) {
+                        (true, Some(track)) => track.title = value,
+                        (false, Some(track)) => track.performer = value,
+                        (true, None) => sheet.title = value,
+                        (false, None) => sheet.performer = value,
+                    }
+                }
+                "INDEX" if in_track => {
+                    let Some((index, time)) = parse_index(rest) else {
+                        continue;
+                    };
+                    let Some(track) = sheet.files.last_mut().and_then(|f| f.tracks.last_mut())
+                    else {
+                        continue;
+                    };
+
+                    match index {
+                        0 if !indexed => track.start = time,
+                        1 => {
+                            track.start = time;
+                            indexed = true;
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        sheet.files.retain(|file| !file.tracks.is_empty());
+        (!sheet.files.is_empty()).then_some(sheet)
+    }
+}
+
+impl CueFile {
+    /// The audio file as it is next to the sheet at `sheet_path`.
+    ///
+    /// Sheets are often written for the WAV a CD was ripped to and kept after it's been
+    /// compressed, so a FLAC of the same name stands in when the named file is missing.
+    pub fn locate(&self, sheet_path: &Path) -> Option<PathBuf> {
+        let path = sheet_path.parent()?.join(self.name.replace('\\', "/"));
+
+        match path.is_file() {
+            true => Some(path),
+            false => Some(path.with_extension("flac")).filter(|path| path.is_file()),
+        }
+    }
+
+    /// Start and end of each track, the last running to the end of the file.
+    pub fn bounds(&self) -> Vec<(Duration, Option<Duration>)> {
+        self.tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| (track.start, self.tracks.get(i + 1).map(|next| next.start)))
+            .collect()
+    }
+}
+
+/// The number and time of `INDEX 01 03:25:40`.
+fn parse_index(rest: &str) -> Option<(u32, Duration)> {
+    let mut fields = rest.split_whitespace();
+    let index = fields.next()?.parse().ok()?;
+    let time = parse_timestamp(fields.next()?)?;
+
+    Some((index, time))
+}
+
+/// `mm:ss:ff`, where the minutes can go past 59 and frames are 1/75th of a second.
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let mut fields = value.split(':').map(|field| field.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (fields.next()??, fields.next()??, fields.next()??);
+
+    Some(
+        Duration::from_secs(minutes * 60 + seconds)
+            + Duration::from_nanos(frames * 1_000_000_000 / FRAMES_PER_SECOND),
+    )
+}
+
+/// The file name from `FILE "name" WAVE`, quoted or not.
+fn file_name(rest: &str) -> String {
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default().to_string(),
+        None => rest
+            .rsplit_once(char::is_whitespace)
+            .map(|(name, _)| name)
+            .unwrap_or(rest)
+            .to_string(),
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starts(file: &CueFile) -> Vec<(u32, Duration)> {
+        file.tracks
+            .iter()
+            .map(|track| (track.number, track.start))
+            .collect()
+    }
+
+    #[test]
+    fn tracks_start_at_index_01_and_pregaps_stay_with_the_track_before() {
+        let sheet = CueSheet::parse(
+            "PERFORMER \"Artist\"\n\
+             TITLE \"Album\"\n\
+             FILE \"album.wav\" WAVE\n\
+             \x20 TRACK 01 AUDIO\n\
+             \x20   TITLE \"One\"\n\
+             \x20   INDEX 01 00:00:00\n\
+             \x20 TRACK 02 AUDIO\n\
+             \x20   TITLE \"Two\"\n\
+             \x20   PERFORMER \"Guest\"\n\
+             \x20   INDEX 00 03:10:00\n\
+             \x20   INDEX 01 03:12:30\n",
+        )
+        .unwrap();
+
+        assert_eq!(sheet.title.as_deref(), Some("Album"));
+        assert_eq!(sheet.performer.as_deref(), Some("Artist"));
+        assert_eq!(sheet.files.len(), 1);
+
+        let file = &sheet.files[0];
+        assert_eq!(file.name, "album.wav");
+        assert_eq!(
+            starts(file),
+            [
+                (1, Duration::ZERO),
+                (2, Duration::from_secs(192) + Duration::from_millis(400))
+            ]
+        );
+        assert_eq!(file.tracks[1].title.as_deref(), Some("Two"));
+        assert_eq!(file.tracks[1].performer.as_deref(), Some("Guest"));
+        assert_eq!(
+            file.bounds()[0],
+            (Duration::ZERO, Some(file.tracks[1].start))
+        );
+        assert_eq!(file.bounds()[1], (file.tracks[1].start, None));
+    }
+
+    #[test]
+    fn a_track_without_index_01_starts_at_its_index_00() {
+        let sheet =
+            CueSheet::parse("FILE \"a.flac\" WAVE\nTRACK 01 AUDIO\nINDEX 00 00:01:00\n").unwrap();
+
+        assert_eq!(starts(&sheet.files[0]), [(1, Duration::from_secs(1))]);
+    }
+
+    #[test]
+    fn tracks_are_kept_with_the_file_they_follow() {
+        let sheet = CueSheet::parse(
+            "FILE \"disc 1.wav\" WAVE\n\
+             TRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+             TRACK 02 AUDIO\nINDEX 01 04:00:00\n\
+             FILE side-b.wav WAVE\n\
+             TRACK 03 AUDIO\nINDEX 01 00:00:00\n\
+             FILE \"empty.wav\" WAVE\n",
+        )
+        .unwrap();
+
+        let names: Vec<_> = sheet.files.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names, ["disc 1.wav", "side-b.wav"]);
+        assert_eq!(sheet.files[0].tracks.len(), 2);
+        assert_eq!(starts(&sheet.files[1]), [(3, Duration::ZERO)]);
+    }
+
+    #[test]
+    fn a_missing_file_line_is_tolerated() {
+        let sheet = CueSheet::parse("TRACK 01 AUDIO\nTITLE \"Only\"\nINDEX 01 00:00:00\n").unwrap();
+
+        assert_eq!(sheet.files.len(), 1);
+        assert_eq!(sheet.files[0].name, "");
+        assert_eq!(sheet.files[0].tracks[0].title.as_deref(), Some("Only"));
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped_rather_than_the_sheet() {
+        let sheet = CueSheet::parse(
+            "FILE \"a.wav\" WAVE\n\
+             TRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+             INDEX 01 garbage\n\
+             TRACK xx AUDIO\nTITLE \"Lost\"\nINDEX 01 01:00:00\n\
+             %%% not a command\n\
+             TRACK 03 AUDIO\nINDEX 01 02:00:00\n",
+        )
+        .unwrap();
+
+        let file = &sheet.files[0];
+        assert_eq!(
+            starts(file),
+            [(1, Duration::ZERO), (3, Duration::from_secs(120))]
+        );
+        assert_eq!(file.tracks[0].title, None);
+    }
+
+    #[test]
+    fn a_sheet_without_tracks_is_none() {
+        assert_eq!(
+            CueSheet::parse("REM nothing here\nFILE \"a.wav\" WAVE\n"),
+            None
+        );
+    }
+}
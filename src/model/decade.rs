@@ -0,0 +1,43 @@
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+/// Ten years of releases, listed in the library the way an artist or genre is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decade {
+    /// The first year, such as 1990.
+    pub start: i32,
+    /// "1990s".
+    pub name: String,
+}
+
+impl Decade {
+    pub fn new(start: i32) -> Self {
+        Self {
+            start,
+            name: format!("{}s", start),
+        }
+    }
+
+    /// The decade `year` falls in.
+    pub fn of(year: i32) -> Self {
+        Self::new(year.div_euclid(10) * 10)
+    }
+
+    pub fn years(&self) -> RangeInclusive<i32> {
+        self.start..=self.start + 9
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn years_are_filed_under_the_decade_they_start() {
+        assert_eq!(Decade::of(1997), Decade::new(1990));
+        assert_eq!(Decade::of(2000).name, "2000s");
+        assert_eq!(Decade::of(2009).years(), 2000..=2009);
+        assert_eq!(Decade::of(-5).start, -10);
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Genre {
+    pub name: String,
+    pub releases: Vec<String>,
+}
+
+impl Genre {
+    pub fn add_release(&mut self, title: &str) {
+        if !self.releases.iter().any(|r| r == title) {
+            self.releases.push(title.to_string())
+        }
+    }
+
+    pub fn remove_release(&mut self, title: &str) {
+        self.releases.retain(|r| r != title)
+    }
+}
@@ -0,0 +1,46 @@
+use std::{path::PathBuf, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::model::track::Track;
+
+/// Tracks shorter than this are never counted as listened to.
+const MIN_DURATION: Duration = Duration::from_secs(30);
+/// Playing this long always counts, however long the track is.
+const MAX_THRESHOLD: Duration = Duration::from_secs(240);
+
+/// A track played far enough to count, waiting to be scrobbled.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Listen {
+    pub title: String,
+    pub artist: String,
+    pub release: Option<String>,
+    pub duration: Duration,
+    /// When playback of the track started.
+    pub listened_at: DateTime<Utc>,
+    /// The file the track was played from, which tells it apart from others sharing its title
+    /// and artist. Listens read back from the history or the scrobble queue don't keep it.
+    pub path: Option<PathBuf>,
+    /// Where the track starts within the file.
+    pub start: Option<Duration>,
+}
+
+impl Listen {
+    pub fn new(track: &Track, listened_at: DateTime<Utc>) -> Self {
+        Self {
+            title: track.title.clone(),
+            artist: track.artist.clone().unwrap_or_default(),
+            release: track.release.clone(),
+            duration: track.duration,
+            listened_at,
+            path: Some(track.path.clone()),
+            start: track.start,
+        }
+    }
+
+    /// How far into a track of `duration` playback has to get before it counts, half the track
+    /// or four minutes, whichever comes first.
+    pub fn threshold(duration: Duration) -> Option<Duration> {
+        (duration > MIN_DURATION).then(|| (duration / 2).min(MAX_THRESHOLD))
+    }
+}
@@ -0,0 +1,291 @@
+use std::{fs::File, io::Read, path::Path, time::Duration};
+
+/// ID3v2 timestamp format for absolute milliseconds, the only SYLT format that can be followed
+/// without decoding MPEG frame counts.
+const SYLT_MILLISECONDS: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyricLine {
+    pub time: Duration,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lyrics {
+    Unsynced(String),
+    /// Lines in time order.
+    Synced(Vec<LyricLine>),
+}
+
+impl Lyrics {
+    /// Parses LRC, falling back to plain text when there are no timestamps.
+    ///
+    /// Lines with several timestamps (`[00:12.00][01:30.00]chorus`) are repeated at each of them
+    /// and an `[offset:+/-ms]` tag shifts every line.
+    pub fn parse(contents: &str) -> Option<Lyrics> {
+        let mut offset: i64 = 0;
+        let mut lines = Vec::new();
+
+        for raw in contents.lines() {
+            let mut rest = raw.trim();
+            let mut times = Vec::new();
+
+            while let Some(tag) = rest.strip_prefix('[') {
+                let Some(end) = tag.find(']') else {
+                    break;
+                };
+
+                let (body, remainder) = (&tag[..end], &tag[end + 1..]);
+                match parse_timestamp(body) {
+                    Some(time) => times.push(time),
+                    None => {
+                        if let Some(value) = body.strip_prefix("offset:") {
+                            offset = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+                rest = remainder;
+            }
+
+            for time in times {
+                lines.push((time, rest.trim().to_string()));
+            }
+        }
+
+        if lines.is_empty() {
+            let text = contents.trim();
+            return (!text.is_empty()).then(|| Lyrics::Unsynced(text.to_string()));
+        }
+
+        // A positive offset makes the lyrics appear sooner.
+        let mut lines: Vec<LyricLine> = lines
+            .into_iter()
+            .map(|(time, text)| LyricLine {
+                time: Duration::from_millis((time as i64 - offset).max(0) as u64),
+                text,
+            })
+            .collect();
+        lines.sort_by_key(|line| line.time);
+
+        Some(Lyrics::Synced(lines))
+    }
+
+    /// Index of the line being sung at `position`, `None` before the first one or for unsynced
+    /// lyrics.
+    pub fn line_at(&self, position: Duration) -> Option<usize> {
+        match self {
+            Lyrics::Unsynced(_) => None,
+            Lyrics::Synced(lines) => lines
+                .partition_point(|line| line.time <= position)
+                .checked_sub(1),
+        }
+    }
+
+    pub fn lines(&self) -> Vec<&str> {
+        match self {
+            Lyrics::Unsynced(text) => text.lines().collect(),
+            Lyrics::Synced(lines) => lines.iter().map(|line| line.text.as_str()).collect(),
+        }
+    }
+
+    /// The words alone, for the search index.
+    pub fn text(&self) -> String {
+        self.lines().join("\n")
+    }
+}
+
+/// `mm:ss`, `mm:ss.xx` or `mm:ss.xxx`, in milliseconds.
+fn parse_timestamp(body: &str) -> Option<u64> {
+    let (minutes, seconds) = body.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().parse().ok()?;
+
+    (seconds >= 0.0).then(|| minutes * 60_000 + (seconds * 1000.0).round() as u64)
+}
+
+/// Reads the first millisecond-timed SYLT frame from an ID3v2.3 or v2.4 tag at the start of the
+/// file at `path`.
+///
+/// Symphonia skips SYLT frames, so the tag is walked here directly. Only the tag is read, not
+/// the audio after it. Tags using unsynchronisation are not supported.
+pub fn read_sylt(path: &Path) -> Option<Vec<LyricLine>> {
+    let mut file = File::open(path).ok()?;
+
+    let mut header = [0; 10];
+    file.read_exact(&mut header).ok()?;
+    if &header[..3] != b"ID3" || header[5] & 0x80 != 0 {
+        return None;
+    }
+
+    let mut data = header.to_vec();
+    file.take(syncsafe(&header[6..10]) as u64)
+        .read_to_end(&mut data)
+        .ok()?;
+
+    let version = header[3];
+    let tag_end = data.len();
+    let mut position = 10;
+
+    // Skip the extended header.
+    if header[5] & 0x40 != 0 {
+        let size = data.get(10..14)?;
+        position += match version {
+            4 => syncsafe(size),
+            _ => u32::from_be_bytes(size.try_into().ok()?) as usize + 4,
+        };
+    }
+
+    while position + 10 <= tag_end {
+        let id = &data[position..position + 4];
+        if id[0] == 0 {
+            break;
+        }
+
+        let size_bytes = &data[position + 4..position + 8];
+        let size = match version {
+            4 => syncsafe(size_bytes),
+            _ => u32::from_be_bytes(size_bytes.try_into().ok()?) as usize,
+        };
+        let body = data.get(position + 10..position + 10 + size)?;
+
+        if id == b"SYLT"
+            && let Some(lines) = parse_sylt(body)
+        {
+            return Some(lines);
+        }
+
+        position += 10 + size;
+    }
+
+    None
+}
+
+fn parse_sylt(body: &[u8]) -> Option<Vec<LyricLine>> {
+    let encoding = *body.first()?;
+    if *body.get(4)? != SYLT_MILLISECONDS {
+        return None;
+    }
+
+    // Skip the language, timestamp format and content type, then the descriptor.
+    let (_, mut rest) = split_text(body.get(6..)?, encoding)?;
+    let mut lines = Vec::new();
+
+    while !rest.is_empty() {
+        let (text, remainder) = split_text(rest, encoding)?;
+        let time = u32::from_be_bytes(remainder.get(..4)?.try_into().ok()?);
+
+        lines.push(LyricLine {
+            time: Duration::from_millis(time.into()),
+            // Entries often carry their own line breaks.
+            text: text.trim_matches(['\n', '\r']).to_string(),
+        });
+        rest = &remainder[4..];
+    }
+
+    lines.sort_by_key(|line| line.time);
+    (!lines.is_empty()).then_some(lines)
+}
+
+/// Splits a terminated string in the given ID3 text encoding off the front of `bytes`.
+fn split_text(bytes: &[u8], encoding: u8) -> Option<(String, &[u8])> {
+    match encoding {
+        // UTF-16 with a BOM, or big endian without one, terminated by two zero bytes.
+        1 | 2 => {
+            let end = bytes
+                .chunks_exact(2)
+                .position(|pair| pair == [0, 0])
+                .map(|index| index * 2)?;
+            let raw = &bytes[..end];
+
+            let (big_endian, raw) = match raw {
+                [0xFF, 0xFE, rest @ ..] => (false, rest),
+                [0xFE, 0xFF, rest @ ..] => (true, rest),
+                _ => (encoding == 2, raw),
+            };
+            let units: Vec<u16> = raw
+                .chunks_exact(2)
+                .map(|pair| match big_endian {
+                    true => u16::from_be_bytes([pair[0], pair[1]]),
+                    false => u16::from_le_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+
+            Some((String::from_utf16_lossy(&units), &bytes[end + 2..]))
+        }
+        // ISO-8859-1 maps straight onto the first 256 code points.
+        0 => {
+            let end = bytes.iter().position(|byte| *byte == 0)?;
+            let text = bytes[..end].iter().map(|byte| char::from(*byte)).collect();
+            Some((text, &bytes[end + 1..]))
+        }
+        _ => {
+            let end = bytes.iter().position(|byte| *byte == 0)?;
+            let text = String::from_utf8_lossy(&bytes[..end]).into_owned();
+            Some((text, &bytes[end + 1..]))
+        }
+    }
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, byte| (size << 7) | usize::from(byte & 0x7F))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An ID3v2.3 tag holding one SYLT frame, followed by stand-in audio.
+    fn tagged_file() -> Vec<u8> {
+        let mut frame = vec![0, b'e', b'n', b'g', SYLT_MILLISECONDS, 1, 0];
+        for (text, time) in [("second", 2000u32), ("first", 1000)] {
+            frame.extend(text.as_bytes());
+            frame.push(0);
+            frame.extend(time.to_be_bytes());
+        }
+
+        let mut tag = b"SYLT".to_vec();
+        tag.extend((frame.len() as u32).to_be_bytes());
+        tag.extend([0, 0]);
+        tag.extend(frame);
+
+        let size = tag.len() as u32;
+        let mut file = b"ID3\x03\x00\x00".to_vec();
+        file.extend([3, 2, 1, 0].map(|shift| ((size >> (7 * shift)) & 0x7f) as u8));
+        file.extend(tag);
+        file.extend([0xFF; 4096]);
+        file
+    }
+
+    #[test]
+    fn reads_sylt_from_the_tag() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("track.mp3");
+        std::fs::write(&path, tagged_file()).unwrap();
+
+        let lines = read_sylt(&path).unwrap();
+        assert_eq!(
+            lines,
+            [
+                LyricLine {
+                    time: Duration::from_millis(1000),
+                    text: "first".to_string(),
+                },
+                LyricLine {
+                    time: Duration::from_millis(2000),
+                    text: "second".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn untagged_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("track.flac");
+        std::fs::write(&path, b"fLaC").unwrap();
+
+        assert_eq!(read_sylt(&path), None);
+    }
+}
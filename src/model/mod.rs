@@ -1,10 +1,24 @@
 use serde::{Deserialize, Serialize};
 use sqlx::{Row, prelude::FromRow};
 
-use crate::model::{artist::Artist, release::Release, track::Track};
+use crate::model::{
+    artist::Artist, book::Book, decade::Decade, genre::Genre, playlist::Playlist, release::Release,
+    track::Track,
+};
 
 pub mod artist;
+pub mod book;
+pub mod chapter;
+pub mod collation;
+pub mod container;
+pub mod cue;
+pub mod decade;
+pub mod genre;
+pub mod listen;
+pub mod lyrics;
+pub mod playlist;
 pub mod release;
+pub mod stats;
 pub mod track;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +33,10 @@ pub enum CatalogMetadata {
     Track(Track),
     Artist(Artist),
     Release(Release),
+    Playlist(Playlist),
+    Genre(Genre),
+    Decade(Decade),
+    Book(Book),
 }
 
 impl CatalogMetadata {
@@ -27,6 +45,10 @@ impl CatalogMetadata {
             CatalogMetadata::Artist(artist) => &artist.name,
             CatalogMetadata::Release(release) => &release.title,
             CatalogMetadata::Track(track) => &track.title,
+            CatalogMetadata::Playlist(playlist) => &playlist.name,
+            CatalogMetadata::Genre(genre) => &genre.name,
+            CatalogMetadata::Decade(decade) => &decade.name,
+            CatalogMetadata::Book(book) => &book.title,
         }
     }
 }
@@ -51,6 +73,46 @@ impl From<CatalogItem<Release>> for CatalogItem<CatalogMetadata> {
     }
 }
 
+impl From<CatalogItem<Playlist>> for CatalogItem<CatalogMetadata> {
+    fn from(item: CatalogItem<Playlist>) -> Self {
+        CatalogItem {
+            id: item.id,
+            favorite: item.favorite,
+            metadata: CatalogMetadata::Playlist(item.metadata),
+        }
+    }
+}
+
+impl From<CatalogItem<Genre>> for CatalogItem<CatalogMetadata> {
+    fn from(item: CatalogItem<Genre>) -> Self {
+        CatalogItem {
+            id: item.id,
+            favorite: item.favorite,
+            metadata: CatalogMetadata::Genre(item.metadata),
+        }
+    }
+}
+
+impl From<CatalogItem<Decade>> for CatalogItem<CatalogMetadata> {
+    fn from(item: CatalogItem<Decade>) -> Self {
+        CatalogItem {
+            id: item.id,
+            favorite: item.favorite,
+            metadata: CatalogMetadata::Decade(item.metadata),
+        }
+    }
+}
+
+impl From<CatalogItem<Book>> for CatalogItem<CatalogMetadata> {
+    fn from(item: CatalogItem<Book>) -> Self {
+        CatalogItem {
+            id: item.id,
+            favorite: item.favorite,
+            metadata: CatalogMetadata::Book(item.metadata),
+        }
+    }
+}
+
 impl From<CatalogItem<Track>> for CatalogItem<CatalogMetadata> {
     fn from(item: CatalogItem<Track>) -> Self {
         CatalogItem {
@@ -0,0 +1,507 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::model::track::Track;
+
+/// File extensions read as playlists during sync.
+pub const PLAYLIST_EXTENSIONS: [&str; 4] = ["m3u", "m3u8", "pls", "xspf"];
+
+#[derive(Debug, Error)]
+pub enum PlaylistError {
+    #[error("unable to read playlist: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("unsupported playlist format")]
+    UnsupportedFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Playlist {
+    pub name: String,
+    /// The file the playlist was imported from.
+    pub path: Option<PathBuf>,
+    /// Entries which didn't match any track in the catalog when last imported.
+    #[serde(default)]
+    pub unresolved: Vec<String>,
+    /// When the file had last been modified as of the import, in seconds since the epoch.
+    #[serde(default)]
+    pub modified: Option<i64>,
+    /// Changed on the device since it was imported, the file no longer replaces it.
+    #[serde(default)]
+    pub edited: bool,
+}
+
+/// One line of a playlist file, whatever it says about the track it refers to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistEntry {
+    /// A path, relative to the playlist or absolute, or a `file://` URL.
+    pub location: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+impl PlaylistEntry {
+    /// Where the entry points on this machine, with `.` and `..` resolved against `base`.
+    ///
+    /// Remote URLs have no local path.
+    pub fn local_path(&self, base: &Path) -> Option<PathBuf> {
+        let location = match self.location.split_once("://") {
+            Some(("file", rest)) => percent_decode(rest.strip_prefix("localhost").unwrap_or(rest)),
+            Some(_) => return None,
+            None => self.location.clone(),
+        };
+
+        // Playlists written on Windows use backslashes, even for relative entries.
+        let location = match location.contains('\\') && !location.contains('/') {
+            true => location.replace('\\', "/"),
+            false => location,
+        };
+
+        Some(normalize(&base.join(location)))
+    }
+
+    /// Description for reporting an entry that couldn't be resolved.
+    pub fn describe(&self) -> String {
+        match (&self.artist, &self.title) {
+            (Some(artist), Some(title)) => format!("{} - {} ({})", artist, title, self.location),
+            (None, Some(title)) => format!("{} ({})", title, self.location),
+            _ => self.location.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaylistFormat {
+    M3u,
+    Pls,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    pub fn from_path(path: &Path) -> Option<PlaylistFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3u),
+            "pls" => Some(PlaylistFormat::Pls),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            _ => None,
+        }
+    }
+}
+
+impl Playlist {
+    /// Reads a playlist file, named after its own title where the format has one and after the
+    /// file otherwise.
+    pub fn read(path: &Path) -> Result<(Playlist, Vec<PlaylistEntry>), PlaylistError> {
+        let format = PlaylistFormat::from_path(path).ok_or(PlaylistError::UnsupportedFormat)?;
+        let contents = decode(&std::fs::read(path)?);
+
+        let (name, entries) = match format {
+            PlaylistFormat::M3u => parse_m3u(&contents),
+            PlaylistFormat::Pls => (None, parse_pls(&contents)),
+            PlaylistFormat::Xspf => parse_xspf(&contents),
+        };
+
+        let name = name.unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+        Ok((
+            Playlist {
+                name,
+                path: Some(path.to_path_buf()),
+                unresolved: Vec::new(),
+                modified: Playlist::modified(path),
+                edited: false,
+            },
+            entries,
+        ))
+    }
+
+    /// When the playlist file at `path` was last modified, in seconds since the epoch.
+    pub fn modified(path: &Path) -> Option<i64> {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+        Some(since_epoch.as_secs() as i64)
+    }
+
+    /// Renders the playlist as extended M3U with absolute paths.
+    pub fn to_m3u8(&self, tracks: &[Track]) -> String {
+        let mut m3u = format!("#EXTM3U\n#PLAYLIST:{}\n", self.name);
+        for track in tracks {
+            let title = match &track.artist {
+                Some(artist) => format!("{} - {}", artist, track.title),
+                None => track.title.clone(),
+            };
+            m3u.push_str(&format!(
+                "#EXTINF:{},{}\n{}\n",
+                track.duration.as_secs(),
+                title,
+                track.path.display()
+            ));
+        }
+        m3u
+    }
+}
+
+/// UTF-8 with or without a byte order mark, anything else is taken as Latin-1 which is what
+/// plain `.m3u` and `.cue` files usually are.
+pub(crate) fn decode(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|b| *b as char).collect(),
+    }
+}
+
+/// Plain and extended M3U, `#EXTINF:<seconds>,<artist> - <title>` describes the next entry.
+fn parse_m3u(contents: &str) -> (Option<String>, Vec<PlaylistEntry>) {
+    let mut name = None;
+    let mut entries = Vec::new();
+    let mut info = PlaylistEntry::default();
+
+    for line in contents.lines().map(str::trim) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            let (seconds, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            // Attributes such as `tvg-id="..."` can follow the duration.
+            let seconds = seconds.split_whitespace().next().unwrap_or_default();
+
+            info.duration = seconds
+                .parse::<i64>()
+                .ok()
+                .filter(|s| *s > 0)
+                .map(|s| Duration::from_secs(s as u64));
+            (info.artist, info.title) = split_title(title);
+        } else if let Some(title) = line.strip_prefix("#PLAYLIST:") {
+            name = Some(title.trim().to_string()).filter(|n| !n.is_empty());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entries.push(PlaylistEntry {
+                location: line.to_string(),
+                ..std::mem::take(&mut info)
+            });
+        }
+    }
+
+    (name, entries)
+}
+
+/// PLS, numbered `FileN`, `TitleN` and `LengthN` keys under `[playlist]`.
+fn parse_pls(contents: &str) -> Vec<PlaylistEntry> {
+    let mut entries: Vec<(u32, PlaylistEntry)> = Vec::new();
+
+    for line in contents.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        let key = key.trim().to_ascii_lowercase();
+        let field = ["file", "title", "length"]
+            .into_iter()
+            .find_map(|field| Some((field, key.strip_prefix(field)?.parse::<u32>().ok()?)));
+        let Some((field, number)) = field else {
+            continue;
+        };
+
+        let position = match entries.iter().position(|(n, _)| *n == number) {
+            Some(position) => position,
+            None => {
+                entries.push((number, PlaylistEntry::default()));
+                entries.len() - 1
+            }
+        };
+
+        let entry = &mut entries[position].1;
+        let value = value.trim().to_string();
+        match field {
+            "file" => entry.location = value,
+            "title" => (entry.artist, entry.title) = split_title(&value),
+            _ => {
+                entry.duration = value
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .map(|s| Duration::from_secs(s as u64))
+            }
+        }
+    }
+
+    entries.sort_by_key(|(number, _)| *number);
+    entries
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .filter(|entry| !entry.location.is_empty())
+        .collect()
+}
+
+/// XSPF, only the elements needed to find the track are read.
+fn parse_xspf(contents: &str) -> (Option<String>, Vec<PlaylistEntry>) {
+    let (head, track_list) = contents.split_once("<trackList").unwrap_or((contents, ""));
+
+    let name = element(head, "title").filter(|n| !n.is_empty());
+    let entries = blocks(track_list, "track")
+        .filter_map(|track| {
+            Some(PlaylistEntry {
+                location: element(track, "location")?,
+                title: element(track, "title"),
+                artist: element(track, "creator"),
+                duration: element(track, "duration")
+                    .and_then(|ms| ms.parse::<u64>().ok())
+                    .map(Duration::from_millis),
+            })
+        })
+        .collect();
+
+    (name, entries)
+}
+
+/// Splits an "Artist - Title" description, which is all M3U and PLS have to go on.
+fn split_title(value: &str) -> (Option<String>, Option<String>) {
+    match value.split_once(" - ") {
+        Some((artist, title)) => (
+            Some(artist.trim().to_string()),
+            Some(title.trim().to_string()),
+        ),
+        None => (
+            None,
+            Some(value.trim().to_string()).filter(|t| !t.is_empty()),
+        ),
+    }
+}
+
+/// The contents of every `<name>` element in `xml`, in order.
+fn blocks<'a>(xml: &'a str, name: &str) -> impl Iterator<Item = &'a str> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let mut rest = xml;
+
+    std::iter::from_fn(move || {
+        let start = rest.find(&open)? + open.len();
+        let end = start + rest[start..].find(&close)?;
+        let body = &rest[start..end];
+        rest = &rest[end + close.len()..];
+        Some(body)
+    })
+}
+
+/// The unescaped text of the first `<name>` element in `xml`.
+fn element(xml: &str, name: &str) -> Option<String> {
+    let body = blocks(xml, name).next()?;
+    let body = body
+        .trim()
+        .strip_prefix("<![CDATA[")
+        .and_then(|b| b.strip_suffix("]]>"))
+        .map(str::to_string)
+        .unwrap_or_else(|| unescape(body.trim()));
+
+    Some(body)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Decodes `%XX` escapes in a URL path.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Resolves `.` and `..` without touching the filesystem, playlists often point through
+/// directories that don't exist on this machine.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(location: &str) -> PlaylistEntry {
+        PlaylistEntry {
+            location: location.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn extended_m3u_describes_the_entry_after_it() {
+        let (name, entries) = parse_m3u(
+            "#EXTM3U\n\
+             #PLAYLIST: Road Trip \n\
+             #EXTINF:245 tvg-id=\"x\",Massive Attack - Teardrop\n\
+             music/teardrop.flac\n\
+             \n\
+             # a comment\n\
+             #EXTINF:-1,Live Stream\n\
+             http://radio.example/stream\n\
+             plain.mp3\n",
+        );
+
+        assert_eq!(name.as_deref(), Some("Road Trip"));
+        assert_eq!(
+            entries,
+            [
+                PlaylistEntry {
+                    location: "music/teardrop.flac".to_string(),
+                    title: Some("Teardrop".to_string()),
+                    artist: Some("Massive Attack".to_string()),
+                    duration: Some(Duration::from_secs(245)),
+                },
+                PlaylistEntry {
+                    title: Some("Live Stream".to_string()),
+                    ..entry("http://radio.example/stream")
+                },
+                entry("plain.mp3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls_entries_are_gathered_by_number() {
+        let entries = parse_pls(
+            "[playlist]\n\
+             NumberOfEntries=3\n\
+             File2=b.flac\n\
+             Title1=A - One\n\
+             File1=a.flac\n\
+             Length1=61\n\
+             Title3=No file\n\
+             Version=2\n",
+        );
+
+        assert_eq!(
+            entries,
+            [
+                PlaylistEntry {
+                    location: "a.flac".to_string(),
+                    title: Some("One".to_string()),
+                    artist: Some("A".to_string()),
+                    duration: Some(Duration::from_secs(61)),
+                },
+                entry("b.flac"),
+            ]
+        );
+    }
+
+    #[test]
+    fn xspf_reads_the_title_and_each_track() {
+        let (name, entries) = parse_xspf(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <playlist version="1" xmlns="http://xspf.org/ns/0/">
+              <title>Mix &amp; Match</title>
+              <trackList>
+                <track>
+                  <location>file:///music/Caf%C3%A9.flac</location>
+                  <title><![CDATA[Café <Live>]]></title>
+                  <creator>Björk</creator>
+                  <duration>61500</duration>
+                </track>
+                <track><title>Missing</title></track>
+                <track><location>b.flac</location></track>
+              </trackList>
+            </playlist>"#,
+        );
+
+        assert_eq!(name.as_deref(), Some("Mix & Match"));
+        assert_eq!(
+            entries,
+            [
+                PlaylistEntry {
+                    location: "file:///music/Caf%C3%A9.flac".to_string(),
+                    title: Some("Café <Live>".to_string()),
+                    artist: Some("Björk".to_string()),
+                    duration: Some(Duration::from_millis(61_500)),
+                },
+                entry("b.flac"),
+            ]
+        );
+    }
+
+    #[test]
+    fn locations_resolve_against_the_playlist_directory() {
+        let base = Path::new("/music/playlists");
+
+        assert_eq!(
+            entry("../Album/01.flac").local_path(base),
+            Some(PathBuf::from("/music/Album/01.flac"))
+        );
+        assert_eq!(
+            entry("..\\Album\\01.flac").local_path(base),
+            Some(PathBuf::from("/music/Album/01.flac"))
+        );
+        assert_eq!(
+            entry("/abs/./a.flac").local_path(base),
+            Some(PathBuf::from("/abs/a.flac"))
+        );
+        assert_eq!(
+            entry("file://localhost/music/Caf%C3%A9%20Del%20Mar.flac").local_path(base),
+            Some(PathBuf::from("/music/Café Del Mar.flac"))
+        );
+        assert_eq!(entry("https://example.com/a.mp3").local_path(base), None);
+    }
+
+    #[test]
+    fn files_are_named_after_their_title_or_file_and_decoded() {
+        let dir = tempfile::tempdir().expect("tempdir");
+
+        let latin1 = dir.path().join("Caf\u{e9}.m3u");
+        std::fs::write(&latin1, b"#EXTINF:10,Caf\xe9\nA.mp3\n").expect("write");
+        let (playlist, entries) = Playlist::read(&latin1).expect("read");
+        assert_eq!(playlist.name, "Café");
+        assert_eq!(entries[0].title.as_deref(), Some("Café"));
+        assert!(playlist.modified.is_some());
+
+        let titled = dir.path().join("list.M3U8");
+        std::fs::write(&titled, "\u{feff}#PLAYLIST:Titled\nA.mp3\n").expect("write");
+        assert_eq!(Playlist::read(&titled).expect("read").0.name, "Titled");
+
+        let other = dir.path().join("list.txt");
+        std::fs::write(&other, "A.mp3").expect("write");
+        assert!(matches!(
+            Playlist::read(&other),
+            Err(PlaylistError::UnsupportedFormat)
+        ));
+    }
+}
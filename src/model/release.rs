@@ -1,11 +1,29 @@
+use std::path::{Component, Path, PathBuf};
+
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+/// Who compilations are filed under.
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
 pub struct Release {
     pub title: String,
     pub tracks: Vec<String>,
+    /// Who the release is filed under, [`VARIOUS_ARTISTS`] for compilations.
     pub artist: Option<String>,
+    /// Everyone performing on the release, so it's listed under each of them.
+    #[serde(default)]
+    pub artists: Vec<String>,
+    #[serde(default)]
+    pub compilation: bool,
+    /// Where the release's files are, which tells apart releases sharing a title.
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+    #[serde(default)]
+    pub genre: Option<String>,
+    #[serde(default)]
+    pub year: Option<i32>,
 }
 
 impl Release {
@@ -14,4 +32,144 @@ impl Release {
             self.tracks.push(title.to_string())
         }
     }
+
+    pub fn add_artist(&mut self, name: &str) {
+        if !self.artists.iter().any(|a| a == name) {
+            self.artists.push(name.to_string())
+        }
+    }
+
+    /// Takes the genre and year from a track when the release has none yet.
+    pub fn fill_in(&mut self, genre: Option<&String>, year: Option<i32>) {
+        if self.genre.is_none() {
+            self.genre = genre.cloned();
+        }
+        if self.year.is_none() {
+            self.year = year;
+        }
+    }
+
+    /// Files the release under [`VARIOUS_ARTISTS`].
+    pub fn mark_compilation(&mut self) {
+        self.compilation = true;
+        self.artist = Some(VARIOUS_ARTISTS.to_string());
+    }
+
+    /// Whether a release whose tracks are led by `primaries`, one per track, is a compilation
+    /// going by them alone: no artist leads as many as half of them. A guest leading a track or
+    /// two on someone's album doesn't make it one.
+    pub fn is_various(primaries: &[String]) -> bool {
+        Self::lead(primaries).is_some_and(|(_, count)| count * 2 < primaries.len())
+    }
+
+    /// The artist leading the most of `primaries`, the earliest of them on a tie.
+    pub fn lead_artist(primaries: &[String]) -> Option<&String> {
+        Self::lead(primaries).map(|(artist, _)| artist)
+    }
+
+    fn lead(primaries: &[String]) -> Option<(&String, usize)> {
+        primaries.iter().fold(None, |lead, artist| {
+            let count = primaries.iter().filter(|a| *a == artist).count();
+            match lead {
+                Some((_, most)) if most >= count => lead,
+                _ => Some((artist, count)),
+            }
+        })
+    }
+
+    /// The directory a release in the file at `path` is kept in, a disc's own folder such as
+    /// `CD2` counts as its parent's.
+    pub fn directory_of(path: &Path) -> Option<PathBuf> {
+        let parent = path.parent()?;
+        let is_disc = match parent.components().next_back() {
+            Some(Component::Normal(name)) => {
+                let name = name.to_string_lossy().to_ascii_lowercase();
+                let rest = ["cd", "disc", "disk"]
+                    .into_iter()
+                    .find_map(|prefix| name.strip_prefix(prefix))
+                    .map(|rest| rest.trim_start_matches([' ', '_', '-']));
+
+                rest.is_some_and(|rest| {
+                    !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit())
+                })
+            }
+            _ => false,
+        };
+
+        match is_disc {
+            true => parent.parent().map(Path::to_path_buf),
+            false => Some(parent.to_path_buf()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn the_first_genre_and_year_seen_are_kept() {
+        let mut release = Release::default();
+
+        release.fill_in(None, Some(1997));
+        release.fill_in(Some(&"Trip Hop".to_string()), Some(1998));
+        release.fill_in(Some(&"Electronic".to_string()), None);
+
+        assert_eq!(release.genre.as_deref(), Some("Trip Hop"));
+        assert_eq!(release.year, Some(1997));
+    }
+
+    #[test]
+    fn disc_folders_belong_to_the_release_above_them() {
+        let directory = |path: &str| Release::directory_of(Path::new(path));
+
+        assert_eq!(
+            directory("/music/Album/01.flac"),
+            Some(PathBuf::from("/music/Album"))
+        );
+        for disc in ["CD2", "cd 2", "Disc_1", "disk-10"] {
+            assert_eq!(
+                directory(&format!("/music/Album/{}/01.flac", disc)),
+                Some(PathBuf::from("/music/Album")),
+                "{}",
+                disc
+            );
+        }
+        for other in ["CD", "Discography", "cd2 bonus"] {
+            assert_eq!(
+                directory(&format!("/music/{}/01.flac", other)),
+                Some(PathBuf::from(format!("/music/{}", other)))
+            );
+        }
+        assert_eq!(directory("01.flac"), Some(PathBuf::new()));
+    }
+
+    #[test]
+    fn a_guest_led_track_does_not_make_a_compilation() {
+        let primaries = names(&["Guest", "A", "A", "A", "A", "A", "A", "A", "A", "A"]);
+
+        assert!(!Release::is_various(&primaries));
+        assert_eq!(Release::lead_artist(&primaries).unwrap(), "A");
+    }
+
+    #[test]
+    fn tracks_led_by_many_artists_make_a_compilation() {
+        assert!(Release::is_various(&names(&["A", "B", "C"])));
+        assert!(Release::is_various(&names(&["A", "A", "B", "C", "D", "E"])));
+        assert!(!Release::is_various(&names(&["A", "A", "B", "C"])));
+        assert!(!Release::is_various(&names(&["A", "B"])));
+        assert!(!Release::is_various(&[]));
+    }
+
+    #[test]
+    fn the_earliest_artist_leads_on_a_tie() {
+        assert_eq!(
+            Release::lead_artist(&names(&["B", "A", "A", "B"])).unwrap(),
+            "B"
+        );
+    }
 }
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Totals over the catalog and play history.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LibraryStats {
+    pub tracks: i64,
+    pub releases: i64,
+    pub artists: i64,
+    #[serde(rename = "total_seconds", serialize_with = "seconds")]
+    pub total_duration: Duration,
+    pub plays: i64,
+    #[serde(rename = "listening_seconds", serialize_with = "seconds")]
+    pub listening_time: Duration,
+    /// Artists with their number of plays, most played first.
+    pub top_artists: Vec<(String, i64)>,
+    /// Listening time per week, newest first.
+    pub weekly: Vec<WeeklyListening>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeeklyListening {
+    /// The Monday starting the week, as `YYYY-MM-DD`.
+    pub week: String,
+    #[serde(rename = "seconds", serialize_with = "seconds")]
+    pub time: Duration,
+}
+
+fn seconds<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+/// "3d 4h", "4h 12m" or "12m", for long spans of time.
+pub fn format_span(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, hours) => format!("{}h {}m", hours, minutes),
+        (days, hours) => format!("{}d {}h", days, hours),
+    }
+}
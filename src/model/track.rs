@@ -1,4 +1,7 @@
-use std::{path::PathBuf, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
@@ -11,7 +14,18 @@ use symphonia::core::{
 };
 use thiserror::Error;
 
+use crate::model::{
+    chapter::{self, Chapter},
+    cue::{CueFile, CueSheet, CueTrack},
+    lyrics::{self, Lyrics},
+};
+
 const DEFAULT_COVER_ART: &[u8] = include_bytes!("../resources/cover_art.png");
+/// Track numbers FLAC cuesheets give the lead-out, for CD-DA and otherwise.
+const FLAC_LEAD_OUT_CDDA: u32 = 170;
+const FLAC_LEAD_OUT: u32 = 255;
+/// Tags marking a release as gapless, ID3 has them as user defined text frames.
+const GAPLESS_TAGS: [&str; 3] = ["GAPLESS", "ITUNESGAPLESS", "ITUNPGAP"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cover {
@@ -19,6 +33,17 @@ pub struct Cover {
     pub mime_type: String,
 }
 
+/// Technical details of the audio stream, shown on request in the player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub codec: String,
+    pub sample_rate: Option<u32>,
+    pub bits_per_sample: Option<u32>,
+    pub channels: Option<usize>,
+    /// Average bitrate over the whole file, in kbit/s.
+    pub bitrate: Option<u32>,
+}
+
 #[derive(Debug, Clone, Error)]
 pub enum TrackError {
     #[error("track is missing a required tag")]
@@ -27,18 +52,465 @@ pub enum TrackError {
     Unknown,
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, FromRow)]
+/// Whether a track is music or spoken word such as an audiobook or podcast, which are kept out of
+/// the music views and browsed as books instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    #[default]
+    Music,
+    SpokenWord,
+}
+
+impl MediaKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaKind::Music => "music",
+            MediaKind::SpokenWord => "spoken_word",
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize, FromRow)]
 pub struct Track {
     pub title: String,
     pub release: Option<String>,
+    /// Who performed the track as credited, from the Artist tag or else the AlbumArtist tag.
     pub artist: Option<String>,
+    /// Each artist credited on the track, the main artist first. Holds the tag values as they
+    /// are until [`Track::split_artists`] breaks up credits such as "A feat. B".
+    #[serde(default)]
+    pub artists: Vec<String>,
+    /// Who the release is credited to, when the file says so separately from the track.
+    #[serde(default)]
+    pub album_artist: Option<String>,
     pub path: PathBuf,
     pub duration: Duration,
+    /// Catalog id of the release, assigned when the track is added to the catalog.
+    #[serde(default)]
+    pub release_id: Option<i64>,
+    #[serde(default)]
+    pub track_number: Option<u32>,
+    #[serde(default)]
+    pub track_total: Option<u32>,
+    #[serde(default)]
+    pub disc_number: Option<u32>,
+    #[serde(default)]
+    pub disc_total: Option<u32>,
+    /// Whether the file is tagged as part of a compilation.
+    #[serde(default)]
+    pub compilation: bool,
+    /// Whether the release is meant to play straight through, such as a live album or a mix,
+    /// from a gapless playback tag or the tracks being cut from one file by a CUE sheet.
+    #[serde(default)]
+    pub gapless: bool,
+    #[serde(default)]
+    pub genre: Option<String>,
+    /// The year the track was released, from the date tags.
+    #[serde(default)]
+    pub year: Option<i32>,
+    /// Where the track starts within the file, when a CUE sheet splits it into several.
+    #[serde(default)]
+    pub start: Option<Duration>,
+    /// Where the track ends within the file, `None` plays on to the end.
+    #[serde(default)]
+    pub end: Option<Duration>,
+    /// Where playback was left off, kept for tracks that resume until they're played through.
+    #[serde(default)]
+    pub resume: Option<Duration>,
+    /// Playback speed chosen for this track, otherwise the one for its kind applies.
+    #[serde(default)]
+    pub speed: Option<f32>,
+    #[serde(default)]
+    pub kind: MediaKind,
+    /// Chapters marked within the file, in order.
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+    /// Catalog id of the book a spoken word track is filed under.
+    #[serde(default)]
+    pub book_id: Option<i64>,
 }
 
 impl Track {
     pub fn from_path(path: PathBuf) -> Result<Self, TrackError> {
-        let file = File::open(&path).map_err(|_| TrackError::Unknown)?;
+        let probe = FileProbe::read(&path)?;
+        let artist = probe
+            .credit()
+            .or(probe.album_artist.clone())
+            .ok_or(TrackError::TagMissing)?;
+        let artists = match probe.artists.is_empty() {
+            true => vec![artist.clone()],
+            false => probe.artists,
+        };
+        // A file whose chapters can't be read still plays, just without them.
+        let chapters = chapter::read(&path).unwrap_or_default();
+
+        Ok(Track {
+            title: probe.title.ok_or(TrackError::TagMissing)?,
+            artist: Some(artist),
+            artists,
+            album_artist: probe.album_artist,
+            // Spoken word can do without an album, it's filed under its title instead.
+            release: probe.album,
+            duration: probe.duration.ok_or(TrackError::Unknown)?,
+            path,
+            release_id: None,
+            track_number: probe.track_number,
+            track_total: probe.track_total,
+            disc_number: probe.disc_number,
+            disc_total: probe.disc_total,
+            compilation: probe.compilation,
+            gapless: probe.gapless,
+            genre: probe.genre,
+            year: probe.year,
+            start: None,
+            end: None,
+            resume: None,
+            speed: None,
+            kind: MediaKind::Music,
+            chapters,
+            book_id: None,
+        })
+    }
+
+    /// Reads every track in a file, which is more than one for an album ripped to a single FLAC
+    /// with a cuesheet embedded in it.
+    pub fn from_path_with_cues(path: PathBuf) -> Result<Vec<Self>, TrackError> {
+        let probe = FileProbe::read(&path)?;
+
+        if let Some(sheet) = probe.cuesheet.as_deref().and_then(CueSheet::parse)
+            && let Some(file) = sheet.files.first()
+        {
+            return Self::split(&sheet, file, path, probe);
+        }
+
+        if !probe.cues.is_empty() {
+            return Self::split_cues(path, probe);
+        }
+
+        Ok(vec![Self::from_path(path)?])
+    }
+
+    /// The track in the file at `path` which starts at `start`, from a CUE sheet next to the file
+    /// or one embedded in it. `None` is the whole file.
+    pub fn from_path_at(path: PathBuf, start: Option<Duration>) -> Result<Self, TrackError> {
+        let Some(start) = start else {
+            return Self::from_path(path);
+        };
+
+        let sheets = path
+            .parent()
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|sheet_path| {
+                sheet_path
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("cue"))
+            });
+
+        for sheet_path in sheets {
+            let Ok(Some(sheet)) = CueSheet::read(&sheet_path) else {
+                continue;
+            };
+
+            for file in &sheet.files {
+                if file.locate(&sheet_path).as_ref() != Some(&path) {
+                    continue;
+                }
+
+                let found = Self::from_cue(&sheet, file, path.clone())?
+                    .into_iter()
+                    .find(|track| track.start == Some(start));
+                if let Some(found) = found {
+                    return Ok(found);
+                }
+            }
+        }
+
+        Self::from_path_with_cues(path)?
+            .into_iter()
+            .find(|track| track.start == Some(start))
+            .ok_or(TrackError::Unknown)
+    }
+
+    /// The tracks `file` in a CUE sheet describes, with `path` being where that file is.
+    pub fn from_cue(
+        sheet: &CueSheet,
+        file: &CueFile,
+        path: PathBuf,
+    ) -> Result<Vec<Self>, TrackError> {
+        let probe = FileProbe::read(&path)?;
+        Self::split(sheet, file, path, probe)
+    }
+
+    /// Virtual tracks named from the sheet, falling back on the file's own tags.
+    fn split(
+        sheet: &CueSheet,
+        file: &CueFile,
+        path: PathBuf,
+        probe: FileProbe,
+    ) -> Result<Vec<Self>, TrackError> {
+        let total = probe.duration.ok_or(TrackError::Unknown)?;
+        let release = sheet
+            .title
+            .clone()
+            .or(probe.album.clone())
+            .ok_or(TrackError::TagMissing)?;
+        let album_artist = sheet.performer.clone().or(probe.album_artist.clone());
+        let artist = probe.credit().or_else(|| album_artist.clone());
+
+        file.tracks
+            .iter()
+            .zip(file.bounds())
+            .map(|(track, (start, end))| {
+                let artist = track
+                    .performer
+                    .clone()
+                    .or_else(|| artist.clone())
+                    .ok_or(TrackError::TagMissing)?;
+
+                Ok(Track {
+                    title: track
+                        .title
+                        .clone()
+                        .unwrap_or_else(|| format!("Track {}", track.number)),
+                    artists: vec![artist.clone()],
+                    artist: Some(artist),
+                    album_artist: album_artist.clone(),
+                    release: Some(release.clone()),
+                    duration: end.unwrap_or(total).saturating_sub(start),
+                    path: path.clone(),
+                    release_id: None,
+                    track_number: Some(track.number),
+                    track_total: Some(file.tracks.len() as u32),
+                    disc_number: probe.disc_number,
+                    disc_total: probe.disc_total,
+                    compilation: probe.compilation,
+                    // Played back to back they're the file played straight through.
+                    gapless: true,
+                    genre: probe.genre.clone(),
+                    year: probe.year,
+                    start: Some(start),
+                    end,
+                    resume: None,
+                    speed: None,
+                    kind: MediaKind::Music,
+                    chapters: Vec::new(),
+                    book_id: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Virtual tracks from a native FLAC cuesheet block, which has offsets but no titles.
+    fn split_cues(path: PathBuf, probe: FileProbe) -> Result<Vec<Self>, TrackError> {
+        let sheet = CueSheet {
+            title: None,
+            performer: None,
+            files: vec![CueFile {
+                name: String::new(),
+                tracks: probe
+                    .cues
+                    .iter()
+                    .map(|(number, start)| CueTrack {
+                        number: *number,
+                        title: None,
+                        performer: None,
+                        start: *start,
+                    })
+                    .collect(),
+            }],
+        };
+
+        // The lead-out marks where the last track ends.
+        let mut tracks = Self::split(&sheet, &sheet.files[0], path, probe.clone())?;
+        if let (Some(lead_out), Some(last)) = (probe.lead_out, tracks.last_mut()) {
+            last.end = Some(lead_out);
+            last.duration = lead_out.saturating_sub(last.start.unwrap_or_default());
+        }
+
+        Ok(tracks)
+    }
+
+    /// Whether `other` is from the same release, by catalog id once both have one and otherwise
+    /// by title and album artist.
+    pub fn same_release(&self, other: &Track) -> bool {
+        match (self.release_id, other.release_id) {
+            (Some(id), Some(other_id)) => id == other_id,
+            _ => self.release == other.release && self.album_artist == other.album_artist,
+        }
+    }
+
+    /// Breaks each credit in `artists` up at any of `separators`, ignoring case, so that
+    /// "A feat. B" credits A and B.
+    pub fn split_artists(&mut self, separators: &[String]) {
+        let mut artists: Vec<String> = Vec::new();
+
+        for credit in &self.artists {
+            for name in split_credit(credit, separators) {
+                if !artists.contains(&name) {
+                    artists.push(name);
+                }
+            }
+        }
+
+        self.artists = artists;
+    }
+
+    /// Probes the file for its codec and format, the bitrate is averaged from the file size.
+    pub fn stream_info(&self) -> Result<StreamInfo, TrackError> {
+        let probe = symphonia::default::get_probe()
+            .format(
+                &Hint::default(),
+                self.open()?,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|_| TrackError::Unknown)?;
+
+        let params = &probe
+            .format
+            .default_track()
+            .ok_or(TrackError::Unknown)?
+            .codec_params;
+
+        let codec = symphonia::default::get_codecs()
+            .get_codec(params.codec)
+            .map(|descriptor| descriptor.short_name.to_uppercase())
+            .unwrap_or_else(|| "UNKNOWN".to_string());
+
+        let size = std::fs::metadata(&self.path)
+            .map_err(|_| TrackError::Unknown)?
+            .len();
+        // The size is of the whole file, which a CUE sheet may have split into several tracks.
+        let seconds = params
+            .time_base
+            .zip(params.n_frames)
+            .map(|(time_base, n_frames)| time_base.calc_time(n_frames).seconds)
+            .unwrap_or(self.duration.as_secs());
+        let bitrate = (seconds > 0).then(|| (size * 8 / seconds / 1000) as u32);
+
+        Ok(StreamInfo {
+            codec,
+            sample_rate: params.sample_rate,
+            bits_per_sample: params.bits_per_sample,
+            channels: params.channels.map(|channels| channels.count()),
+            bitrate,
+        })
+    }
+
+    /// Synced lyrics from a sidecar `.lrc` file or a SYLT frame, otherwise whatever is in the
+    /// lyrics tag.
+    pub fn lyrics(&self) -> Option<Lyrics> {
+        // Whatever the file holds is for all of it, not one of the tracks a CUE sheet splits
+        // it into.
+        if self.start.is_some() {
+            return None;
+        }
+
+        std::fs::read_to_string(self.path.with_extension("lrc"))
+            .ok()
+            .and_then(|contents| Lyrics::parse(&contents))
+            .or_else(|| lyrics::read_sylt(&self.path).map(Lyrics::Synced))
+            .or_else(|| self.tagged_lyrics())
+    }
+
+    /// Reads the USLT or `LYRICS` tag, which some taggers fill with LRC.
+    fn tagged_lyrics(&self) -> Option<Lyrics> {
+        let mut probe = symphonia::default::get_probe()
+            .format(
+                &Hint::default(),
+                self.open().ok()?,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .ok()?;
+
+        let metadata = probe.format.metadata();
+        let tag = metadata
+            .current()?
+            .tags()
+            .iter()
+            .find(|tag| tag.std_key == Some(StandardTagKey::Lyrics))?;
+
+        Lyrics::parse(&tag.value.to_string())
+    }
+
+    pub fn open(&self) -> Result<MediaSourceStream, TrackError> {
+        let file = File::open(&self.path).map_err(|_| TrackError::Unknown)?;
+        Ok(MediaSourceStream::new(Box::new(file), Default::default()))
+    }
+
+    /// Reads the front cover embedded in the file's tags.
+    pub fn embedded_cover(&self) -> Option<Cover> {
+        let file = File::open(&self.path).ok()?;
+        let media_stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut probe = symphonia::default::get_probe()
+            .format(
+                &Hint::default(),
+                media_stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .ok()?;
+
+        let metadata = probe.format.metadata();
+        let visual = metadata
+            .current()?
+            .visuals()
+            .iter()
+            .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))?;
+
+        Some(Cover {
+            mime_type: visual.media_type.clone(),
+            byte_data: visual.data.to_vec(),
+        })
+    }
+
+    pub fn cover(&self) -> Cover {
+        self.embedded_cover().unwrap_or_else(|| Cover {
+            mime_type: "image/png".to_string(),
+            byte_data: DEFAULT_COVER_ART.to_vec(),
+        })
+    }
+}
+
+/// What the container and its tags say about a file, before it's turned into tracks.
+#[derive(Debug, Clone, Default)]
+struct FileProbe {
+    title: Option<String>,
+    /// One for each Artist tag, files crediting several artists may have more than one.
+    artists: Vec<String>,
+    album_artist: Option<String>,
+    album: Option<String>,
+    track_number: Option<u32>,
+    track_total: Option<u32>,
+    disc_number: Option<u32>,
+    disc_total: Option<u32>,
+    compilation: bool,
+    gapless: bool,
+    genre: Option<String>,
+    year: Option<i32>,
+    duration: Option<Duration>,
+    /// A CUE sheet stored in a `CUESHEET` tag.
+    cuesheet: Option<String>,
+    /// Track numbers and starts from a native FLAC cuesheet block.
+    cues: Vec<(u32, Duration)>,
+    lead_out: Option<Duration>,
+}
+
+impl FileProbe {
+    /// The artists from the Artist tags as a single credit.
+    fn credit(&self) -> Option<String> {
+        (!self.artists.is_empty()).then(|| self.artists.join(" & "))
+    }
+
+    fn read(path: &Path) -> Result<FileProbe, TrackError> {
+        let file = File::open(path).map_err(|_| TrackError::Unknown)?;
         let media_stream = MediaSourceStream::new(Box::new(file), Default::default());
 
         let probe_result = symphonia::default::get_probe()
@@ -51,20 +523,13 @@ impl Track {
             .map_err(|_| TrackError::Unknown)?;
 
         let mut format = probe_result.format;
+        let mut probe = FileProbe::default();
 
-        let mut title = None;
-        let mut artist = None;
-        let mut album = None;
-        let duration = format.default_track().and_then(|track| {
-            let time_base = track.codec_params.time_base?;
-            let n_frames = track.codec_params.n_frames?;
-
-            // Skip if values seem invalid
-            if n_frames == 0 {
-                return None;
-            }
-
-            let time = time_base.calc_time(n_frames);
+        let time_base = format
+            .default_track()
+            .and_then(|track| track.codec_params.time_base);
+        let to_duration = |ts: u64| {
+            let time = time_base?.calc_time(ts);
 
             // Guard against negative or huge values
             if time.frac.is_nan() || time.frac.is_infinite() || time.frac < 0.0 {
@@ -72,65 +537,215 @@ impl Track {
             }
 
             Some(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
-        });
+        };
+
+        probe.duration = format
+            .default_track()
+            .and_then(|track| track.codec_params.n_frames)
+            // Skip if values seem invalid
+            .filter(|n_frames| *n_frames > 0)
+            .and_then(to_duration);
 
-        // Read the files metadata tags.
-        if let Some(metadata) = format.metadata().current() {
+        for cue in format.cues() {
+            // The index points within a track are INDEX 00 then 01, the track starts at 01.
+            let offset = cue
+                .points
+                .get(1)
+                .or(cue.points.first())
+                .map(|point| point.start_offset_ts)
+                .unwrap_or(0);
+            let Some(start) = to_duration(cue.start_ts + offset) else {
+                continue;
+            };
+
+            match cue.index {
+                FLAC_LEAD_OUT_CDDA | FLAC_LEAD_OUT => probe.lead_out = Some(start),
+                number => probe.cues.push((number, start)),
+            }
+        }
+
+        // Read the files metadata tags. An MP3's ID3 tag comes before the stream, so it's the
+        // probe rather than the format that reads it.
+        let container = format.metadata();
+        let mut probed = probe_result.metadata;
+        let probed = probed.get();
+        let revision = container
+            .current()
+            .or_else(|| probed.as_ref().and_then(|metadata| metadata.current()));
+
+        if let Some(metadata) = revision {
             for tag in metadata.tags() {
                 match tag.std_key {
-                    Some(StandardTagKey::TrackTitle) => title = Some(tag.value.to_string()),
-                    Some(StandardTagKey::AlbumArtist) => artist = Some(tag.value.to_string()),
-                    Some(StandardTagKey::Album) => album = Some(tag.value.to_string()),
+                    Some(StandardTagKey::TrackTitle) => probe.title = Some(tag.value.to_string()),
+                    Some(StandardTagKey::Artist) => probe.artists.push(tag.value.to_string()),
+                    Some(StandardTagKey::AlbumArtist) => {
+                        probe.album_artist = Some(tag.value.to_string())
+                    }
+                    Some(StandardTagKey::Album) => probe.album = Some(tag.value.to_string()),
+                    Some(StandardTagKey::TrackNumber) => {
+                        let (number, total) = number_of(&tag.value.to_string());
+                        probe.track_number = number;
+                        probe.track_total = probe.track_total.or(total);
+                    }
+                    Some(StandardTagKey::TrackTotal) => {
+                        probe.track_total = tag.value.to_string().trim().parse().ok()
+                    }
+                    Some(StandardTagKey::DiscNumber) => {
+                        let (number, total) = number_of(&tag.value.to_string());
+                        probe.disc_number = number;
+                        probe.disc_total = probe.disc_total.or(total);
+                    }
+                    Some(StandardTagKey::DiscTotal) => {
+                        probe.disc_total = tag.value.to_string().trim().parse().ok()
+                    }
+                    Some(StandardTagKey::Compilation) => {
+                        probe.compilation = flag_of(&tag.value.to_string())
+                    }
+                    // Only the first genre is kept, a release is browsed under one.
+                    Some(StandardTagKey::Genre) if probe.genre.is_none() => {
+                        let genre = tag.value.to_string();
+                        probe.genre = Some(genre.trim().to_string()).filter(|g| !g.is_empty())
+                    }
+                    Some(
+                        StandardTagKey::Date
+                        | StandardTagKey::OriginalDate
+                        | StandardTagKey::ReleaseDate,
+                    ) if probe.year.is_none() => probe.year = year_of(&tag.value.to_string()),
+                    _ if tag.key.eq_ignore_ascii_case("CUESHEET") => {
+                        probe.cuesheet = Some(tag.value.to_string())
+                    }
+                    _ if GAPLESS_TAGS.iter().any(|key| {
+                        let name = tag.key.strip_prefix("TXXX:").unwrap_or(&tag.key);
+                        name.eq_ignore_ascii_case(key)
+                    }) =>
+                    {
+                        probe.gapless = flag_of(&tag.value.to_string())
+                    }
                     _ => {}
                 }
             }
         }
 
-        Ok(Track {
-            title: title.ok_or_else(|| TrackError::TagMissing)?,
-            artist: Some(artist.ok_or_else(|| TrackError::TagMissing)?),
-            release: Some(album.ok_or_else(|| TrackError::TagMissing)?),
-            duration: duration.ok_or_else(|| TrackError::Unknown)?,
-            path,
-        })
+        Ok(probe)
     }
+}
 
-    pub fn open(&self) -> Result<MediaSourceStream, TrackError> {
-        let file = File::open(&self.path).map_err(|_| TrackError::Unknown)?;
-        Ok(MediaSourceStream::new(Box::new(file), Default::default()))
-    }
+/// A number and the total it's out of, some taggers write "3/12" rather than a separate total.
+fn number_of(value: &str) -> (Option<u32>, Option<u32>) {
+    let mut parts = value.split('/');
+    let number = parts.next().and_then(|n| n.trim().parse().ok());
+    let total = parts.next().and_then(|n| n.trim().parse().ok());
 
-    pub fn cover(&self) -> Cover {
-        if let Ok(file) = File::open(&self.path).map_err(|_| TrackError::Unknown) {
-            let media_stream = MediaSourceStream::new(Box::new(file), Default::default());
+    (number, total)
+}
 
-            let probe_result = symphonia::default::get_probe().format(
-                &Hint::default(),
-                media_stream,
-                &FormatOptions::default(),
-                &MetadataOptions::default(),
-            );
-
-            if let Ok(mut probe) = probe_result {
-                if let Some(metadata) = probe.format.metadata().current() {
-                    for visual in metadata.visuals() {
-                        match visual.usage {
-                            Some(StandardVisualKey::FrontCover) => {
-                                return Cover {
-                                    mime_type: visual.media_type.clone(),
-                                    byte_data: visual.data.to_vec(),
-                                };
-                            }
-                            _ => {}
-                        }
-                    }
-                }
+/// Whether a tag holding a flag is set.
+fn flag_of(value: &str) -> bool {
+    matches!(value.trim(), "1" | "true" | "TRUE" | "True")
+}
+
+/// The year a date tag such as "1997-05-21" or "1997" starts with.
+fn year_of(value: &str) -> Option<i32> {
+    let value = value.trim();
+    value
+        .get(..4)
+        .filter(|year| year.chars().all(|c| c.is_ascii_digit()))?
+        .parse()
+        .ok()
+}
+
+/// The names in a credit, with the brackets often put around "feat." parts taken off.
+fn split_credit(credit: &str, separators: &[String]) -> Vec<String> {
+    // Only ASCII is folded, so offsets into `lower` are offsets into `credit` too.
+    let lower = credit.to_ascii_lowercase();
+    let separators: Vec<String> = separators
+        .iter()
+        .filter(|separator| !separator.is_empty())
+        .map(|separator| separator.to_ascii_lowercase())
+        .collect();
+    let mut names = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < credit.len() {
+        // Separators that are words, such as "feat.", only count at the start of one.
+        let word_start = !credit[..i]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        let found = separators.iter().find(|separator| {
+            lower[i..].starts_with(separator.as_str())
+                && (word_start || !separator.starts_with(|c: char| c.is_alphanumeric()))
+        });
+
+        match found {
+            Some(separator) => {
+                names.push(&credit[start..i]);
+                i += separator.len();
+                start = i;
             }
+            None => i += credit[i..].chars().next().map_or(1, char::len_utf8),
         }
+    }
+    names.push(&credit[start..]);
 
-        Cover {
-            mime_type: "image/png".to_string(),
-            byte_data: DEFAULT_COVER_ART.to_vec(),
-        }
+    names
+        .into_iter()
+        .map(|name| name.trim_matches(|c: char| c.is_whitespace() || "()[],".contains(c)))
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::LibrarySettings;
+
+    fn split(credit: &str) -> Vec<String> {
+        split_credit(credit, &LibrarySettings::default().artist_separators)
+    }
+
+    #[test]
+    fn years_are_read_from_the_start_of_date_tags() {
+        assert_eq!(year_of("1997"), Some(1997));
+        assert_eq!(year_of(" 1997-05-21 "), Some(1997));
+        assert_eq!(year_of("2001-01"), Some(2001));
+        assert_eq!(year_of("97"), None);
+        assert_eq!(year_of("May 1997"), None);
+        assert_eq!(year_of(""), None);
+    }
+
+    #[test]
+    fn credits_split_on_the_default_separators() {
+        assert_eq!(split("A feat. B"), ["A", "B"]);
+        assert_eq!(split("A (feat. B)"), ["A", "B"]);
+        assert_eq!(split("A Featuring B ft. C"), ["A", "B", "C"]);
+        assert_eq!(split("A; B"), ["A", "B"]);
+        assert_eq!(split("A / B"), ["A", "B"]);
+    }
+
+    #[test]
+    fn names_holding_a_separator_stay_whole() {
+        assert_eq!(split("AC/DC"), ["AC/DC"]);
+        assert_eq!(split("The Unfeaturing"), ["The Unfeaturing"]);
+        assert_eq!(split("Craft. Work"), ["Craft. Work"]);
+    }
+
+    #[test]
+    fn a_bare_slash_splits_when_configured() {
+        let separators = vec!["/".to_string()];
+        assert_eq!(split_credit("AC/DC", &separators), ["AC", "DC"]);
+    }
+
+    #[test]
+    fn split_artists_trims_and_drops_repeats() {
+        let mut track = Track {
+            artists: vec!["  A feat. B ".to_string(), "B;A; ".to_string()],
+            ..Default::default()
+        };
+
+        track.split_artists(&LibrarySettings::default().artist_separators);
+        assert_eq!(track.artists, ["A", "B"]);
     }
 }
@@ -0,0 +1,103 @@
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PowerError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("shutdown command exited with {0}")]
+    CommandFailed(std::process::ExitStatus),
+}
+
+/// Turns the device off.
+pub trait PowerControl: Send + Sync {
+    fn power_off(&self) -> Result<(), PowerError>;
+}
+
+/// Asks systemd-logind to power off, which works unprivileged for the seat's active user.
+pub struct Logind;
+
+impl PowerControl for Logind {
+    fn power_off(&self) -> Result<(), PowerError> {
+        run(Command::new("busctl").args([
+            "call",
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+            "PowerOff",
+            "b",
+            "false",
+        ]))
+    }
+}
+
+/// Runs an arbitrary command, for systems without logind.
+pub struct ShutdownCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl PowerControl for ShutdownCommand {
+    fn power_off(&self) -> Result<(), PowerError> {
+        run(Command::new(&self.program).args(&self.args))
+    }
+}
+
+/// How the device is powered off, picked in settings.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum ShutdownMethod {
+    #[default]
+    Logind,
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+impl ShutdownMethod {
+    pub fn control(&self) -> Box<dyn PowerControl> {
+        match self {
+            ShutdownMethod::Logind => Box::new(Logind),
+            ShutdownMethod::Command { program, args } => Box::new(ShutdownCommand {
+                program: program.clone(),
+                args: args.clone(),
+            }),
+        }
+    }
+}
+
+fn run(command: &mut Command) -> Result<(), PowerError> {
+    let status = command.status()?;
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(PowerError::CommandFailed(status)),
+    }
+}
+
+/// Counts power offs rather than carrying them out, for tests.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct RecordingControl {
+    pub power_offs: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(test)]
+impl RecordingControl {
+    pub fn count(&self) -> usize {
+        self.power_offs.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+impl PowerControl for RecordingControl {
+    fn power_off(&self) -> Result<(), PowerError> {
+        self.power_offs
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+}
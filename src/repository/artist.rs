@@ -1,6 +1,9 @@
 use crate::{
-    model::{CatalogItem, artist::Artist},
-    repository::{Repository, RepositoryError},
+    model::{CatalogItem, artist::Artist, collation},
+    repository::{
+        Conditions, Page, Repository, RepositoryError, SortKey, select_count, select_initials,
+        select_page, setup_table,
+    },
 };
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -17,12 +20,60 @@ impl ArtistRepository {
         repository.setup().await?;
         Ok(repository)
     }
+
+    fn conditions(filter: &ArtistFilter) -> Conditions {
+        let mut conditions = Vec::new();
+        let mut binds = Vec::new();
+
+        if filter.name.is_some() {
+            conditions.push("metadata->>'name' LIKE ?");
+        }
+        if filter.track.is_some() {
+            conditions.push(
+                "EXISTS (
+                    SELECT 1 FROM json_each(metadata, '$.tracks')
+                    WHERE CAST(json_each.value AS INTEGER) = ?
+                )",
+            );
+        }
+        match filter.favorite {
+            Some(true) => conditions.push("favorite = TRUE"),
+            Some(false) => conditions.push("favorite = FALSE"),
+            None => {}
+        }
+
+        if let Some(name) = &filter.name {
+            binds.push(format!("%{}%", name));
+        }
+        if let Some(track) = &filter.track {
+            binds.push(track.clone());
+        }
+
+        (conditions, binds)
+    }
 }
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct ArtistFilter {
     pub name: Option<String>,
     pub track: Option<String>,
+    pub favorite: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ArtistSort {
+    #[default]
+    Name,
+    Id,
+}
+
+impl SortKey for ArtistSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            ArtistSort::Name => "sort_key COLLATE library",
+            ArtistSort::Id => "id",
+        }
+    }
 }
 
 #[async_trait]
@@ -31,19 +82,10 @@ impl Repository for ArtistRepository {
 
     type Item = Artist;
     type Filter = ArtistFilter;
+    type Sort = ArtistSort;
 
     async fn setup(&mut self) -> Result<(), RepositoryError> {
-        sqlx::query(&format!(
-            "CREATE TABLE IF NOT EXISTS {} (
-                id INTEGER PRIMARY KEY,
-                favorite BOOL NOT NULL DEFAULT FALSE,
-                metadata TEXT NOT NULL
-            )",
-            Self::TABLE_NAME
-        ))
-        .execute(&self.pool)
-        .await
-        .expect("failed to create artists table");
+        setup_table::<Self::Item>(&self.pool, Self::TABLE_NAME, |artist| &artist.name).await?;
 
         Ok(())
     }
@@ -53,10 +95,12 @@ impl Repository for ArtistRepository {
         item: Self::Item,
     ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
         let catalog_item: CatalogItem<Artist> = sqlx::query_as(&format!(
-            "INSERT INTO {} (metadata) VALUES ($1) RETURNING id, favorite, metadata",
+            "INSERT INTO {} (metadata, sort_key, created_at, updated_at)
+             VALUES ($1, $2, unixepoch(), unixepoch()) RETURNING id, favorite, metadata",
             Self::TABLE_NAME
         ))
         .bind(serde_json::to_string(&item).map_err(|e| RepositoryError::ItemCreate(e.to_string()))?)
+        .bind(collation::sort_key(&item.name))
         .fetch_one(&self.pool)
         .await
         .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -69,7 +113,7 @@ impl Repository for ArtistRepository {
             "SELECT id, favorite, metadata FROM {} WHERE id = $1",
             Self::TABLE_NAME
         ))
-        .bind(&id)
+        .bind(id)
         .fetch_one(&self.pool)
         .await
         .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -82,15 +126,17 @@ impl Repository for ArtistRepository {
         item: CatalogItem<Self::Item>,
     ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
         sqlx::query(&format!(
-            "UPDATE {} SET metadata = $1, favorite = $2 WHERE id = $3",
+            "UPDATE {} SET metadata = $1, favorite = $2, sort_key = $3, updated_at = unixepoch()
+             WHERE id = $4",
             Self::TABLE_NAME
         ))
         .bind(
             &serde_json::to_string(&item.metadata)
                 .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?,
         )
-        .bind(&item.favorite)
-        .bind(&item.id)
+        .bind(item.favorite)
+        .bind(collation::sort_key(&item.metadata.name))
+        .bind(item.id)
         .execute(&self.pool)
         .await
         .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -100,7 +146,7 @@ impl Repository for ArtistRepository {
 
     async fn delete(&mut self, id: &i64) -> Result<(), RepositoryError> {
         sqlx::query(&format!("DELETE FROM {} WHERE id = $1", Self::TABLE_NAME))
-            .bind(&id)
+            .bind(id)
             .execute(&self.pool)
             .await
             .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -108,43 +154,25 @@ impl Repository for ArtistRepository {
         Ok(())
     }
 
-    async fn find(
+    async fn find_page(
         &self,
         filter: Self::Filter,
+        page: Page<Self::Sort>,
     ) -> Result<Vec<CatalogItem<Self::Item>>, RepositoryError> {
-        let mut sql = format!("SELECT id, favorite, metadata FROM {}", Self::TABLE_NAME);
-        let mut conditions: Vec<String> = Vec::new();
-
-        if filter.name.is_some() {
-            conditions.push("metadata->>'name' LIKE ?".into());
-        }
-        if filter.track.is_some() {
-            conditions.push(
-                "EXISTS (
-                    SELECT 1 FROM json_each(metadata, '$.tracks')
-                    WHERE CAST(json_each.value AS INTEGER) = ?
-                )"
-                .into(),
-            );
-        }
-
-        if !conditions.is_empty() {
-            sql.push_str(" WHERE ");
-            sql.push_str(&conditions.join(" AND "));
-        }
-
-        let mut query = sqlx::query_as::<_, CatalogItem<Self::Item>>(&sql);
+        select_page(
+            &self.pool,
+            Self::TABLE_NAME,
+            Self::conditions(&filter),
+            page,
+        )
+        .await
+    }
 
-        if let Some(name) = &filter.name {
-            query = query.bind(format!("%{}%", name));
-        }
-        if let Some(track_id) = &filter.track {
-            query = query.bind(track_id);
-        }
+    async fn count(&self, filter: Self::Filter) -> Result<i64, RepositoryError> {
+        select_count(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
 
-        query
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    async fn initials(&self, filter: Self::Filter) -> Result<Vec<(String, i64)>, RepositoryError> {
+        select_initials(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
     }
 }
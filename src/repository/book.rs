@@ -0,0 +1,172 @@
+use crate::{
+    model::{CatalogItem, book::Book, collation},
+    repository::{
+        Conditions, Page, Repository, RepositoryError, SortKey, select_count, select_initials,
+        select_page, setup_table,
+    },
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone)]
+pub struct BookRepository {
+    pool: SqlitePool,
+}
+
+impl BookRepository {
+    pub async fn new(pool: SqlitePool) -> Result<Self, RepositoryError> {
+        let mut repository = Self { pool };
+        repository.setup().await?;
+        Ok(repository)
+    }
+
+    fn conditions(filter: &BookFilter) -> Conditions {
+        let mut conditions = Vec::new();
+        let mut binds = Vec::new();
+
+        if filter.title.is_some() {
+            conditions.push("metadata->>'title' LIKE ?");
+        }
+        if filter.author.is_some() {
+            conditions.push("metadata->>'author' LIKE ?");
+        }
+
+        if let Some(title) = &filter.title {
+            binds.push(format!("%{}%", title));
+        }
+        if let Some(author) = &filter.author {
+            binds.push(format!("%{}%", author));
+        }
+
+        (conditions, binds)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct BookFilter {
+    pub title: Option<String>,
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum BookSort {
+    #[default]
+    Title,
+    Author,
+    /// When sync first found the book.
+    Added,
+    Id,
+}
+
+impl SortKey for BookSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            BookSort::Title => "sort_key COLLATE library",
+            BookSort::Author => "metadata->>'author'",
+            BookSort::Added => "created_at",
+            BookSort::Id => "id",
+        }
+    }
+}
+
+#[async_trait]
+impl Repository for BookRepository {
+    const TABLE_NAME: &'static str = "books";
+
+    type Item = Book;
+    type Filter = BookFilter;
+    type Sort = BookSort;
+
+    async fn setup(&mut self) -> Result<(), RepositoryError> {
+        setup_table::<Self::Item>(&self.pool, Self::TABLE_NAME, |book| &book.title).await?;
+
+        Ok(())
+    }
+
+    async fn create(
+        &mut self,
+        item: Self::Item,
+    ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
+        let catalog_item: CatalogItem<Book> = sqlx::query_as(&format!(
+            "INSERT INTO {} (metadata, sort_key, created_at, updated_at)
+             VALUES ($1, $2, unixepoch(), unixepoch()) RETURNING id, favorite, metadata",
+            Self::TABLE_NAME
+        ))
+        .bind(serde_json::to_string(&item).map_err(|e| RepositoryError::ItemCreate(e.to_string()))?)
+        .bind(collation::sort_key(&item.title))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(catalog_item)
+    }
+
+    async fn read(&mut self, id: &i64) -> Result<CatalogItem<Self::Item>, RepositoryError> {
+        let catalog_item: CatalogItem<Book> = sqlx::query_as(&format!(
+            "SELECT id, favorite, metadata FROM {} WHERE id = $1",
+            Self::TABLE_NAME
+        ))
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(catalog_item)
+    }
+
+    async fn update(
+        &mut self,
+        item: CatalogItem<Self::Item>,
+    ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
+        sqlx::query(&format!(
+            "UPDATE {} SET metadata = $1, favorite = $2, sort_key = $3, updated_at = unixepoch()
+             WHERE id = $4",
+            Self::TABLE_NAME
+        ))
+        .bind(
+            &serde_json::to_string(&item.metadata)
+                .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?,
+        )
+        .bind(item.favorite)
+        .bind(collation::sort_key(&item.metadata.title))
+        .bind(item.id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(item)
+    }
+
+    async fn delete(&mut self, id: &i64) -> Result<(), RepositoryError> {
+        sqlx::query(&format!("DELETE FROM {} WHERE id = $1", Self::TABLE_NAME))
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_page(
+        &self,
+        filter: Self::Filter,
+        page: Page<Self::Sort>,
+    ) -> Result<Vec<CatalogItem<Self::Item>>, RepositoryError> {
+        select_page(
+            &self.pool,
+            Self::TABLE_NAME,
+            Self::conditions(&filter),
+            page,
+        )
+        .await
+    }
+
+    async fn count(&self, filter: Self::Filter) -> Result<i64, RepositoryError> {
+        select_count(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
+
+    async fn initials(&self, filter: Self::Filter) -> Result<Vec<(String, i64)>, RepositoryError> {
+        select_initials(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{
+    model::{CatalogItem, collation, genre::Genre},
+    repository::{
+        Conditions, Page, Repository, RepositoryError, SortKey, select_count, select_initials,
+        select_page, setup_table,
+    },
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone)]
+pub struct GenreRepository {
+    pool: SqlitePool,
+}
+
+impl GenreRepository {
+    pub async fn new(pool: SqlitePool) -> Result<Self, RepositoryError> {
+        let mut repository = Self { pool };
+        repository.setup().await?;
+        Ok(repository)
+    }
+
+    fn conditions(filter: &GenreFilter) -> Conditions {
+        let mut conditions = Vec::new();
+        let mut binds = Vec::new();
+
+        if filter.name.is_some() {
+            conditions.push("metadata->>'name' LIKE ?");
+        }
+
+        if let Some(name) = &filter.name {
+            binds.push(format!("%{}%", name));
+        }
+
+        (conditions, binds)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct GenreFilter {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum GenreSort {
+    #[default]
+    Name,
+    Id,
+}
+
+impl SortKey for GenreSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            GenreSort::Name => "sort_key COLLATE library",
+            GenreSort::Id => "id",
+        }
+    }
+}
+
+#[async_trait]
+impl Repository for GenreRepository {
+    const TABLE_NAME: &'static str = "genres";
+
+    type Item = Genre;
+    type Filter = GenreFilter;
+    type Sort = GenreSort;
+
+    async fn setup(&mut self) -> Result<(), RepositoryError> {
+        setup_table::<Self::Item>(&self.pool, Self::TABLE_NAME, |genre| &genre.name).await?;
+
+        Ok(())
+    }
+
+    async fn create(
+        &mut self,
+        item: Self::Item,
+    ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
+        let catalog_item: CatalogItem<Genre> = sqlx::query_as(&format!(
+            "INSERT INTO {} (metadata, sort_key, created_at, updated_at)
+             VALUES ($1, $2, unixepoch(), unixepoch()) RETURNING id, favorite, metadata",
+            Self::TABLE_NAME
+        ))
+        .bind(serde_json::to_string(&item).map_err(|e| RepositoryError::ItemCreate(e.to_string()))?)
+        .bind(collation::sort_key(&item.name))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(catalog_item)
+    }
+
+    async fn read(&mut self, id: &i64) -> Result<CatalogItem<Self::Item>, RepositoryError> {
+        let catalog_item: CatalogItem<Genre> = sqlx::query_as(&format!(
+            "SELECT id, favorite, metadata FROM {} WHERE id = $1",
+            Self::TABLE_NAME
+        ))
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(catalog_item)
+    }
+
+    async fn update(
+        &mut self,
+        item: CatalogItem<Self::Item>,
+    ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
+        sqlx::query(&format!(
+            "UPDATE {} SET metadata = $1, favorite = $2, sort_key = $3, updated_at = unixepoch()
+             WHERE id = $4",
+            Self::TABLE_NAME
+        ))
+        .bind(
+            &serde_json::to_string(&item.metadata)
+                .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?,
+        )
+        .bind(item.favorite)
+        .bind(collation::sort_key(&item.metadata.name))
+        .bind(item.id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(item)
+    }
+
+    async fn delete(&mut self, id: &i64) -> Result<(), RepositoryError> {
+        sqlx::query(&format!("DELETE FROM {} WHERE id = $1", Self::TABLE_NAME))
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_page(
+        &self,
+        filter: Self::Filter,
+        page: Page<Self::Sort>,
+    ) -> Result<Vec<CatalogItem<Self::Item>>, RepositoryError> {
+        select_page(
+            &self.pool,
+            Self::TABLE_NAME,
+            Self::conditions(&filter),
+            page,
+        )
+        .await
+    }
+
+    async fn count(&self, filter: Self::Filter) -> Result<i64, RepositoryError> {
+        select_count(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
+
+    async fn initials(&self, filter: Self::Filter) -> Result<Vec<(String, i64)>, RepositoryError> {
+        select_initials(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
+}
@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use chrono::DateTime;
+use sqlx::{Row, SqlitePool, sqlite::SqliteRow};
+
+use crate::{model::listen::Listen, repository::RepositoryError};
+
+/// Every listen ever recorded, kept after it's been scrobbled.
+#[derive(Debug, Clone)]
+pub struct HistoryRepository {
+    pool: SqlitePool,
+}
+
+impl HistoryRepository {
+    const TABLE_NAME: &'static str = "plays";
+
+    pub async fn new(pool: SqlitePool) -> Result<Self, RepositoryError> {
+        let repository = Self { pool };
+        repository.setup().await?;
+        Ok(repository)
+    }
+
+    async fn setup(&self) -> Result<(), RepositoryError> {
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
+                id INTEGER PRIMARY KEY,
+                title TEXT NOT NULL,
+                artist TEXT NOT NULL,
+                release TEXT,
+                duration INTEGER NOT NULL,
+                played_at INTEGER NOT NULL,
+                listened INTEGER,
+                track_id INTEGER
+            )",
+            table = Self::TABLE_NAME
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+        let columns: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?)")
+            .bind(Self::TABLE_NAME)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| RepositoryError::Setup)?;
+
+        // Listens recorded before the time heard was kept fall back to the track's duration.
+        if !columns.iter().any(|(name,)| name == "listened") {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN listened INTEGER",
+                Self::TABLE_NAME
+            ))
+            .execute(&self.pool)
+            .await
+            .map_err(|_| RepositoryError::Setup)?;
+        }
+
+        // Listens recorded before they were kept by track go to the first track with their title
+        // and artist, which is as close as what's left of them gets.
+        if !columns.iter().any(|(name,)| name == "track_id") {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN track_id INTEGER",
+                Self::TABLE_NAME
+            ))
+            .execute(&self.pool)
+            .await
+            .map_err(|_| RepositoryError::Setup)?;
+
+            sqlx::query(&format!(
+                "UPDATE {} SET track_id = (
+                    SELECT MIN(tracks.id) FROM tracks
+                    WHERE tracks.metadata->>'title' = plays.title
+                    AND tracks.metadata->>'artist' = plays.artist
+                )",
+                Self::TABLE_NAME
+            ))
+            .execute(&self.pool)
+            .await
+            .map_err(|_| RepositoryError::Setup)?;
+        }
+
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS idx_{table}_played_at ON {table}(played_at)",
+            table = Self::TABLE_NAME
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+        // Tracks are matched to their listens by id, the index by title and artist is no
+        // longer used for it.
+        sqlx::query(&format!(
+            "DROP INDEX IF EXISTS idx_{}_track",
+            Self::TABLE_NAME
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS idx_{table}_track_id ON {table}(track_id, played_at)",
+            table = Self::TABLE_NAME
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+        Ok(())
+    }
+
+    /// Records `listen` of the track with `track_id`, `None` when it isn't in the catalog.
+    pub async fn push(
+        &mut self,
+        listen: &Listen,
+        track_id: Option<i64>,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(&format!(
+            "INSERT INTO {} (title, artist, release, duration, played_at, listened, track_id)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            Self::TABLE_NAME
+        ))
+        .bind(&listen.title)
+        .bind(&listen.artist)
+        .bind(&listen.release)
+        .bind(listen.duration.as_secs() as i64)
+        .bind(listen.listened_at.timestamp())
+        // Until the track is left all that's known is it got far enough to count.
+        .bind(
+            Listen::threshold(listen.duration)
+                .unwrap_or(listen.duration)
+                .as_secs() as i64,
+        )
+        .bind(track_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Sets how long `listen` was actually heard for, once playback has moved on from it.
+    pub async fn set_listened(
+        &mut self,
+        listen: &Listen,
+        listened: Duration,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(&format!(
+            "UPDATE {} SET listened = $1 WHERE title = $2 AND artist = $3 AND played_at = $4",
+            Self::TABLE_NAME
+        ))
+        .bind(listened.as_secs() as i64)
+        .bind(&listen.title)
+        .bind(&listen.artist)
+        .bind(listen.listened_at.timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Every listen, oldest first.
+    pub async fn all(&self) -> Result<Vec<Listen>, RepositoryError> {
+        let rows = sqlx::query(&format!(
+            "SELECT title, artist, release, duration, played_at FROM {} ORDER BY played_at, id",
+            Self::TABLE_NAME
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))?;
+
+        rows.iter()
+            .map(listen_from_row)
+            .collect::<Result<_, sqlx::Error>>()
+            .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    /// Number of listens and the time spent on them.
+    pub async fn totals(&self) -> Result<(i64, Duration), RepositoryError> {
+        let (count, seconds): (i64, i64) = sqlx::query_as(&format!(
+            "SELECT COUNT(*), COALESCE(SUM(COALESCE(listened, duration)), 0) FROM {}",
+            Self::TABLE_NAME
+        ))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))?;
+
+        Ok((count, Duration::from_secs(seconds.max(0) as u64)))
+    }
+
+    /// The most played artists with their number of listens.
+    pub async fn top_artists(&self, limit: usize) -> Result<Vec<(String, i64)>, RepositoryError> {
+        sqlx::query_as(&format!(
+            "SELECT artist, COUNT(*) AS plays FROM {}
+             GROUP BY artist ORDER BY plays DESC, artist LIMIT $1",
+            Self::TABLE_NAME
+        ))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    /// Time spent listening in each of the latest `limit` weeks with any listens, newest first,
+    /// keyed by the Monday starting the week.
+    pub async fn weekly(&self, limit: usize) -> Result<Vec<(String, Duration)>, RepositoryError> {
+        let weeks: Vec<(String, i64)> = sqlx::query_as(&format!(
+            "SELECT date(played_at, 'unixepoch', 'localtime', 'weekday 0', '-6 days') AS week,
+                SUM(COALESCE(listened, duration))
+             FROM {} GROUP BY week ORDER BY week DESC LIMIT $1",
+            Self::TABLE_NAME
+        ))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))?;
+
+        Ok(weeks
+            .into_iter()
+            .map(|(week, seconds)| (week, Duration::from_secs(seconds.max(0) as u64)))
+            .collect())
+    }
+}
+
+fn listen_from_row(row: &SqliteRow) -> Result<Listen, sqlx::Error> {
+    let duration: i64 = row.try_get("duration")?;
+    let played_at: i64 = row.try_get("played_at")?;
+
+    Ok(Listen {
+        title: row.try_get("title")?,
+        artist: row.try_get("artist")?,
+        release: row.try_get("release")?,
+        duration: Duration::from_secs(duration.max(0) as u64),
+        listened_at: DateTime::from_timestamp(played_at, 0).unwrap_or_default(),
+        ..Default::default()
+    })
+}
@@ -7,12 +7,21 @@ use thiserror::Error;
 use tokio::sync::Mutex;
 
 use crate::{
-    model::CatalogItem,
-    repository::{artist::ArtistRepository, release::ReleaseRepository, track::TrackRepository},
+    model::{CatalogItem, collation},
+    repository::{
+        artist::ArtistRepository, book::BookRepository, genre::GenreRepository,
+        history::HistoryRepository, playlist::PlaylistRepository, release::ReleaseRepository,
+        scrobble::ScrobbleRepository, track::TrackRepository,
+    },
 };
 
 pub mod artist;
+pub mod book;
+pub mod genre;
+pub mod history;
+pub mod playlist;
 pub mod release;
+pub mod scrobble;
 pub mod track;
 
 #[derive(Debug, Error)]
@@ -25,12 +34,68 @@ pub enum RepositoryError {
     ItemRead(String),
 }
 
+/// Maps a repository specific sort option onto the SQL expression rows are ordered by.
+pub trait SortKey {
+    fn expression(&self) -> &'static str;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    fn sql(&self) -> &'static str {
+        match self {
+            SortDirection::Ascending => "ASC",
+            SortDirection::Descending => "DESC",
+        }
+    }
+}
+
+/// Ordering and window applied to a `Repository::find_page` query, a `limit` of `None` returns
+/// every row from `offset` onwards.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Page<S> {
+    pub sort: S,
+    pub direction: SortDirection,
+    pub offset: u32,
+    pub limit: Option<u32>,
+}
+
+impl<S: SortKey> Page<S> {
+    pub fn window(offset: usize, limit: usize) -> Self
+    where
+        S: Default,
+    {
+        Self {
+            offset: offset as u32,
+            limit: Some(limit as u32),
+            ..Default::default()
+        }
+    }
+
+    /// Renders the `ORDER BY` and `LIMIT` clauses, ties are broken on id so windows are stable.
+    fn sql(&self) -> String {
+        format!(
+            " ORDER BY {expression} {direction}, id {direction} LIMIT {limit} OFFSET {offset}",
+            expression = self.sort.expression(),
+            direction = self.direction.sql(),
+            limit = self.limit.map(i64::from).unwrap_or(-1),
+            offset = self.offset,
+        )
+    }
+}
+
 #[async_trait]
 pub trait Repository {
     const TABLE_NAME: &'static str;
 
     type Item: Serialize + for<'de> Deserialize<'de>;
-    type Filter: Default + Serialize + for<'de> Deserialize<'de>;
+    type Filter: Default + Serialize + for<'de> Deserialize<'de> + Send;
+    type Sort: SortKey + Default + Send;
 
     async fn setup(&mut self) -> Result<(), RepositoryError>;
     async fn create(
@@ -46,14 +111,210 @@ pub trait Repository {
     async fn find(
         &self,
         filter: Self::Filter,
+    ) -> Result<Vec<CatalogItem<Self::Item>>, RepositoryError> {
+        self.find_page(filter, Page::default()).await
+    }
+    async fn find_page(
+        &self,
+        filter: Self::Filter,
+        page: Page<Self::Sort>,
     ) -> Result<Vec<CatalogItem<Self::Item>>, RepositoryError>;
+    async fn count(&self, filter: Self::Filter) -> Result<i64, RepositoryError>;
+    /// Returns the first character of each item's sort key with the number of items sharing it,
+    /// in sort order, which is enough to jump to a letter without loading the rows before it.
+    async fn initials(&self, filter: Self::Filter) -> Result<Vec<(String, i64)>, RepositoryError>;
+}
+
+/// Joins filter conditions into a `WHERE` clause.
+fn where_clause(conditions: &[&str]) -> String {
+    match conditions.is_empty() {
+        true => String::new(),
+        false => format!(" WHERE {}", conditions.join(" AND ")),
+    }
+}
+
+/// Filter conditions and the values bound to their placeholders, in order.
+type Conditions = (Vec<&'static str>, Vec<String>);
+
+/// The page of rows in `table` matching `conditions`, ordered by the page's sort key.
+async fn select_page<T, S>(
+    pool: &SqlitePool,
+    table: &str,
+    (conditions, binds): Conditions,
+    page: Page<S>,
+) -> Result<Vec<CatalogItem<T>>, RepositoryError>
+where
+    T: for<'de> Deserialize<'de> + Send + Unpin,
+    S: SortKey,
+{
+    let sql = format!(
+        "SELECT id, favorite, metadata FROM {table}{}{}",
+        where_clause(&conditions),
+        page.sql()
+    );
+
+    let mut query = sqlx::query_as::<_, CatalogItem<T>>(&sql);
+    for bind in binds {
+        query = query.bind(bind);
+    }
+
+    query
+        .fetch_all(pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+}
+
+/// How many rows in `table` match `conditions`.
+async fn select_count(
+    pool: &SqlitePool,
+    table: &str,
+    (conditions, binds): Conditions,
+) -> Result<i64, RepositoryError> {
+    let sql = format!("SELECT COUNT(*) FROM {table}{}", where_clause(&conditions));
+
+    let mut query = sqlx::query_scalar::<_, i64>(&sql);
+    for bind in binds {
+        query = query.bind(bind);
+    }
+
+    query
+        .fetch_one(pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+}
+
+/// The first character of the sort key of the rows in `table` matching `conditions`, with how
+/// many share it, see `Repository::initials`.
+async fn select_initials(
+    pool: &SqlitePool,
+    table: &str,
+    (conditions, binds): Conditions,
+) -> Result<Vec<(String, i64)>, RepositoryError> {
+    let sql = format!(
+        "SELECT substr(sort_key, 1, 1) AS initial, COUNT(*) FROM {table}{}
+         GROUP BY initial ORDER BY initial COLLATE {collation}",
+        where_clause(&conditions),
+        collation = collation::COLLATION
+    );
+
+    let mut query = sqlx::query_as::<_, (String, i64)>(&sql);
+    for bind in binds {
+        query = query.bind(bind);
+    }
+
+    query
+        .fetch_all(pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+}
+
+/// Creates the columns shared by every catalog table and backfills sort keys and timestamps for
+/// rows written before those columns existed.
+///
+/// `created_at` is when sync first saw the item and `updated_at` when it last changed, both in
+/// seconds since the Unix epoch.
+async fn setup_table<T>(
+    pool: &SqlitePool,
+    table: &str,
+    sort_text: impl Fn(&T) -> &str,
+) -> Result<(), RepositoryError>
+where
+    T: for<'de> Deserialize<'de> + Send + Unpin,
+{
+    sqlx::query(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id INTEGER PRIMARY KEY,
+            favorite BOOL NOT NULL DEFAULT FALSE,
+            metadata TEXT NOT NULL,
+            sort_key TEXT,
+            created_at INTEGER,
+            updated_at INTEGER
+        )"
+    ))
+    .execute(pool)
+    .await
+    .map_err(|_| RepositoryError::Setup)?;
+
+    let columns: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?)")
+        .bind(table)
+        .fetch_all(pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+    for (column, kind) in [
+        ("sort_key", "TEXT"),
+        ("created_at", "INTEGER"),
+        ("updated_at", "INTEGER"),
+    ] {
+        if !columns.iter().any(|(name,)| name == column) {
+            sqlx::query(&format!("ALTER TABLE {table} ADD COLUMN {column} {kind}"))
+                .execute(pool)
+                .await
+                .map_err(|_| RepositoryError::Setup)?;
+        }
+    }
+
+    // Nothing tells when older rows were added, so they count as added now.
+    sqlx::query(&format!(
+        "UPDATE {table} SET created_at = COALESCE(created_at, unixepoch()),
+            updated_at = COALESCE(updated_at, unixepoch())
+         WHERE created_at IS NULL OR updated_at IS NULL"
+    ))
+    .execute(pool)
+    .await
+    .map_err(|_| RepositoryError::Setup)?;
+
+    let unsorted: Vec<CatalogItem<T>> = sqlx::query_as(&format!(
+        "SELECT id, favorite, metadata FROM {table} WHERE sort_key IS NULL"
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|_| RepositoryError::Setup)?;
+
+    for item in unsorted {
+        sqlx::query(&format!("UPDATE {table} SET sort_key = ? WHERE id = ?"))
+            .bind(collation::sort_key(sort_text(&item.metadata)))
+            .bind(item.id)
+            .execute(pool)
+            .await
+            .map_err(|_| RepositoryError::Setup)?;
+    }
+
+    // Ordered by the library collation, the index on the plain keys sorted them bytewise.
+    sqlx::query(&format!("DROP INDEX IF EXISTS idx_{table}_sort_key"))
+        .execute(pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+    sqlx::query(&format!(
+        "CREATE INDEX IF NOT EXISTS idx_{table}_collated_sort_key
+         ON {table}(sort_key COLLATE {collation}, id)",
+        collation = collation::COLLATION
+    ))
+    .execute(pool)
+    .await
+    .map_err(|_| RepositoryError::Setup)?;
+
+    sqlx::query(&format!(
+        "CREATE INDEX IF NOT EXISTS idx_{table}_created_at ON {table}(created_at, id)"
+    ))
+    .execute(pool)
+    .await
+    .map_err(|_| RepositoryError::Setup)?;
+
+    Ok(())
 }
 
 #[derive(Debug, Clone)]
 pub struct RepositoryContext {
     pub artist: Arc<Mutex<ArtistRepository>>,
     pub release: Arc<Mutex<ReleaseRepository>>,
+    pub genre: Arc<Mutex<GenreRepository>>,
+    pub book: Arc<Mutex<BookRepository>>,
     pub track: Arc<Mutex<TrackRepository>>,
+    pub scrobble: Arc<Mutex<ScrobbleRepository>>,
+    pub history: Arc<Mutex<HistoryRepository>>,
+    pub playlist: Arc<Mutex<PlaylistRepository>>,
 }
 
 impl RepositoryContext {
@@ -61,7 +322,76 @@ impl RepositoryContext {
         Ok(Self {
             artist: Arc::new(Mutex::new(ArtistRepository::new(pool.clone()).await?)),
             release: Arc::new(Mutex::new(ReleaseRepository::new(pool.clone()).await?)),
+            genre: Arc::new(Mutex::new(GenreRepository::new(pool.clone()).await?)),
+            book: Arc::new(Mutex::new(BookRepository::new(pool.clone()).await?)),
             track: Arc::new(Mutex::new(TrackRepository::new(pool.clone()).await?)),
+            scrobble: Arc::new(Mutex::new(ScrobbleRepository::new(pool.clone()).await?)),
+            history: Arc::new(Mutex::new(HistoryRepository::new(pool.clone()).await?)),
+            playlist: Arc::new(Mutex::new(PlaylistRepository::new(pool.clone()).await?)),
         })
     }
+
+    /// A fresh database held in memory, one connection so every repository sees the same one.
+    #[cfg(test)]
+    pub async fn in_memory() -> Self {
+        use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+        use std::str::FromStr;
+
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")
+            .expect("error parsing database url")
+            .collation(collation::COLLATION, collation::compare);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("error opening database");
+
+        Self::new(pool)
+            .await
+            .expect("error initializing repositories")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::artist::Artist,
+        repository::artist::{ArtistFilter, ArtistSort},
+    };
+
+    async fn artists(names: &[&str]) -> RepositoryContext {
+        let context = RepositoryContext::in_memory().await;
+        for name in names {
+            let artist = Artist {
+                name: name.to_string(),
+                releases: Vec::new(),
+            };
+            context.artist.lock().await.create(artist).await.unwrap();
+        }
+        context
+    }
+
+    #[tokio::test]
+    async fn pages_counts_and_initials_follow_the_filter_and_sort() {
+        let context = artists(&["Beirut", "Air", "Bonobo", "Arca", "Caribou"]).await;
+        let artists = context.artist.lock().await;
+
+        let page = artists
+            .find_page(ArtistFilter::default(), Page::<ArtistSort>::window(1, 3))
+            .await
+            .unwrap();
+        let names: Vec<_> = page.iter().map(|a| a.metadata.name.as_str()).collect();
+        assert_eq!(names, ["Arca", "Beirut", "Bonobo"]);
+
+        let filter = || ArtistFilter {
+            name: Some("b".into()),
+            ..Default::default()
+        };
+        assert_eq!(artists.count(filter()).await.unwrap(), 3);
+        assert_eq!(
+            artists.initials(filter()).await.unwrap(),
+            [("b".to_string(), 2), ("c".to_string(), 1)]
+        );
+    }
 }
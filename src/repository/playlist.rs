@@ -0,0 +1,288 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    model::{CatalogItem, collation, playlist::Playlist, track::Track},
+    repository::{
+        Conditions, Page, Repository, RepositoryError, SortKey, select_count, select_initials,
+        select_page, setup_table,
+    },
+};
+
+/// Playlists, with their tracks kept in order in a separate table.
+#[derive(Debug, Clone)]
+pub struct PlaylistRepository {
+    pool: SqlitePool,
+}
+
+impl PlaylistRepository {
+    const ENTRIES_TABLE_NAME: &'static str = "playlist_tracks";
+
+    pub async fn new(pool: SqlitePool) -> Result<Self, RepositoryError> {
+        let mut repository = Self { pool };
+        repository.setup().await?;
+        Ok(repository)
+    }
+
+    fn conditions(filter: &PlaylistFilter) -> Conditions {
+        let mut conditions = Vec::new();
+        let mut binds = Vec::new();
+
+        if filter.name.is_some() {
+            conditions.push("metadata->>'name' LIKE ?");
+        }
+        if filter.path.is_some() {
+            conditions.push("metadata->>'path' = ?");
+        }
+
+        if let Some(name) = &filter.name {
+            binds.push(format!("%{}%", name));
+        }
+        if let Some(path) = &filter.path {
+            binds.push(path.clone());
+        }
+
+        (conditions, binds)
+    }
+
+    /// Replaces the tracks of the playlist with `id`, in the given order.
+    pub async fn set_tracks(&mut self, id: i64, track_ids: &[i64]) -> Result<(), RepositoryError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE playlist_id = $1",
+            Self::ENTRIES_TABLE_NAME
+        ))
+        .bind(id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        for (position, track_id) in track_ids.iter().enumerate() {
+            sqlx::query(&format!(
+                "INSERT INTO {} (playlist_id, position, track_id) VALUES ($1, $2, $3)",
+                Self::ENTRIES_TABLE_NAME
+            ))
+            .bind(id)
+            .bind(position as i64)
+            .bind(track_id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))
+    }
+
+    /// Ids of the tracks in the playlist with `id`, in playlist order, skipping any no longer in
+    /// the catalog so positions line up with `tracks_page`.
+    pub async fn track_ids(&self, id: i64) -> Result<Vec<i64>, RepositoryError> {
+        sqlx::query_scalar(&format!(
+            "SELECT track_id FROM {entries}
+             JOIN tracks ON tracks.id = {entries}.track_id
+             WHERE {entries}.playlist_id = $1 ORDER BY {entries}.position",
+            entries = Self::ENTRIES_TABLE_NAME
+        ))
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    /// A window of the tracks in the playlist with `id`, in playlist order.
+    pub async fn tracks_page(
+        &self,
+        id: i64,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<CatalogItem<Track>>, RepositoryError> {
+        sqlx::query_as(&format!(
+            "SELECT tracks.id, tracks.favorite, tracks.metadata FROM {entries}
+             JOIN tracks ON tracks.id = {entries}.track_id
+             WHERE {entries}.playlist_id = $1
+             ORDER BY {entries}.position LIMIT $2 OFFSET $3",
+            entries = Self::ENTRIES_TABLE_NAME
+        ))
+        .bind(id)
+        .bind(limit.map(|l| l as i64).unwrap_or(-1))
+        .bind(offset as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    pub async fn count_tracks(&self, id: i64) -> Result<i64, RepositoryError> {
+        sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {entries}
+             JOIN tracks ON tracks.id = {entries}.track_id
+             WHERE {entries}.playlist_id = $1",
+            entries = Self::ENTRIES_TABLE_NAME
+        ))
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct PlaylistFilter {
+    pub name: Option<String>,
+    /// The file the playlist was imported from, matched exactly.
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum PlaylistSort {
+    #[default]
+    Name,
+    Id,
+}
+
+impl SortKey for PlaylistSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            PlaylistSort::Name => "sort_key COLLATE library",
+            PlaylistSort::Id => "id",
+        }
+    }
+}
+
+#[async_trait]
+impl Repository for PlaylistRepository {
+    const TABLE_NAME: &'static str = "playlists";
+
+    type Item = Playlist;
+    type Filter = PlaylistFilter;
+    type Sort = PlaylistSort;
+
+    async fn setup(&mut self) -> Result<(), RepositoryError> {
+        setup_table::<Self::Item>(&self.pool, Self::TABLE_NAME, |playlist| &playlist.name).await?;
+
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS idx_{table}_path ON {table}((metadata->>'path'))",
+            table = Self::TABLE_NAME
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                playlist_id INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                track_id INTEGER NOT NULL,
+                PRIMARY KEY (playlist_id, position)
+            )",
+            Self::ENTRIES_TABLE_NAME
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+        Ok(())
+    }
+
+    async fn create(
+        &mut self,
+        item: Self::Item,
+    ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
+        let catalog_item: CatalogItem<Self::Item> = sqlx::query_as(&format!(
+            "INSERT INTO {} (metadata, sort_key, created_at, updated_at)
+             VALUES ($1, $2, unixepoch(), unixepoch()) RETURNING id, favorite, metadata",
+            Self::TABLE_NAME
+        ))
+        .bind(serde_json::to_string(&item).map_err(|e| RepositoryError::ItemCreate(e.to_string()))?)
+        .bind(collation::sort_key(&item.name))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(catalog_item)
+    }
+
+    async fn read(&mut self, id: &i64) -> Result<CatalogItem<Self::Item>, RepositoryError> {
+        let catalog_item: CatalogItem<Self::Item> = sqlx::query_as(&format!(
+            "SELECT id, favorite, metadata FROM {} WHERE id = $1",
+            Self::TABLE_NAME
+        ))
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))?;
+
+        Ok(catalog_item)
+    }
+
+    async fn update(
+        &mut self,
+        item: CatalogItem<Self::Item>,
+    ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
+        sqlx::query(&format!(
+            "UPDATE {} SET metadata = $1, favorite = $2, sort_key = $3, updated_at = unixepoch()
+             WHERE id = $4",
+            Self::TABLE_NAME
+        ))
+        .bind(
+            &serde_json::to_string(&item.metadata)
+                .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?,
+        )
+        .bind(item.favorite)
+        .bind(collation::sort_key(&item.metadata.name))
+        .bind(item.id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(item)
+    }
+
+    async fn delete(&mut self, id: &i64) -> Result<(), RepositoryError> {
+        sqlx::query(&format!("DELETE FROM {} WHERE id = $1", Self::TABLE_NAME))
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE playlist_id = $1",
+            Self::ENTRIES_TABLE_NAME
+        ))
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_page(
+        &self,
+        filter: Self::Filter,
+        page: Page<Self::Sort>,
+    ) -> Result<Vec<CatalogItem<Self::Item>>, RepositoryError> {
+        select_page(
+            &self.pool,
+            Self::TABLE_NAME,
+            Self::conditions(&filter),
+            page,
+        )
+        .await
+    }
+
+    async fn count(&self, filter: Self::Filter) -> Result<i64, RepositoryError> {
+        select_count(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
+
+    async fn initials(&self, filter: Self::Filter) -> Result<Vec<(String, i64)>, RepositoryError> {
+        select_initials(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
+}
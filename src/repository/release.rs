@@ -1,10 +1,15 @@
+use std::ops::RangeInclusive;
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 
 use crate::{
-    model::{CatalogItem, release::Release},
-    repository::{Repository, RepositoryError},
+    model::{CatalogItem, collation, release::Release},
+    repository::{
+        Conditions, Page, Repository, RepositoryError, SortKey, select_count, select_initials,
+        select_page, setup_table,
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -18,12 +23,96 @@ impl ReleaseRepository {
         repository.setup().await?;
         Ok(repository)
     }
+
+    fn conditions(filter: &ReleaseFilter) -> Conditions {
+        let mut conditions = Vec::new();
+        let mut binds = Vec::new();
+
+        if filter.title.is_some() {
+            conditions.push("metadata->>'title' LIKE ?");
+        }
+        if filter.artist.is_some() {
+            // Compilations are listed under everyone on them as well as Various Artists.
+            conditions.push(
+                "(metadata->>'artist' LIKE ?
+                  OR EXISTS (SELECT 1 FROM json_each(metadata, '$.artists') WHERE json_each.value LIKE ?))",
+            );
+        }
+
+        if filter.genre.is_some() {
+            conditions.push("metadata->>'genre' = ? COLLATE NOCASE");
+        }
+        if filter.years.is_some() {
+            conditions.push("metadata->>'year' BETWEEN CAST(? AS INTEGER) AND CAST(? AS INTEGER)");
+        }
+        match filter.favorite {
+            Some(true) => conditions.push("favorite = TRUE"),
+            Some(false) => conditions.push("favorite = FALSE"),
+            None => {}
+        }
+
+        if let Some(title) = &filter.title {
+            binds.push(format!("%{}%", title));
+        }
+        if let Some(artist) = &filter.artist {
+            binds.push(format!("%{}%", artist));
+            binds.push(format!("%{}%", artist));
+        }
+        if let Some(genre) = &filter.genre {
+            binds.push(genre.clone());
+        }
+        if let Some(years) = &filter.years {
+            binds.push(years.start().to_string());
+            binds.push(years.end().to_string());
+        }
+
+        (conditions, binds)
+    }
+
+    /// The decades releases are from, by first year, with how many releases each has.
+    pub async fn decades(&self) -> Result<Vec<(i32, i64)>, RepositoryError> {
+        sqlx::query_as(&format!(
+            "SELECT CAST(metadata->>'year' AS INTEGER) / 10 * 10 AS decade, COUNT(*) FROM {}
+             WHERE metadata->>'year' IS NOT NULL GROUP BY decade ORDER BY decade",
+            Self::TABLE_NAME
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
 }
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct ReleaseFilter {
     pub title: Option<String>,
     pub artist: Option<String>,
+    pub genre: Option<String>,
+    /// Releases from any of these years.
+    pub years: Option<RangeInclusive<i32>>,
+    pub favorite: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ReleaseSort {
+    #[default]
+    Title,
+    Artist,
+    Year,
+    /// When sync first found the release.
+    Added,
+    Id,
+}
+
+impl SortKey for ReleaseSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            ReleaseSort::Title => "sort_key COLLATE library",
+            ReleaseSort::Artist => "metadata->>'artist'",
+            ReleaseSort::Year => "metadata->>'year'",
+            ReleaseSort::Added => "created_at",
+            ReleaseSort::Id => "id",
+        }
+    }
 }
 
 #[async_trait]
@@ -32,19 +121,10 @@ impl Repository for ReleaseRepository {
 
     type Item = Release;
     type Filter = ReleaseFilter;
+    type Sort = ReleaseSort;
 
     async fn setup(&mut self) -> Result<(), RepositoryError> {
-        sqlx::query(&format!(
-            "CREATE TABLE IF NOT EXISTS {} (
-                id INTEGER PRIMARY KEY,
-                favorite BOOL NOT NULL DEFAULT FALSE,
-                metadata TEXT NOT NULL
-            )",
-            Self::TABLE_NAME
-        ))
-        .execute(&self.pool)
-        .await
-        .expect("failed to create artists table");
+        setup_table::<Self::Item>(&self.pool, Self::TABLE_NAME, |release| &release.title).await?;
 
         Ok(())
     }
@@ -54,10 +134,12 @@ impl Repository for ReleaseRepository {
         item: Self::Item,
     ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
         let catalog_item: CatalogItem<Self::Item> = sqlx::query_as(&format!(
-            "INSERT INTO {} (metadata) VALUES ($1) RETURNING id, favorite, metadata",
+            "INSERT INTO {} (metadata, sort_key, created_at, updated_at)
+             VALUES ($1, $2, unixepoch(), unixepoch()) RETURNING id, favorite, metadata",
             Self::TABLE_NAME
         ))
         .bind(serde_json::to_string(&item).map_err(|e| RepositoryError::ItemCreate(e.to_string()))?)
+        .bind(collation::sort_key(&item.title))
         .fetch_one(&self.pool)
         .await
         .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -70,7 +152,7 @@ impl Repository for ReleaseRepository {
             "SELECT id, favorite, metadata FROM {} WHERE id = $1",
             Self::TABLE_NAME
         ))
-        .bind(&id)
+        .bind(id)
         .fetch_one(&self.pool)
         .await
         .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -83,15 +165,17 @@ impl Repository for ReleaseRepository {
         item: CatalogItem<Self::Item>,
     ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
         sqlx::query(&format!(
-            "UPDATE {} SET metadata = $1, favorite = $2 WHERE id = $3",
+            "UPDATE {} SET metadata = $1, favorite = $2, sort_key = $3, updated_at = unixepoch()
+             WHERE id = $4",
             Self::TABLE_NAME
         ))
         .bind(
             &serde_json::to_string(&item.metadata)
                 .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?,
         )
-        .bind(&item.favorite)
-        .bind(&item.id)
+        .bind(item.favorite)
+        .bind(collation::sort_key(&item.metadata.title))
+        .bind(item.id)
         .execute(&self.pool)
         .await
         .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -101,7 +185,7 @@ impl Repository for ReleaseRepository {
 
     async fn delete(&mut self, id: &i64) -> Result<(), RepositoryError> {
         sqlx::query(&format!("DELETE FROM {} WHERE id = $1", Self::TABLE_NAME))
-            .bind(&id)
+            .bind(id)
             .execute(&self.pool)
             .await
             .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -109,46 +193,138 @@ impl Repository for ReleaseRepository {
         Ok(())
     }
 
-    async fn find(
+    async fn find_page(
         &self,
         filter: Self::Filter,
+        page: Page<Self::Sort>,
     ) -> Result<Vec<CatalogItem<Self::Item>>, RepositoryError> {
-        let mut sql = format!("SELECT id, favorite, metadata FROM {}", Self::TABLE_NAME);
-        let mut conditions: Vec<String> = Vec::new();
+        select_page(
+            &self.pool,
+            Self::TABLE_NAME,
+            Self::conditions(&filter),
+            page,
+        )
+        .await
+    }
 
-        if filter.title.is_some() {
-            conditions.push(format!("metadata->>'title' LIKE ${}", conditions.len() + 1));
-        }
-        if filter.artist.is_some() {
-            conditions.push(format!(
-                "metadata->>'artist' LIKE ${}",
-                conditions.len() + 1
-            ));
-        }
+    async fn count(&self, filter: Self::Filter) -> Result<i64, RepositoryError> {
+        select_count(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
 
-        if !conditions.is_empty() {
-            sql.push_str(" WHERE ");
-            sql.push_str(&conditions.join(" AND "));
-        }
+    async fn initials(&self, filter: Self::Filter) -> Result<Vec<(String, i64)>, RepositoryError> {
+        select_initials(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
+}
 
-        let mut query = sqlx::query_as::<_, CatalogItem<Self::Item>>(&sql);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{RepositoryContext, SortDirection};
 
-        if let Some(title) = &filter.title {
-            query = query.bind(format!("%{}%", title));
+    fn release(title: &str, genre: &str, year: Option<i32>) -> Release {
+        Release {
+            title: title.to_string(),
+            genre: Some(genre.to_string()),
+            year,
+            ..Default::default()
         }
-        if let Some(artist) = &filter.artist {
-            query = query.bind(format!("%{}%", artist));
+    }
+
+    #[tokio::test]
+    async fn releases_are_found_by_genre_and_years_and_counted_by_decade() {
+        let context = RepositoryContext::in_memory().await;
+        let mut releases = context.release.lock().await;
+
+        for item in [
+            release("Dummy", "Trip Hop", Some(1994)),
+            release("Mezzanine", "trip hop", Some(1998)),
+            release("Kid A", "Electronic", Some(2000)),
+            release("Untitled", "Electronic", None),
+        ] {
+            releases.create(item).await.unwrap();
         }
 
-        let all: Vec<(String,)> = sqlx::query_as("SELECT metadata FROM releases")
-            .fetch_all(&self.pool)
+        let titles = |found: Vec<CatalogItem<Release>>| -> Vec<String> {
+            found.into_iter().map(|item| item.metadata.title).collect()
+        };
+
+        let trip_hop = ReleaseFilter {
+            genre: Some("TRIP HOP".to_string()),
+            ..Default::default()
+        };
+        let found = releases.find_page(trip_hop, Page::default()).await.unwrap();
+        assert_eq!(titles(found), ["Dummy", "Mezzanine"]);
+
+        let nineties = || ReleaseFilter {
+            years: Some(1990..=1999),
+            ..Default::default()
+        };
+        let by_year = Page {
+            sort: ReleaseSort::Year,
+            direction: SortDirection::Descending,
+            ..Default::default()
+        };
+        let found = releases.find_page(nineties(), by_year).await.unwrap();
+        assert_eq!(titles(found), ["Mezzanine", "Dummy"]);
+        assert_eq!(releases.count(nineties()).await.unwrap(), 2);
+
+        let electronic = ReleaseFilter {
+            genre: Some("Electronic".to_string()),
+            years: Some(2000..=2009),
+            ..Default::default()
+        };
+        assert_eq!(releases.count(electronic).await.unwrap(), 1);
+
+        assert_eq!(releases.decades().await.unwrap(), [(1990, 2), (2000, 1)]);
+    }
+
+    #[tokio::test]
+    async fn recently_added_and_favorite_releases_are_listed() {
+        let context = RepositoryContext::in_memory().await;
+        let mut releases = context.release.lock().await;
+
+        let old = releases.create(release("Old", "Rock", None)).await.unwrap();
+        let mut loved = releases
+            .create(release("Loved", "Rock", None))
+            .await
+            .unwrap();
+        releases.create(release("New", "Rock", None)).await.unwrap();
+
+        // Sync found the first release long after the others.
+        sqlx::query("UPDATE releases SET created_at = created_at + 3600 WHERE id = ?")
+            .bind(old.id)
+            .execute(&releases.pool)
             .await
             .unwrap();
-        println!("All releases: {:?}", all);
+        loved.favorite = true;
+        releases.update(loved).await.unwrap();
 
-        query
-            .fetch_all(&self.pool)
+        let recent = Page {
+            sort: ReleaseSort::Added,
+            direction: SortDirection::Descending,
+            ..Default::default()
+        };
+        let found = releases
+            .find_page(ReleaseFilter::default(), recent)
             .await
-            .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+            .unwrap();
+        let titles: Vec<String> = found.into_iter().map(|item| item.metadata.title).collect();
+        assert_eq!(titles, ["Old", "New", "Loved"]);
+
+        let favorites = ReleaseFilter {
+            favorite: Some(true),
+            ..Default::default()
+        };
+        let found = releases
+            .find_page(favorites, Page::default())
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].metadata.title, "Loved");
+        let others = ReleaseFilter {
+            favorite: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(releases.count(others).await.unwrap(), 2);
     }
 }
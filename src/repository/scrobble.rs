@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use chrono::DateTime;
+use sqlx::{Row, SqlitePool};
+
+use crate::{model::listen::Listen, repository::RepositoryError};
+
+/// Listens waiting to be submitted, kept on disk since the device is offline most of the time.
+#[derive(Debug, Clone)]
+pub struct ScrobbleRepository {
+    pool: SqlitePool,
+}
+
+impl ScrobbleRepository {
+    const TABLE_NAME: &'static str = "scrobbles";
+
+    pub async fn new(pool: SqlitePool) -> Result<Self, RepositoryError> {
+        let repository = Self { pool };
+        repository.setup().await?;
+        Ok(repository)
+    }
+
+    async fn setup(&self) -> Result<(), RepositoryError> {
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY,
+                title TEXT NOT NULL,
+                artist TEXT NOT NULL,
+                release TEXT,
+                duration INTEGER NOT NULL,
+                listened_at INTEGER NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0
+            )",
+            Self::TABLE_NAME
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+        Ok(())
+    }
+
+    pub async fn push(&mut self, listen: &Listen) -> Result<i64, RepositoryError> {
+        sqlx::query_scalar(&format!(
+            "INSERT INTO {} (title, artist, release, duration, listened_at)
+             VALUES ($1, $2, $3, $4, $5) RETURNING id",
+            Self::TABLE_NAME
+        ))
+        .bind(&listen.title)
+        .bind(&listen.artist)
+        .bind(&listen.release)
+        .bind(listen.duration.as_secs() as i64)
+        .bind(listen.listened_at.timestamp())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))
+    }
+
+    /// The oldest `limit` listens with their ids.
+    pub async fn pending(&self, limit: usize) -> Result<Vec<(i64, Listen)>, RepositoryError> {
+        let rows = sqlx::query(&format!(
+            "SELECT id, title, artist, release, duration, listened_at FROM {}
+             ORDER BY listened_at, id LIMIT $1",
+            Self::TABLE_NAME
+        ))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let listened_at: i64 = row.try_get("listened_at")?;
+                let duration: i64 = row.try_get("duration")?;
+
+                Ok((
+                    row.try_get("id")?,
+                    Listen {
+                        title: row.try_get("title")?,
+                        artist: row.try_get("artist")?,
+                        release: row.try_get("release")?,
+                        duration: Duration::from_secs(duration.max(0) as u64),
+                        listened_at: DateTime::from_timestamp(listened_at, 0).unwrap_or_default(),
+                        ..Default::default()
+                    },
+                ))
+            })
+            .collect::<Result<_, sqlx::Error>>()
+            .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    pub async fn count(&self) -> Result<i64, RepositoryError> {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", Self::TABLE_NAME))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    /// Drops listens once they've been accepted.
+    pub async fn remove(&mut self, ids: &[i64]) -> Result<(), RepositoryError> {
+        for id in ids {
+            sqlx::query(&format!("DELETE FROM {} WHERE id = $1", Self::TABLE_NAME))
+                .bind(id)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Counts a rejected submission against each listen, giving up on those rejected
+    /// `max_attempts` times.
+    pub async fn reject(&mut self, ids: &[i64], max_attempts: u32) -> Result<(), RepositoryError> {
+        for id in ids {
+            sqlx::query(&format!(
+                "UPDATE {} SET attempts = attempts + 1 WHERE id = $1",
+                Self::TABLE_NAME
+            ))
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+        }
+
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE attempts >= $1",
+            Self::TABLE_NAME
+        ))
+        .bind(max_attempts)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(())
+    }
+}
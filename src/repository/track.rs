@@ -1,12 +1,27 @@
+use std::{ops::RangeInclusive, path::Path, time::Duration};
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 
 use crate::{
-    model::{CatalogItem, track::Track},
-    repository::{Repository, RepositoryError},
+    model::{
+        CatalogItem, collation,
+        track::{MediaKind, Track},
+    },
+    repository::{
+        Conditions, Page, Repository, RepositoryError, SortKey, select_count, select_initials,
+        select_page, setup_table,
+    },
 };
 
+/// Where a track starts within its file in milliseconds, -1 for one that is the whole file.
+/// Tracks split from one file by a CUE sheet share its path, so this tells them apart.
+const START_MILLIS: &str = "COALESCE(
+    (metadata->'start'->>'secs') * 1000 + (metadata->'start'->>'nanos') / 1000000,
+    -1
+)";
+
 #[derive(Debug, Clone)]
 pub struct TrackRepository {
     pool: SqlitePool,
@@ -18,13 +33,359 @@ impl TrackRepository {
         repository.setup().await?;
         Ok(repository)
     }
+
+    fn conditions(filter: &TrackFilter) -> Conditions {
+        let mut conditions = Vec::new();
+        let mut binds = Vec::new();
+
+        if filter.name.is_some() {
+            conditions.push("metadata->>'name' LIKE ?");
+        }
+        if filter.artist.is_some() {
+            conditions.push("metadata->>'artist' LIKE ?");
+        }
+        if filter.release.is_some() {
+            conditions.push("metadata->>'release' LIKE ?");
+        }
+        if filter.release_id.is_some() {
+            conditions.push("metadata->>'release_id' = CAST(? AS INTEGER)");
+        }
+        if filter.book_id.is_some() {
+            conditions.push("metadata->>'book_id' = CAST(? AS INTEGER)");
+        }
+        // Tracks stored before spoken word was told apart are all music.
+        if filter.kind.is_some() {
+            conditions.push("COALESCE(metadata->>'kind', 'music') = ?");
+        }
+        if filter.featuring.is_some() {
+            conditions.push(
+                "id IN (
+                    SELECT track_artists.track_id FROM track_artists
+                    JOIN artists ON artists.id = track_artists.artist_id
+                    WHERE track_artists.position > 0 AND artists.metadata->>'name' = ?
+                )",
+            );
+        }
+        if filter.genre.is_some() {
+            conditions.push("metadata->>'genre' = ? COLLATE NOCASE");
+        }
+        if filter.years.is_some() {
+            conditions.push("metadata->>'year' BETWEEN CAST(? AS INTEGER) AND CAST(? AS INTEGER)");
+        }
+        match filter.played {
+            Some(true) => {
+                conditions.push("EXISTS (SELECT 1 FROM plays WHERE plays.track_id = tracks.id)")
+            }
+            Some(false) => {
+                conditions.push("NOT EXISTS (SELECT 1 FROM plays WHERE plays.track_id = tracks.id)")
+            }
+            None => {}
+        }
+        if filter.lyrics.is_some() {
+            conditions.push("id IN (SELECT rowid FROM track_lyrics WHERE track_lyrics MATCH ?)");
+        }
+        match filter.favorite {
+            Some(true) => conditions.push("favorite = TRUE"),
+            Some(false) => conditions.push("favorite = FALSE"),
+            None => {}
+        }
+
+        if let Some(name) = &filter.name {
+            binds.push(format!("%{}%", name));
+        }
+        if let Some(artist) = &filter.artist {
+            binds.push(format!("%{}%", artist));
+        }
+        if let Some(release) = &filter.release {
+            binds.push(format!("%{}%", release));
+        }
+        if let Some(release_id) = &filter.release_id {
+            binds.push(release_id.to_string());
+        }
+        if let Some(book_id) = &filter.book_id {
+            binds.push(book_id.to_string());
+        }
+        if let Some(kind) = &filter.kind {
+            binds.push(kind.as_str().to_string());
+        }
+        if let Some(featuring) = &filter.featuring {
+            binds.push(featuring.clone());
+        }
+        if let Some(genre) = &filter.genre {
+            binds.push(genre.clone());
+        }
+        if let Some(years) = &filter.years {
+            binds.push(years.start().to_string());
+            binds.push(years.end().to_string());
+        }
+        if let Some(lyrics) = &filter.lyrics {
+            // Quoted as a phrase so punctuation isn't read as FTS query syntax.
+            binds.push(format!("\"{}\"", lyrics.replace('"', "\"\"")));
+        }
+
+        (conditions, binds)
+    }
+
+    /// Combined length of every track in the catalog.
+    pub async fn total_duration(&self) -> Result<Duration, RepositoryError> {
+        let seconds: i64 = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(SUM(metadata->'duration'->>'secs'), 0) FROM {}",
+            Self::TABLE_NAME
+        ))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))?;
+
+        Ok(Duration::from_secs(seconds.max(0) as u64))
+    }
+
+    /// The first track filed on each release, in one query rather than one per release.
+    pub async fn first_of_releases(&self) -> Result<Vec<CatalogItem<Track>>, RepositoryError> {
+        sqlx::query_as(&format!(
+            "SELECT id, favorite, metadata FROM {table} WHERE id IN (
+                SELECT MIN(id) FROM {table} WHERE metadata->>'release_id' IS NOT NULL
+                GROUP BY metadata->>'release_id'
+            )",
+            table = Self::TABLE_NAME
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    /// The track read from `path`, which is stored exactly as found during sync.
+    pub async fn find_by_path(
+        &self,
+        path: &str,
+    ) -> Result<Option<CatalogItem<Track>>, RepositoryError> {
+        sqlx::query_as(&format!(
+            "SELECT id, favorite, metadata FROM {} WHERE metadata->>'path' = $1 LIMIT 1",
+            Self::TABLE_NAME
+        ))
+        .bind(path)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    /// The stored track read from the same file, and the same part of it, as `track`.
+    pub async fn find_by_source(
+        &self,
+        track: &Track,
+    ) -> Result<Option<CatalogItem<Track>>, RepositoryError> {
+        sqlx::query_as(&format!(
+            "SELECT id, favorite, metadata FROM {} WHERE metadata->>'path' = $1
+             AND {START_MILLIS} = $2 LIMIT 1",
+            Self::TABLE_NAME
+        ))
+        .bind(track.path.to_string_lossy())
+        .bind(start_millis(track))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    /// The id of the track read from `path`, starting at `start` within it.
+    pub async fn id_of_source(
+        &self,
+        path: &Path,
+        start: Option<Duration>,
+    ) -> Result<Option<i64>, RepositoryError> {
+        sqlx::query_scalar(&format!(
+            "SELECT id FROM {} WHERE metadata->>'path' = $1 AND {START_MILLIS} = $2 LIMIT 1",
+            Self::TABLE_NAME
+        ))
+        .bind(path.to_string_lossy())
+        .bind(start.map_or(-1, |start| start.as_millis() as i64))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    /// A track whose title, and artist when given, match exactly apart from case.
+    pub async fn find_by_tags(
+        &self,
+        title: &str,
+        artist: Option<&str>,
+    ) -> Result<Option<CatalogItem<Track>>, RepositoryError> {
+        sqlx::query_as(&format!(
+            "SELECT id, favorite, metadata FROM {} WHERE metadata->>'title' = $1 COLLATE NOCASE
+             AND ($2 IS NULL OR metadata->>'artist' = $2 COLLATE NOCASE)
+             ORDER BY id LIMIT 1",
+            Self::TABLE_NAME
+        ))
+        .bind(title)
+        .bind(artist)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+
+    /// Stores where playback of `track` was left off, `None` once it's been played through.
+    pub async fn set_resume(
+        &mut self,
+        track: &Track,
+        position: Option<Duration>,
+    ) -> Result<(), RepositoryError> {
+        self.set_field(track, "$.resume", &position).await
+    }
+
+    /// Stores the speed `track` plays at, `None` going back to the one for its kind.
+    pub async fn set_speed(
+        &mut self,
+        track: &Track,
+        speed: Option<f32>,
+    ) -> Result<(), RepositoryError> {
+        self.set_field(track, "$.speed", &speed).await
+    }
+
+    /// Sets one field of the stored `track` without reading it back first.
+    async fn set_field(
+        &mut self,
+        track: &Track,
+        field: &str,
+        value: &impl Serialize,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(&format!(
+            "UPDATE {} SET metadata = json_set(metadata, $1, json($2)),
+                updated_at = unixepoch()
+             WHERE metadata->>'path' = $3 AND {START_MILLIS} = $4",
+            Self::TABLE_NAME
+        ))
+        .bind(field)
+        .bind(serde_json::to_string(value).map_err(|e| RepositoryError::ItemCreate(e.to_string()))?)
+        .bind(track.path.to_string_lossy())
+        .bind(start_millis(track))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Replaces the searchable lyrics of the track with `id`.
+    pub async fn index_lyrics(&mut self, id: i64, lyrics: &str) -> Result<(), RepositoryError> {
+        sqlx::query("DELETE FROM track_lyrics WHERE rowid = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        sqlx::query("INSERT INTO track_lyrics (rowid, lyrics) VALUES ($1, $2)")
+            .bind(id)
+            .bind(lyrics)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Replaces the artists credited on the track with `id`, the main artist first.
+    pub async fn set_artists(
+        &mut self,
+        id: i64,
+        artist_ids: &[i64],
+    ) -> Result<(), RepositoryError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        sqlx::query("DELETE FROM track_artists WHERE track_id = $1")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        for (position, artist_id) in artist_ids.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO track_artists (track_id, position, artist_id) VALUES ($1, $2, $3)",
+            )
+            .bind(id)
+            .bind(position as i64)
+            .bind(artist_id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))
+    }
+
+    /// How many tracks credit the artist with `artist_id`.
+    pub async fn credits(&self, artist_id: i64) -> Result<i64, RepositoryError> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM track_artists WHERE artist_id = $1")
+            .bind(artist_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+    }
+}
+
+/// The start of `track` as matched against [`START_MILLIS`].
+fn start_millis(track: &Track) -> i64 {
+    track
+        .start
+        .map(|start| start.as_millis() as i64)
+        .unwrap_or(-1)
 }
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct TrackFilter {
     pub name: Option<String>,
     pub release: Option<String>,
+    /// Catalog id of the release, the way to list one release's tracks exactly.
+    pub release_id: Option<i64>,
+    /// Catalog id of the book, listing one book's tracks.
+    pub book_id: Option<i64>,
+    /// Music or spoken word.
+    pub kind: Option<MediaKind>,
     pub artist: Option<String>,
+    /// An artist credited on the track other than first, such as one it features.
+    pub featuring: Option<String>,
+    pub genre: Option<String>,
+    /// Tracks from any of these years.
+    pub years: Option<RangeInclusive<i32>>,
+    /// Whether the track is in the listening history.
+    pub played: Option<bool>,
+    /// Words appearing in the track's lyrics.
+    pub lyrics: Option<String>,
+    pub favorite: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum TrackSort {
+    #[default]
+    Title,
+    Release,
+    Artist,
+    /// Position on the release, by disc then track.
+    Number,
+    /// When the track was last listened to, never played tracks first.
+    Played,
+    Id,
+}
+
+impl SortKey for TrackSort {
+    fn expression(&self) -> &'static str {
+        match self {
+            TrackSort::Title => "sort_key COLLATE library",
+            TrackSort::Release => "metadata->>'release'",
+            TrackSort::Artist => "metadata->>'artist'",
+            TrackSort::Number => {
+                "COALESCE(metadata->>'disc_number', 1) * 10000 + COALESCE(metadata->>'track_number', 0)"
+            }
+            TrackSort::Played => {
+                "(SELECT MAX(played_at) FROM plays WHERE plays.track_id = tracks.id)"
+            }
+            TrackSort::Id => "id",
+        }
+    }
 }
 
 #[async_trait]
@@ -33,14 +394,15 @@ impl Repository for TrackRepository {
 
     type Item = Track;
     type Filter = TrackFilter;
+    type Sort = TrackSort;
 
     async fn setup(&mut self) -> Result<(), RepositoryError> {
+        setup_table::<Self::Item>(&self.pool, Self::TABLE_NAME, |track| &track.title).await?;
+
+        // A track is a file or a part of one, the same title can be on a release more than once,
+        // such as on each disc of a set. The index on its tags turned those away.
         sqlx::query(&format!(
-            "CREATE TABLE IF NOT EXISTS {} (
-                id INTEGER PRIMARY KEY,
-                favorite BOOL NOT NULL DEFAULT FALSE,
-                metadata TEXT NOT NULL
-            )",
+            "DROP INDEX IF EXISTS idx_{}_unique",
             Self::TABLE_NAME
         ))
         .execute(&self.pool)
@@ -48,18 +410,51 @@ impl Repository for TrackRepository {
         .map_err(|_| RepositoryError::Setup)?;
 
         sqlx::query(&format!(
-            "CREATE UNIQUE INDEX IF NOT EXISTS idx_{}_unique ON {}(
-                (metadata->>'title'),
-                (metadata->>'artist'),
-                (metadata->>'release')
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_{table}_source ON {table}(
+                (metadata->>'path'),
+                ({START_MILLIS})
             )",
-            Self::TABLE_NAME,
-            Self::TABLE_NAME
+            table = Self::TABLE_NAME
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+        // Sync looks every file up by its path, as do playlist entries.
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS idx_{table}_path ON {table}((metadata->>'path'))",
+            table = Self::TABLE_NAME
         ))
         .execute(&self.pool)
         .await
         .map_err(|_| RepositoryError::Setup)?;
 
+        // Full text index over lyrics, keyed by track id.
+        sqlx::query("CREATE VIRTUAL TABLE IF NOT EXISTS track_lyrics USING fts5(lyrics)")
+            .execute(&self.pool)
+            .await
+            .map_err(|_| RepositoryError::Setup)?;
+
+        // Everyone credited on each track, in credit order.
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS track_artists (
+                track_id INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                artist_id INTEGER NOT NULL,
+                PRIMARY KEY (track_id, position)
+            )",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_track_artists_artist ON track_artists(artist_id)",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RepositoryError::Setup)?;
+
         Ok(())
     }
 
@@ -68,10 +463,12 @@ impl Repository for TrackRepository {
         item: Self::Item,
     ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
         let catalog_item: CatalogItem<Self::Item> = sqlx::query_as(&format!(
-            "INSERT INTO {} (metadata) VALUES ($1) RETURNING id, favorite, metadata",
+            "INSERT INTO {} (metadata, sort_key, created_at, updated_at)
+             VALUES ($1, $2, unixepoch(), unixepoch()) RETURNING id, favorite, metadata",
             Self::TABLE_NAME
         ))
         .bind(serde_json::to_string(&item).map_err(|e| RepositoryError::ItemCreate(e.to_string()))?)
+        .bind(collation::sort_key(&item.title))
         .fetch_one(&self.pool)
         .await
         .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -84,7 +481,7 @@ impl Repository for TrackRepository {
             "SELECT id, favorite, metadata FROM {} WHERE id = $1",
             Self::TABLE_NAME
         ))
-        .bind(&id)
+        .bind(id)
         .fetch_one(&self.pool)
         .await
         .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -97,15 +494,17 @@ impl Repository for TrackRepository {
         item: CatalogItem<Self::Item>,
     ) -> Result<CatalogItem<Self::Item>, RepositoryError> {
         sqlx::query(&format!(
-            "UPDATE {} SET metadata = $1, favorite = $2 WHERE id = $3",
+            "UPDATE {} SET metadata = $1, favorite = $2, sort_key = $3, updated_at = unixepoch()
+             WHERE id = $4",
             Self::TABLE_NAME
         ))
         .bind(
             &serde_json::to_string(&item.metadata)
                 .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?,
         )
-        .bind(&item.favorite)
-        .bind(&item.id)
+        .bind(item.favorite)
+        .bind(collation::sort_key(&item.metadata.title))
+        .bind(item.id)
         .execute(&self.pool)
         .await
         .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -115,7 +514,19 @@ impl Repository for TrackRepository {
 
     async fn delete(&mut self, id: &i64) -> Result<(), RepositoryError> {
         sqlx::query(&format!("DELETE FROM {} WHERE id = $1", Self::TABLE_NAME))
-            .bind(&id)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        sqlx::query("DELETE FROM track_lyrics WHERE rowid = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
+
+        sqlx::query("DELETE FROM track_artists WHERE track_id = $1")
+            .bind(id)
             .execute(&self.pool)
             .await
             .map_err(|e| RepositoryError::ItemCreate(e.to_string()))?;
@@ -123,43 +534,109 @@ impl Repository for TrackRepository {
         Ok(())
     }
 
-    async fn find(
+    async fn find_page(
         &self,
         filter: Self::Filter,
+        page: Page<Self::Sort>,
     ) -> Result<Vec<CatalogItem<Self::Item>>, RepositoryError> {
-        let mut sql = format!("SELECT id, favorite, metadata FROM {}", Self::TABLE_NAME);
-        let mut conditions: Vec<String> = Vec::new();
+        select_page(
+            &self.pool,
+            Self::TABLE_NAME,
+            Self::conditions(&filter),
+            page,
+        )
+        .await
+    }
 
-        if filter.name.is_some() {
-            conditions.push("metadata->>'name' LIKE ?".into());
-        }
-        if filter.artist.is_some() {
-            conditions.push("metadata->>'artist' LIKE ?".into());
-        }
-        if filter.release.is_some() {
-            conditions.push("metadata->>'release' LIKE ?".into());
-        }
+    async fn count(&self, filter: Self::Filter) -> Result<i64, RepositoryError> {
+        select_count(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
 
-        if !conditions.is_empty() {
-            sql.push_str(" WHERE ");
-            sql.push_str(&conditions.join(" AND "));
-        }
+    async fn initials(&self, filter: Self::Filter) -> Result<Vec<(String, i64)>, RepositoryError> {
+        select_initials(&self.pool, Self::TABLE_NAME, Self::conditions(&filter)).await
+    }
+}
 
-        let mut query = sqlx::query_as::<_, CatalogItem<Self::Item>>(&sql);
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
 
-        if let Some(name) = &filter.name {
-            query = query.bind(format!("%{}%", name));
-        }
-        if let Some(artist) = &filter.artist {
-            query = query.bind(format!("%{}%", artist));
-        }
-        if let Some(release) = &filter.release {
-            query = query.bind(format!("%{}%", release));
+    use super::*;
+    use crate::repository::RepositoryContext;
+
+    fn track(title: &str, path: &str, disc: u32) -> Track {
+        Track {
+            title: title.to_string(),
+            artist: Some("Artist".to_string()),
+            release: Some("Set".to_string()),
+            path: PathBuf::from(path),
+            disc_number: Some(disc),
+            ..Default::default()
         }
+    }
 
-        query
-            .fetch_all(&self.pool)
+    #[tokio::test]
+    async fn a_title_can_repeat_on_another_disc_but_a_file_is_stored_once() {
+        let context = RepositoryContext::in_memory().await;
+        let mut tracks = context.track.lock().await;
+
+        tracks
+            .create(track("Intro", "set/cd1/01.flac", 1))
             .await
-            .map_err(|e| RepositoryError::ItemRead(e.to_string()))
+            .unwrap();
+        tracks
+            .create(track("Intro", "set/cd2/01.flac", 2))
+            .await
+            .unwrap();
+        assert!(
+            tracks
+                .create(track("Other", "set/cd2/01.flac", 2))
+                .await
+                .is_err()
+        );
+
+        assert_eq!(tracks.count(TrackFilter::default()).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn path_lookups_use_the_index() {
+        let context = RepositoryContext::in_memory().await;
+        let tracks = context.track.lock().await;
+
+        let plan: Vec<(i64, i64, i64, String)> = sqlx::query_as(
+            "EXPLAIN QUERY PLAN SELECT id FROM tracks WHERE metadata->>'path' = 'a.flac'",
+        )
+        .fetch_all(&tracks.pool)
+        .await
+        .unwrap();
+
+        assert!(
+            plan.iter()
+                .any(|(.., detail)| detail.contains("idx_tracks_path"))
+        );
+    }
+
+    #[tokio::test]
+    async fn a_resume_position_is_kept_for_the_track_split_from_a_file_only() {
+        let context = RepositoryContext::in_memory().await;
+        let mut tracks = context.track.lock().await;
+
+        let split = |title: &str, start: u64| Track {
+            start: Some(Duration::from_secs(start)),
+            ..track(title, "mix.flac", 1)
+        };
+        let first = tracks.create(split("One", 0)).await.unwrap();
+        let second = tracks.create(split("Two", 600)).await.unwrap();
+
+        let position = Some(Duration::from_millis(754_250));
+        tracks.set_resume(&second.metadata, position).await.unwrap();
+        assert_eq!(
+            tracks.read(&second.id).await.unwrap().metadata.resume,
+            position
+        );
+        assert_eq!(tracks.read(&first.id).await.unwrap().metadata.resume, None);
+
+        tracks.set_resume(&second.metadata, None).await.unwrap();
+        assert_eq!(tracks.read(&second.id).await.unwrap().metadata.resume, None);
     }
 }
@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use md5::{Digest, Md5};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    model::listen::Listen,
+    scrobble::{ScrobbleClient, ScrobbleError},
+};
+
+/// Last.fm error codes meaning the service is down rather than the request being wrong.
+const TEMPORARY_ERRORS: [u32; 3] = [11, 16, 29];
+/// Last.fm error codes for a bad API key, signature or session.
+const AUTH_ERRORS: [u32; 5] = [4, 9, 10, 13, 26];
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: u32,
+    message: String,
+}
+
+/// The Last.fm `track.scrobble` API, signed with the application secret.
+///
+/// Needs a session key, which comes from authorising the application against the account
+/// beforehand.
+pub struct LastFm {
+    http: reqwest::Client,
+    url: String,
+    api_key: String,
+    api_secret: String,
+    session_key: String,
+}
+
+impl LastFm {
+    pub fn new(
+        http: reqwest::Client,
+        url: String,
+        api_key: String,
+        api_secret: String,
+        session_key: String,
+    ) -> Self {
+        Self {
+            http,
+            url,
+            api_key,
+            api_secret,
+            session_key,
+        }
+    }
+
+    pub fn default_url() -> String {
+        "https://ws.audioscrobbler.com/2.0/".to_string()
+    }
+
+    /// Hash of every parameter, sorted by name, followed by the secret.
+    fn signature(&self, params: &[(String, String)]) -> String {
+        let mut sorted: Vec<_> = params.iter().collect();
+        sorted.sort();
+
+        let mut hasher = Md5::new();
+        for (name, value) in sorted {
+            hasher.update(name);
+            hasher.update(value);
+        }
+        hasher.update(&self.api_secret);
+
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[async_trait]
+impl ScrobbleClient for LastFm {
+    fn batch_size(&self) -> usize {
+        50
+    }
+
+    async fn submit(&self, listens: &[Listen]) -> Result<(), ScrobbleError> {
+        let mut params = vec![
+            ("method".to_string(), "track.scrobble".to_string()),
+            ("api_key".to_string(), self.api_key.clone()),
+            ("sk".to_string(), self.session_key.clone()),
+        ];
+
+        for (index, listen) in listens.iter().enumerate() {
+            params.push((format!("artist[{}]", index), listen.artist.clone()));
+            params.push((format!("track[{}]", index), listen.title.clone()));
+            params.push((
+                format!("timestamp[{}]", index),
+                listen.listened_at.timestamp().to_string(),
+            ));
+            params.push((
+                format!("duration[{}]", index),
+                listen.duration.as_secs().to_string(),
+            ));
+            if let Some(release) = &listen.release {
+                params.push((format!("album[{}]", index), release.clone()));
+            }
+        }
+
+        let signature = self.signature(&params);
+        params.push(("api_sig".to_string(), signature));
+        // Not part of the signature.
+        params.push(("format".to_string(), "json".to_string()));
+
+        let response = self.http.post(&self.url).form(&params).send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        // Errors come back as JSON with a code saying whether it's worth trying again.
+        let body = response.text().await.unwrap_or_default();
+        match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(e) if TEMPORARY_ERRORS.contains(&e.error) => {
+                Err(ScrobbleError::Unavailable(e.message))
+            }
+            Ok(e) if AUTH_ERRORS.contains(&e.error) => Err(ScrobbleError::Unauthorized(e.message)),
+            Ok(e) => Err(ScrobbleError::Rejected(e.message)),
+            Err(_) if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN => {
+                Err(ScrobbleError::Unauthorized(format!("{}: {}", status, body)))
+            }
+            Err(_) if status.is_server_error() => {
+                Err(ScrobbleError::Unavailable(format!("{}: {}", status, body)))
+            }
+            Err(_) => Err(ScrobbleError::Rejected(format!("{}: {}", status, body))),
+        }
+    }
+}
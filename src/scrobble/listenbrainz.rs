@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+use crate::{
+    model::listen::Listen,
+    scrobble::{ScrobbleClient, ScrobbleError, check},
+};
+
+/// The ListenBrainz submission API, also served by Maloja and other compatible servers.
+pub struct ListenBrainz {
+    http: reqwest::Client,
+    url: String,
+    token: String,
+}
+
+impl ListenBrainz {
+    pub fn new(http: reqwest::Client, url: String, token: String) -> Self {
+        Self { http, url, token }
+    }
+
+    pub fn default_url() -> String {
+        "https://api.listenbrainz.org".to_string()
+    }
+}
+
+#[async_trait]
+impl ScrobbleClient for ListenBrainz {
+    fn batch_size(&self) -> usize {
+        100
+    }
+
+    async fn submit(&self, listens: &[Listen]) -> Result<(), ScrobbleError> {
+        let payload: Vec<_> = listens.iter().map(listen_json).collect();
+
+        // "single" is only allowed for exactly one listen.
+        let listen_type = match payload.len() {
+            1 => "single",
+            _ => "import",
+        };
+
+        let response = self
+            .http
+            .post(format!(
+                "{}/1/submit-listens",
+                self.url.trim_end_matches('/')
+            ))
+            .header("Authorization", format!("Token {}", self.token))
+            .json(&json!({ "listen_type": listen_type, "payload": payload }))
+            .send()
+            .await?;
+
+        check(response).await.map(|_| ())
+    }
+}
+
+/// A listen in the ListenBrainz format, as submitted and as found in their exports.
+pub fn listen_json(listen: &Listen) -> Value {
+    json!({
+        "listened_at": listen.listened_at.timestamp(),
+        "track_metadata": {
+            "artist_name": listen.artist,
+            "track_name": listen.title,
+            "release_name": listen.release,
+            "additional_info": {
+                "duration_ms": listen.duration.as_millis() as u64,
+            },
+        },
+    })
+}
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::{
+    model::listen::Listen,
+    scrobble::{ScrobbleClient, ScrobbleError, check},
+};
+
+/// Maloja's native API, which takes one scrobble per request.
+pub struct Maloja {
+    http: reqwest::Client,
+    url: String,
+    key: String,
+}
+
+impl Maloja {
+    pub fn new(http: reqwest::Client, url: String, key: String) -> Self {
+        Self { http, url, key }
+    }
+}
+
+#[async_trait]
+impl ScrobbleClient for Maloja {
+    fn batch_size(&self) -> usize {
+        1
+    }
+
+    async fn submit(&self, listens: &[Listen]) -> Result<(), ScrobbleError> {
+        for listen in listens {
+            let response = self
+                .http
+                .post(format!(
+                    "{}/apis/mlj_1/newscrobble",
+                    self.url.trim_end_matches('/')
+                ))
+                .json(&json!({
+                    "key": self.key,
+                    "artists": [listen.artist],
+                    "title": listen.title,
+                    "album": listen.release,
+                    "duration": listen.duration.as_secs(),
+                    "time": listen.listened_at.timestamp(),
+                }))
+                .send()
+                .await?;
+
+            check(response).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    model::listen::Listen,
+    scrobble::{lastfm::LastFm, listenbrainz::ListenBrainz, maloja::Maloja},
+};
+
+pub mod lastfm;
+pub mod listenbrainz;
+pub mod maloja;
+
+#[derive(Debug, Error)]
+pub enum ScrobbleError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    /// The server couldn't take the listens right now, they're worth sending again later.
+    #[error("scrobble service unavailable: {0}")]
+    Unavailable(String),
+    #[error("listens rejected: {0}")]
+    Rejected(String),
+    /// The credentials were refused, nothing about the listens themselves is wrong.
+    #[error("scrobble credentials refused: {0}")]
+    Unauthorized(String),
+}
+
+impl ScrobbleError {
+    /// Whether the service couldn't be reached at all, the normal state of affairs away from
+    /// wifi.
+    pub fn is_offline(&self) -> bool {
+        match self {
+            ScrobbleError::Http(e) => e.is_connect() || e.is_timeout(),
+            _ => false,
+        }
+    }
+}
+
+/// Submits listens to a scrobbling service.
+#[async_trait]
+pub trait ScrobbleClient: Send + Sync {
+    /// Most listens accepted in one submission.
+    fn batch_size(&self) -> usize;
+    async fn submit(&self, listens: &[Listen]) -> Result<(), ScrobbleError>;
+}
+
+/// Where listens are sent, picked in settings. Every service takes a base `url` so a
+/// self-hosted or local server can stand in for the public one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "service", rename_all = "snake_case")]
+pub enum ScrobbleTarget {
+    ListenBrainz {
+        #[serde(default = "ListenBrainz::default_url")]
+        url: String,
+        token: String,
+    },
+    LastFm {
+        #[serde(default = "LastFm::default_url")]
+        url: String,
+        api_key: String,
+        api_secret: String,
+        session_key: String,
+    },
+    Maloja {
+        url: String,
+        key: String,
+    },
+}
+
+impl ScrobbleTarget {
+    pub fn client(&self, http: reqwest::Client) -> Arc<dyn ScrobbleClient> {
+        match self.clone() {
+            ScrobbleTarget::ListenBrainz { url, token } => {
+                Arc::new(ListenBrainz::new(http, url, token))
+            }
+            ScrobbleTarget::LastFm {
+                url,
+                api_key,
+                api_secret,
+                session_key,
+            } => Arc::new(LastFm::new(http, url, api_key, api_secret, session_key)),
+            ScrobbleTarget::Maloja { url, key } => Arc::new(Maloja::new(http, url, key)),
+        }
+    }
+}
+
+/// Sorts a failed response into something to retry later or something the server won't take.
+async fn check(response: Response) -> Result<Response, ScrobbleError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let reason = format!("{}: {}", status, response.text().await.unwrap_or_default());
+
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err(ScrobbleError::Unauthorized(reason))
+        }
+        StatusCode::TOO_MANY_REQUESTS => Err(ScrobbleError::Unavailable(reason)),
+        status if status.is_server_error() => Err(ScrobbleError::Unavailable(reason)),
+        _ => Err(ScrobbleError::Rejected(reason)),
+    }
+}
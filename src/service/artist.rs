@@ -2,7 +2,10 @@ use thiserror::Error;
 
 use crate::{
     model::{CatalogItem, artist::Artist},
-    repository::{Repository, RepositoryContext, artist::ArtistFilter},
+    repository::{
+        Page, Repository, RepositoryContext,
+        artist::{ArtistFilter, ArtistSort},
+    },
 };
 
 #[derive(Debug, Error)]
@@ -37,6 +40,7 @@ impl ArtistService {
             .find(ArtistFilter {
                 name: Some(name.to_string()),
                 track: None,
+                favorite: None,
             })
             .await
             .map_err(|e| ArtistServiceError::Internal(e.into()))?;
@@ -59,4 +63,41 @@ impl ArtistService {
             .await
             .map_err(|e| ArtistServiceError::Internal(e.into()))
     }
+
+    pub async fn list_artists_page(
+        &mut self,
+        filter: ArtistFilter,
+        page: Page<ArtistSort>,
+    ) -> Result<Vec<CatalogItem<Artist>>, ArtistServiceError> {
+        self.repository_context
+            .artist
+            .lock()
+            .await
+            .find_page(filter, page)
+            .await
+            .map_err(|e| ArtistServiceError::Internal(e.into()))
+    }
+
+    pub async fn count_artists(&mut self, filter: ArtistFilter) -> Result<i64, ArtistServiceError> {
+        self.repository_context
+            .artist
+            .lock()
+            .await
+            .count(filter)
+            .await
+            .map_err(|e| ArtistServiceError::Internal(e.into()))
+    }
+
+    pub async fn artist_initials(
+        &mut self,
+        filter: ArtistFilter,
+    ) -> Result<Vec<(String, i64)>, ArtistServiceError> {
+        self.repository_context
+            .artist
+            .lock()
+            .await
+            .initials(filter)
+            .await
+            .map_err(|e| ArtistServiceError::Internal(e.into()))
+    }
 }
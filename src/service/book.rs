@@ -0,0 +1,99 @@
+use thiserror::Error;
+
+use crate::{
+    model::{CatalogItem, book::Book},
+    repository::{
+        Page, Repository, RepositoryContext,
+        book::{BookFilter, BookSort},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum BookServiceError {
+    #[error("book was not found")]
+    NotFound,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct BookService {
+    repository_context: RepositoryContext,
+}
+
+impl BookService {
+    pub fn new(repository_context: RepositoryContext) -> BookService {
+        Self { repository_context }
+    }
+}
+
+impl BookService {
+    pub async fn get_book(&mut self, title: &str) -> Result<CatalogItem<Book>, BookServiceError> {
+        let books = self
+            .repository_context
+            .book
+            .lock()
+            .await
+            .find(BookFilter {
+                title: Some(title.to_string()),
+                author: None,
+            })
+            .await
+            .map_err(|e| BookServiceError::Internal(e.into()))?;
+
+        books
+            .first()
+            .cloned()
+            .ok_or_else(|| BookServiceError::NotFound)
+    }
+
+    pub async fn list_books(
+        &mut self,
+        filter: BookFilter,
+    ) -> Result<Vec<CatalogItem<Book>>, BookServiceError> {
+        self.repository_context
+            .book
+            .lock()
+            .await
+            .find(filter)
+            .await
+            .map_err(|e| BookServiceError::Internal(e.into()))
+    }
+
+    pub async fn list_books_page(
+        &mut self,
+        filter: BookFilter,
+        page: Page<BookSort>,
+    ) -> Result<Vec<CatalogItem<Book>>, BookServiceError> {
+        self.repository_context
+            .book
+            .lock()
+            .await
+            .find_page(filter, page)
+            .await
+            .map_err(|e| BookServiceError::Internal(e.into()))
+    }
+
+    pub async fn count_books(&mut self, filter: BookFilter) -> Result<i64, BookServiceError> {
+        self.repository_context
+            .book
+            .lock()
+            .await
+            .count(filter)
+            .await
+            .map_err(|e| BookServiceError::Internal(e.into()))
+    }
+
+    pub async fn book_initials(
+        &mut self,
+        filter: BookFilter,
+    ) -> Result<Vec<(String, i64)>, BookServiceError> {
+        self.repository_context
+            .book
+            .lock()
+            .await
+            .initials(filter)
+            .await
+            .map_err(|e| BookServiceError::Internal(e.into()))
+    }
+}
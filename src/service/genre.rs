@@ -0,0 +1,98 @@
+use thiserror::Error;
+
+use crate::{
+    model::{CatalogItem, genre::Genre},
+    repository::{
+        Page, Repository, RepositoryContext,
+        genre::{GenreFilter, GenreSort},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum GenreServiceError {
+    #[error("genre was not found")]
+    NotFound,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct GenreService {
+    repository_context: RepositoryContext,
+}
+
+impl GenreService {
+    pub fn new(repository_context: RepositoryContext) -> GenreService {
+        Self { repository_context }
+    }
+}
+
+impl GenreService {
+    pub async fn get_genre(&mut self, name: &str) -> Result<CatalogItem<Genre>, GenreServiceError> {
+        let genres = self
+            .repository_context
+            .genre
+            .lock()
+            .await
+            .find(GenreFilter {
+                name: Some(name.to_string()),
+            })
+            .await
+            .map_err(|e| GenreServiceError::Internal(e.into()))?;
+
+        genres
+            .first()
+            .cloned()
+            .ok_or_else(|| GenreServiceError::NotFound)
+    }
+
+    pub async fn list_genres(
+        &mut self,
+        filter: GenreFilter,
+    ) -> Result<Vec<CatalogItem<Genre>>, GenreServiceError> {
+        self.repository_context
+            .genre
+            .lock()
+            .await
+            .find(filter)
+            .await
+            .map_err(|e| GenreServiceError::Internal(e.into()))
+    }
+
+    pub async fn list_genres_page(
+        &mut self,
+        filter: GenreFilter,
+        page: Page<GenreSort>,
+    ) -> Result<Vec<CatalogItem<Genre>>, GenreServiceError> {
+        self.repository_context
+            .genre
+            .lock()
+            .await
+            .find_page(filter, page)
+            .await
+            .map_err(|e| GenreServiceError::Internal(e.into()))
+    }
+
+    pub async fn count_genres(&mut self, filter: GenreFilter) -> Result<i64, GenreServiceError> {
+        self.repository_context
+            .genre
+            .lock()
+            .await
+            .count(filter)
+            .await
+            .map_err(|e| GenreServiceError::Internal(e.into()))
+    }
+
+    pub async fn genre_initials(
+        &mut self,
+        filter: GenreFilter,
+    ) -> Result<Vec<(String, i64)>, GenreServiceError> {
+        self.repository_context
+            .genre
+            .lock()
+            .await
+            .initials(filter)
+            .await
+            .map_err(|e| GenreServiceError::Internal(e.into()))
+    }
+}
@@ -0,0 +1,285 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::{
+    model::{
+        CatalogItem,
+        artist::Artist,
+        listen::Listen,
+        release::Release,
+        stats::{LibraryStats, WeeklyListening},
+        track::Track,
+    },
+    repository::{
+        Repository, RepositoryContext, artist::ArtistFilter, release::ReleaseFilter,
+        track::TrackFilter,
+    },
+};
+
+/// Artists listed in the stats.
+const TOP_ARTISTS: usize = 10;
+/// Weeks of listening time listed in the stats.
+const WEEKS: usize = 12;
+
+#[derive(Debug, Error)]
+pub enum HistoryServiceError {
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryService {
+    repository_context: RepositoryContext,
+}
+
+impl HistoryService {
+    pub fn new(repository_context: RepositoryContext) -> HistoryService {
+        Self { repository_context }
+    }
+
+    /// Adds `listen` to the history, kept with the track it was of.
+    pub async fn record(&mut self, listen: &Listen) -> Result<(), HistoryServiceError> {
+        let track_id = match &listen.path {
+            Some(path) => self
+                .repository_context
+                .track
+                .lock()
+                .await
+                .id_of_source(path, listen.start)
+                .await
+                .map_err(|e| HistoryServiceError::Internal(e.into()))?,
+            None => None,
+        };
+
+        self.repository_context
+            .history
+            .lock()
+            .await
+            .push(listen, track_id)
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))
+    }
+
+    /// Records how long `listen` was actually heard for, which is what listening time adds up.
+    pub async fn listened(
+        &mut self,
+        listen: &Listen,
+        listened: Duration,
+    ) -> Result<(), HistoryServiceError> {
+        self.repository_context
+            .history
+            .lock()
+            .await
+            .set_listened(listen, listened)
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))
+    }
+
+    /// Every listen, oldest first.
+    pub async fn history(&self) -> Result<Vec<Listen>, HistoryServiceError> {
+        self.repository_context
+            .history
+            .lock()
+            .await
+            .all()
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))
+    }
+
+    pub async fn favorite_tracks(&self) -> Result<Vec<CatalogItem<Track>>, HistoryServiceError> {
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .find(TrackFilter {
+                favorite: Some(true),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))
+    }
+
+    pub async fn favorite_releases(
+        &self,
+    ) -> Result<Vec<CatalogItem<Release>>, HistoryServiceError> {
+        self.repository_context
+            .release
+            .lock()
+            .await
+            .find(ReleaseFilter {
+                favorite: Some(true),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))
+    }
+
+    pub async fn favorite_artists(&self) -> Result<Vec<CatalogItem<Artist>>, HistoryServiceError> {
+        self.repository_context
+            .artist
+            .lock()
+            .await
+            .find(ArtistFilter {
+                favorite: Some(true),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))
+    }
+
+    pub async fn stats(&self) -> Result<LibraryStats, HistoryServiceError> {
+        let context = &self.repository_context;
+
+        let tracks = context.track.lock().await;
+        let (track_count, total_duration) = (
+            tracks
+                .count(TrackFilter::default())
+                .await
+                .map_err(|e| HistoryServiceError::Internal(e.into()))?,
+            tracks
+                .total_duration()
+                .await
+                .map_err(|e| HistoryServiceError::Internal(e.into()))?,
+        );
+        drop(tracks);
+
+        let releases = context
+            .release
+            .lock()
+            .await
+            .count(ReleaseFilter::default())
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))?;
+        let artists = context
+            .artist
+            .lock()
+            .await
+            .count(ArtistFilter::default())
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))?;
+
+        let history = context.history.lock().await;
+        let (plays, listening_time) = history
+            .totals()
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))?;
+        let top_artists = history
+            .top_artists(TOP_ARTISTS)
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))?;
+        let weekly = history
+            .weekly(WEEKS)
+            .await
+            .map_err(|e| HistoryServiceError::Internal(e.into()))?
+            .into_iter()
+            .map(|(week, time)| WeeklyListening { week, time })
+            .collect();
+
+        Ok(LibraryStats {
+            tracks: track_count,
+            releases,
+            artists,
+            total_duration,
+            plays,
+            listening_time,
+            top_artists,
+            weekly,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::repository::{Page, track::TrackSort};
+
+    fn listen(title: &str, duration: u64) -> Listen {
+        Listen {
+            title: title.to_string(),
+            artist: "Artist".to_string(),
+            release: None,
+            duration: Duration::from_secs(duration),
+            listened_at: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn listening_time_is_the_time_heard() {
+        let mut history = HistoryService::new(RepositoryContext::in_memory().await);
+        let (skipped, finished) = (listen("Skipped", 300), listen("Finished", 200));
+
+        history.record(&skipped).await.unwrap();
+        history.record(&finished).await.unwrap();
+        history
+            .listened(&skipped, Duration::from_secs(170))
+            .await
+            .unwrap();
+        history
+            .listened(&finished, Duration::from_secs(200))
+            .await
+            .unwrap();
+
+        let stats = history.stats().await.unwrap();
+        assert_eq!(stats.plays, 2);
+        assert_eq!(stats.listening_time, Duration::from_secs(370));
+        assert_eq!(stats.weekly[0].time, Duration::from_secs(370));
+    }
+
+    #[tokio::test]
+    async fn listens_count_their_threshold_until_left() {
+        let mut history = HistoryService::new(RepositoryContext::in_memory().await);
+        history.record(&listen("Playing", 600)).await.unwrap();
+
+        let stats = history.stats().await.unwrap();
+        assert_eq!(stats.listening_time, Duration::from_secs(240));
+    }
+
+    #[tokio::test]
+    async fn plays_belong_to_the_track_played_not_its_namesakes() {
+        let context = RepositoryContext::in_memory().await;
+        let mut history = HistoryService::new(context.clone());
+        let track = |release: &str| Track {
+            title: "Song".to_string(),
+            artist: Some("Artist".to_string()),
+            release: Some(release.to_string()),
+            path: format!("{release}/song.flac").into(),
+            duration: Duration::from_secs(200),
+            ..Default::default()
+        };
+
+        let mut tracks = context.track.lock().await;
+        let album = tracks.create(track("Album")).await.unwrap();
+        tracks.create(track("Best Of")).await.unwrap();
+        drop(tracks);
+
+        let listen = Listen::new(
+            &album.metadata,
+            Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+        );
+        history.record(&listen).await.unwrap();
+
+        let played = |played| TrackFilter {
+            played: Some(played),
+            ..Default::default()
+        };
+        let tracks = context.track.lock().await;
+        let heard = tracks.find(played(true)).await.unwrap();
+        assert_eq!(heard.len(), 1);
+        assert_eq!(heard[0].id, album.id);
+        assert_eq!(tracks.count(played(false)).await.unwrap(), 1);
+
+        let page = Page {
+            sort: TrackSort::Played,
+            ..Default::default()
+        };
+        let by_play = tracks
+            .find_page(TrackFilter::default(), page)
+            .await
+            .unwrap();
+        assert_eq!(by_play.last().unwrap().id, album.id);
+    }
+}
@@ -1,18 +1,42 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use thiserror::Error;
 use tokio::sync::Mutex;
 
 use crate::{
-    model::track::Track,
-    repository::RepositoryContext,
-    service::{artist::ArtistService, release::ReleaseService, track::TrackService},
+    artwork::{self, ArtworkCache, ArtworkKey},
+    model::{
+        CatalogItem, container,
+        cue::CueSheet,
+        playlist::{PLAYLIST_EXTENSIONS, Playlist},
+        release::Release,
+        track::{MediaKind, Track, TrackError},
+    },
+    repository::{RepositoryContext, artist::ArtistFilter, release::ReleaseFilter},
+    service::{
+        artist::ArtistService, book::BookService, genre::GenreService, history::HistoryService,
+        playlist::PlaylistService, release::ReleaseService, scrobble::ScrobbleService,
+        track::TrackService,
+    },
+    settings::LibrarySettings,
 };
 
 pub mod artist;
+pub mod book;
+pub mod genre;
+pub mod history;
+pub mod playlist;
 pub mod release;
+pub mod scrobble;
 pub mod track;
 
+/// Formats audiobooks and podcasts come in, besides FLAC.
+const SPOKEN_WORD_EXTENSIONS: &[&str] = &["m4b", "m4a", "mp3"];
+
 #[derive(Debug, Error)]
 pub enum CatalogServiceError {
     #[error(transparent)]
@@ -25,21 +49,44 @@ pub enum CatalogServiceError {
 pub struct CatalogService {
     pub artist: Arc<Mutex<ArtistService>>,
     pub release: Arc<Mutex<ReleaseService>>,
+    pub genre: Arc<Mutex<GenreService>>,
+    pub book: Arc<Mutex<BookService>>,
     pub track: Arc<Mutex<TrackService>>,
+    pub scrobble: Arc<Mutex<ScrobbleService>>,
+    pub history: Arc<Mutex<HistoryService>>,
+    pub playlist: Arc<Mutex<PlaylistService>>,
+    pub artwork: ArtworkCache,
 }
 
 impl CatalogService {
-    pub async fn new(context: RepositoryContext) -> Result<Self, CatalogServiceError> {
+    pub async fn new(
+        context: RepositoryContext,
+        artwork: ArtworkCache,
+    ) -> Result<Self, CatalogServiceError> {
         Ok(Self {
             artist: Arc::new(Mutex::new(ArtistService::new(context.clone()))),
             release: Arc::new(Mutex::new(ReleaseService::new(context.clone()))),
+            genre: Arc::new(Mutex::new(GenreService::new(context.clone()))),
+            book: Arc::new(Mutex::new(BookService::new(context.clone()))),
             track: Arc::new(Mutex::new(TrackService::new(context.clone()))),
+            scrobble: Arc::new(Mutex::new(ScrobbleService::new(context.clone()))),
+            history: Arc::new(Mutex::new(HistoryService::new(context.clone()))),
+            playlist: Arc::new(Mutex::new(PlaylistService::new(context.clone()))),
+            artwork,
         })
     }
 
     /// Synchronizes the files contained within a `PathBuf`'s directory structure.
-    pub async fn sync(&self, path: PathBuf) -> Result<(), CatalogServiceError> {
+    pub async fn sync(
+        &self,
+        path: PathBuf,
+        settings: &LibrarySettings,
+    ) -> Result<(), CatalogServiceError> {
         let mut track_paths = vec![];
+        // Files in other formats than FLAC, only kept when they turn out to be spoken word.
+        let mut spoken_word_paths = HashSet::new();
+        let mut cue_paths = vec![];
+        let mut playlist_paths = vec![];
         let mut dirs = vec![path.clone()];
 
         while let Some(dir) = dirs.pop() {
@@ -53,20 +100,475 @@ impl CatalogService {
                 .map_err(|e| CatalogServiceError::Internal(e.into()))?
             {
                 let path = entry.path();
+                let extension = extension_of(&path);
+                let extension = extension.as_deref();
+
                 if path.is_dir() {
                     dirs.push(path);
-                } else if path.extension().and_then(|e| e.to_str()) == Some("flac") {
+                } else if extension == Some("flac") {
+                    track_paths.push(path);
+                } else if extension.is_some_and(|e| SPOKEN_WORD_EXTENSIONS.contains(&e)) {
+                    spoken_word_paths.insert(path.clone());
                     track_paths.push(path);
+                } else if extension == Some("cue") {
+                    cue_paths.push(path);
+                } else if extension.is_some_and(|e| PLAYLIST_EXTENSIONS.contains(&e)) {
+                    playlist_paths.push(path);
                 }
             }
         }
 
+        // A CUE sheet next to a file takes over from anything embedded in it.
+        let (cue_tracks, covered) = tokio::task::spawn_blocking(move || read_cue_sheets(cue_paths))
+            .await
+            .map_err(|e| CatalogServiceError::Internal(e.into()))?;
+        // Whatever a sheet points to is an album, in whichever format it was ripped to.
+        spoken_word_paths.retain(|path| !covered.contains(path));
+
+        let mut tracks = cue_tracks;
         for path in track_paths {
-            let track = Track::from_path(path.clone()).expect("error creating track");
-            let test2 = self.track.lock().await.create_track(track).await;
-            tracing::error!("{:?}", test2);
+            if covered.contains(&path) || !worth_reading(&path, settings) {
+                continue;
+            }
+
+            match Track::from_path_with_cues(path.clone()) {
+                Ok(read) => tracks.extend(read),
+                Err(e) => tracing::warn!("unable to read track {}: {}", path.display(), e),
+            }
+        }
+
+        for mut track in tracks {
+            track.kind = settings.media_kind(&track);
+            // The music library is FLAC, other formats are only read for spoken word.
+            if track.kind == MediaKind::Music && spoken_word_paths.contains(&track.path) {
+                continue;
+            }
+            // Music is filed on its album, only spoken word can do without one.
+            if track.kind == MediaKind::Music && track.release.is_none() {
+                let e = TrackError::TagMissing;
+                tracing::warn!("unable to read track {}: {}", track.path.display(), e);
+                continue;
+            }
+
+            track.split_artists(&settings.artist_separators);
+            let path = track.path.clone();
+
+            // Tracks already in the catalog take up whatever changed in their tags.
+            let mut service = self.track.lock().await;
+            let synced = match service.find_stored(&track).await {
+                Ok(Some(stored)) => service.refresh_track(stored, track).await,
+                Ok(None) => service.create_track(track).await.map(Some),
+                Err(e) => Err(e),
+            };
+            drop(service);
+
+            match synced {
+                // Lyrics that can't be indexed leave the track without them, the sync goes on.
+                Ok(Some(item)) => {
+                    if let Err(e) = self.sync_lyrics(item).await {
+                        tracing::warn!("unable to index lyrics of {}: {}", path.display(), e);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("unable to sync track {}: {}", path.display(), e),
+            }
+        }
+
+        // Tracks first, playlists refer to them.
+        for path in playlist_paths {
+            self.sync_playlist(path).await?;
+        }
+
+        self.sync_artwork().await
+    }
+
+    /// Imports a playlist file that's new or changed since it was last imported, reporting the
+    /// entries that aren't in the catalog. One edited on the device is left as it is.
+    async fn sync_playlist(&self, path: PathBuf) -> Result<(), CatalogServiceError> {
+        let modified = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || Playlist::modified(&path))
+                .await
+                .map_err(|e| CatalogServiceError::Internal(e.into()))?
+        };
+
+        let imported = self
+            .playlist
+            .lock()
+            .await
+            .imported(&path)
+            .await
+            .map_err(|e| CatalogServiceError::Internal(e.into()))?;
+
+        if let Some(imported) = imported {
+            let unchanged = modified.is_some() && imported.metadata.modified == modified;
+            if imported.metadata.edited && !unchanged {
+                tracing::info!(
+                    "playlist {} was edited on the device, not importing {}",
+                    imported.metadata.name,
+                    path.display()
+                );
+            }
+            if imported.metadata.edited || unchanged {
+                return Ok(());
+            }
+        }
+
+        let read = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || Playlist::read(&path))
+                .await
+                .map_err(|e| CatalogServiceError::Internal(e.into()))?
+        };
+
+        let (playlist, entries) = match read {
+            Ok(read) => read,
+            Err(e) => {
+                tracing::warn!("unable to read playlist {}: {}", path.display(), e);
+                return Ok(());
+            }
+        };
+
+        let item = self
+            .playlist
+            .lock()
+            .await
+            .import(playlist, entries)
+            .await
+            .map_err(|e| CatalogServiceError::Internal(e.into()))?;
+
+        for entry in &item.metadata.unresolved {
+            tracing::warn!("playlist {}: no track for {}", item.metadata.name, entry);
         }
 
         Ok(())
     }
+
+    /// Indexes the lyrics of a newly added track so they can be searched.
+    async fn sync_lyrics(&self, item: CatalogItem<Track>) -> Result<(), CatalogServiceError> {
+        let lyrics = tokio::task::spawn_blocking(move || item.metadata.lyrics())
+            .await
+            .map_err(|e| CatalogServiceError::Internal(e.into()))?;
+
+        match lyrics {
+            Some(lyrics) => self
+                .track
+                .lock()
+                .await
+                .index_lyrics(item.id, &lyrics)
+                .await
+                .map_err(|e| CatalogServiceError::Internal(e.into())),
+            None => Ok(()),
+        }
+    }
+
+    /// Caches the front cover of every release that doesn't have one yet, an image such as
+    /// `cover.jpg` next to the tracks is preferred over art embedded in them. Artists then get
+    /// an `artist.jpg` from the directory above their releases or a collage of release covers,
+    /// both stored again on every sync so a new photo or release shows up. Neither is rescaled
+    /// when it's unchanged.
+    async fn sync_artwork(&self) -> Result<(), CatalogServiceError> {
+        let releases = self
+            .release
+            .lock()
+            .await
+            .list_releases(ReleaseFilter::default())
+            .await
+            .map_err(|e| CatalogServiceError::Internal(e.into()))?;
+
+        let mut first_tracks = self
+            .track
+            .lock()
+            .await
+            .first_of_releases()
+            .await
+            .map_err(|e| CatalogServiceError::Internal(e.into()))?;
+
+        let mut release_dirs: HashMap<i64, PathBuf> = HashMap::new();
+
+        for release in &releases {
+            let Some(track) = first_tracks.remove(&release.id) else {
+                continue;
+            };
+
+            if let Some(dir) = track.path.parent() {
+                release_dirs.insert(release.id, dir.to_path_buf());
+            }
+
+            let key = ArtworkKey::Release(release.id);
+            if self.artwork.contains(key) {
+                continue;
+            }
+
+            let cache = self.artwork.clone();
+
+            // Decoding and scaling is CPU bound, keep it off the async workers.
+            let result = tokio::task::spawn_blocking(move || {
+                let sidecar = track
+                    .path
+                    .parent()
+                    .and_then(artwork::find_sidecar)
+                    .and_then(|path| std::fs::read(path).ok());
+
+                match sidecar.or_else(|| track.embedded_cover().map(|cover| cover.byte_data)) {
+                    Some(bytes) => cache.store(key, &bytes),
+                    None => Ok(()),
+                }
+            })
+            .await
+            .map_err(|e| CatalogServiceError::Internal(e.into()))?;
+
+            if let Err(e) = result {
+                tracing::warn!("unable to cache cover for release {}: {}", release.id, e);
+            }
+        }
+
+        let artists = self
+            .artist
+            .lock()
+            .await
+            .list_artists(ArtistFilter::default())
+            .await
+            .map_err(|e| CatalogServiceError::Internal(e.into()))?;
+
+        for artist in artists {
+            let key = ArtworkKey::Artist(artist.id);
+
+            let artist_releases: Vec<&CatalogItem<Release>> = releases
+                .iter()
+                .filter(|release| release.metadata.artist.as_ref() == Some(&artist.metadata.name))
+                .collect();
+
+            let artist_dir = artist_releases
+                .iter()
+                .find_map(|release| release_dirs.get(&release.id))
+                .and_then(|dir| dir.parent())
+                .map(Path::to_path_buf);
+
+            let sources: Vec<ArtworkKey> = artist_releases
+                .iter()
+                .map(|release| ArtworkKey::Release(release.id))
+                .collect();
+
+            let cache = self.artwork.clone();
+
+            let result = tokio::task::spawn_blocking(move || {
+                let photo = artist_dir
+                    .as_deref()
+                    .and_then(artwork::find_artist_sidecar)
+                    .and_then(|path| std::fs::read(path).ok());
+
+                match photo {
+                    Some(bytes) => cache.store(key, &bytes),
+                    None => cache.store_collage(key, &sources).map(|_| ()),
+                }
+            })
+            .await
+            .map_err(|e| CatalogServiceError::Internal(e.into()))?;
+
+            if let Err(e) = result {
+                tracing::warn!("unable to cache image for artist {}: {}", artist.id, e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The tracks the CUE sheets at `paths` describe, and the audio files they cover.
+fn read_cue_sheets(paths: Vec<PathBuf>) -> (Vec<Track>, HashSet<PathBuf>) {
+    let mut tracks = Vec::new();
+    let mut covered = HashSet::new();
+
+    for sheet_path in paths {
+        let sheet = match CueSheet::read(&sheet_path) {
+            Ok(Some(sheet)) => sheet,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("unable to read cue sheet {}: {}", sheet_path.display(), e);
+                continue;
+            }
+        };
+
+        for file in &sheet.files {
+            let Some(path) = file.locate(&sheet_path) else {
+                tracing::warn!("cue sheet {}: no file {}", sheet_path.display(), file.name);
+                continue;
+            };
+
+            match Track::from_cue(&sheet, file, path.clone()) {
+                Ok(read) => {
+                    tracks.extend(read);
+                    covered.insert(path);
+                }
+                Err(e) => tracing::warn!("unable to split {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    (tracks, covered)
+}
+
+/// Whether the file at `path` is read in full during sync. FLAC, the music library's format, and
+/// M4B audiobooks always are. Other formats are only read for spoken word, so they have to be
+/// kept where it is or tagged with its genre, which is read on its own first.
+fn worth_reading(path: &Path, settings: &LibrarySettings) -> bool {
+    match extension_of(path).as_deref() {
+        Some("flac" | "m4b") => true,
+        _ => {
+            settings.in_spoken_word_dir(path)
+                || container::read_genre(path)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|genre| settings.is_spoken_word_genre(&genre))
+        }
+    }
+}
+
+/// The extension of the file at `path` in lower case, `.FLAC` is as much FLAC as `.flac`.
+fn extension_of(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        path::Path,
+        time::{Duration, SystemTime},
+    };
+
+    use super::*;
+    use crate::repository::Repository;
+
+    async fn catalog() -> (CatalogService, RepositoryContext) {
+        let context = RepositoryContext::in_memory().await;
+        let catalog = CatalogService::new(context.clone(), ArtworkCache::new("artwork".into()))
+            .await
+            .unwrap();
+        (catalog, context)
+    }
+
+    /// Writes an M3U8 playlist with the given entries, last modified `modified` seconds into the
+    /// epoch.
+    fn write_playlist(path: &Path, entries: &[&str], modified: u64) {
+        fs::write(path, format!("#EXTM3U\n{}\n", entries.join("\n"))).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(modified))
+            .unwrap();
+    }
+
+    /// Writes a second of 16-bit mono silence as a WAV file.
+    fn write_wav(path: &Path) {
+        const RATE: u32 = 8_000;
+        let data = vec![0u8; RATE as usize * 2];
+
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&RATE.to_le_bytes());
+        wav.extend_from_slice(&(RATE * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&data);
+
+        fs::write(path, wav).unwrap();
+    }
+
+    async fn unresolved(catalog: &CatalogService, path: &Path) -> Vec<String> {
+        let imported = catalog.playlist.lock().await.imported(path).await.unwrap();
+        imported.unwrap().metadata.unresolved
+    }
+
+    #[tokio::test]
+    async fn playlists_are_imported_again_only_when_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mix.m3u8");
+        let (catalog, _) = catalog().await;
+
+        write_playlist(&path, &["a.flac"], 1_700_000_000);
+        catalog.sync_playlist(path.clone()).await.unwrap();
+        assert_eq!(unresolved(&catalog, &path).await, ["a.flac"]);
+
+        write_playlist(&path, &["a.flac", "b.flac"], 1_700_000_000);
+        catalog.sync_playlist(path.clone()).await.unwrap();
+        assert_eq!(unresolved(&catalog, &path).await, ["a.flac"]);
+
+        write_playlist(&path, &["a.flac", "b.flac"], 1_700_000_100);
+        catalog.sync_playlist(path.clone()).await.unwrap();
+        assert_eq!(unresolved(&catalog, &path).await, ["a.flac", "b.flac"]);
+    }
+
+    #[tokio::test]
+    async fn playlists_edited_on_the_device_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mix.m3u8");
+        let (catalog, context) = catalog().await;
+
+        write_playlist(&path, &["a.flac"], 1_700_000_000);
+        catalog.sync_playlist(path.clone()).await.unwrap();
+
+        let imported = catalog.playlist.lock().await.imported(&path).await.unwrap();
+        let mut imported = imported.unwrap();
+        imported.metadata.edited = true;
+        context
+            .playlist
+            .lock()
+            .await
+            .update(imported)
+            .await
+            .unwrap();
+
+        write_playlist(&path, &["a.flac", "b.flac"], 1_700_000_100);
+        catalog.sync_playlist(path.clone()).await.unwrap();
+        assert_eq!(unresolved(&catalog, &path).await, ["a.flac"]);
+    }
+
+    #[tokio::test]
+    async fn albums_split_by_a_cue_sheet_are_synced_in_any_format() {
+        let dir = tempfile::tempdir().unwrap();
+        write_wav(&dir.path().join("album.wav"));
+        fs::write(
+            dir.path().join("Album.CUE"),
+            "PERFORMER \"Artist\"\nTITLE \"Album\"\nFILE \"album.wav\" WAVE\n\
+             TRACK 01 AUDIO\nTITLE \"One\"\nINDEX 01 00:00:00\n\
+             TRACK 02 AUDIO\nTITLE \"Two\"\nINDEX 01 00:00:40\n",
+        )
+        .unwrap();
+        let (catalog, _) = catalog().await;
+
+        catalog
+            .sync(dir.path().to_path_buf(), &LibrarySettings::default())
+            .await
+            .unwrap();
+
+        let tracks = catalog
+            .track
+            .lock()
+            .await
+            .list_tracks(Default::default())
+            .await
+            .unwrap();
+        let mut titles: Vec<_> = tracks.iter().map(|t| t.metadata.title.as_str()).collect();
+        titles.sort();
+        assert_eq!(titles, ["One", "Two"]);
+    }
+
+    #[test]
+    fn extensions_are_matched_ignoring_case() {
+        assert_eq!(extension_of(Path::new("a/B.FLAC")).as_deref(), Some("flac"));
+        assert_eq!(extension_of(Path::new("a/b.Cue")).as_deref(), Some("cue"));
+        assert_eq!(extension_of(Path::new("a/b")), None);
+    }
 }
@@ -0,0 +1,286 @@
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::{
+    model::{
+        CatalogItem,
+        playlist::{Playlist, PlaylistEntry},
+        track::Track,
+    },
+    repository::{
+        Page, Repository, RepositoryContext,
+        playlist::{PlaylistFilter, PlaylistSort},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum PlaylistServiceError {
+    #[error("playlist was not found")]
+    NotFound,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaylistService {
+    repository_context: RepositoryContext,
+}
+
+impl PlaylistService {
+    pub fn new(repository_context: RepositoryContext) -> PlaylistService {
+        Self { repository_context }
+    }
+}
+
+impl PlaylistService {
+    /// Stores a playlist read from a file, replacing the one imported from the same file before
+    /// unless that's been edited on the device since.
+    ///
+    /// Entries are matched to catalog tracks by path, then by the title and artist the playlist
+    /// gives or the file's own tags. Those matching nothing are kept on the playlist as
+    /// `unresolved`.
+    pub async fn import(
+        &mut self,
+        mut playlist: Playlist,
+        entries: Vec<PlaylistEntry>,
+    ) -> Result<CatalogItem<Playlist>, PlaylistServiceError> {
+        let base = playlist
+            .path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut track_ids = Vec::new();
+        playlist.unresolved.clear();
+        for entry in entries {
+            match self.resolve(&entry, &base).await? {
+                Some(id) => track_ids.push(id),
+                None => playlist.unresolved.push(entry.describe()),
+            }
+        }
+
+        let existing = match &playlist.path {
+            Some(path) => self.imported(path).await?,
+            None => None,
+        };
+        if let Some(existing) = existing.clone().filter(|existing| existing.metadata.edited) {
+            return Ok(existing);
+        }
+
+        let mut repository = self.repository_context.playlist.lock().await;
+        let item = match existing {
+            Some(existing) => repository
+                .update(CatalogItem {
+                    metadata: playlist,
+                    ..existing
+                })
+                .await
+                .map_err(|e| PlaylistServiceError::Internal(e.into()))?,
+            None => repository
+                .create(playlist)
+                .await
+                .map_err(|e| PlaylistServiceError::Internal(e.into()))?,
+        };
+
+        repository
+            .set_tracks(item.id, &track_ids)
+            .await
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))?;
+
+        Ok(item)
+    }
+
+    /// The playlist imported from the file at `path`, if there is one.
+    pub async fn imported(
+        &self,
+        path: &Path,
+    ) -> Result<Option<CatalogItem<Playlist>>, PlaylistServiceError> {
+        self.repository_context
+            .playlist
+            .lock()
+            .await
+            .find(PlaylistFilter {
+                name: None,
+                path: Some(path.to_string_lossy().to_string()),
+            })
+            .await
+            .map(|found| found.into_iter().next())
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))
+    }
+
+    /// The id of the catalog track an entry refers to.
+    async fn resolve(
+        &self,
+        entry: &PlaylistEntry,
+        base: &Path,
+    ) -> Result<Option<i64>, PlaylistServiceError> {
+        let path = entry.local_path(base);
+        let tracks = self.repository_context.track.lock().await;
+
+        if let Some(path) = &path {
+            let found = tracks
+                .find_by_path(&path.to_string_lossy())
+                .await
+                .map_err(|e| PlaylistServiceError::Internal(e.into()))?;
+
+            if let Some(found) = found {
+                return Ok(Some(found.id));
+            }
+        }
+
+        // The playlist may have been written elsewhere, so fall back to what it says about the
+        // track, or failing that to the tags of the file it points at.
+        let tags = match (&entry.title, path) {
+            (Some(title), _) => Some((title.clone(), entry.artist.clone())),
+            (None, Some(path)) if path.is_file() => {
+                tokio::task::spawn_blocking(move || Track::from_path(path).ok())
+                    .await
+                    .map_err(|e| PlaylistServiceError::Internal(e.into()))?
+                    .map(|track| (track.title, track.artist))
+            }
+            (None, _) => None,
+        };
+
+        let Some((title, artist)) = tags else {
+            return Ok(None);
+        };
+
+        tracks
+            .find_by_tags(&title, artist.as_deref())
+            .await
+            .map(|found| found.map(|item| item.id))
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))
+    }
+
+    pub async fn get_playlist(
+        &mut self,
+        id: i64,
+    ) -> Result<CatalogItem<Playlist>, PlaylistServiceError> {
+        self.repository_context
+            .playlist
+            .lock()
+            .await
+            .read(&id)
+            .await
+            .map_err(|_| PlaylistServiceError::NotFound)
+    }
+
+    pub async fn list_playlists(
+        &mut self,
+        filter: PlaylistFilter,
+    ) -> Result<Vec<CatalogItem<Playlist>>, PlaylistServiceError> {
+        self.repository_context
+            .playlist
+            .lock()
+            .await
+            .find(filter)
+            .await
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))
+    }
+
+    pub async fn list_playlists_page(
+        &mut self,
+        filter: PlaylistFilter,
+        page: Page<PlaylistSort>,
+    ) -> Result<Vec<CatalogItem<Playlist>>, PlaylistServiceError> {
+        self.repository_context
+            .playlist
+            .lock()
+            .await
+            .find_page(filter, page)
+            .await
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))
+    }
+
+    pub async fn count_playlists(
+        &mut self,
+        filter: PlaylistFilter,
+    ) -> Result<i64, PlaylistServiceError> {
+        self.repository_context
+            .playlist
+            .lock()
+            .await
+            .count(filter)
+            .await
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))
+    }
+
+    pub async fn playlist_initials(
+        &mut self,
+        filter: PlaylistFilter,
+    ) -> Result<Vec<(String, i64)>, PlaylistServiceError> {
+        self.repository_context
+            .playlist
+            .lock()
+            .await
+            .initials(filter)
+            .await
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))
+    }
+
+    /// A window of the playlist's tracks in order, `None` fetches the rest of them.
+    pub async fn list_tracks_page(
+        &mut self,
+        id: i64,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<CatalogItem<Track>>, PlaylistServiceError> {
+        self.repository_context
+            .playlist
+            .lock()
+            .await
+            .tracks_page(id, offset, limit)
+            .await
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))
+    }
+
+    pub async fn count_tracks(&mut self, id: i64) -> Result<i64, PlaylistServiceError> {
+        self.repository_context
+            .playlist
+            .lock()
+            .await
+            .count_tracks(id)
+            .await
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))
+    }
+
+    /// Removes the track at `position` from the playlist, which keeps sync from importing its
+    /// file over it from then on.
+    pub async fn remove_track(
+        &mut self,
+        id: i64,
+        position: usize,
+    ) -> Result<(), PlaylistServiceError> {
+        let mut repository = self.repository_context.playlist.lock().await;
+        let mut track_ids = repository
+            .track_ids(id)
+            .await
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))?;
+
+        if position >= track_ids.len() {
+            return Ok(());
+        }
+        track_ids.remove(position);
+
+        repository
+            .set_tracks(id, &track_ids)
+            .await
+            .map_err(|e| PlaylistServiceError::Internal(e.into()))?;
+
+        let mut item = repository
+            .read(&id)
+            .await
+            .map_err(|_| PlaylistServiceError::NotFound)?;
+        if !item.metadata.edited {
+            item.metadata.edited = true;
+            repository
+                .update(item)
+                .await
+                .map_err(|e| PlaylistServiceError::Internal(e.into()))?;
+        }
+
+        Ok(())
+    }
+}
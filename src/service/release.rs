@@ -1,8 +1,11 @@
 use thiserror::Error;
 
 use crate::{
-    model::{CatalogItem, release::Release},
-    repository::{Repository, RepositoryContext, release::ReleaseFilter},
+    model::{CatalogItem, decade::Decade, release::Release},
+    repository::{
+        Page, Repository, RepositoryContext,
+        release::{ReleaseFilter, ReleaseSort},
+    },
 };
 
 #[derive(Debug, Error)]
@@ -37,6 +40,9 @@ impl ReleaseService {
             .find(ReleaseFilter {
                 title: Some(title.to_string()),
                 artist: None,
+                genre: None,
+                years: None,
+                favorite: None,
             })
             .await
             .map_err(|e| ReleaseServiceError::Internal(e.into()))?;
@@ -59,4 +65,61 @@ impl ReleaseService {
             .await
             .map_err(|e| ReleaseServiceError::Internal(e.into()))
     }
+
+    pub async fn list_releases_page(
+        &mut self,
+        filter: ReleaseFilter,
+        page: Page<ReleaseSort>,
+    ) -> Result<Vec<CatalogItem<Release>>, ReleaseServiceError> {
+        self.repository_context
+            .release
+            .lock()
+            .await
+            .find_page(filter, page)
+            .await
+            .map_err(|e| ReleaseServiceError::Internal(e.into()))
+    }
+
+    pub async fn count_releases(
+        &mut self,
+        filter: ReleaseFilter,
+    ) -> Result<i64, ReleaseServiceError> {
+        self.repository_context
+            .release
+            .lock()
+            .await
+            .count(filter)
+            .await
+            .map_err(|e| ReleaseServiceError::Internal(e.into()))
+    }
+
+    pub async fn release_initials(
+        &mut self,
+        filter: ReleaseFilter,
+    ) -> Result<Vec<(String, i64)>, ReleaseServiceError> {
+        self.repository_context
+            .release
+            .lock()
+            .await
+            .initials(filter)
+            .await
+            .map_err(|e| ReleaseServiceError::Internal(e.into()))
+    }
+
+    /// Every decade with releases from it, oldest first.
+    pub async fn list_decades(&mut self) -> Result<Vec<Decade>, ReleaseServiceError> {
+        self.repository_context
+            .release
+            .lock()
+            .await
+            .decades()
+            .await
+            .map(|decades| {
+                decades
+                    .into_iter()
+                    .map(|(start, _)| Decade::new(start))
+                    .collect()
+            })
+            .map_err(|e| ReleaseServiceError::Internal(e.into()))
+    }
 }
@@ -0,0 +1,219 @@
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{
+    model::listen::Listen,
+    repository::RepositoryContext,
+    scrobble::{ScrobbleClient, ScrobbleError},
+};
+
+/// Rejections a listen survives before it's dropped from the queue.
+const MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Error)]
+pub enum ScrobbleServiceError {
+    #[error(transparent)]
+    Scrobble(#[from] ScrobbleError),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct ScrobbleService {
+    repository_context: RepositoryContext,
+}
+
+impl ScrobbleService {
+    pub fn new(repository_context: RepositoryContext) -> ScrobbleService {
+        Self { repository_context }
+    }
+
+    pub async fn record(&mut self, listen: Listen) -> Result<(), ScrobbleServiceError> {
+        info!("recording listen: {} - {}", listen.artist, listen.title);
+
+        self.repository_context
+            .scrobble
+            .lock()
+            .await
+            .push(&listen)
+            .await
+            .map_err(|e| ScrobbleServiceError::Internal(e.into()))?;
+
+        Ok(())
+    }
+
+    pub async fn pending_count(&self) -> Result<i64, ScrobbleServiceError> {
+        self.repository_context
+            .scrobble
+            .lock()
+            .await
+            .count()
+            .await
+            .map_err(|e| ScrobbleServiceError::Internal(e.into()))
+    }
+
+    /// Sends queued listens in batches, oldest first, until the queue is empty or a batch fails.
+    /// Returns how many were accepted. Only a rejection counts against the listens, a batch the
+    /// service couldn't take or refused the credentials for stays queued as it was.
+    pub async fn submit(&self, client: &dyn ScrobbleClient) -> Result<usize, ScrobbleServiceError> {
+        let mut submitted = 0;
+
+        loop {
+            let batch = self
+                .repository_context
+                .scrobble
+                .lock()
+                .await
+                .pending(client.batch_size())
+                .await
+                .map_err(|e| ScrobbleServiceError::Internal(e.into()))?;
+
+            if batch.is_empty() {
+                return Ok(submitted);
+            }
+
+            let (ids, listens): (Vec<i64>, Vec<Listen>) = batch.into_iter().unzip();
+
+            match client.submit(&listens).await {
+                Ok(()) => {
+                    self.repository_context
+                        .scrobble
+                        .lock()
+                        .await
+                        .remove(&ids)
+                        .await
+                        .map_err(|e| ScrobbleServiceError::Internal(e.into()))?;
+
+                    submitted += ids.len();
+                }
+                Err(ScrobbleError::Rejected(reason)) => {
+                    warn!("{} listens rejected: {}", ids.len(), reason);
+
+                    self.repository_context
+                        .scrobble
+                        .lock()
+                        .await
+                        .reject(&ids, MAX_ATTEMPTS)
+                        .await
+                        .map_err(|e| ScrobbleServiceError::Internal(e.into()))?;
+
+                    return Err(ScrobbleError::Rejected(reason).into());
+                }
+                Err(ScrobbleError::Unauthorized(reason)) => {
+                    warn!(
+                        "scrobble credentials refused, keeping listens queued: {}",
+                        reason
+                    );
+                    return Err(ScrobbleError::Unauthorized(reason).into());
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{method, path},
+    };
+
+    use super::*;
+    use crate::scrobble::listenbrainz::ListenBrainz;
+
+    async fn queued(count: usize) -> ScrobbleService {
+        let service = ScrobbleService::new(RepositoryContext::in_memory().await);
+
+        for i in 0..count {
+            let listen = Listen {
+                title: format!("Track {}", i),
+                artist: "Artist".to_string(),
+                release: None,
+                duration: Duration::from_secs(200),
+                listened_at: Utc
+                    .timestamp_opt(1_700_000_000 + i as i64 * 200, 0)
+                    .unwrap(),
+                ..Default::default()
+            };
+            service.clone().record(listen).await.unwrap();
+        }
+        service
+    }
+
+    async fn responding(status: u16) -> (MockServer, ListenBrainz) {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/1/submit-listens"))
+            .respond_with(ResponseTemplate::new(status))
+            .mount(&server)
+            .await;
+
+        let client = ListenBrainz::new(reqwest::Client::new(), server.uri(), "token".to_string());
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn accepted_listens_leave_the_queue() {
+        let service = queued(3).await;
+        let (_server, client) = responding(200).await;
+
+        assert_eq!(service.submit(&client).await.unwrap(), 3);
+        assert_eq!(service.pending_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn unavailable_listens_are_retried() {
+        for status in [503, 429] {
+            let service = queued(2).await;
+            let (_server, client) = responding(status).await;
+
+            for _ in 0..=MAX_ATTEMPTS {
+                assert!(matches!(
+                    service.submit(&client).await,
+                    Err(ScrobbleServiceError::Scrobble(ScrobbleError::Unavailable(
+                        _
+                    )))
+                ));
+            }
+            assert_eq!(service.pending_count().await.unwrap(), 2);
+
+            let (_server, client) = responding(200).await;
+            assert_eq!(service.submit(&client).await.unwrap(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn refused_credentials_keep_the_listens() {
+        for status in [401, 403] {
+            let service = queued(2).await;
+            let (_server, client) = responding(status).await;
+
+            for _ in 0..=MAX_ATTEMPTS {
+                assert!(matches!(
+                    service.submit(&client).await,
+                    Err(ScrobbleServiceError::Scrobble(ScrobbleError::Unauthorized(
+                        _
+                    )))
+                ));
+            }
+            assert_eq!(service.pending_count().await.unwrap(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejected_listens_are_dropped_after_the_last_attempt() {
+        let service = queued(2).await;
+        let (_server, client) = responding(400).await;
+
+        for _ in 1..MAX_ATTEMPTS {
+            assert!(service.submit(&client).await.is_err());
+        }
+        assert_eq!(service.pending_count().await.unwrap(), 2);
+
+        assert!(service.submit(&client).await.is_err());
+        assert_eq!(service.pending_count().await.unwrap(), 0);
+    }
+}
@@ -1,11 +1,25 @@
+use std::{collections::HashMap, time::Duration};
+
 use thiserror::Error;
 use tracing::info;
 
 use crate::{
-    model::{CatalogItem, artist::Artist, release::Release, track::Track},
+    model::{
+        CatalogItem,
+        artist::Artist,
+        book::Book,
+        genre::Genre,
+        lyrics::Lyrics,
+        release::{Release, VARIOUS_ARTISTS},
+        track::{MediaKind, Track},
+    },
     repository::{
-        Repository, RepositoryContext, artist::ArtistFilter, release::ReleaseFilter,
-        track::TrackFilter,
+        Page, Repository, RepositoryContext,
+        artist::ArtistFilter,
+        book::BookFilter,
+        genre::GenreFilter,
+        release::ReleaseFilter,
+        track::{TrackFilter, TrackSort},
     },
 };
 
@@ -29,6 +43,15 @@ impl TrackService {
 }
 
 impl TrackService {
+    /// Adds a track to the catalog, filing it on its release and under its artists.
+    ///
+    /// The release is the one with the same title credited to the same album artist, or lacking
+    /// one the same track artist or directory. Releases tagged as compilations, or without an
+    /// album artist whose tracks turn out to be led by many artists with none leading most of
+    /// them, are filed under [`VARIOUS_ARTISTS`] while staying listed under each track artist. Artists the track only
+    /// features are credited on it without the release being listed under them.
+    ///
+    /// Spoken word is filed on a book instead, and kept away from artists, releases and genres.
     pub async fn create_track(
         &mut self,
         track: Track,
@@ -40,98 +63,746 @@ impl TrackService {
             .track
             .lock()
             .await
-            .create(track.clone())
+            .create(track)
             .await
             .map_err(|e| TrackServiceError::Internal(e.into()))?;
 
-        let related_release = self
+        self.file_track(created_track).await
+    }
+
+    /// The catalog's copy of `track`, read from the same file and the same part of it.
+    pub async fn find_stored(
+        &mut self,
+        track: &Track,
+    ) -> Result<Option<CatalogItem<Track>>, TrackServiceError> {
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .find_by_source(track)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// Brings a track already in the catalog up to date with `track` as read from its file,
+    /// keeping where it was left off and its speed, then files it again the way
+    /// [`TrackService::create_track`] does and takes it off wherever its old tags had it.
+    /// `None` when nothing changed.
+    pub async fn refresh_track(
+        &mut self,
+        mut stored: CatalogItem<Track>,
+        track: Track,
+    ) -> Result<Option<CatalogItem<Track>>, TrackServiceError> {
+        let track = Track {
+            release_id: stored.metadata.release_id,
+            book_id: stored.metadata.book_id,
+            resume: stored.metadata.resume,
+            speed: stored.metadata.speed,
+            ..track
+        };
+        if track == stored.metadata {
+            return Ok(None);
+        }
+
+        info!("refreshing track: {:?}", track);
+        let previous = std::mem::replace(&mut stored.metadata, track);
+
+        let updated = self
+            .repository_context
+            .track
+            .lock()
+            .await
+            .update(stored)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+        let filed = self.file_track(updated).await?;
+        self.unfile_track(&previous).await?;
+
+        Ok(Some(filed))
+    }
+
+    /// Takes a refiled track off the release or book its `previous` tags filed it on, removing
+    /// that when nothing else is on it, along with the artists and genre it leaves with nothing.
+    async fn unfile_track(&mut self, previous: &Track) -> Result<(), TrackServiceError> {
+        let mut credited: Vec<String> = previous.artists.clone();
+        credited.extend(previous.artist.clone());
+
+        if let Some(release_id) = previous.release_id {
+            credited.extend(self.unfile_release(release_id).await?);
+        }
+        if let Some(book_id) = previous.book_id {
+            self.unfile_book(book_id).await?;
+        }
+
+        for name in &credited {
+            self.prune_artist(name).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Brings the release with `release_id` in line with the tracks still filed on it, giving
+    /// back the artists it's no longer listed under.
+    async fn unfile_release(&mut self, release_id: i64) -> Result<Vec<String>, TrackServiceError> {
+        let remaining = self
+            .repository_context
+            .track
+            .lock()
+            .await
+            .find(TrackFilter {
+                release_id: Some(release_id),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+        let mut release_item = self
             .repository_context
             .release
             .lock()
             .await
-            .find(ReleaseFilter {
-                title: track.release.clone(),
-                artist: None,
+            .read(&release_id)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+        let release = &mut release_item.metadata;
+        let title = release.title.clone();
+
+        if remaining.is_empty() {
+            self.repository_context
+                .release
+                .lock()
+                .await
+                .delete(&release_id)
+                .await
+                .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+            let mut dropped = release.artists.clone();
+            dropped.extend(release.artist.clone());
+            for name in &dropped {
+                self.remove_artist_release(name, &title).await?;
+            }
+            if let Some(genre) = &release.genre {
+                self.remove_genre_release(genre, &title).await?;
+            }
+
+            return Ok(dropped);
+        }
+
+        let primaries: Vec<String> = remaining
+            .iter()
+            .filter_map(|item| {
+                let track = &item.metadata;
+                track.artists.first().or(track.artist.as_ref()).cloned()
+            })
+            .collect();
+        release
+            .tracks
+            .retain(|title| remaining.iter().any(|item| &item.metadata.title == title));
+        let (kept, dropped): (Vec<String>, Vec<String>) = release
+            .artists
+            .drain(..)
+            .partition(|artist| primaries.contains(artist));
+        release.artists = kept;
+        let dropped: Vec<String> = dropped
+            .into_iter()
+            .filter(|artist| release.artist.as_ref() != Some(artist))
+            .collect();
+
+        self.repository_context
+            .release
+            .lock()
+            .await
+            .update(release_item)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+        for name in &dropped {
+            self.remove_artist_release(name, &title).await?;
+        }
+
+        Ok(dropped)
+    }
+
+    /// Brings the book with `book_id` in line with the tracks still filed on it, removing it
+    /// when there are none.
+    async fn unfile_book(&mut self, book_id: i64) -> Result<(), TrackServiceError> {
+        let remaining = self
+            .repository_context
+            .track
+            .lock()
+            .await
+            .find(TrackFilter {
+                book_id: Some(book_id),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+        let mut repository = self.repository_context.book.lock().await;
+        if remaining.is_empty() {
+            return repository
+                .delete(&book_id)
+                .await
+                .map_err(|e| TrackServiceError::Internal(e.into()));
+        }
+
+        let mut book_item = repository
+            .read(&book_id)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+        book_item
+            .metadata
+            .tracks
+            .retain(|title| remaining.iter().any(|item| &item.metadata.title == title));
+
+        repository
+            .update(book_item)
+            .await
+            .map(|_| ())
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// Stops listing `release` under the artist called `name`, unless another release of that
+    /// title still is by them.
+    async fn remove_artist_release(
+        &mut self,
+        name: &str,
+        release: &str,
+    ) -> Result<(), TrackServiceError> {
+        if self.releases_titled(release).await?.iter().any(|item| {
+            item.metadata.artist.as_deref() == Some(name)
+                || item.metadata.artists.iter().any(|artist| artist == name)
+        }) {
+            return Ok(());
+        }
+
+        let Some(mut artist_item) = self.find_artist(name).await? else {
+            return Ok(());
+        };
+        artist_item.metadata.remove_release(release);
+
+        self.repository_context
+            .artist
+            .lock()
+            .await
+            .update(artist_item)
+            .await
+            .map(|_| ())
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// Stops listing `release` under the genre called `name`, unless another release of that
+    /// title is still in it, and removes the genre once it lists nothing.
+    async fn remove_genre_release(
+        &mut self,
+        name: &str,
+        release: &str,
+    ) -> Result<(), TrackServiceError> {
+        if self.releases_titled(release).await?.iter().any(|item| {
+            item.metadata
+                .genre
+                .as_ref()
+                .is_some_and(|genre| genre.eq_ignore_ascii_case(name))
+        }) {
+            return Ok(());
+        }
+
+        let mut repository = self.repository_context.genre.lock().await;
+        let existing = repository
+            .find(GenreFilter {
+                name: Some(name.to_string()),
             })
             .await
             .map_err(|e| TrackServiceError::Internal(e.into()))?
-            .pop();
+            .into_iter()
+            .find(|item| item.metadata.name.eq_ignore_ascii_case(name));
+        let Some(mut genre_item) = existing else {
+            return Ok(());
+        };
+
+        genre_item.metadata.remove_release(release);
+        match genre_item.metadata.releases.is_empty() {
+            true => repository.delete(&genre_item.id).await,
+            false => repository.update(genre_item).await.map(|_| ()),
+        }
+        .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// Removes the artist called `name` once no release is listed under them and no track
+    /// credits them.
+    async fn prune_artist(&mut self, name: &str) -> Result<(), TrackServiceError> {
+        let Some(artist_item) = self.find_artist(name).await? else {
+            return Ok(());
+        };
+        if !artist_item.metadata.releases.is_empty() {
+            return Ok(());
+        }
+
+        let credits = self
+            .repository_context
+            .track
+            .lock()
+            .await
+            .credits(artist_item.id)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+        if credits > 0 {
+            return Ok(());
+        }
+
+        self.repository_context
+            .artist
+            .lock()
+            .await
+            .delete(&artist_item.id)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// The releases called exactly `title`.
+    async fn releases_titled(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<CatalogItem<Release>>, TrackServiceError> {
+        let releases = self
+            .repository_context
+            .release
+            .lock()
+            .await
+            .find(ReleaseFilter {
+                title: Some(title.to_string()),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+        Ok(releases
+            .into_iter()
+            .filter(|item| item.metadata.title == title)
+            .collect())
+    }
 
-        let releated_artist = self
+    /// The artist called exactly `name`.
+    async fn find_artist(
+        &mut self,
+        name: &str,
+    ) -> Result<Option<CatalogItem<Artist>>, TrackServiceError> {
+        let artists = self
             .repository_context
             .artist
             .lock()
             .await
             .find(ArtistFilter {
-                name: track.artist.clone(),
+                name: Some(name.to_string()),
                 track: None,
+                favorite: None,
+            })
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+        Ok(artists.into_iter().find(|item| item.metadata.name == name))
+    }
+
+    /// Files a stored track on its release, or book for spoken word, and credits its artists.
+    async fn file_track(
+        &mut self,
+        mut created_track: CatalogItem<Track>,
+    ) -> Result<CatalogItem<Track>, TrackServiceError> {
+        let track = created_track.metadata.clone();
+
+        if track.kind == MediaKind::SpokenWord {
+            return self.add_book_track(created_track).await;
+        }
+
+        let Some(release_name) = track.release.clone() else {
+            self.credit_artists(&created_track, None).await?;
+            return Ok(created_track);
+        };
+
+        // Releases are filed by the main artist, a guest on one track doesn't make a compilation.
+        let primary = track.artists.first().cloned().or(track.artist.clone());
+
+        let album_artist = match (&track.album_artist, track.compilation) {
+            (Some(album_artist), _) => Some(album_artist.clone()),
+            (None, true) => Some(VARIOUS_ARTISTS.to_string()),
+            (None, false) => None,
+        };
+        let directory = Release::directory_of(&track.path);
+
+        let related_release = self
+            .repository_context
+            .release
+            .lock()
+            .await
+            .find(ReleaseFilter {
+                title: Some(release_name.clone()),
+                artist: None,
+                genre: None,
+                years: None,
+                favorite: None,
             })
             .await
             .map_err(|e| TrackServiceError::Internal(e.into()))?
-            .pop();
-
-        if let Some(release_name) = track.release.clone() {
-            match related_release {
-                Some(mut release_item) => {
-                    release_item.metadata.add_track(&track.title);
-
-                    self.repository_context
-                        .release
-                        .lock()
-                        .await
-                        .update(release_item)
-                        .await
-                        .map_err(|e| TrackServiceError::Internal(e.into()))?;
-                }
+            .into_iter()
+            .filter(|item| item.metadata.title == release_name)
+            .find(|item| match &album_artist {
+                Some(album_artist) => item.metadata.artist.as_ref() == Some(album_artist),
                 None => {
-                    self.repository_context
-                        .release
-                        .lock()
-                        .await
-                        .create(Release {
-                            title: release_name.clone(),
-                            artist: track.artist.clone(),
-                            tracks: vec![created_track.metadata.title.clone()],
-                        })
-                        .await
-                        .map_err(|e| TrackServiceError::Internal(e.into()))?;
+                    item.metadata.artist == primary
+                        || (directory.is_some() && item.metadata.directory == directory)
                 }
-            }
-        }
+            });
 
-        if let Some(artist_name) = track.artist {
-            match releated_artist {
-                Some(mut artist_item) => {
-                    if let Some(release) = &track.release {
-                        artist_item.metadata.add_release(release);
+        let release_item = match related_release {
+            Some(mut release_item) => {
+                // Untagged, what makes a compilation is who leads the release's tracks.
+                let primaries = match album_artist.is_none() && !track.compilation {
+                    true => {
+                        self.release_primaries(release_item.id, created_track.id)
+                            .await?
                     }
+                    false => None,
+                };
 
-                    self.repository_context
-                        .artist
-                        .lock()
-                        .await
-                        .update(artist_item)
-                        .await
-                        .map_err(|e| TrackServiceError::Internal(e.into()))?;
+                let release = &mut release_item.metadata;
+                release.add_track(&track.title);
+                release.fill_in(track.genre.as_ref(), track.year);
+                if let Some(artist) = &primary {
+                    release.add_artist(artist);
                 }
-                None => {
-                    self.repository_context
-                        .artist
-                        .lock()
-                        .await
-                        .create(Artist {
-                            name: artist_name,
-                            releases: Vec::new(),
-                        })
-                        .await
-                        .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+                if let Some(mut primaries) = primaries {
+                    primaries.extend(primary.clone());
+
+                    match Release::is_various(&primaries) {
+                        true => release.mark_compilation(),
+                        false => {
+                            release.compilation = false;
+                            release.artist = Release::lead_artist(&primaries).cloned();
+                        }
+                    }
+                }
+
+                self.repository_context
+                    .release
+                    .lock()
+                    .await
+                    .update(release_item)
+                    .await
+                    .map_err(|e| TrackServiceError::Internal(e.into()))?
+            }
+            None => {
+                let mut release = Release {
+                    title: release_name.clone(),
+                    artist: album_artist.clone().or(primary.clone()),
+                    tracks: vec![created_track.metadata.title.clone()],
+                    artists: primary.clone().into_iter().collect(),
+                    compilation: false,
+                    directory,
+                    genre: track.genre.clone(),
+                    year: track.year,
+                };
+                if track.compilation || album_artist.as_deref() == Some(VARIOUS_ARTISTS) {
+                    release.mark_compilation();
                 }
+
+                self.repository_context
+                    .release
+                    .lock()
+                    .await
+                    .create(release)
+                    .await
+                    .map_err(|e| TrackServiceError::Internal(e.into()))?
             }
         };
 
+        created_track.metadata.release_id = Some(release_item.id);
+        created_track = self
+            .repository_context
+            .track
+            .lock()
+            .await
+            .update(created_track)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+        if let Some(artist) = &release_item.metadata.artist
+            && primary.as_ref() != Some(artist)
+        {
+            self.add_artist_release(artist, Some(&release_name)).await?;
+        }
+        self.credit_artists(&created_track, Some(&release_name))
+            .await?;
+        if let Some(genre) = &release_item.metadata.genre {
+            self.add_genre_release(genre, &release_name).await?;
+        }
+
         Ok(created_track)
     }
 
+    /// Who leads each track filed on the release with `release_id` other than the one with
+    /// `track_id`, `None` when any of them is tagged with who the release is by, which then
+    /// settles it.
+    async fn release_primaries(
+        &mut self,
+        release_id: i64,
+        track_id: i64,
+    ) -> Result<Option<Vec<String>>, TrackServiceError> {
+        let tracks = self
+            .repository_context
+            .track
+            .lock()
+            .await
+            .find(TrackFilter {
+                release_id: Some(release_id),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+        let tagged = tracks
+            .iter()
+            .any(|item| item.metadata.album_artist.is_some() || item.metadata.compilation);
+
+        Ok((!tagged).then(|| {
+            tracks
+                .into_iter()
+                .filter(|item| item.id != track_id)
+                .filter_map(|item| {
+                    let track = item.metadata;
+                    track.artists.into_iter().next().or(track.artist)
+                })
+                .collect()
+        }))
+    }
+
+    /// Files a spoken word track on the book with its album title, or its own title when it's a
+    /// single file, by the same author or in the same directory.
+    async fn add_book_track(
+        &mut self,
+        mut track: CatalogItem<Track>,
+    ) -> Result<CatalogItem<Track>, TrackServiceError> {
+        let title = track
+            .metadata
+            .release
+            .clone()
+            .unwrap_or_else(|| track.metadata.title.clone());
+        let author = track
+            .metadata
+            .album_artist
+            .clone()
+            .or(track.metadata.artist.clone());
+        let directory = Release::directory_of(&track.metadata.path);
+
+        let mut repository = self.repository_context.book.lock().await;
+        let existing = repository
+            .find(BookFilter {
+                title: Some(title.clone()),
+                author: None,
+            })
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?
+            .into_iter()
+            .filter(|item| item.metadata.title == title)
+            .find(|item| {
+                item.metadata.author == author
+                    || (directory.is_some() && item.metadata.directory == directory)
+            });
+
+        let book_item = match existing {
+            Some(mut book_item) => {
+                book_item.metadata.add_track(&track.metadata.title);
+
+                repository
+                    .update(book_item)
+                    .await
+                    .map_err(|e| TrackServiceError::Internal(e.into()))?
+            }
+            None => repository
+                .create(Book {
+                    title,
+                    author,
+                    tracks: vec![track.metadata.title.clone()],
+                    directory,
+                })
+                .await
+                .map_err(|e| TrackServiceError::Internal(e.into()))?,
+        };
+        drop(repository);
+
+        track.metadata.book_id = Some(book_item.id);
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .update(track)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// Links the track to everyone credited on it, listing `release` under the main artist.
+    async fn credit_artists(
+        &mut self,
+        track: &CatalogItem<Track>,
+        release: Option<&str>,
+    ) -> Result<(), TrackServiceError> {
+        let names = match track.metadata.artists.is_empty() {
+            true => track.metadata.artist.iter().cloned().collect(),
+            false => track.metadata.artists.clone(),
+        };
+
+        let mut artist_ids = Vec::new();
+        for (position, name) in names.iter().enumerate() {
+            let release = release.filter(|_| position == 0);
+            artist_ids.push(self.add_artist_release(name, release).await?);
+        }
+
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .set_artists(track.id, &artist_ids)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// Lists `release` under the artist called `name`, adding the artist if it's new, and gives
+    /// back the artist's id.
+    async fn add_artist_release(
+        &mut self,
+        name: &str,
+        release: Option<&str>,
+    ) -> Result<i64, TrackServiceError> {
+        let mut repository = self.repository_context.artist.lock().await;
+        let existing = repository
+            .find(ArtistFilter {
+                name: Some(name.to_string()),
+                track: None,
+                favorite: None,
+            })
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?
+            .into_iter()
+            .find(|item| item.metadata.name == name);
+
+        match existing {
+            Some(mut artist_item) => {
+                if let Some(release) = release {
+                    artist_item.metadata.add_release(release);
+                }
+
+                repository
+                    .update(artist_item)
+                    .await
+                    .map(|item| item.id)
+                    .map_err(|e| TrackServiceError::Internal(e.into()))
+            }
+            None => repository
+                .create(Artist {
+                    name: name.to_string(),
+                    releases: release.map(str::to_string).into_iter().collect(),
+                })
+                .await
+                .map(|item| item.id)
+                .map_err(|e| TrackServiceError::Internal(e.into())),
+        }
+    }
+
+    /// Lists `release` under the genre called `name`, adding the genre if it's new.
+    async fn add_genre_release(
+        &mut self,
+        name: &str,
+        release: &str,
+    ) -> Result<(), TrackServiceError> {
+        let mut repository = self.repository_context.genre.lock().await;
+        let existing = repository
+            .find(GenreFilter {
+                name: Some(name.to_string()),
+            })
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?
+            .into_iter()
+            .find(|item| item.metadata.name.eq_ignore_ascii_case(name));
+
+        match existing {
+            Some(mut genre_item) => {
+                genre_item.metadata.add_release(release);
+
+                repository
+                    .update(genre_item)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| TrackServiceError::Internal(e.into()))
+            }
+            None => repository
+                .create(Genre {
+                    name: name.to_string(),
+                    releases: vec![release.to_string()],
+                })
+                .await
+                .map(|_| ())
+                .map_err(|e| TrackServiceError::Internal(e.into())),
+        }
+    }
+
+    /// Remembers where playback of `track` was left off, or forgets it with `None`.
+    pub async fn save_resume(
+        &mut self,
+        track: &Track,
+        position: Option<Duration>,
+    ) -> Result<(), TrackServiceError> {
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .set_resume(track, position)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// Remembers the speed `track` plays at, or goes back to the one for its kind with `None`.
+    pub async fn save_speed(
+        &mut self,
+        track: &Track,
+        speed: Option<f32>,
+    ) -> Result<(), TrackServiceError> {
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .set_speed(track, speed)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// Makes the track's lyrics searchable.
+    pub async fn index_lyrics(
+        &mut self,
+        id: i64,
+        lyrics: &Lyrics,
+    ) -> Result<(), TrackServiceError> {
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .index_lyrics(id, &lyrics.text())
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    /// Tracks whose lyrics contain `query`.
+    pub async fn search_lyrics(
+        &mut self,
+        query: &str,
+    ) -> Result<Vec<CatalogItem<Track>>, TrackServiceError> {
+        self.list_tracks(TrackFilter {
+            lyrics: Some(query.to_string()),
+            ..Default::default()
+        })
+        .await
+    }
+
     pub async fn favorite_track(
         &mut self,
         title: &str,
@@ -158,6 +829,15 @@ impl TrackService {
                 name: Some(name.to_string()),
                 artist: None,
                 release: None,
+                release_id: None,
+                book_id: None,
+                kind: None,
+                featuring: None,
+                genre: None,
+                years: None,
+                played: None,
+                lyrics: None,
+                favorite: None,
             })
             .await
             .map_err(|e| TrackServiceError::Internal(e.into()))?;
@@ -180,4 +860,217 @@ impl TrackService {
             .await
             .map_err(|e| TrackServiceError::Internal(e.into()))
     }
+
+    /// The first track of every release, keyed by release id.
+    pub async fn first_of_releases(&mut self) -> Result<HashMap<i64, Track>, TrackServiceError> {
+        let tracks = self
+            .repository_context
+            .track
+            .lock()
+            .await
+            .first_of_releases()
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))?;
+
+        Ok(tracks
+            .into_iter()
+            .filter_map(|item| Some((item.metadata.release_id?, item.metadata)))
+            .collect())
+    }
+
+    pub async fn list_tracks_page(
+        &mut self,
+        filter: TrackFilter,
+        page: Page<TrackSort>,
+    ) -> Result<Vec<CatalogItem<Track>>, TrackServiceError> {
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .find_page(filter, page)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    pub async fn count_tracks(&mut self, filter: TrackFilter) -> Result<i64, TrackServiceError> {
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .count(filter)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+
+    pub async fn track_initials(
+        &mut self,
+        filter: TrackFilter,
+    ) -> Result<Vec<(String, i64)>, TrackServiceError> {
+        self.repository_context
+            .track
+            .lock()
+            .await
+            .initials(filter)
+            .await
+            .map_err(|e| TrackServiceError::Internal(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn track(number: u32, artist: &str) -> Track {
+        Track {
+            title: format!("Track {number}"),
+            artist: Some(artist.to_string()),
+            artists: vec![artist.to_string()],
+            release: Some("Album".to_string()),
+            path: PathBuf::from(format!("music/album/{number:02}.flac")),
+            track_number: Some(number),
+            ..Default::default()
+        }
+    }
+
+    /// Files tracks by `artists` in order as one album and gives back the releases made.
+    async fn file(artists: &[&str]) -> Vec<Release> {
+        let context = RepositoryContext::in_memory().await;
+        let mut service = TrackService::new(context.clone());
+
+        for (number, artist) in artists.iter().enumerate() {
+            service
+                .create_track(track(number as u32 + 1, artist))
+                .await
+                .unwrap();
+        }
+
+        let releases = context.release.lock().await.find(Default::default()).await;
+        releases.unwrap().into_iter().map(|r| r.metadata).collect()
+    }
+
+    #[tokio::test]
+    async fn an_album_with_a_guest_led_track_stays_with_its_artist() {
+        let releases = file(&["Guest", "A", "A", "A", "A", "A", "A", "A"]).await;
+
+        assert_eq!(releases.len(), 1);
+        assert!(!releases[0].compilation);
+        assert_eq!(releases[0].artist.as_deref(), Some("A"));
+    }
+
+    #[tokio::test]
+    async fn an_album_led_by_many_artists_is_a_compilation() {
+        let releases = file(&["A", "B", "C", "D", "A"]).await;
+
+        assert_eq!(releases.len(), 1);
+        assert!(releases[0].compilation);
+        assert_eq!(releases[0].artist.as_deref(), Some(VARIOUS_ARTISTS));
+    }
+
+    #[tokio::test]
+    async fn a_compilation_tag_is_kept_whoever_leads_the_tracks() {
+        let context = RepositoryContext::in_memory().await;
+        let mut service = TrackService::new(context.clone());
+
+        for number in 1..=3 {
+            let track = Track {
+                compilation: true,
+                ..track(number, "A")
+            };
+            service.create_track(track).await.unwrap();
+        }
+
+        let releases = context.release.lock().await.find(Default::default()).await;
+        let release = &releases.unwrap()[0].metadata;
+        assert!(release.compilation);
+        assert_eq!(release.artist.as_deref(), Some(VARIOUS_ARTISTS));
+    }
+
+    /// Names of everything of one kind in the catalog, sorted.
+    async fn names(context: &RepositoryContext) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let releases = context.release.lock().await.find(Default::default()).await;
+        let artists = context.artist.lock().await.find(Default::default()).await;
+        let genres = context.genre.lock().await.find(Default::default()).await;
+
+        let mut releases: Vec<_> = releases
+            .unwrap()
+            .into_iter()
+            .map(|r| r.metadata.title)
+            .collect();
+        let mut artists: Vec<_> = artists
+            .unwrap()
+            .into_iter()
+            .map(|a| a.metadata.name)
+            .collect();
+        let mut genres: Vec<_> = genres
+            .unwrap()
+            .into_iter()
+            .map(|g| g.metadata.name)
+            .collect();
+        releases.sort();
+        artists.sort();
+        genres.sort();
+
+        (releases, artists, genres)
+    }
+
+    async fn retag(service: &mut TrackService, track: Track) {
+        let stored = service.find_stored(&track).await.unwrap().unwrap();
+        service.refresh_track(stored, track).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_retagged_track_leaves_nothing_behind() {
+        let context = RepositoryContext::in_memory().await;
+        let mut service = TrackService::new(context.clone());
+        let old = Track {
+            genre: Some("Rock".to_string()),
+            ..track(1, "Old Artist")
+        };
+        service.create_track(old).await.unwrap();
+
+        retag(
+            &mut service,
+            Track {
+                release: Some("Renamed".to_string()),
+                genre: Some("Jazz".to_string()),
+                ..track(1, "New Artist")
+            },
+        )
+        .await;
+
+        assert_eq!(
+            names(&context).await,
+            (
+                vec!["Renamed".to_string()],
+                vec!["New Artist".to_string()],
+                vec!["Jazz".to_string()]
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn a_release_keeps_the_tracks_still_on_it() {
+        let context = RepositoryContext::in_memory().await;
+        let mut service = TrackService::new(context.clone());
+        service.create_track(track(1, "A")).await.unwrap();
+        service.create_track(track(2, "A")).await.unwrap();
+        service.create_track(track(3, "Guest")).await.unwrap();
+
+        retag(
+            &mut service,
+            Track {
+                title: "Renamed".to_string(),
+                ..track(3, "A")
+            },
+        )
+        .await;
+
+        let releases = context.release.lock().await.find(Default::default()).await;
+        let release = &releases.unwrap()[0].metadata;
+        assert_eq!(release.tracks, ["Track 1", "Track 2", "Renamed"]);
+        assert_eq!(release.artists, ["A"]);
+        assert_eq!(names(&context).await.1, ["A"]);
+    }
 }
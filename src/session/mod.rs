@@ -0,0 +1,51 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// What was playing when the application last stopped, restored on the next start.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub track: Option<PathBuf>,
+    /// Where the track starts within its file, when a CUE sheet splits the file up.
+    pub start: Option<Duration>,
+    pub position: Duration,
+}
+
+/// Reads and writes the [`Session`] as JSON.
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    path: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Loads the last saved session, if there is a readable one.
+    pub fn load(&self) -> Option<Session> {
+        let bytes = fs::read(&self.path).ok()?;
+
+        serde_json::from_slice(&bytes)
+            .inspect_err(|e| warn!("ignoring invalid session in {:?}: {}", self.path, e))
+            .ok()
+    }
+
+    pub async fn save(&self, session: Session) -> Result<(), SessionError> {
+        let contents = serde_json::to_vec_pretty(&session)?;
+        tokio::fs::write(&self.path, contents).await?;
+
+        Ok(())
+    }
+}
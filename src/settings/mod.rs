@@ -0,0 +1,370 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    artwork::render::ArtStyle,
+    audio::dsp::DspSettings,
+    model::track::{MediaKind, Track},
+    power::ShutdownMethod,
+    scrobble::ScrobbleTarget,
+};
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// User preferences, missing fields fall back to their defaults so older files keep loading.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub art_style: ArtStyle,
+    pub display: DisplaySettings,
+    pub power: PowerSettings,
+    pub visualizer: VisualizerSettings,
+    pub library: LibrarySettings,
+    pub playback: PlaybackSettings,
+    pub dsp: DspSettings,
+    /// Where listens are submitted, they're still queued while this isn't set.
+    pub scrobble: Option<ScrobbleTarget>,
+}
+
+/// What's drawn once the display goes to sleep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleScreen {
+    Blank,
+    #[default]
+    Clock,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    /// Seconds without input before the display dims, 0 never dims.
+    pub dim_after: u64,
+    /// Seconds without input before the display sleeps, 0 never sleeps.
+    pub sleep_after: u64,
+    pub idle_screen: IdleScreen,
+    /// Nudge the whole interface by a few pixels every couple of minutes.
+    pub pixel_shift: bool,
+    /// Backlight level while in use, as a fraction of the maximum.
+    pub brightness: f32,
+    /// Backlight level while dimmed or showing the idle clock.
+    pub dim_brightness: f32,
+    /// sysfs backlight directory such as `/sys/class/backlight/<name>`, without one dimming is
+    /// drawn over the interface instead.
+    pub backlight: Option<PathBuf>,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            dim_after: 30,
+            sleep_after: 120,
+            idle_screen: IdleScreen::default(),
+            pixel_shift: true,
+            brightness: 1.0,
+            dim_brightness: 0.2,
+            backlight: None,
+        }
+    }
+}
+
+impl DisplaySettings {
+    pub fn dim_after(&self) -> Option<Duration> {
+        (self.dim_after > 0).then(|| Duration::from_secs(self.dim_after))
+    }
+
+    pub fn sleep_after(&self) -> Option<Duration> {
+        (self.sleep_after > 0).then(|| Duration::from_secs(self.sleep_after))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerSettings {
+    /// Charge percentages that raise a low battery warning as the charge drops past them.
+    pub warning_levels: Vec<u8>,
+    /// Charge percentage at which the session is saved and the device powered off, 0 disables.
+    pub critical_level: u8,
+    /// Seconds without playback or a button press after which the device powers off, 0 never.
+    pub idle_power_off: u64,
+    pub shutdown: ShutdownMethod,
+    /// sysfs power supply directory such as `/sys/class/power_supply/<name>`, detected when
+    /// not set.
+    pub battery: Option<PathBuf>,
+}
+
+impl Default for PowerSettings {
+    fn default() -> Self {
+        Self {
+            warning_levels: vec![20, 10],
+            critical_level: 5,
+            idle_power_off: 0,
+            shutdown: ShutdownMethod::default(),
+            battery: None,
+        }
+    }
+}
+
+impl PowerSettings {
+    pub fn idle_power_off(&self) -> Option<Duration> {
+        (self.idle_power_off > 0).then(|| Duration::from_secs(self.idle_power_off))
+    }
+}
+
+/// What the player draws from the audio while it plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VisualizerMode {
+    #[default]
+    Off,
+    Spectrum,
+    Waveform,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VisualizerSettings {
+    pub mode: VisualizerMode,
+    /// Number of bars the spectrum is split into.
+    pub bars: u16,
+}
+
+impl Default for VisualizerSettings {
+    fn default() -> Self {
+        Self {
+            mode: VisualizerMode::default(),
+            bars: 24,
+        }
+    }
+}
+
+/// How tags are read when the library is synced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibrarySettings {
+    /// What separates the names in an artist credit, matched ignoring case. Words such as
+    /// "feat." only match at the start of a word, `/` needs spaces around it so "AC/DC" stays
+    /// whole unless it's added bare.
+    pub artist_separators: Vec<String>,
+    /// Genres that mark a track as spoken word rather than music, matched ignoring case.
+    pub spoken_word_genres: Vec<String>,
+    /// Directories whose files are all spoken word, such as where podcasts are downloaded to.
+    pub spoken_word_dirs: Vec<PathBuf>,
+}
+
+impl Default for LibrarySettings {
+    fn default() -> Self {
+        Self {
+            artist_separators: [";", " / ", "feat.", "ft.", "featuring"]
+                .map(str::to_string)
+                .to_vec(),
+            spoken_word_genres: ["Audiobook", "Podcast", "Spoken Word", "Speech"]
+                .map(str::to_string)
+                .to_vec(),
+            spoken_word_dirs: Vec::new(),
+        }
+    }
+}
+
+impl LibrarySettings {
+    /// Whether `track` is spoken word, going by its genre, where it's kept or it being an M4B
+    /// audiobook.
+    pub fn media_kind(&self, track: &Track) -> MediaKind {
+        let genre = track
+            .genre
+            .as_ref()
+            .is_some_and(|genre| self.is_spoken_word_genre(genre));
+        let audiobook = track
+            .path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("m4b"));
+
+        match genre || self.in_spoken_word_dir(&track.path) || audiobook {
+            true => MediaKind::SpokenWord,
+            false => MediaKind::Music,
+        }
+    }
+
+    pub fn is_spoken_word_genre(&self, genre: &str) -> bool {
+        self.spoken_word_genres
+            .iter()
+            .any(|spoken| spoken.eq_ignore_ascii_case(genre))
+    }
+
+    pub fn in_spoken_word_dir(&self, path: &Path) -> bool {
+        self.spoken_word_dirs
+            .iter()
+            .any(|dir| path.starts_with(dir))
+    }
+}
+
+/// Which tracks pick up where they were left off, such as audiobooks, podcasts and long mixes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaybackSettings {
+    /// Tracks at least this many seconds long resume, 0 leaves it to the genres.
+    pub resume_longer_than: u64,
+    /// Genres whose tracks resume whatever their length, matched ignoring case.
+    pub resume_genres: Vec<String>,
+    /// Speed music plays at unless a track has its own.
+    pub music_speed: f32,
+    /// Speed spoken word plays at unless a track has its own.
+    pub spoken_word_speed: f32,
+    /// Seconds the volume fades out over before the sleep timer stops playback, 0 stops at once.
+    pub sleep_fade: u64,
+    /// Power the device off once the sleep timer has stopped playback.
+    pub sleep_power_off: bool,
+    /// Seconds one track fades into the next over, 0 plays them gaplessly.
+    pub crossfade: u64,
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        Self {
+            resume_longer_than: 1200,
+            resume_genres: ["Audiobook", "Podcast", "Spoken Word"]
+                .map(str::to_string)
+                .to_vec(),
+            music_speed: 1.0,
+            spoken_word_speed: 1.0,
+            sleep_fade: 30,
+            sleep_power_off: false,
+            crossfade: 0,
+        }
+    }
+}
+
+impl PlaybackSettings {
+    /// The speed `track` plays at, its own or else the one for its kind.
+    pub fn speed(&self, track: &Track) -> f32 {
+        track.speed.unwrap_or(match track.kind {
+            MediaKind::Music => self.music_speed,
+            MediaKind::SpokenWord => self.spoken_word_speed,
+        })
+    }
+
+    pub fn sleep_fade(&self) -> Option<Duration> {
+        (self.sleep_fade > 0).then(|| Duration::from_secs(self.sleep_fade))
+    }
+
+    pub fn crossfade(&self) -> Option<Duration> {
+        (self.crossfade > 0).then(|| Duration::from_secs(self.crossfade))
+    }
+
+    /// Whether playback of `track` is remembered and picked back up, spoken word always is.
+    pub fn resumes(&self, track: &Track) -> bool {
+        let long = self.resume_longer_than > 0
+            && track.duration >= Duration::from_secs(self.resume_longer_than);
+        let genre = track.genre.as_ref().is_some_and(|genre| {
+            self.resume_genres
+                .iter()
+                .any(|resumed| resumed.eq_ignore_ascii_case(genre))
+        });
+
+        long || genre || track.kind == MediaKind::SpokenWord
+    }
+}
+
+/// Speeds offered for playback, from half to double.
+pub const SPEED_OPTIONS: [f32; 7] = [0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+
+/// Reads and writes [`Settings`] as JSON.
+#[derive(Debug, Clone)]
+pub struct SettingsStore {
+    path: PathBuf,
+}
+
+impl SettingsStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Loads the stored settings, a missing or unreadable file gives the defaults.
+    pub fn load(&self) -> Settings {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Settings::default(),
+            Err(e) => {
+                warn!("unable to read settings from {:?}: {}", self.path, e);
+                return Settings::default();
+            }
+        };
+
+        serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            warn!("ignoring invalid settings in {:?}: {}", self.path, e);
+            Settings::default()
+        })
+    }
+
+    pub async fn save(&self, settings: Settings) -> Result<(), SettingsError> {
+        let contents = serde_json::to_vec_pretty(&settings)?;
+        tokio::fs::write(&self.path, contents).await?;
+
+        Ok(())
+    }
+}
+
+/// The position after (or before) `position` in a list of `len`, wrapping around.
+pub fn cycle_index(position: usize, len: usize, forward: bool) -> usize {
+    match forward {
+        true => (position + 1) % len,
+        false => (position + len - 1) % len,
+    }
+}
+
+/// The option after (or before) `current`, wrapping around, values not in the list snap to the
+/// first option.
+pub fn cycle_option<T: Copy + PartialEq>(options: &[T], current: T, forward: bool) -> T {
+    match options.iter().position(|option| *option == current) {
+        Some(position) => options[cycle_index(position, options.len(), forward)],
+        None => options[0],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(minutes: u64, genre: Option<&str>) -> Track {
+        Track {
+            duration: Duration::from_secs(minutes * 60),
+            genre: genre.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn long_tracks_listed_genres_and_spoken_word_resume() {
+        let playback = PlaybackSettings::default();
+
+        assert!(!playback.resumes(&track(19, None)));
+        assert!(playback.resumes(&track(20, None)));
+        assert!(playback.resumes(&track(3, Some("podcast"))));
+        assert!(!playback.resumes(&track(3, Some("Podcasts"))));
+        assert!(playback.resumes(&Track {
+            kind: MediaKind::SpokenWord,
+            ..track(3, None)
+        }));
+
+        let genres_only = PlaybackSettings {
+            resume_longer_than: 0,
+            ..Default::default()
+        };
+        assert!(!genres_only.resumes(&track(90, Some("Electronic"))));
+        assert!(genres_only.resumes(&track(90, Some("Audiobook"))));
+    }
+}